@@ -1,2 +1,2 @@
-#!C:\Program Files\Git\bin\sh.exe
-py.exe .dev-suite/hooked/post-receive.py
+#!/bin/sh
+exec hooked run post-receive "$@"
@@ -1,2 +1,2 @@
-#!C:\Program Files\Git\bin\sh.exe
-py.exe .dev-suite/hooked/pre-auto-gc.py
+#!/bin/sh
+exec hooked run pre-auto-gc "$@"
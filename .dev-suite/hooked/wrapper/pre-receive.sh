@@ -1,2 +1,2 @@
-#!C:\Program Files\Git\bin\sh.exe
-py.exe .dev-suite/hooked/pre-receive.py
+#!/bin/sh
+exec hooked run pre-receive "$@"
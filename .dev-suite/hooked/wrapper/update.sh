@@ -1,2 +1,2 @@
-#!C:\Program Files\Git\bin\sh.exe
-py.exe .dev-suite/hooked/update.py
+#!/bin/sh
+exec hooked run update "$@"
@@ -1,31 +1,55 @@
 //! config management lib for dev-suite
 use anyhow::{
+  bail,
   format_err,
   Result,
 };
 use dirs::config_dir;
+use log::*;
 use serde::{
   Deserialize,
   Serialize,
 };
-use shared::find_root;
+use sha2::{
+  Digest,
+  Sha256,
+};
+use shared::{
+  audit,
+  find_root,
+};
 use std::{
+  collections::BTreeMap,
+  fmt,
   fs,
   path::PathBuf,
+  process::Command,
 };
 use uuid::Uuid;
 
+/// Adds something the current user did with the tool to the repo's audit log.
+/// The log is only a record so failing to write to it doesn't stop anything.
+pub fn log_event(tool: &str, action: impl Into<String>) {
+  let who = get_user_config()
+    .ok()
+    .map(|config| (config.uuid, config.name));
+  if let Err(e) = audit::record(tool, who, action) {
+    warn!("Unable to write to the audit log: {}", e);
+  }
+}
+
 /// Creates a new user config if it does not exist
-pub fn create_user_config(name: impl Into<String>) -> Result<()> {
-  let conf_dir = config_dir()
-    .ok_or_else(|| format_err!("Unable to get the config dir for the OS"))?
-    .join("dev-suite");
+pub fn create_user_config(
+  name: impl Into<String>,
+  identity: Identity,
+) -> Result<()> {
+  let conf_dir = user_dir()?;
   if !conf_dir.exists() {
     fs::create_dir_all(&conf_dir)?;
   }
   let conf_path = conf_dir.join("user-config.toml");
   if !conf_path.exists() {
-    let user_config = UserConfig::new(name);
+    let user_config = UserConfig::with_identity(name, identity)?;
     fs::write(&conf_path, toml::to_string_pretty(&user_config)?)?;
   }
   Ok(())
@@ -45,16 +69,20 @@ pub fn create_repo_config() -> Result<()> {
   Ok(())
 }
 
-/// Get the path for the user config
-fn user_config_path() -> Result<PathBuf> {
+/// Get the directory where dev-suite keeps files for the current user
+pub fn user_dir() -> Result<PathBuf> {
   Ok(
     config_dir()
       .ok_or_else(|| format_err!("Unable to get the config dir for the OS"))?
-      .join("dev-suite")
-      .join("user-config.toml"),
+      .join("dev-suite"),
   )
 }
 
+/// Get the path for the user config
+fn user_config_path() -> Result<PathBuf> {
+  Ok(user_dir()?.join("user-config.toml"))
+}
+
 /// Get the path for the repo config
 fn repo_config_path() -> Result<PathBuf> {
   Ok(find_root()?.join(".dev-suite").join("repo-config.toml"))
@@ -62,11 +90,20 @@ fn repo_config_path() -> Result<PathBuf> {
 
 /// Reads in the user config
 pub fn get_user_config() -> Result<UserConfig> {
-  Ok(toml::from_slice(&fs::read(&user_config_path()?)?)?)
+  let mut config: UserConfig =
+    toml::from_slice(&fs::read(&user_config_path()?)?)?;
+  // The uuid always comes from the email so editing one without the other
+  // can't split someone into two people
+  if let Identity::Email { email } = &config.identity {
+    config.uuid = email_uuid(email);
+  }
+  Ok(config)
 }
 /// Reads in the repo config
 pub fn get_repo_config() -> Result<RepoConfig> {
-  Ok(toml::from_slice(&fs::read(&repo_config_path()?)?)?)
+  let config: RepoConfig = toml::from_slice(&fs::read(&repo_config_path()?)?)?;
+  config.check_fields()?;
+  Ok(config)
 }
 
 /// Writes the user config to disk
@@ -92,6 +129,23 @@ pub struct UserConfig {
   pub name: String,
   /// The uuid of the user using dev-suite
   pub uuid: Uuid,
+  /// Where the uuid came from
+  #[serde(default, skip_serializing_if = "Identity::is_local")]
+  pub identity: Identity,
+  /// The key to sign comments and changes to tickets with, if the user signs
+  /// them
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub signing: Option<Signing>,
+  /// Presets for `ds init --template <name>` keyed by name
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub templates: BTreeMap<String, Template>,
+  /// Shorthands for ticket commands keyed by the name used in their place,
+  /// e.g. `mine = "list --assignee me"`
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub aliases: BTreeMap<String, String>,
+  /// Flags to always pass to a ticket subcommand keyed by the subcommand
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub defaults: BTreeMap<String, String>,
 }
 
 impl UserConfig {
@@ -100,14 +154,286 @@ impl UserConfig {
     Self {
       name: name.into(),
       uuid: Uuid::new_v4(),
+      identity: Identity::Local,
+      signing: None,
+      templates: BTreeMap::new(),
+      aliases: BTreeMap::new(),
+      defaults: BTreeMap::new(),
+    }
+  }
+}
+
+impl UserConfig {
+  /// Create a new `UserConfig` with the uuid coming from the identity
+  pub fn with_identity(
+    name: impl Into<String>,
+    identity: Identity,
+  ) -> Result<Self> {
+    let uuid = identity.uuid()?;
+    Ok(Self {
+      uuid,
+      identity,
+      ..Self::new(name)
+    })
+  }
+}
+
+/// Where a user's uuid comes from. Anything other than `Local` gives the same
+/// uuid on every machine so tickets are attributed to the same person no
+/// matter where they were made.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum Identity {
+  /// A random uuid made when the user config was created
+  #[default]
+  Local,
+  /// A uuid derived from an email address
+  Email {
+    /// The address, e.g. the one used for SSO
+    email: String,
+  },
+  /// A uuid from a command, run when the user config is created
+  Command {
+    /// What to run. It prints the uuid or a verified email address to derive
+    /// it from, e.g. by asking the company's SSO who is logged in.
+    command: String,
+  },
+}
+
+impl Identity {
+  fn is_local(&self) -> bool {
+    *self == Identity::Local
+  }
+
+  /// Works out the uuid for this identity
+  pub fn uuid(&self) -> Result<Uuid> {
+    match self {
+      Identity::Local => Ok(Uuid::new_v4()),
+      Identity::Email { email } => {
+        if !email.contains('@') {
+          bail!("'{}' is not an email address", email);
+        }
+        Ok(email_uuid(email))
+      }
+      Identity::Command { command } => {
+        #[cfg(not(windows))]
+        let output = Command::new("sh").arg("-c").arg(command).output()?;
+        #[cfg(windows)]
+        let output = Command::new("cmd").arg("/C").arg(command).output()?;
+        if !output.status.success() {
+          bail!(
+            "The identity command '{}' failed with {}. {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+          );
+        }
+        let out = String::from_utf8(output.stdout)?;
+        let out = out.trim();
+        match Uuid::parse_str(out) {
+          Ok(uuid) => Ok(uuid),
+          Err(_) if out.contains('@') => Ok(email_uuid(out)),
+          Err(_) => bail!(
+            "The identity command '{}' printed '{}' rather than a uuid or an \
+             email address",
+            command,
+            out
+          ),
+        }
+      }
     }
   }
 }
 
+impl fmt::Display for Identity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Identity::Local => write!(f, "made on this machine"),
+      Identity::Email { email } => write!(f, "from {}", email),
+      Identity::Command { command } => write!(f, "from '{}'", command),
+    }
+  }
+}
+
+/// A key for signing with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum Signing {
+  /// A gpg key
+  Gpg {
+    /// Anything `gpg --local-user` takes, e.g. the key's fingerprint or email
+    key: String,
+  },
+  /// An ssh key
+  Ssh {
+    /// The path to the private key, or to the public key if the private key
+    /// is in ssh-agent
+    key: PathBuf,
+  },
+}
+
+/// The uuid for an email address, which is the same everywhere it's made. It
+/// comes from the SHA-256 hash of the address ignoring case and surrounding
+/// whitespace, marked as a custom (version 8) uuid.
+pub fn email_uuid(email: &str) -> Uuid {
+  let hash = Sha256::new()
+    .chain(b"dev-suite identity:")
+    .chain(email.trim().to_lowercase().as_bytes())
+    .result();
+  let mut bytes = [0; 16];
+  bytes.copy_from_slice(&hash[..16]);
+  bytes[6] = (bytes[6] & 0x0f) | 0x80;
+  bytes[8] = (bytes[8] & 0x3f) | 0x80;
+  Uuid::from_bytes(bytes)
+}
+
+/// A preset of how to set up dev-suite in a repo
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Template {
+  /// Which tools to enable, 'hooked' and/or 'ticket'
+  pub tools: Vec<String>,
+  /// Which language to use for hooks, 'bash', 'python', or 'ruby'
+  pub hook_language: Option<String>,
+  /// The contents to start each hook off with keyed by the name of the hook
+  #[serde(default)]
+  pub hooks: BTreeMap<String, String>,
+}
+
 /// Repo Config struct
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct RepoConfig {
-  maintainers: Vec<(String, Uuid)>,
+  /// The name and uuid of everyone maintaining the repo
+  pub maintainers: Vec<(String, Uuid)>,
+  /// Statuses tickets can be in between Open and Closed, in order
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub statuses: Vec<String>,
+  /// Who new tickets are assigned to, either a maintainer by name or uuid or
+  /// `creator` for whoever opened the ticket
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub default_assignee: Option<String>,
+  /// How ticket files are written, either 'toml' (the default) or 'markdown'
+  /// for the description as markdown after the rest of the ticket as toml
+  /// frontmatter
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub format: Option<String>,
+  /// The gpg or ssh public key of each maintainer by their uuid, which their
+  /// signatures are checked against and, for gpg keys, encrypted tickets are
+  /// encrypted for
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub keys: BTreeMap<Uuid, String>,
+  /// Who vouched for each key in `keys` and their signature of it, so a key
+  /// added or changed since a trusted copy of the config can be checked
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub key_signatures: BTreeMap<Uuid, KeySignature>,
+  /// Rules for moving tickets between statuses
+  #[serde(default, skip_serializing_if = "WorkflowConfig::is_empty")]
+  pub workflow: WorkflowConfig,
+  /// The parts of the project tickets can be filed under, e.g. 'tui' or
+  /// 'hooks', by name
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub components: BTreeMap<String, Component>,
+  /// Custom fields tickets can have on top of the built in ones
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub fields: Vec<Field>,
+  /// Urls to send json to when tickets are created, closed, or commented on
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub webhooks: Vec<Webhook>,
+}
+
+/// A maintainer vouching for someone's key with a signature made by their own
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeySignature {
+  /// The uuid of the maintainer whose key made the signature
+  pub by: Uuid,
+  /// The detached signature of the key
+  pub signature: String,
+}
+
+/// A `[[webhooks]]` table of the repo config
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Webhook {
+  /// Where to POST the events to, e.g. a Slack or Matrix incoming webhook
+  pub url: String,
+  /// Which events to send, any of 'created', 'closed', and 'commented'. Every
+  /// event is sent if this is empty.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub events: Vec<String>,
+}
+
+/// A `[components.<name>]` table of the repo config
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Component {
+  /// The maintainers who own the component, by name or uuid. New tickets
+  /// filed under it are assigned to them.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub owners: Vec<String>,
+}
+
+/// A `[[fields]]` table of the repo config, a custom field of tickets
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Field {
+  /// What the field is called, which is also its key in ticket files
+  pub name: String,
+  /// What kind of values the field holds
+  #[serde(rename = "type")]
+  pub kind: FieldKind,
+  /// Whether every new ticket has to have a value for the field
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub required: bool,
+  /// The values an enum field can be set to
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub values: Vec<String>,
+}
+
+/// What kind of values a custom field holds
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+  /// Any text
+  String,
+  /// One of the field's `values`
+  Enum,
+  /// A number like 3 or 2.5
+  Number,
+  /// A date like 2020-07-01
+  Date,
+}
+
+impl fmt::Display for FieldKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FieldKind::String => write!(f, "string"),
+      FieldKind::Enum => write!(f, "enum"),
+      FieldKind::Number => write!(f, "number"),
+      FieldKind::Date => write!(f, "date"),
+    }
+  }
+}
+
+/// The `[workflow]` table of the repo config
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct WorkflowConfig {
+  /// The statuses tickets in a status can move to. Tickets in a status that
+  /// isn't listed can move to any status.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub transitions: BTreeMap<String, Vec<String>>,
+  /// Statuses tickets can only move to if a reason is given
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub require_reason: Vec<String>,
+  /// Commands run before a ticket moves to a status. If the command fails the
+  /// ticket stays where it is.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub on_enter: BTreeMap<String, String>,
+}
+
+impl WorkflowConfig {
+  /// Whether there are no rules, so any move is allowed
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.transitions.is_empty()
+      && self.require_reason.is_empty()
+      && self.on_enter.is_empty()
+  }
 }
 
 impl RepoConfig {
@@ -116,8 +442,59 @@ impl RepoConfig {
   pub fn new() -> Self {
     Self {
       maintainers: Vec::new(),
+      statuses: Vec::new(),
+      default_assignee: None,
+      format: None,
+      keys: BTreeMap::new(),
+      key_signatures: BTreeMap::new(),
+      workflow: WorkflowConfig::default(),
+      components: BTreeMap::new(),
+      fields: Vec::new(),
+      webhooks: Vec::new(),
     }
   }
+
+  /// The custom field with the name
+  #[must_use]
+  pub fn field(&self, name: &str) -> Option<&Field> {
+    self.fields.iter().find(|field| field.name == name)
+  }
+
+  /// Makes sure the custom fields make sense, so tickets aren't checked
+  /// against a schema that's wrong
+  fn check_fields(&self) -> Result<()> {
+    for (i, field) in self.fields.iter().enumerate() {
+      if field.name.is_empty()
+        || !field
+          .name
+          .chars()
+          .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+      {
+        bail!(
+          "Field names in the repo config may only contain letters, numbers, \
+           '-', and '_', got '{}'",
+          field.name
+        );
+      }
+      if self.fields[..i].iter().any(|f| f.name == field.name) {
+        bail!("The field '{}' is in the repo config twice", field.name);
+      }
+      match (field.kind, field.values.is_empty()) {
+        (FieldKind::Enum, true) => bail!(
+          "The enum field '{}' in the repo config needs a list of values",
+          field.name
+        ),
+        (FieldKind::Enum, false) | (_, true) => {}
+        (kind, false) => bail!(
+          "Only enum fields can have values, but '{}' in the repo config is a \
+           {}",
+          field.name,
+          kind
+        ),
+      }
+    }
+    Ok(())
+  }
 }
 
 /// Show repo config
@@ -132,7 +509,7 @@ pub fn show_repo_config() -> Result<()> {
 /// Show repo config
 pub fn show_user_config() -> Result<()> {
   let conf = get_user_config()?;
-  println!("{} - {}", conf.name, conf.uuid);
+  println!("{} - {} ({})", conf.name, conf.uuid, conf.identity);
   Ok(())
 }
 
@@ -0,0 +1,105 @@
+//! Flags commits that were made without running the pre-commit and
+//! commit-msg hooks, e.g. with `git commit --no-verify`
+
+use configamajig::get_user_config;
+use shared::audit;
+use std::{
+  error::Error,
+  fs::{
+    self,
+    OpenOptions,
+  },
+  io::Write,
+  path::PathBuf,
+  process::{
+    self,
+    Command,
+  },
+  time::{
+    SystemTime,
+    UNIX_EPOCH,
+  },
+};
+
+const VERIFY_HOOKS: [&str; 2] = ["pre-commit", "commit-msg"];
+
+fn git(args: &[&str]) -> Result<String, Box<dyn Error>> {
+  let output = Command::new("git").args(args).output()?;
+  if !output.status.success() {
+    return Err(format!("Failed to run git {}", args.join(" ")).into());
+  }
+  Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+}
+
+fn main() {
+  if let Err(e) = || -> Result<(), Box<dyn Error>> {
+    // .git is a file pointing to the real one in worktrees and submodules
+    let git_dir = PathBuf::from(git(&["rev-parse", "--git-dir"])?);
+    // Rebases and cherry-picks make commits without running pre-commit so
+    // they'd always look like they bypassed the hooks, and merge commits are
+    // made by git from commits that were already checked
+    let parents = git(&["rev-list", "--parents", "-n", "1", "HEAD"])?
+      .split_whitespace()
+      .count()
+      - 1;
+    if git_dir.join("rebase-merge").exists()
+      || git_dir.join("rebase-apply").exists()
+      || git_dir.join("CHERRY_PICK_HEAD").exists()
+      || parents > 1
+    {
+      return Ok(());
+    }
+
+    let dir = git_dir.join("hooked");
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let tree = git(&["rev-parse", "HEAD^{tree}"])?;
+
+    let mut skipped = Vec::new();
+    for hook in &VERIFY_HOOKS {
+      let marker = dir.join("markers").join(hook);
+      match fs::read_to_string(&marker) {
+        Ok(ref marked) if marked.trim() == tree => fs::remove_file(&marker)?,
+        _ => skipped.push(*hook),
+      }
+    }
+
+    if !skipped.is_empty() {
+      fs::create_dir_all(&dir)?;
+      let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("bypassed"))?;
+      writeln!(
+        log,
+        "{} {}",
+        commit,
+        SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+      )?;
+      let who = get_user_config()
+        .ok()
+        .map(|config| (config.uuid, config.name));
+      if let Err(e) = audit::record(
+        "hooked",
+        who,
+        format!(
+          "Made commit {} without running the {} hook(s)",
+          commit,
+          skipped.join(" and ")
+        ),
+      ) {
+        eprintln!("warning: unable to write to the audit log: {}", e);
+      }
+      eprintln!(
+        "warning: commit {} was made without running the {} hook(s). Run \
+         'hooked audit' to see every commit that did this.",
+        commit,
+        skipped.join(" and ")
+      );
+    }
+
+    Ok(())
+  }() {
+    eprintln!("{}", e);
+    process::exit(1);
+  }
+}
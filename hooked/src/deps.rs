@@ -0,0 +1,142 @@
+//! Tools hooks need that aren't part of the repo, e.g. linters. They're
+//! declared in `.dev-suite/hooked/deps.toml`, keyed by the name of the
+//! executable:
+//!
+//! ```toml
+//! [shellcheck]
+//! apt = "shellcheck"
+//! brew = "shellcheck"
+//! help = "https://github.com/koalaman/shellcheck#installing"
+//! ```
+//!
+//! Every key other than `help` is the name of the package for that package
+//! manager.
+
+use anyhow::{
+  bail,
+  Result,
+};
+use log::*;
+use serde::Deserialize;
+use shared::find_root;
+use std::{
+  collections::BTreeMap,
+  env,
+  fs,
+  path::PathBuf,
+  process::Command,
+};
+
+#[derive(Deserialize, Debug)]
+struct Dep {
+  /// Where to find out how to install the dependency by hand
+  help: Option<String>,
+  /// The name of the package for each package manager
+  #[serde(flatten)]
+  packages: BTreeMap<String, String>,
+}
+
+/// A package manager deps can be installed with, the executable used to
+/// detect it, and the command to install a package with
+const MANAGERS: [(&str, &str, &[&str]); 5] = [
+  ("apt", "apt-get", &["sudo", "apt-get", "install", "-y"]),
+  ("dnf", "dnf", &["sudo", "dnf", "install", "-y"]),
+  ("pacman", "pacman", &["sudo", "pacman", "-S", "--noconfirm"]),
+  ("brew", "brew", &["brew", "install"]),
+  ("scoop", "scoop", &["scoop", "install"]),
+];
+
+fn manifest() -> Result<PathBuf> {
+  Ok(
+    find_root()?
+      .join(".dev-suite")
+      .join("hooked")
+      .join("deps.toml"),
+  )
+}
+
+fn get_deps() -> Result<BTreeMap<String, Dep>> {
+  let path = manifest()?;
+  if path.exists() {
+    Ok(toml::from_slice(&fs::read(&path)?)?)
+  } else {
+    debug!("No deps manifest at {}.", path.display());
+    Ok(BTreeMap::new())
+  }
+}
+
+/// Whether an executable can be found on the PATH
+pub fn on_path(name: &str) -> bool {
+  env::var_os("PATH").is_some_and(|paths| {
+    env::split_paths(&paths).any(|dir| {
+      dir.join(name).is_file()
+        || cfg!(windows) && dir.join(name).with_extension("exe").is_file()
+    })
+  })
+}
+
+/// The names of the declared deps that aren't on the PATH
+pub fn missing() -> Result<Vec<String>> {
+  Ok(
+    get_deps()?
+      .into_keys()
+      .filter(|name| !on_path(name))
+      .collect(),
+  )
+}
+
+/// Installs any missing deps with the first package manager on the system that
+/// knows about them, or tells the user how to install them if none do. With
+/// `check` nothing is installed.
+pub fn install_deps(check: bool) -> Result<()> {
+  let mut deps = get_deps()?;
+  let missing = missing()?;
+  if missing.is_empty() {
+    info!("All of the hook dependencies are installed.");
+    return Ok(());
+  }
+
+  let mut failed = 0;
+  for name in missing {
+    let dep = deps.remove(&name).unwrap_or_else(|| Dep {
+      help: None,
+      packages: BTreeMap::new(),
+    });
+    let install = MANAGERS.iter().filter(|(_, exe, _)| on_path(exe)).find_map(
+      |(manager, _, cmd)| {
+        dep.packages.get(*manager).map(|package| (cmd, package))
+      },
+    );
+    match install {
+      Some((cmd, package)) if !check => {
+        info!("Installing {} with '{} {}'", name, cmd.join(" "), package);
+        let status =
+          Command::new(cmd[0]).args(&cmd[1..]).arg(package).status()?;
+        if !status.success() {
+          error!("Failed to install {}", name);
+          failed += 1;
+        }
+      }
+      Some((cmd, package)) => {
+        warn!(
+          "{} is missing, install it with '{} {}'",
+          name,
+          cmd.join(" "),
+          package
+        );
+        failed += 1;
+      }
+      None => {
+        match dep.help {
+          Some(help) => warn!("{} is missing, see {}", name, help),
+          None => warn!("{} is missing and needs to be installed", name),
+        }
+        failed += 1;
+      }
+    }
+  }
+  if failed > 0 {
+    bail!("{} hook dependencies are not installed", failed);
+  }
+  Ok(())
+}
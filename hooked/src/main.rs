@@ -1,11 +1,20 @@
 //! git hook manager tool
+mod deps;
 
 use anyhow::{
   bail,
   Result,
 };
+use configamajig::{
+  get_user_config,
+  log_event,
+};
 use log::*;
-use shared::find_root;
+use shared::{
+  find_root,
+  set_root,
+  ROOT_ENV,
+};
 #[cfg(not(windows))]
 use std::os::unix::fs::{
   symlink,
@@ -17,7 +26,14 @@ use std::{
   env,
   fs,
   io::Write,
-  path::Path,
+  path::{
+    Path,
+    PathBuf,
+  },
+  process::{
+    self,
+    Command,
+  },
 };
 
 const HOOKS: [&str; 18] = [
@@ -41,12 +57,50 @@ const HOOKS: [&str; 18] = [
   "sendemail-validate",
 ];
 
+/// Hooks that are skipped by `git commit --no-verify`
+const VERIFY_HOOKS: [&str; 2] = ["pre-commit", "commit-msg"];
+
+#[derive(structopt::StructOpt)]
+struct Args {
+  /// Use this directory as the repo root instead of searching for it
+  #[structopt(long, global = true, parse(from_os_str))]
+  root: Option<PathBuf>,
+  #[structopt(subcommand)]
+  cmd: Cmd,
+}
+
 #[derive(structopt::StructOpt)]
-enum Args {
+enum Cmd {
   /// Initialize the repo to use hooked
   Init(Language),
   /// Link pre existing hooks to your .git folder
   Link,
+  /// Run a hook with the dev-suite environment variables set
+  Run {
+    /// The name of the git hook to run
+    hook: String,
+    /// Arguments git passed to the hook
+    args: Vec<String>,
+  },
+  /// Install the tools hooks depend on that are declared in
+  /// .dev-suite/hooked/deps.toml
+  InstallDeps {
+    /// Only check that they're installed
+    #[structopt(long)]
+    check: bool,
+  },
+  /// Check that the hooks are set up properly and can run
+  Doctor {
+    /// Make the hook scripts executable again, both on disk and in git
+    #[structopt(long)]
+    permissions: bool,
+  },
+  /// List recent commits that were made without running the hooks
+  Audit {
+    /// How many commits to show
+    #[structopt(long, default_value = "20")]
+    count: usize,
+  },
 }
 
 /// Which language the repo should be initialized with for hooks
@@ -66,12 +120,20 @@ fn main(args: Args) {
     .ok()
     .map_or_else(|| env::set_var("RUST_LOG", "info"), drop);
   pretty_env_logger::init();
-  if let Err(e) = match args {
-    Args::Init(lang) => init(lang),
-    Args::Link => link(),
+  if let Err(e) = args.root.map_or(Ok(()), set_root) {
+    error!("{}", e);
+    process::exit(1);
+  }
+  if let Err(e) = match args.cmd {
+    Cmd::Init(lang) => init(lang),
+    Cmd::Link => link(),
+    Cmd::Run { hook, args } => run(&hook, &args),
+    Cmd::Audit { count } => audit(count),
+    Cmd::InstallDeps { check } => deps::install_deps(check),
+    Cmd::Doctor { permissions } => doctor(permissions),
   } {
     error!("{}", e);
-    std::process::exit(1);
+    process::exit(1);
   }
 }
 
@@ -89,8 +151,6 @@ fn init(lang: Language) -> Result<()> {
     debug!("dev-suite hook path: {}", path.display());
     let git_hook = &git_hooks.join(hook);
     debug!("git_hook path: {}", git_hook.display());
-    let mut wrapper_hook = (&wrapper_dir).join(hook);
-    let _ = wrapper_hook.set_extension("sh");
     let _ = match lang {
       Language::Bash => path.set_extension("sh"),
       Language::Python => path.set_extension("py"),
@@ -102,64 +162,26 @@ fn init(lang: Language) -> Result<()> {
     } else {
       debug!("Creating dev-suite hook.");
       let mut file = fs::File::create(&path)?;
-      let mut wrapper = fs::File::create(&wrapper_hook)?;
       trace!("File created.");
       #[cfg(not(windows))]
       {
         let mut perms = file.metadata()?.permissions();
-        let mut wrapper_perms = wrapper.metadata()?.permissions();
         debug!("Setting dev-suite hook to be executable.");
         perms.set_mode(0o755);
-        wrapper_perms.set_mode(0o755);
         file.set_permissions(perms)?;
-        wrapper.set_permissions(wrapper_perms)?;
         trace!("Permissions were set.");
       }
+      debug!("Writing data to file.");
       match lang {
-        Language::Bash => {
-          file.write_all(b"#!/usr/bin/env bash")?;
-          wrapper.write_all(
-            format!(
-              "#!C:\\Program Files\\Git\\bin\\sh.exe\n\
-               bash.exe .dev-suite/hooked/{}.sh\n",
-              hook
-            )
-            .as_bytes(),
-          )?;
-        }
-        Language::Python => {
-          file.write_all(b"#!/usr/bin/env python3")?;
-          wrapper.write_all(
-            format!(
-              "#!C:\\Program Files\\Git\\bin\\sh.exe\n\
-               py.exe .dev-suite/hooked/{}.py\n",
-              hook
-            )
-            .as_bytes(),
-          )?;
-        }
-        Language::Ruby => {
-          file.write_all(b"#!/usr/bin/env ruby")?;
-          wrapper.write_all(
-            format!(
-              "#!C:\\Program Files\\Git\\bin\\sh.exe\n\
-               ruby.exe .dev-suite/hooked/{}.rb\n",
-              hook
-            )
-            .as_bytes(),
-          )?;
-        }
+        Language::Bash => file.write_all(b"#!/usr/bin/env bash")?,
+        Language::Python => file.write_all(b"#!/usr/bin/env python3")?,
+        Language::Ruby => file.write_all(b"#!/usr/bin/env ruby")?,
       }
-      debug!("Writing data to file.");
       debug!("Created git hook {}.", hook);
     }
 
-    #[cfg(not(windows))]
-    let link_path = path.canonicalize()?;
-    #[cfg(windows)]
-    let link_path = wrapper_hook.canonicalize()?;
-
-    inner_link(&link_path, &git_hook, hook)?;
+    let wrapper_hook = write_wrapper(wrapper_dir, hook)?;
+    inner_link(&wrapper_hook.canonicalize()?, git_hook, hook)?;
   }
   info!(
     "Created and symlinked tickets to .git/hooks from {}.",
@@ -171,6 +193,7 @@ fn init(lang: Language) -> Result<()> {
     warn!("If you don't they won't be set as executable on unix systems");
   }
 
+  log_event("hooked", "Set up the hooks");
   Ok(())
 }
 
@@ -180,48 +203,323 @@ fn link() -> Result<()> {
   debug!("git_hooks base path: {}", git_hooks.display());
   let root = root.join(".dev-suite").join("hooked");
   debug!("root base path: {}", root.display());
+  let wrapper_dir = &root.join("wrapper");
+  fs::create_dir_all(wrapper_dir)?;
 
   for hook in &HOOKS {
-    let path = {
-      #[cfg(windows)]
-      let mut path = root.join("wrapper").join(hook);
-      #[cfg(not(windows))]
-      let mut path = root.join(hook);
-      debug!("PATH: {}", path.display());
-
-      let mut path_python = path.clone();
-      let _ = path_python.set_extension("py");
-      let mut path_ruby = path.clone();
-      let _ = path_ruby.set_extension("rb");
-      let mut path_bash = path.clone();
-      let _ = path_bash.set_extension("sh");
-
-      if path_python.exists() {
-        path_python
-      } else if path_ruby.exists() {
-        path_ruby
-      } else if path_bash.exists() {
-        path_bash
-      } else {
-        let _ = path.set_extension("");
-        bail!(
-          "The path {} does not exist. Have you initialized the repo to use hooked?",
-          path.display()
-        );
-      }
-    };
-    let path = path.canonicalize()?;
+    let path = hook_script(&root, hook)?;
     debug!("dev-suite hook path: {}", path.display());
+    let wrapper_hook = write_wrapper(wrapper_dir, hook)?;
     let git_hook = &git_hooks.join(hook);
     debug!("git_hook path: {}", git_hook.display());
-    inner_link(&path, &git_hook, hook)?;
+    inner_link(&wrapper_hook.canonicalize()?, git_hook, hook)?;
   }
 
+  log_event("hooked", "Linked the hooks into .git/hooks");
   info!("Successfully symlinked all githooks to .git/hooks");
   Ok(())
 }
 
+/// Runs the dev-suite hook with the environment variables hooks can rely on
+/// being set. This is what the generated wrappers that git calls use.
+fn run(hook: &str, args: &[String]) -> Result<()> {
+  let root = find_root()?;
+  let script = hook_script(&root.join(".dev-suite").join("hooked"), hook)?;
+  debug!("Running hook {}.", script.display());
+
+  #[cfg(not(windows))]
+  let mut command = Command::new(&script);
+  #[cfg(windows)]
+  let mut command = {
+    // Windows can't run scripts directly from their shebang line so we have
+    // to pick the interpreter ourselves
+    let interpreter = match script.extension().and_then(|e| e.to_str()) {
+      Some("py") => "py.exe",
+      Some("rb") => "ruby.exe",
+      _ => "bash.exe",
+    };
+    let mut command = Command::new(interpreter);
+    let _ = command.arg(&script);
+    command
+  };
+
+  let _ = command
+    .args(args)
+    .env(ROOT_ENV, &root)
+    .env("DEVSUITE_HOOK_NAME", hook);
+  match get_user_config() {
+    Ok(user) => {
+      let _ = command
+        .env("DEVSUITE_USER_NAME", user.name)
+        .env("DEVSUITE_USER_UUID", user.uuid.to_string());
+    }
+    Err(e) => debug!("No user config so user variables are unset: {}", e),
+  }
+
+  let status = command.status()?;
+  if !status.success() {
+    process::exit(status.code().unwrap_or(1));
+  }
+  if VERIFY_HOOKS.contains(&hook) {
+    write_marker(&root, hook)?;
+  }
+  Ok(())
+}
+
+/// Record that a hook which `git commit --no-verify` would skip passed for
+/// what's currently staged. `hooked-post-commit` checks these against the
+/// tree of each new commit to find commits that bypassed the hooks.
+fn write_marker(root: &Path, hook: &str) -> Result<()> {
+  let output = Command::new("git")
+    .arg("write-tree")
+    .current_dir(root)
+    .output()?;
+  if !output.status.success() {
+    debug!(
+      "Unable to get the staged tree, not writing a marker for {}",
+      hook
+    );
+    return Ok(());
+  }
+  let markers = git_dir(root)?.join("hooked").join("markers");
+  fs::create_dir_all(&markers)?;
+  fs::write(markers.join(hook), output.stdout)?;
+  trace!("Wrote marker for {}.", hook);
+  Ok(())
+}
+
+/// The repo's git directory, which is elsewhere in worktrees and submodules
+/// where .git is a file pointing to it
+fn git_dir(root: &Path) -> Result<PathBuf> {
+  let output = Command::new("git")
+    .args(["rev-parse", "--git-dir"])
+    .current_dir(root)
+    .output()?;
+  if !output.status.success() {
+    bail!("Unable to find the git directory of {}", root.display());
+  }
+  Ok(root.join(String::from_utf8(output.stdout)?.trim()))
+}
+
+/// List the commits `hooked-post-commit` found were made without the hooks
+fn audit(count: usize) -> Result<()> {
+  let root = find_root()?;
+  let log = git_dir(&root)?.join("hooked").join("bypassed");
+  if !log.exists() {
+    info!("No commits have bypassed the hooks.");
+    return Ok(());
+  }
+  let contents = fs::read_to_string(&log)?;
+  let lines = contents.lines().collect::<Vec<&str>>();
+  for line in lines.iter().rev().take(count) {
+    let commit = line.split_whitespace().next().unwrap_or_default();
+    let output = Command::new("git")
+      .args(["log", "-1", "--format=%h %an %ar: %s", commit])
+      .current_dir(&root)
+      .output()?;
+    if output.status.success() {
+      print!("{}", String::from_utf8_lossy(&output.stdout));
+    } else {
+      println!("{} (no longer exists)", commit);
+    }
+  }
+  Ok(())
+}
+
+/// Looks for anything that would keep the hooks from running and reports all
+/// of it rather than stopping at the first problem
+fn doctor(permissions: bool) -> Result<()> {
+  let root = find_root()?;
+  let git_hooks = root.join(".git").join("hooks");
+  let hooked = root.join(".dev-suite").join("hooked");
+  let mut problems = 0;
+
+  if !deps::on_path("hooked") {
+    warn!("hooked isn't on your PATH so git can't run the hooks");
+    problems += 1;
+  }
+  for hook in &HOOKS {
+    if let Err(e) = hook_script(&hooked, hook) {
+      warn!("{}", e);
+      problems += 1;
+      continue;
+    }
+    let wrapper = hooked.join("wrapper").join(hook).with_extension("sh");
+    let linked = wrapper.canonicalize().ok().is_some_and(|wrapper| {
+      fs::read_link(git_hooks.join(hook)).ok() == Some(wrapper)
+    });
+    if !linked {
+      warn!(
+        "The {} hook isn't linked, run 'hooked link' to fix it",
+        hook
+      );
+      problems += 1;
+    }
+  }
+  for dep in deps::missing()? {
+    warn!(
+      "{} is needed by the hooks but isn't installed, run 'hooked \
+       install-deps' to install it",
+      dep
+    );
+    problems += 1;
+  }
+  problems += check_permissions(&root, permissions)?;
+
+  if problems > 0 {
+    bail!("Found {} problems with the hooks", problems);
+  }
+  info!("Everything looks good.");
+  Ok(())
+}
+
+/// Checks that the hook scripts and wrappers are executable on disk and that
+/// git records them as executable, since cloning on Windows or unpacking some
+/// archives drops the executable bit. Returns how many problems are left
+/// after fixing them if `fix` is set.
+fn check_permissions(root: &Path, fix: bool) -> Result<usize> {
+  let hooked = root.join(".dev-suite").join("hooked");
+  let mut scripts = Vec::new();
+  for hook in &HOOKS {
+    if let Ok(script) = hook_script(&hooked, hook) {
+      scripts.push(script);
+    }
+    let wrapper = hooked.join("wrapper").join(hook).with_extension("sh");
+    if wrapper.exists() {
+      scripts.push(wrapper.canonicalize()?);
+    }
+  }
+  let mut problems = 0;
+
+  #[cfg(not(windows))]
+  for script in &scripts {
+    let mut perms = fs::metadata(script)?.permissions();
+    if perms.mode() & 0o111 == 0o111 {
+      continue;
+    }
+    if fix {
+      perms.set_mode(0o755);
+      fs::set_permissions(script, perms)?;
+      log_event("hooked", format!("Made {} executable", script.display()));
+      info!("Made {} executable", script.display());
+    } else {
+      warn!(
+        "{} isn't executable, run 'hooked doctor --permissions' to fix it",
+        script.display()
+      );
+      problems += 1;
+    }
+  }
+
+  // Windows doesn't have an executable bit so git's index is the only place
+  // it's kept for teammates on other platforms
+  let root = root.canonicalize()?;
+  let relative = scripts
+    .iter()
+    .filter_map(|s| s.strip_prefix(&root).ok())
+    .map(|s| s.to_string_lossy().replace('\\', "/"))
+    .collect::<Vec<_>>();
+  if relative.is_empty() {
+    return Ok(problems);
+  }
+  let output = Command::new("git")
+    .args(["ls-files", "--stage", "--"])
+    .args(&relative)
+    .current_dir(&root)
+    .output()?;
+  if !output.status.success() {
+    debug!("Unable to read the git index, skipping its permission check");
+    return Ok(problems);
+  }
+  for line in String::from_utf8_lossy(&output.stdout).lines() {
+    // Each line is '<mode> <object> <stage>\t<path>'
+    let (info, path) = match line.split_once('\t') {
+      Some(entry) => entry,
+      None => continue,
+    };
+    if !info.starts_with("100644 ") {
+      continue;
+    }
+    if fix {
+      let status = Command::new("git")
+        .args(["update-index", "--chmod=+x", "--", path])
+        .current_dir(&root)
+        .status()?;
+      if !status.success() {
+        bail!("Unable to mark {} as executable in git", path);
+      }
+      log_event("hooked", format!("Marked {} as executable in git", path));
+      info!("Marked {} as executable in git", path);
+    } else {
+      warn!(
+        "git doesn't have {} marked as executable, run 'hooked doctor \
+         --permissions' to fix it",
+        path
+      );
+      problems += 1;
+    }
+  }
+  Ok(problems)
+}
+
+/// Finds the user's script for a hook in the hooked directory regardless of
+/// which language it's written in
+fn hook_script(root: &Path, hook: &str) -> Result<PathBuf> {
+  let mut path = root.join(hook);
+  debug!("PATH: {}", path.display());
+
+  let mut path_python = path.clone();
+  let _ = path_python.set_extension("py");
+  let mut path_ruby = path.clone();
+  let _ = path_ruby.set_extension("rb");
+  let mut path_bash = path.clone();
+  let _ = path_bash.set_extension("sh");
+
+  if path_python.exists() {
+    Ok(path_python.canonicalize()?)
+  } else if path_ruby.exists() {
+    Ok(path_ruby.canonicalize()?)
+  } else if path_bash.exists() {
+    Ok(path_bash.canonicalize()?)
+  } else {
+    let _ = path.set_extension("");
+    bail!(
+      "The path {} does not exist. Have you initialized the repo to use hooked?",
+      path.display()
+    );
+  }
+}
+
+/// Writes the wrapper git calls for a hook. It hands off to `hooked run` so
+/// that the dev-suite environment variables are set before the user's script
+/// runs. The wrappers are always regenerated since they aren't meant to be
+/// edited.
+fn write_wrapper(wrapper_dir: &Path, hook: &str) -> Result<PathBuf> {
+  let mut wrapper_hook = wrapper_dir.join(hook);
+  let _ = wrapper_hook.set_extension("sh");
+  debug!("Writing wrapper {}.", wrapper_hook.display());
+  let mut wrapper = fs::File::create(&wrapper_hook)?;
+  #[cfg(not(windows))]
+  {
+    let mut wrapper_perms = wrapper.metadata()?.permissions();
+    wrapper_perms.set_mode(0o755);
+    wrapper.set_permissions(wrapper_perms)?;
+  }
+  wrapper.write_all(
+    format!("#!/bin/sh\nexec hooked run {} \"$@\"\n", hook).as_bytes(),
+  )?;
+  Ok(wrapper_hook)
+}
+
 fn inner_link(path: &Path, git_hook: &Path, hook: &str) -> Result<()> {
+  // Hooks linked by older versions of hooked point straight at the script
+  // rather than the wrapper so they need to be relinked
+  if let Ok(target) = fs::read_link(git_hook) {
+    if target != path {
+      debug!("Relinking git hook {}.", hook);
+      fs::remove_file(git_hook)?;
+    }
+  }
   if !git_hook.exists() {
     debug!("Symlinking git hook {}.", hook);
     #[cfg(not(windows))]
@@ -88,7 +88,7 @@ fn lang(lang: &str) -> Result<(), Box<dyn Error>> {
       _ => unreachable!(),
     }
     #[cfg(windows)]
-    assert_eq!(shebang, "#!C:\\Program Files\\Git\\bin\\sh.exe")
+    assert_eq!(shebang, "#!/bin/sh")
   }
   Ok(())
 }
@@ -107,3 +107,133 @@ fn init_python() -> Result<(), Box<dyn Error>> {
 fn init_ruby() -> Result<(), Box<dyn Error>> {
   lang("ruby")
 }
+
+#[cfg(not(windows))]
+#[test]
+fn run_sets_env() -> Result<(), Box<dyn Error>> {
+  let dir = tempdir()?;
+  let _ = Repository::init(&dir)?;
+  let _ = Command::cargo_bin("hooked")?
+    .arg("init")
+    .arg("bash")
+    .current_dir(&dir)
+    .assert()
+    .success();
+  let conf = dir.path().join("config").join("dev-suite");
+  fs::create_dir_all(&conf)?;
+  fs::write(
+    conf.join("user-config.toml"),
+    "name = 'Tester'\nuuid = 'a4ad1e12-7aa2-4c2b-a3ce-32b6d8c10a5d'\n",
+  )?;
+  fs::write(
+    dir.path().join(".dev-suite").join("hooked").join("pre-commit.sh"),
+    "#!/usr/bin/env bash\n\
+     echo \"$DEVSUITE_HOOK_NAME $DEVSUITE_USER_NAME $DEVSUITE_USER_UUID $1\" > out\n\
+     echo \"$DEVSUITE_ROOT\" >> out\n",
+  )?;
+  let _ = Command::cargo_bin("hooked")?
+    .args(["run", "pre-commit", "arg"])
+    .current_dir(&dir)
+    .env("XDG_CONFIG_HOME", dir.path().join("config"))
+    .assert()
+    .success();
+
+  let out = fs::read_to_string(dir.path().join("out"))?;
+  let mut lines = out.lines();
+  assert_eq!(
+    lines.next(),
+    Some("pre-commit Tester a4ad1e12-7aa2-4c2b-a3ce-32b6d8c10a5d arg")
+  );
+  assert_eq!(
+    lines.next().map(std::path::PathBuf::from),
+    Some(dir.path().canonicalize()?)
+  );
+  Ok(())
+}
+
+#[cfg(not(windows))]
+#[test]
+fn doctor_checks_deps() -> Result<(), Box<dyn Error>> {
+  let dir = tempdir()?;
+  let _ = Repository::init(&dir)?;
+  let _ = Command::cargo_bin("hooked")?
+    .arg("init")
+    .arg("bash")
+    .current_dir(&dir)
+    .assert()
+    .success();
+  // The wrappers call hooked so it has to be on the PATH for doctor to pass
+  let bin = std::path::Path::new(env!("CARGO_BIN_EXE_hooked"));
+  let path = std::env::join_paths(
+    bin
+      .parent()
+      .into_iter()
+      .map(std::path::Path::to_path_buf)
+      .chain(std::env::split_paths(
+        &std::env::var_os("PATH").unwrap_or_default(),
+      )),
+  )?;
+  let deps = dir
+    .path()
+    .join(".dev-suite")
+    .join("hooked")
+    .join("deps.toml");
+
+  fs::write(&deps, "[sh]\n")?;
+  let _ = Command::cargo_bin("hooked")?
+    .arg("doctor")
+    .current_dir(&dir)
+    .env("PATH", &path)
+    .assert()
+    .success();
+
+  fs::write(&deps, "[not-a-real-tool]\nhelp = 'https://example.com'\n")?;
+  let _ = Command::cargo_bin("hooked")?
+    .arg("doctor")
+    .current_dir(&dir)
+    .env("PATH", &path)
+    .assert()
+    .failure();
+  Ok(())
+}
+
+#[cfg(not(windows))]
+#[test]
+fn doctor_fixes_permissions() -> Result<(), Box<dyn Error>> {
+  let dir = tempdir()?;
+  let _ = Repository::init(&dir)?;
+  let _ = Command::cargo_bin("hooked")?
+    .arg("init")
+    .arg("bash")
+    .current_dir(&dir)
+    .assert()
+    .success();
+  let hook = dir
+    .path()
+    .join(".dev-suite")
+    .join("hooked")
+    .join("pre-commit.sh");
+  let _ = Command::new("git")
+    .args(["add", "--chmod=-x", ".dev-suite"])
+    .current_dir(&dir)
+    .status()?;
+  fs::set_permissions(&hook, fs::Permissions::from_mode(0o644))?;
+
+  let _ = Command::cargo_bin("hooked")?
+    .arg("doctor")
+    .current_dir(&dir)
+    .assert()
+    .failure();
+  let _ = Command::cargo_bin("hooked")?
+    .args(["doctor", "--permissions"])
+    .current_dir(&dir)
+    .assert();
+
+  assert_eq!(hook.metadata()?.permissions().mode() & 511, 0o755);
+  let staged = Command::new("git")
+    .args(["ls-files", "--stage", ".dev-suite/hooked/pre-commit.sh"])
+    .current_dir(&dir)
+    .output()?;
+  assert!(String::from_utf8(staged.stdout)?.starts_with("100755 "));
+  Ok(())
+}
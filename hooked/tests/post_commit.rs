@@ -0,0 +1,74 @@
+use assert_cmd::prelude::*;
+use std::{
+  error::Error,
+  fs,
+  path::Path,
+  process::{
+    Command,
+    Output,
+  },
+};
+use tempfile::tempdir;
+
+fn git(dir: &Path, args: &[&str]) -> Result<(), Box<dyn Error>> {
+  let status = Command::new("git")
+    .args(args)
+    .env("GIT_AUTHOR_NAME", "Tester")
+    .env("GIT_AUTHOR_EMAIL", "tester@example.com")
+    .env("GIT_COMMITTER_NAME", "Tester")
+    .env("GIT_COMMITTER_EMAIL", "tester@example.com")
+    .current_dir(dir)
+    .status()?;
+  if !status.success() {
+    return Err(format!("git {} failed", args.join(" ")).into());
+  }
+  Ok(())
+}
+
+/// Commits a new file without any hooks having run
+fn commit(dir: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+  fs::write(dir.join(name), name)?;
+  git(dir, &["add", name])?;
+  git(dir, &["commit", "-q", "--no-verify", "-m", name])
+}
+
+fn post_commit(dir: &Path) -> Result<Output, Box<dyn Error>> {
+  let output = Command::cargo_bin("hooked-post-commit")?
+    .current_dir(dir)
+    .output()?;
+  assert!(output.status.success());
+  Ok(output)
+}
+
+fn flagged(output: &Output) -> bool {
+  String::from_utf8_lossy(&output.stderr).contains("without running")
+}
+
+#[test]
+fn flags_commits_that_bypass_hooks() -> Result<(), Box<dyn Error>> {
+  let dir = tempdir()?;
+  let repo = dir.path().join("repo");
+  fs::create_dir(&repo)?;
+  git(&repo, &["init", "-q"])?;
+  commit(&repo, "first")?;
+  assert!(flagged(&post_commit(&repo)?));
+  let bypassed = repo.join(".git").join("hooked").join("bypassed");
+  assert_eq!(fs::read_to_string(&bypassed)?.lines().count(), 1);
+
+  // Merge commits are made from commits that were already checked
+  git(&repo, &["checkout", "-q", "-b", "side"])?;
+  commit(&repo, "side")?;
+  git(&repo, &["checkout", "-q", "-"])?;
+  commit(&repo, "main")?;
+  git(&repo, &["merge", "-q", "--no-edit", "--no-verify", "side"])?;
+  assert!(!flagged(&post_commit(&repo)?));
+
+  // Worktrees keep their git directory elsewhere, with .git as a file
+  let worktree = dir.path().join("worktree");
+  let path = worktree.to_string_lossy();
+  git(&repo, &["worktree", "add", "-q", "-b", "work", &path])?;
+  assert!(worktree.join(".git").is_file());
+  commit(&worktree, "work")?;
+  assert!(flagged(&post_commit(&worktree)?));
+  Ok(())
+}
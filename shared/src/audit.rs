@@ -0,0 +1,106 @@
+//! An append-only log of what the tools did in the repo, kept as JSON lines in
+//! `.dev-suite/log`. Everyone appends to a file of their own so that merging
+//! branches never conflicts over it.
+use crate::find_root;
+use anyhow::{
+  format_err,
+  Result,
+};
+use chrono::prelude::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+use std::{
+  fs::{
+    self,
+    OpenOptions,
+  },
+  io::Write,
+  path::PathBuf,
+};
+use uuid::Uuid;
+
+/// Something one of the tools did
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+  /// When it happened
+  pub at: DateTime<Utc>,
+  /// Which tool did it, e.g. `ticket`
+  pub tool: String,
+  /// Who ran the tool, if they have a user config
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub who: Option<Who>,
+  /// What happened, e.g. `Closed ticket 3fa2b1c`
+  pub action: String,
+}
+
+/// The user an entry was made by
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Who {
+  /// Their uuid from their user config
+  pub uuid: Uuid,
+  /// Their name from their user config
+  pub name: String,
+}
+
+/// Where the log lives
+pub fn log_dir() -> Result<PathBuf> {
+  Ok(find_root()?.join(".dev-suite").join("log"))
+}
+
+/// Appends an entry to the log. Nothing is written in repos that haven't been
+/// set up with dev-suite yet.
+pub fn record(
+  tool: &str,
+  who: Option<(Uuid, String)>,
+  action: impl Into<String>,
+) -> Result<()> {
+  let dir = log_dir()?;
+  if !dir.parent().is_some_and(|p| p.is_dir()) {
+    return Ok(());
+  }
+  fs::create_dir_all(&dir)?;
+  let file = match &who {
+    Some((uuid, _)) => uuid.to_string(),
+    None => "anonymous".into(),
+  };
+  let entry = Entry {
+    at: Utc::now(),
+    tool: tool.into(),
+    who: who.map(|(uuid, name)| Who { uuid, name }),
+    action: action.into(),
+  };
+  let mut log = OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(dir.join(file).with_extension("jsonl"))?;
+  // One write per entry so entries from tools running at once don't interleave
+  log.write_all(format!("{}\n", serde_json::to_string(&entry)?).as_bytes())?;
+  Ok(())
+}
+
+/// Every entry in the log from oldest to newest
+pub fn entries() -> Result<Vec<Entry>> {
+  let dir = log_dir()?;
+  let mut entries = Vec::new();
+  if !dir.exists() {
+    return Ok(entries);
+  }
+  for file in fs::read_dir(&dir)? {
+    let path = file?.path();
+    if path.extension().is_none_or(|ext| ext != "jsonl") {
+      continue;
+    }
+    for (i, line) in fs::read_to_string(&path)?.lines().enumerate() {
+      if line.trim().is_empty() {
+        continue;
+      }
+      entries.push(serde_json::from_str(line).map_err(|e| {
+        format_err!("Line {} of {} is invalid: {}", i + 1, path.display(), e)
+      })?);
+    }
+  }
+  entries.sort_by_key(|e: &Entry| e.at);
+  Ok(entries)
+}
@@ -1,32 +1,121 @@
 //! Common functionality needed in many of the tools
 
+pub mod audit;
+
 use anyhow::{
   bail,
+  format_err,
   Result,
 };
+use log::*;
 use std::{
   env,
-  path::PathBuf,
+  fs::{
+    self,
+    File,
+  },
+  io::Write,
+  path::{
+    Path,
+    PathBuf,
+  },
+  process,
+  sync::Once,
 };
 
+/// Environment variable used to override where the repo root is. It's set by
+/// the `--root` flag of each tool so that any tools they spawn use the same
+/// root as well.
+pub const ROOT_ENV: &str = "DEVSUITE_ROOT";
+
+/// Keeps `find_root` from warning about the same nested repo every time it's
+/// called
+static NESTED_WARNING: Once = Once::new();
+
+/// Force `find_root` to use the given directory as the repo root for this
+/// process and any child processes it spawns
+pub fn set_root(root: impl AsRef<Path>) -> Result<()> {
+  let root = root.as_ref();
+  if !root.is_dir() {
+    bail!("The root {} is not a directory", root.display());
+  }
+  env::set_var(ROOT_ENV, root.canonicalize()?);
+  Ok(())
+}
+
 /// Finds the top level folder of the repo and returns it's canonicalized path
+///
+/// The nearest ancestor containing a `.dev-suite` folder is preferred over the
+/// nearest git repo so that running a tool inside of a submodule or a nested
+/// repo still uses the dev-suite setup of the outer repo. If there is no
+/// `.dev-suite` folder at all the nearest git repo is used so that repos can
+/// be initialized.
 pub fn find_root() -> Result<PathBuf> {
-  let mut location = env::current_dir()?;
-  let mut found_root = false;
-
-  for loc in location.ancestors() {
-    let mut loc = loc.join(".git");
-    if loc.exists() {
-      let _ = loc.pop();
-      found_root = true;
-      location = loc.canonicalize()?;
-      break;
+  if let Some(root) = env::var_os(ROOT_ENV) {
+    let root = PathBuf::from(root);
+    if !root.is_dir() {
+      bail!(
+        "The root {} set by {} is not a directory",
+        root.display(),
+        ROOT_ENV
+      );
     }
+    return Ok(root.canonicalize()?);
   }
 
-  if found_root {
-    Ok(location)
-  } else {
-    bail!("Unable to find a valid git repo");
+  let location = env::current_dir()?;
+  let git_root = location.ancestors().find(|loc| loc.join(".git").exists());
+  let dev_suite_root = location
+    .ancestors()
+    .find(|loc| loc.join(".dev-suite").is_dir());
+
+  match (git_root, dev_suite_root) {
+    (Some(git), Some(dev_suite)) => {
+      if git != dev_suite {
+        NESTED_WARNING.call_once(|| {
+          warn!(
+            "Using {} as the dev-suite root but the nearest git repo is {}. \
+             Pass --root to choose a different one.",
+            dev_suite.display(),
+            git.display()
+          )
+        });
+      }
+      Ok(dev_suite.canonicalize()?)
+    }
+    (Some(root), None) | (None, Some(root)) => Ok(root.canonicalize()?),
+    (None, None) => bail!("Unable to find a valid git repo"),
+  }
+}
+
+/// Writes the file so that it's either all there or not changed at all, even
+/// if the process or machine dies partway through. The contents go to a
+/// hidden temporary file next to it that's synced to disk then renamed over
+/// the file, and the directory is synced so the rename is kept too.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+  let name = match path.file_name() {
+    Some(name) => name,
+    None => bail!("{} isn't a path to a file", path.display()),
+  };
+  let dir = match path.parent() {
+    Some(dir) if !dir.as_os_str().is_empty() => dir,
+    _ => Path::new("."),
+  };
+  let tmp =
+    dir.join(format!(".{}.tmp-{}", name.to_string_lossy(), process::id()));
+  let written = File::create(&tmp)
+    .and_then(|mut file| {
+      file.write_all(contents.as_ref())?;
+      file.sync_all()
+    })
+    .and_then(|_| fs::rename(&tmp, path));
+  if let Err(e) = written {
+    let _ = fs::remove_file(&tmp);
+    return Err(format_err!("Unable to write {}: {}", path.display(), e));
   }
+  // Directories can't be opened to sync them on Windows, where the rename is
+  // durable once it returns anyway
+  #[cfg(not(windows))]
+  File::open(dir)?.sync_all()?;
+  Ok(())
 }
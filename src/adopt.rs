@@ -0,0 +1,392 @@
+//! `ds config repo adopt` looks at how an existing repo already does things
+//! and proposes a dev-suite setup that matches it
+use crate::set_up;
+use anyhow::{
+  bail,
+  format_err,
+  Result,
+};
+use configamajig::{
+  get_user_config,
+  log_event,
+  Template,
+};
+use dialoguer::{
+  theme::ColorfulTheme,
+  Confirmation,
+};
+use shared::find_root;
+use std::{
+  collections::BTreeMap,
+  fs,
+  path::{
+    Path,
+    PathBuf,
+  },
+  process::Command,
+};
+
+/// Commands from a contributing guide that are worth running before every
+/// commit
+const CHECKS: [&str; 16] = [
+  "cargo test",
+  "cargo fmt",
+  "cargo clippy",
+  "npm test",
+  "npm run lint",
+  "yarn test",
+  "yarn lint",
+  "make test",
+  "make lint",
+  "make check",
+  "pytest",
+  "tox",
+  "go test",
+  "go vet",
+  "bundle exec rspec",
+  "bundle exec rubocop",
+];
+
+/// The hooks that hooked manages
+const HOOKS: [&str; 18] = [
+  "applypatch-msg",
+  "post-applypatch",
+  "pre-commit",
+  "prepare-commit-msg",
+  "commit-msg",
+  "post-commit",
+  "pre-rebase",
+  "post-checkout",
+  "post-merge",
+  "pre-push",
+  "pre-receive",
+  "update",
+  "post-receive",
+  "post-update",
+  "push-to-checkout",
+  "pre-auto-gc",
+  "post-rewrite",
+  "sendemail-validate",
+];
+
+/// What dev-suite would be set up with
+struct Proposal {
+  /// The language to write hooks in
+  hook_language: String,
+  /// The contents of each hook keyed by its name
+  hooks: BTreeMap<String, String>,
+  /// Hooks in .git/hooks that are moved out of the way for hooked's
+  existing_hooks: Vec<PathBuf>,
+  /// Issue templates to turn into ticket templates keyed by their name
+  templates: BTreeMap<String, String>,
+  /// The people with the most commits along with how many they made
+  authors: Vec<(usize, String)>,
+}
+
+pub fn adopt(yes: bool) -> Result<()> {
+  let root = find_root()?;
+  if root.join(".dev-suite").join("repo-config.toml").exists() {
+    bail!("This repo already uses dev-suite");
+  }
+  // Check before anything is moved around rather than failing halfway through
+  let _ = get_user_config().map_err(|_| {
+    format_err!(
+      "It looks like this is your first time using dev-suite. Initialize your \
+       config with 'ds config user init <name>' then rerun 'ds config repo \
+       adopt'."
+    )
+  })?;
+
+  let proposal = propose(&root)?;
+  print_proposal(&proposal);
+  if !yes
+    && !Confirmation::with_theme(&ColorfulTheme::default())
+      .with_text("Set up dev-suite like this?")
+      .interact()?
+  {
+    println!("Nothing was changed.");
+    return Ok(());
+  }
+
+  // hooked won't link over hooks that are already in .git/hooks so they're
+  // kept next to it instead
+  // Anything already in .dev-suite isn't adopt's to remove when rolling back
+  let fresh = !root.join(".dev-suite").exists();
+  let mut moved = Vec::new();
+  for path in &proposal.existing_hooks {
+    let backup = path.with_extension("pre-dev-suite");
+    if let Err(e) = fs::rename(path, &backup) {
+      roll_back(&root, &moved, fresh);
+      return Err(e.into());
+    }
+    moved.push((path.clone(), backup));
+  }
+
+  let template = Template {
+    tools: vec!["hooked".into(), "ticket".into()],
+    hook_language: Some(proposal.hook_language),
+    hooks: proposal.hooks,
+  };
+  let set_up = write_templates(&root, &proposal.templates)
+    .and_then(|_| set_up(Some(template)));
+  if let Err(e) = set_up {
+    roll_back(&root, &moved, fresh);
+    return Err(e);
+  }
+  for (path, backup) in &moved {
+    println!("Moved {} to {}", path.display(), backup.display());
+  }
+  log_event(
+    "ds",
+    format!(
+      "Adopted {} existing hook(s) and {} issue template(s)",
+      proposal.existing_hooks.len(),
+      proposal.templates.len()
+    ),
+  );
+
+  if !proposal.authors.is_empty() {
+    println!(
+      "You were added as a maintainer. Ask the other maintainers to run `ds \
+       config repo add me` so they're added with their own user config."
+    );
+  }
+  Ok(())
+}
+
+/// Turns the issue templates into ticket templates
+fn write_templates(
+  root: &Path,
+  templates: &BTreeMap<String, String>,
+) -> Result<()> {
+  let templates_dir = root.join(".dev-suite").join("ticket").join("templates");
+  if !templates.is_empty() {
+    fs::create_dir_all(&templates_dir)?;
+  }
+  for (name, contents) in templates {
+    fs::write(templates_dir.join(name).with_extension("md"), contents)?;
+  }
+  Ok(())
+}
+
+/// Puts the repo back how it was when adopting fails partway through, so the
+/// existing hooks aren't lost and adopt can be run again
+fn roll_back(root: &Path, moved: &[(PathBuf, PathBuf)], fresh: bool) {
+  // Whatever hooked linked in their place is replaced by the originals
+  for (path, backup) in moved {
+    if let Err(e) = fs::rename(backup, path) {
+      eprintln!(
+        "Unable to move {} back to {}: {}",
+        backup.display(),
+        path.display(),
+        e
+      );
+    }
+  }
+  let dev_suite = root.join(".dev-suite");
+  if fresh && dev_suite.exists() {
+    if let Err(e) = fs::remove_dir_all(&dev_suite) {
+      eprintln!("Unable to remove {}: {}", dev_suite.display(), e);
+    }
+  }
+}
+
+fn propose(root: &Path) -> Result<Proposal> {
+  let mut hooks = BTreeMap::new();
+  let mut existing_hooks = Vec::new();
+  let mut languages = BTreeMap::new();
+  let git_hooks = root.join(".git").join("hooks");
+  if git_hooks.exists() {
+    for entry in fs::read_dir(&git_hooks)? {
+      let path = entry?.path();
+      // Symlinks are likely from another hooks manager and samples are only
+      // examples from git
+      let is_link = fs::symlink_metadata(&path)?.file_type().is_symlink();
+      if is_link
+        || !path.is_file()
+        || path.extension().is_some_and(|e| e == "sample")
+      {
+        continue;
+      }
+      let name = match path.file_name().and_then(|n| n.to_str()) {
+        Some(name) if HOOKS.contains(&name) => name.to_owned(),
+        _ => continue,
+      };
+      let contents = fs::read_to_string(&path)
+        .map_err(|e| format_err!("Unable to read {}: {}", path.display(), e))?;
+      *languages.entry(language(&contents)).or_insert(0) += 1;
+      let _ = hooks.insert(name, contents);
+      existing_hooks.push(path);
+    }
+  }
+  // Go with whatever most of the hooks are already written in
+  let hook_language = languages
+    .into_iter()
+    .max_by_key(|(_, count)| *count)
+    .map_or("bash", |(lang, _)| lang)
+    .to_owned();
+
+  if !hooks.contains_key("pre-commit") && hook_language == "bash" {
+    let checks = contributing_checks(root)?;
+    if !checks.is_empty() {
+      let _ = hooks.insert(
+        "pre-commit".into(),
+        format!("#!/usr/bin/env bash\nset -e\n{}\n", checks.join("\n")),
+      );
+    }
+  }
+
+  Ok(Proposal {
+    hook_language,
+    hooks,
+    existing_hooks,
+    templates: issue_templates(root)?,
+    authors: authors(root)?,
+  })
+}
+
+/// Which of hooked's languages a script is written in from its shebang
+fn language(script: &str) -> &'static str {
+  let shebang = script.lines().next().unwrap_or("");
+  if shebang.contains("python") {
+    "python"
+  } else if shebang.contains("ruby") {
+    "ruby"
+  } else {
+    "bash"
+  }
+}
+
+/// The commands from `CHECKS` that the contributing guide asks people to run
+fn contributing_checks(root: &Path) -> Result<Vec<String>> {
+  let guide = ["CONTRIBUTING.md", ".github/CONTRIBUTING.md", "CONTRIBUTING"]
+    .iter()
+    .map(|name| root.join(name))
+    .find(|path| path.exists());
+  let guide = match guide {
+    Some(guide) => fs::read_to_string(guide)?,
+    None => return Ok(Vec::new()),
+  };
+
+  let mut checks = Vec::new();
+  let mut in_code = false;
+  for line in guide.lines() {
+    let line = line.trim();
+    if line.starts_with("```") {
+      in_code = !in_code;
+      continue;
+    }
+    let command = line.trim_start_matches("$ ");
+    if in_code
+      && CHECKS.iter().any(|check| command.starts_with(check))
+      && !checks.iter().any(|c| c == command)
+    {
+      checks.push(command.to_owned());
+    }
+  }
+  Ok(checks)
+}
+
+/// Issue templates from GitHub or GitLab keyed by their name, without any
+/// front matter
+fn issue_templates(root: &Path) -> Result<BTreeMap<String, String>> {
+  let mut templates = BTreeMap::new();
+  let single = root.join(".github").join("ISSUE_TEMPLATE.md");
+  if single.exists() {
+    let _ = templates.insert("issue".into(), fs::read_to_string(single)?);
+  }
+  for dir in &[
+    root.join(".github").join("ISSUE_TEMPLATE"),
+    root.join(".gitlab").join("issue_templates"),
+  ] {
+    if !dir.exists() {
+      continue;
+    }
+    for entry in fs::read_dir(dir)? {
+      let path = entry?.path();
+      if path.extension().is_none_or(|e| e != "md") {
+        continue;
+      }
+      if let Some(name) = path.file_stem().and_then(|n| n.to_str()) {
+        let _ =
+          templates.insert(name.to_lowercase(), fs::read_to_string(&path)?);
+      }
+    }
+  }
+
+  for contents in templates.values_mut() {
+    if let Some(rest) = contents.strip_prefix("---") {
+      if let Some(end) = rest.find("\n---") {
+        *contents = rest[end + 4..].trim_start().to_owned();
+      }
+    }
+  }
+  Ok(templates)
+}
+
+/// The five people with the most commits in the repo's history
+fn authors(root: &Path) -> Result<Vec<(usize, String)>> {
+  let output = Command::new("git")
+    .args(["shortlog", "-sne", "HEAD"])
+    .current_dir(root)
+    .output()?;
+  // A repo without any commits yet has no history to go off of
+  if !output.status.success() {
+    return Ok(Vec::new());
+  }
+  Ok(
+    String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .filter_map(|line| {
+        let (count, name) = line.trim().split_once('\t')?;
+        Some((count.parse().ok()?, name.to_owned()))
+      })
+      .take(5)
+      .collect(),
+  )
+}
+
+fn print_proposal(proposal: &Proposal) {
+  println!("dev-suite will be set up with hooked and ticket.\n");
+  println!("Hooks will be written in {}.", proposal.hook_language);
+  for path in &proposal.existing_hooks {
+    println!("  - {} will be moved into hooked", path.display());
+  }
+  if let Some(pre_commit) = proposal.hooks.get("pre-commit") {
+    if !proposal
+      .existing_hooks
+      .iter()
+      .any(|p| p.ends_with("pre-commit"))
+    {
+      println!(
+        "  - pre-commit will run the checks from the contributing guide:"
+      );
+      for line in pre_commit.lines().skip(2) {
+        println!("      {}", line);
+      }
+    }
+  }
+
+  if !proposal.templates.is_empty() {
+    println!("\nThese issue templates will become ticket templates:");
+    for name in proposal.templates.keys() {
+      println!("  - {}", name);
+    }
+  }
+
+  if !proposal.authors.is_empty() {
+    println!(
+      "\nYou'll be added as a maintainer. These people have made the most \
+       commits and might want to be maintainers too:"
+    );
+    for (count, name) in &proposal.authors {
+      println!(
+        "  - {} ({} commit{})",
+        name,
+        count,
+        if *count == 1 { "" } else { "s" }
+      );
+    }
+  }
+  println!();
+}
@@ -1,36 +1,93 @@
 //! dev-suite cli tool to install and update devsuite and it's tooling
+mod adopt;
+mod release;
+#[cfg(windows)]
+mod win_path;
+
 use anyhow::{
+  bail,
   format_err,
   Result,
 };
+use chrono::prelude::*;
 use configamajig::*;
 use dialoguer::{
   theme::ColorfulTheme,
   Checkboxes,
   Select,
 };
-use shared::find_root;
+use sha2::{
+  Digest,
+  Sha256,
+};
+use shared::{
+  audit,
+  find_root,
+  set_root,
+};
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::OpenOptionsExt;
-#[cfg(target_os = "macos")]
-use std::path::PathBuf;
 use std::{
+  env,
   fs::{
+    self,
     create_dir_all,
     OpenOptions,
   },
+  io::Write,
+  path::PathBuf,
   process::Command,
 };
 use which::which;
 
 #[derive(structopt::StructOpt)]
-enum Args {
+struct Args {
+  /// Use this directory as the repo root instead of searching for it
+  #[structopt(long, global = true, parse(from_os_str))]
+  root: Option<PathBuf>,
+  #[structopt(subcommand)]
+  cmd: Cmd,
+}
+
+#[derive(structopt::StructOpt)]
+enum Cmd {
   /// Download and install all of dev-suite
   Install,
   /// Initialize the repo to use dev-suite and it's tools
-  Init,
+  Init {
+    /// Set the repo up using a template from your user config rather than
+    /// being asked which tools to use
+    #[structopt(long)]
+    template: Option<String>,
+  },
   /// Commands for configuration of dev-suite
   Config(Config),
+  /// Bump the version of every crate, add the tickets closed since the last
+  /// release to the changelog, tag it, and build the binaries for this
+  /// platform into dist
+  Release {
+    /// The version to release, e.g. 0.2.0
+    version: String,
+    /// Only bump, update the changelog, and tag without building anything
+    #[structopt(long)]
+    no_build: bool,
+    /// A command to upload the built binaries with. The directory they're in
+    /// is set as DS_RELEASE_DIR.
+    #[structopt(long)]
+    upload: Option<String>,
+  },
+  /// Show what the tools have done in this repo, oldest first
+  Log {
+    /// Only show what one tool did, e.g. ticket
+    #[structopt(long)]
+    tool: Option<String>,
+    /// Only show what someone did, by their name or uuid
+    #[structopt(long)]
+    by: Option<String>,
+    /// Only show what happened on or after a date, e.g. 2024-07-01
+    #[structopt(long)]
+    since: Option<NaiveDate>,
+  },
 }
 
 #[derive(structopt::StructOpt)]
@@ -45,7 +102,17 @@ enum Config {
 #[derive(structopt::StructOpt)]
 enum User {
   /// Initialize the user with a name
-  Init { name: String },
+  Init {
+    name: String,
+    /// Derive your uuid from this email address so it's the same on every
+    /// machine
+    #[structopt(long, conflicts_with = "identity-command")]
+    email: Option<String>,
+    /// Get your uuid from a command that prints it or a verified email
+    /// address, e.g. one that asks your company's SSO
+    #[structopt(long)]
+    identity_command: Option<String>,
+  },
   /// Show the current user
   Show,
 }
@@ -58,6 +125,13 @@ enum Repo {
   Show,
   /// Add someone as a maintainer
   Add(Add),
+  /// Set up dev-suite in an existing repo based on the hooks, contributing
+  /// guide, issue templates, and history it already has
+  Adopt {
+    /// Apply the proposed setup without asking first
+    #[structopt(long)]
+    yes: bool,
+  },
 }
 #[derive(structopt::StructOpt)]
 enum Add {
@@ -67,20 +141,47 @@ enum Add {
 
 #[paw::main]
 fn main(args: Args) {
-  if let Err(e) = match args {
-    Args::Init => init(),
-    Args::Install => install(),
-    Args::Config(conf) => match conf {
+  env::var("RUST_LOG")
+    .ok()
+    .map_or_else(|| env::set_var("RUST_LOG", "warn"), drop);
+  pretty_env_logger::init();
+  if let Err(e) = args.root.map_or(Ok(()), set_root) {
+    eprintln!("{}", e);
+    std::process::exit(1);
+  }
+  if let Err(e) = match args.cmd {
+    Cmd::Init { template } => init(template),
+    Cmd::Install => install(),
+    Cmd::Release {
+      version,
+      no_build,
+      upload,
+    } => release::release(&version, no_build, upload),
+    Cmd::Log { tool, by, since } => log(tool, by, since),
+    Cmd::Config(conf) => match conf {
       Config::User(user) => match user {
-        User::Init { name } => create_user_config(name),
+        User::Init {
+          name,
+          email,
+          identity_command,
+        } => create_user_config(
+          name,
+          match (email, identity_command) {
+            (Some(email), _) => Identity::Email { email },
+            (_, Some(command)) => Identity::Command { command },
+            (None, None) => Identity::Local,
+          },
+        ),
         User::Show => show_user_config(),
       },
       Config::Repo(repo) => match repo {
         Repo::Init => create_repo_config(),
         Repo::Show => show_repo_config(),
         Repo::Add(add) => match add {
-          Add::Me => add_self_to_maintainers(),
+          Add::Me => add_self_to_maintainers()
+            .map(|()| log_event("ds", "Added themselves as a maintainer")),
         },
+        Repo::Adopt { yes } => adopt::adopt(yes),
       },
     },
   } {
@@ -90,23 +191,57 @@ fn main(args: Args) {
 }
 
 /// Initialize a git repo with all the tools wanted for it
-fn init() -> Result<()> {
+fn init(template: Option<String>) -> Result<()> {
+  let template = match template {
+    Some(name) => {
+      let mut templates = get_user_config()?.templates;
+      let names = templates.keys().cloned().collect::<Vec<String>>();
+      Some(templates.remove(&name).ok_or_else(|| {
+        format_err!(
+          "There is no template named '{}' in your user config. The \
+           templates available are: {}",
+          name,
+          names.join(", ")
+        )
+      })?)
+    }
+    None => None,
+  };
+  set_up(template)
+}
+
+/// Enables the tools from the template in the repo, or the ones picked from a
+/// prompt if there isn't a template
+fn set_up(template: Option<Template>) -> Result<()> {
   // Make sure we're in a valid git repo
-  let _ = find_root()?;
-  let checkboxes = &["hooked - Managed git hooks", "ticket - In repo tickets"];
-  let defaults = &[true, true];
-  let selections = Checkboxes::with_theme(&ColorfulTheme::default())
-    .with_prompt("Which tools do you want to enable? (defaults to all)")
-    .items(&checkboxes[..])
-    .defaults(&defaults[..])
-    .interact()?
-    .into_iter()
-    .map(|s| match s {
-      0 => Tools::Hooked,
-      1 => Tools::Ticket,
-      _ => unreachable!(),
-    })
-    .collect::<Vec<Tools>>();
+  let root = find_root()?;
+  let selections = if let Some(template) = &template {
+    template
+      .tools
+      .iter()
+      .map(|tool| match tool.as_str() {
+        "hooked" => Ok(Tools::Hooked),
+        "ticket" => Ok(Tools::Ticket),
+        _ => Err(format_err!("The template has an unknown tool '{}'", tool)),
+      })
+      .collect::<Result<Vec<Tools>>>()?
+  } else {
+    let checkboxes =
+      &["hooked - Managed git hooks", "ticket - In repo tickets"];
+    let defaults = &[true, true];
+    Checkboxes::with_theme(&ColorfulTheme::default())
+      .with_prompt("Which tools do you want to enable? (defaults to all)")
+      .items(&checkboxes[..])
+      .defaults(&defaults[..])
+      .interact()?
+      .into_iter()
+      .map(|s| match s {
+        0 => Tools::Hooked,
+        1 => Tools::Ticket,
+        _ => unreachable!(),
+      })
+      .collect::<Vec<Tools>>()
+  };
 
   if selections.is_empty() {
     println!("Nothing selected. dev-suite not enabled in this repository.");
@@ -127,21 +262,52 @@ fn init() -> Result<()> {
               "It looks like hooked is not on your $PATH. Did you run 'ds install'?"
             ))?;
 
-          let langs = &["Python", "Ruby", "Bash"];
-          let mut lang_choice =
-            langs[Select::with_theme(&ColorfulTheme::default())
-              .with_prompt(
-                "Which language do you wish to use for your git hooks?",
-              )
-              .items(langs)
-              .interact()?]
-            .to_string();
-          lang_choice.make_ascii_lowercase();
-          let _ = Command::new("hooked")
+          let lang_choice =
+            match template.as_ref().and_then(|t| t.hook_language.as_ref()) {
+              Some(lang) => lang.to_lowercase(),
+              None => {
+                let langs = &["Python", "Ruby", "Bash"];
+                let mut lang_choice =
+                  langs[Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt(
+                      "Which language do you wish to use for your git hooks?",
+                    )
+                    .items(langs)
+                    .interact()?]
+                  .to_string();
+                lang_choice.make_ascii_lowercase();
+                lang_choice
+              }
+            };
+          let status = Command::new("hooked")
             .arg("init")
             .arg(&lang_choice)
-            .spawn()?
-            .wait()?;
+            .status()?;
+          if !status.success() {
+            bail!("'hooked init {}' failed with {}", lang_choice, status);
+          }
+
+          if let Some(template) = &template {
+            let extension = match lang_choice.as_str() {
+              "python" => "py",
+              "ruby" => "rb",
+              _ => "sh",
+            };
+            for (hook, contents) in &template.hooks {
+              let path = root
+                .join(".dev-suite")
+                .join("hooked")
+                .join(hook)
+                .with_extension(extension);
+              if !path.exists() {
+                return Err(format_err!(
+                  "The template has a hook for '{}' which isn't a git hook",
+                  hook
+                ));
+              }
+              fs::write(path, contents)?;
+            }
+          }
         }
         Tools::Ticket => {
           which("ticket")
@@ -149,10 +315,51 @@ fn init() -> Result<()> {
             .map_err(|_| format_err!(
               "It looks like ticket is not on your $PATH. Did you run 'ds install'?"
             ))?;
-          let _ = Command::new("ticket").arg("init").spawn()?.wait()?;
+          let status = Command::new("ticket").arg("init").status()?;
+          if !status.success() {
+            bail!("'ticket init' failed with {}", status);
+          }
         }
       }
     }
+    log_event("ds", "Set up dev-suite");
+  }
+  Ok(())
+}
+
+/// Prints the audit log, filtered down to what was asked for
+fn log(
+  tool: Option<String>,
+  by: Option<String>,
+  since: Option<NaiveDate>,
+) -> Result<()> {
+  let since = since
+    .and_then(|date| {
+      Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+    })
+    .map(|date| date.with_timezone(&Utc));
+  for entry in audit::entries()? {
+    if tool.as_ref().is_some_and(|tool| *tool != entry.tool)
+      || since.is_some_and(|since| entry.at < since)
+    {
+      continue;
+    }
+    if let Some(by) = &by {
+      if !entry.who.as_ref().is_some_and(|who| {
+        who.name.eq_ignore_ascii_case(by) || who.uuid.to_string() == *by
+      }) {
+        continue;
+      }
+    }
+    println!(
+      "{}  {:<6}  {}  {}",
+      entry.at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+      entry.tool,
+      entry.who.map_or_else(|| "Someone".into(), |who| who.name),
+      entry.action
+    );
   }
   Ok(())
 }
@@ -187,6 +394,19 @@ fn install() -> Result<()> {
   let client = reqwest::blocking::Client::new();
   create_dir_all(&location)?;
 
+  // Releases made with `ds release` come with checksums for every binary
+  let checksums = client
+    .get(&(BASE_URL.to_owned() + release::CHECKSUMS))
+    .send()
+    .ok()
+    .filter(|response| response.status().is_success())
+    .and_then(|response| response.text().ok());
+  if checksums.is_none() {
+    println!(
+      "warning: no checksums were published, downloads can't be verified"
+    );
+  }
+
   for tool in &TOOLS {
     location.push(tool);
     if location.exists() {
@@ -195,7 +415,23 @@ fn install() -> Result<()> {
     } else {
       println!("Installing {}", tool);
       let url = BASE_URL.to_owned() + tool;
-      let mut program = client.get(&url).send()?;
+      let program = client.get(&url).send()?.error_for_status()?.bytes()?;
+      if let Some(checksums) = &checksums {
+        let expected = checksums
+          .lines()
+          .filter_map(|line| line.split_once("  "))
+          .find(|(_, name)| name.trim_end_matches(".exe") == *tool)
+          .map(|(hash, _)| hash)
+          .ok_or_else(|| {
+            format_err!("No checksum was published for {}", tool)
+          })?;
+        if format!("{:x}", Sha256::digest(&program)) != expected {
+          return Err(format_err!(
+            "The download of {} doesn't match its checksum, not installing it",
+            tool
+          ));
+        }
+      }
 
       #[cfg(target_family = "unix")]
       let mut file = OpenOptions::new()
@@ -211,7 +447,7 @@ fn install() -> Result<()> {
           .write(true)
           .open(&location)?
       };
-      let _ = program.copy_to(&mut file)?;
+      file.write_all(&program)?;
       let _ = location.pop();
     }
   }
@@ -219,12 +455,12 @@ fn install() -> Result<()> {
   // We need to add this to the PATH for the local user
   #[cfg(target_os = "windows")]
   {
-    println!("Adding {} to your %PATH%", location.display());
-    let mut command = std::ffi::OsStr::new("setx PATH ").to_os_string();
-    command.push(location.into_os_string());
-    command.push(";%PATH%");
-    let _ = Command::new("cmd").arg("/C").arg(command).output()?;
-    println!("You'll need to restart your computer for the %PATH% changes to take effect");
+    if win_path::add_to_path(&location)? {
+      println!("Added {} to your %PATH%", location.display());
+      println!("Open a new terminal for the %PATH% changes to take effect");
+    } else {
+      println!("{} is already on your %PATH%", location.display());
+    }
   }
   println!("Installation complete");
 
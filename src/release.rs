@@ -0,0 +1,282 @@
+//! `ds release` cuts a release of dev-suite: it bumps the version of every
+//! crate, adds the tickets closed since the last release to the changelog,
+//! tags the release, and builds the binaries along with the checksums that
+//! `ds install` checks downloads against
+use anyhow::{
+  bail,
+  format_err,
+  Result,
+};
+use chrono::{
+  DateTime,
+  FixedOffset,
+};
+use configamajig::log_event;
+use sha2::{
+  Digest,
+  Sha256,
+};
+use shared::{
+  audit,
+  find_root,
+};
+use std::{
+  fs,
+  path::{
+    Path,
+    PathBuf,
+  },
+  process::Command,
+};
+use ticket_core::actions::{
+  closed_tickets,
+  get_archived_tickets,
+  get_closed_tickets,
+};
+
+/// The crates in the workspace whose versions are bumped together
+const CRATES: [&str; 6] = [
+  ".",
+  "configamajig",
+  "shared",
+  "ticket",
+  "ticket-core",
+  "hooked",
+];
+
+/// The binaries that are built and uploaded for each platform
+const BINARIES: [&str; 3] = ["ds", "hooked", "ticket"];
+
+/// The name of the checksum manifest uploaded next to the binaries
+pub const CHECKSUMS: &str = "sha256sums.txt";
+
+pub fn release(
+  version: &str,
+  no_build: bool,
+  upload: Option<String>,
+) -> Result<()> {
+  let parts = version.split('.').collect::<Vec<&str>>();
+  if parts.len() != 3 || parts.iter().any(|p| p.parse::<u64>().is_err()) {
+    bail!("'{}' is not a version, expected one like 0.2.0", version);
+  }
+  let root = find_root()?;
+  let tag = format!("v{}", version);
+  if !git(&root, &["status", "--porcelain"])?.is_empty() {
+    bail!("Commit or stash your changes before making a release");
+  }
+  if git(&root, &["tag", "--list", &tag])? == tag {
+    bail!("There is already a release tagged {}", tag);
+  }
+
+  // Everything is worked out before anything is written so that a manifest
+  // or ticket that can't be read doesn't leave the release half made
+  let mut files = CRATES
+    .iter()
+    .map(|krate| {
+      let manifest = root.join(krate).join("Cargo.toml");
+      let bumped = bump(&manifest, version)?;
+      Ok((manifest, bumped))
+    })
+    .collect::<Result<Vec<_>>>()?;
+  let previous = git(&root, &["describe", "--tags", "--abbrev=0"]).ok();
+  files.push((
+    root.join("CHANGELOG.md"),
+    changelog(&root, &tag, previous.as_deref())?,
+  ));
+  let lock = root.join("Cargo.lock");
+  let backup = files
+    .iter()
+    .map(|(path, _)| path)
+    .chain(Some(&lock))
+    .map(|path| (path.clone(), fs::read(path).ok()))
+    .collect::<Vec<_>>();
+  if let Err(e) = commit(&root, &tag, &files) {
+    for (path, contents) in backup {
+      let _ = match contents {
+        Some(contents) => fs::write(&path, contents),
+        None => fs::remove_file(&path),
+      };
+    }
+    let _ = git(&root, &["reset", "-q"]);
+    return Err(e);
+  }
+  let _ = git(
+    &root,
+    &["tag", "-a", &tag, "-m", &format!("Release {}", tag)],
+  )?;
+  println!("Tagged {}. Push it with 'git push --follow-tags'.", tag);
+
+  if no_build {
+    return Ok(());
+  }
+  let dist = build(&root)?;
+  if let Some(upload) = upload {
+    println!("Uploading {}", dist.display());
+    #[cfg(not(windows))]
+    let mut command = Command::new("sh");
+    #[cfg(not(windows))]
+    let _ = command.arg("-c");
+    #[cfg(windows)]
+    let mut command = Command::new("cmd");
+    #[cfg(windows)]
+    let _ = command.arg("/C");
+    let status = command
+      .arg(&upload)
+      .env("DS_RELEASE_DIR", &dist)
+      .current_dir(&root)
+      .status()?;
+    if !status.success() {
+      bail!("The upload command failed");
+    }
+  } else {
+    println!(
+      "Upload the contents of {} to the release bucket to publish it.",
+      dist.display()
+    );
+  }
+  Ok(())
+}
+
+/// Writes out the bumped manifests and changelog, updates Cargo.lock to
+/// match, and commits them all
+fn commit(root: &Path, tag: &str, files: &[(PathBuf, String)]) -> Result<()> {
+  for (path, contents) in files {
+    fs::write(path, contents)?;
+  }
+  let status = Command::new("cargo")
+    .args(["update", "--workspace"])
+    .current_dir(root)
+    .status()?;
+  if !status.success() {
+    bail!("Updating Cargo.lock to the new version failed");
+  }
+  let mut paths = files
+    .iter()
+    .map(|(path, _)| path.to_string_lossy().into_owned())
+    .collect::<Vec<_>>();
+  // Cargo.lock is only committed in repos that don't ignore it
+  if git(root, &["check-ignore", "-q", "Cargo.lock"]).is_err() {
+    paths.push("Cargo.lock".into());
+  }
+  let mut add = vec!["add", "--"];
+  add.extend(paths.iter().map(String::as_str));
+  let _ = git(root, &add)?;
+  // The log entry goes in the release commit so the tree stays clean
+  log_event("ds", format!("Released {}", tag));
+  if audit::log_dir()?.exists() {
+    let _ = git(root, &["add", "--", ".dev-suite/log"])?;
+  }
+  let _ = git(root, &["commit", "-m", &format!("Release {}", tag)])?;
+  Ok(())
+}
+
+/// The manifest with the version in its [package] section set to `version`
+fn bump(manifest: &Path, version: &str) -> Result<String> {
+  let contents = fs::read_to_string(manifest)?;
+  let mut in_package = false;
+  let mut bumped = false;
+  let mut out = String::new();
+  for line in contents.lines() {
+    if line.starts_with('[') {
+      in_package = line.trim() == "[package]";
+    }
+    if in_package && !bumped && line.starts_with("version = ") {
+      out.push_str(&format!("version = \"{}\"\n", version));
+      bumped = true;
+    } else {
+      out.push_str(line);
+      out.push('\n');
+    }
+  }
+  if !bumped {
+    bail!("{} doesn't have a package version", manifest.display());
+  }
+  Ok(out)
+}
+
+/// The changelog with a section for the release added to the top listing the
+/// tickets closed since the previous release
+fn changelog(root: &Path, tag: &str, previous: Option<&str>) -> Result<String> {
+  let since = match previous {
+    Some(previous) => Some(
+      git(root, &["log", "-1", "--format=%cI", previous])?
+        .parse::<DateTime<FixedOffset>>()?,
+    ),
+    None => None,
+  };
+
+  let mut closed = Vec::new();
+  if closed_tickets()?.exists() {
+    for ticket in get_closed_tickets()?
+      .into_iter()
+      .chain(get_archived_tickets()?)
+    {
+      // Tickets closed before it was recorded when they were closed can only
+      // be placed in the first release
+      let new = match (since, ticket.closed_at) {
+        (None, _) => true,
+        (Some(since), Some(at)) => at > since,
+        (Some(_), None) => false,
+      };
+      if new {
+        closed.push(ticket.title);
+      }
+    }
+  }
+  closed.sort();
+
+  let mut section = format!("# {}\n\n", tag);
+  if closed.is_empty() {
+    section.push_str("No tickets were closed in this release.\n\n");
+  } else {
+    section.push_str("## Closed Tickets\n\n");
+    for title in closed {
+      section.push_str(&format!("- {}\n", title));
+    }
+    section.push('\n');
+  }
+  let old = fs::read_to_string(root.join("CHANGELOG.md")).unwrap_or_default();
+  Ok(section + &old)
+}
+
+/// Builds the release binaries for this platform into `dist/<os>` along with
+/// the checksum manifest for them
+fn build(root: &Path) -> Result<PathBuf> {
+  let status = Command::new("cargo")
+    .args(["build", "--release", "--workspace"])
+    .current_dir(root)
+    .status()?;
+  if !status.success() {
+    bail!("Building the release failed");
+  }
+
+  let os = match std::env::consts::OS {
+    "macos" => "osx",
+    os => os,
+  };
+  let dist = root.join("dist").join(os);
+  fs::create_dir_all(&dist)?;
+  let mut checksums = String::new();
+  for binary in &BINARIES {
+    let name = format!("{}{}", binary, std::env::consts::EXE_SUFFIX);
+    let built = root.join("target").join("release").join(&name);
+    let contents = fs::read(&built)
+      .map_err(|e| format_err!("Unable to read {}: {}", built.display(), e))?;
+    checksums.push_str(&format!("{:x}  {}\n", Sha256::digest(&contents), name));
+    let _ = fs::copy(&built, dist.join(&name))?;
+  }
+  fs::write(dist.join(CHECKSUMS), checksums)?;
+  Ok(dist)
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+  let output = Command::new("git").args(args).current_dir(root).output()?;
+  if !output.status.success() {
+    bail!(
+      "git {} failed: {}",
+      args.join(" "),
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+}
@@ -0,0 +1,106 @@
+//! Adds dev-suite to the user's %PATH% on Windows. `setx` truncates a PATH
+//! longer than 1024 characters and writes back entries like
+//! `%USERPROFILE%\bin` already expanded, so the value in the registry is
+//! edited directly instead.
+use anyhow::Result;
+use std::{
+  ffi::OsStr,
+  io,
+  iter,
+  os::windows::ffi::OsStrExt,
+  path::Path,
+};
+use winapi::um::winuser::{
+  SendMessageTimeoutW,
+  HWND_BROADCAST,
+  SMTO_ABORTIFHUNG,
+  WM_SETTINGCHANGE,
+};
+use winreg::{
+  enums::{
+    RegType,
+    HKEY_CURRENT_USER,
+    KEY_READ,
+    KEY_WRITE,
+  },
+  RegKey,
+  RegValue,
+};
+
+/// Adds `location` to the end of the user's PATH unless it's already there.
+/// Returns whether PATH changed.
+pub fn add_to_path(location: &Path) -> Result<bool> {
+  let env = RegKey::predef(HKEY_CURRENT_USER)
+    .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)?;
+  // Keep the value's type so entries with %VARIABLES% still get expanded
+  let (path, vtype) = match env.get_raw_value("Path") {
+    Ok(value) => (decode(&value.bytes), value.vtype),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+      (String::new(), RegType::REG_EXPAND_SZ)
+    }
+    Err(e) => return Err(e.into()),
+  };
+
+  let location = location.display().to_string();
+  let same = |entry: &str| {
+    entry
+      .trim_end_matches('\\')
+      .eq_ignore_ascii_case(location.trim_end_matches('\\'))
+  };
+  if path.split(';').any(same) {
+    return Ok(false);
+  }
+
+  let path = if path.trim_end_matches(';').is_empty() {
+    location
+  } else {
+    format!("{};{}", path.trim_end_matches(';'), location)
+  };
+  env.set_raw_value(
+    "Path",
+    &RegValue {
+      bytes: encode(&path),
+      vtype,
+    },
+  )?;
+  broadcast();
+  Ok(true)
+}
+
+/// Tells running programs, like Explorer, that the environment changed so
+/// shells opened from them get the new PATH without logging out
+fn broadcast() {
+  let area = wide("Environment");
+  let mut result: usize = 0;
+  let _ = unsafe {
+    SendMessageTimeoutW(
+      HWND_BROADCAST,
+      WM_SETTINGCHANGE,
+      0,
+      area.as_ptr() as isize,
+      SMTO_ABORTIFHUNG,
+      5000,
+      &mut result,
+    )
+  };
+}
+
+/// Turns a string into nul terminated UTF-16
+fn wide(s: &str) -> Vec<u16> {
+  OsStr::new(s).encode_wide().chain(iter::once(0)).collect()
+}
+
+/// Turns a string into the bytes of a nul terminated UTF-16 registry string
+fn encode(s: &str) -> Vec<u8> {
+  wide(s).into_iter().flat_map(u16::to_le_bytes).collect()
+}
+
+/// Reads the bytes of a registry string, dropping the nul terminator
+fn decode(bytes: &[u8]) -> String {
+  let wide = bytes
+    .chunks_exact(2)
+    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+    .take_while(|c| *c != 0)
+    .collect::<Vec<u16>>();
+  String::from_utf16_lossy(&wide)
+}
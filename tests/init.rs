@@ -0,0 +1,172 @@
+use assert_cmd::prelude::*;
+use std::{
+  env,
+  error::Error,
+  ffi::OsStr,
+  fs,
+  path::PathBuf,
+  process::{
+    Command,
+    Output,
+  },
+};
+use tempfile::{
+  tempdir,
+  TempDir,
+};
+
+const USER_CONFIG: &str = "name = 'Tester'
+uuid = '0d6df400-1553-11ea-8001-000502040999'
+
+[templates.rust]
+tools = ['hooked', 'ticket']
+hook_language = 'bash'
+
+[templates.rust.hooks]
+pre-commit = '''
+#!/usr/bin/env bash
+cargo fmt -- --check
+'''
+
+[templates.unknown-hook]
+tools = ['hooked']
+hook_language = 'bash'
+
+[templates.unknown-hook.hooks]
+pre-lunch = 'echo'
+
+[templates.cobol]
+tools = ['hooked']
+hook_language = 'cobol'
+";
+
+/// The directory `ds` was built into, which has `hooked` and `ticket` in it
+/// too when the whole workspace was built
+fn bin_dir() -> Result<PathBuf, Box<dyn Error>> {
+  let ds = Command::cargo_bin("ds")?.get_program().to_owned();
+  let dir = PathBuf::from(ds).parent().map(PathBuf::from);
+  Ok(dir.ok_or("ds has no parent")?)
+}
+
+fn repo() -> Result<TempDir, Box<dyn Error>> {
+  let dir = tempdir()?;
+  assert!(Command::new("git")
+    .args(["init", "-q"])
+    .current_dir(&dir)
+    .status()?
+    .success());
+  let conf = dir.path().join("config").join("dev-suite");
+  fs::create_dir_all(&conf)?;
+  fs::write(conf.join("user-config.toml"), USER_CONFIG)?;
+  Ok(dir)
+}
+
+/// Runs ds in the repo with the rest of the workspace's tools on the $PATH
+fn ds(dir: &TempDir, args: &[&str]) -> Result<Output, Box<dyn Error>> {
+  let path = env::var_os("PATH").unwrap_or_default();
+  let path = env::join_paths(
+    Some(bin_dir()?).into_iter().chain(env::split_paths(&path)),
+  )?;
+  ds_with_path(dir, args, &path)
+}
+
+fn ds_with_path(
+  dir: &TempDir,
+  args: &[&str],
+  path: &OsStr,
+) -> Result<Output, Box<dyn Error>> {
+  Ok(
+    Command::cargo_bin("ds")?
+      .args(args)
+      .current_dir(dir)
+      .env("XDG_CONFIG_HOME", dir.path().join("config"))
+      .env("PATH", path)
+      .output()?,
+  )
+}
+
+fn init(dir: &TempDir, template: &str) -> Result<Output, Box<dyn Error>> {
+  ds(dir, &["init", "--template", template])
+}
+
+#[cfg(not(windows))]
+#[test]
+fn init_from_template() -> Result<(), Box<dyn Error>> {
+  let bins = bin_dir()?;
+  if !bins.join("hooked").exists() || !bins.join("ticket").exists() {
+    eprintln!("Skipping since hooked and ticket haven't been built");
+    return Ok(());
+  }
+
+  let dir = repo()?;
+  let output = init(&dir, "rust")?;
+  assert!(output.status.success());
+  let dev_suite = dir.path().join(".dev-suite");
+  assert_eq!(
+    fs::read_to_string(dev_suite.join("hooked").join("pre-commit.sh"))?,
+    "#!/usr/bin/env bash\ncargo fmt -- --check\n"
+  );
+  assert!(dev_suite.join("ticket").join("open").is_dir());
+  let config = fs::read_to_string(dev_suite.join("repo-config.toml"))?;
+  assert!(config.contains("Tester"));
+
+  let output = init(&repo()?, "unknown-hook")?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("isn't a git hook"));
+
+  // A tool that fails to set itself up stops the rest
+  let output = init(&repo()?, "cobol")?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("'hooked init cobol'"));
+
+  let output = init(&repo()?, "missing")?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("cobol, rust"));
+  Ok(())
+}
+
+#[cfg(not(windows))]
+#[test]
+fn adopt_existing_hooks_and_templates() -> Result<(), Box<dyn Error>> {
+  let bins = bin_dir()?;
+  if !bins.join("hooked").exists() || !bins.join("ticket").exists() {
+    eprintln!("Skipping since hooked and ticket haven't been built");
+    return Ok(());
+  }
+
+  let dir = repo()?;
+  let hook = dir.path().join(".git").join("hooks").join("pre-commit");
+  fs::write(&hook, "#!/usr/bin/env bash\necho checked\n")?;
+  let issues = dir.path().join(".github").join("ISSUE_TEMPLATE");
+  fs::create_dir_all(&issues)?;
+  fs::write(issues.join("bug.md"), "---\nname: Bug\n---\nWhat broke?\n")?;
+
+  // Without hooked set up fails and the repo is left how it was
+  let path = env::var_os("PATH").unwrap_or_default();
+  let path = env::join_paths(
+    env::split_paths(&path).filter(|dir| !dir.join("hooked").exists()),
+  )?;
+  let output =
+    ds_with_path(&dir, &["config", "repo", "adopt", "--yes"], &path)?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("hooked is not on"));
+  assert_eq!(
+    fs::read_to_string(&hook)?,
+    "#!/usr/bin/env bash\necho checked\n"
+  );
+  assert!(!hook.with_extension("pre-dev-suite").exists());
+  assert!(!dir.path().join(".dev-suite").exists());
+
+  let output = ds(&dir, &["config", "repo", "adopt", "--yes"])?;
+  assert!(output.status.success());
+  let dev_suite = dir.path().join(".dev-suite");
+  assert_eq!(
+    fs::read_to_string(dev_suite.join("hooked").join("pre-commit.sh"))?,
+    "#!/usr/bin/env bash\necho checked\n"
+  );
+  assert!(hook.with_extension("pre-dev-suite").exists());
+  let template = fs::read_to_string(dev_suite.join("ticket/templates/bug.md"))?;
+  assert!(template.contains("What broke?"));
+  assert!(!template.contains("name: Bug"));
+  Ok(())
+}
@@ -0,0 +1,128 @@
+use assert_cmd::prelude::*;
+use std::{
+  error::Error,
+  fs,
+  process::{
+    Command,
+    Output,
+  },
+};
+use tempfile::{
+  tempdir,
+  TempDir,
+};
+
+/// The crates `ds release` bumps, which it expects to find in the repo
+const CRATES: [&str; 6] = [
+  ".",
+  "configamajig",
+  "shared",
+  "ticket",
+  "ticket-core",
+  "hooked",
+];
+
+fn git(dir: &TempDir, args: &[&str]) -> Result<String, Box<dyn Error>> {
+  let output = Command::new("git").args(args).current_dir(dir).output()?;
+  assert!(output.status.success(), "git {} failed", args.join(" "));
+  Ok(String::from_utf8(output.stdout)?)
+}
+
+/// A committed workspace laid out like dev-suite's with one closed ticket
+fn repo() -> Result<TempDir, Box<dyn Error>> {
+  let dir = tempdir()?;
+  let _ = git(&dir, &["init", "-q"])?;
+  let _ = git(&dir, &["config", "user.name", "Tester"])?;
+  let _ = git(&dir, &["config", "user.email", "tester@example.com"])?;
+  for krate in &CRATES {
+    let path = dir.path().join(krate);
+    fs::create_dir_all(path.join("src"))?;
+    fs::write(path.join("src").join("lib.rs"), "")?;
+    let name = if *krate == "." { "root" } else { krate };
+    let mut manifest = format!(
+      "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+      name
+    );
+    if *krate == "." {
+      manifest.push_str(
+        "\n[workspace]\nmembers = [\"configamajig\", \"shared\", \"ticket\", \
+         \"ticket-core\", \"hooked\"]\n",
+      );
+    }
+    fs::write(path.join("Cargo.toml"), manifest)?;
+  }
+  let closed = dir.path().join(".dev-suite").join("ticket").join("closed");
+  fs::create_dir_all(&closed)?;
+  fs::write(
+    closed.join("old-bug.toml"),
+    "title = 'Old bug'\nstatus = 'Closed'\n\
+     id = '2d6df400-1553-11ea-8001-000502040903'\nassignees = []\n\
+     description = ''\nversion = 'V1'\n\n[comments]\n",
+  )?;
+  let _ = git(&dir, &["add", "-A"])?;
+  let _ = git(&dir, &["commit", "-qm", "Initial"])?;
+  Ok(dir)
+}
+
+fn release(dir: &TempDir, version: &str) -> Result<Output, Box<dyn Error>> {
+  Ok(
+    Command::cargo_bin("ds")?
+      .args(["release", version, "--no-build"])
+      .current_dir(dir)
+      .env("XDG_CONFIG_HOME", dir.path().join("config"))
+      .output()?,
+  )
+}
+
+#[test]
+fn release_bumps_and_tags() -> Result<(), Box<dyn Error>> {
+  let dir = repo()?;
+  let output = release(&dir, "0.2.0")?;
+  assert!(output.status.success());
+  for krate in &CRATES {
+    let manifest =
+      fs::read_to_string(dir.path().join(krate).join("Cargo.toml"))?;
+    assert!(manifest.contains("version = \"0.2.0\""));
+    assert!(!manifest.contains("0.1.0"));
+  }
+  let changelog = fs::read_to_string(dir.path().join("CHANGELOG.md"))?;
+  assert!(changelog.starts_with("# v0.2.0\n\n## Closed Tickets\n\n- Old bug\n"));
+  assert_eq!(git(&dir, &["tag", "--list"])?, "v0.2.0\n");
+  assert_eq!(
+    git(&dir, &["log", "-1", "--format=%s"])?,
+    "Release v0.2.0\n"
+  );
+  assert!(git(&dir, &["status", "--porcelain"])?.is_empty());
+
+  // Tickets closed in an earlier release aren't listed again
+  let output = release(&dir, "0.3.0")?;
+  assert!(output.status.success());
+  let changelog = fs::read_to_string(dir.path().join("CHANGELOG.md"))?;
+  assert!(changelog.starts_with(
+    "# v0.3.0\n\nNo tickets were closed in this release.\n\n# v0.2.0\n"
+  ));
+  Ok(())
+}
+
+#[test]
+fn release_refuses_dirty_trees_and_existing_tags() -> Result<(), Box<dyn Error>>
+{
+  let dir = repo()?;
+  fs::write(dir.path().join("notes.txt"), "Not committed")?;
+  let output = release(&dir, "0.2.0")?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("Commit or stash"));
+  fs::remove_file(dir.path().join("notes.txt"))?;
+
+  let _ = git(&dir, &["tag", "v0.2.0"])?;
+  let output = release(&dir, "0.2.0")?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("already a release"));
+
+  // Neither touched anything
+  assert!(git(&dir, &["status", "--porcelain"])?.is_empty());
+  assert!(!dir.path().join("CHANGELOG.md").exists());
+  let manifest = fs::read_to_string(dir.path().join("Cargo.toml"))?;
+  assert!(manifest.contains("version = \"0.1.0\""));
+  Ok(())
+}
@@ -0,0 +1,760 @@
+//! Finding, reading, and saving the tickets in the repo
+use crate::{
+  format::{
+    self,
+    FileFormat,
+  },
+  FieldValue,
+  Status,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use chrono::prelude::*;
+use deunicode::deunicode;
+use configamajig::{
+  get_repo_config,
+  get_user_config,
+};
+use git2::Repository;
+use log::*;
+use rand::prelude::*;
+use rayon::prelude::*;
+use sha2::{
+  Digest,
+  Sha256,
+};
+use shared::{
+  find_root,
+  write_atomic,
+};
+use std::{
+  collections::BTreeMap,
+  convert::TryInto,
+  fs,
+  path::{
+    Path,
+    PathBuf,
+  },
+};
+use uuid::{
+  v1::{
+    Context,
+    Timestamp,
+  },
+  Uuid,
+};
+
+/// Reads in every ticket in the repo, in the order of their statuses
+pub fn get_all_tickets() -> Result<Vec<Ticket>> {
+  let by_status = statuses()?
+    .par_iter()
+    .map(get_tickets_with)
+    .collect::<Result<Vec<_>>>()?;
+  Ok(by_status.into_iter().flatten().collect())
+}
+
+/// Reads in the closed tickets
+pub fn get_closed_tickets() -> Result<Vec<Ticket>> {
+  get_tickets(&closed_tickets()?)
+}
+
+/// Reads in the archived tickets, which are left out of every other list of
+/// tickets
+pub fn get_archived_tickets() -> Result<Vec<Ticket>> {
+  let dir = archive_dir()?;
+  if dir.exists() {
+    get_tickets(&dir)
+  } else {
+    Ok(Vec::new())
+  }
+}
+
+/// Reads in the tickets with the status
+pub fn get_tickets_with(status: &Status) -> Result<Vec<Ticket>> {
+  let dir = status_dir(status)?;
+  // Directories for the statuses from the repo config are only made once a
+  // ticket is moved into them
+  if dir.exists() {
+    get_tickets(&dir)
+  } else {
+    Ok(Vec::new())
+  }
+}
+
+/// Every status a ticket can be in, in order. That's Open, then the ones from
+/// the repo config, and then Closed.
+pub fn statuses() -> Result<Vec<Status>> {
+  let mut statuses = vec![Status::Open];
+  match get_repo_config() {
+    Ok(config) => {
+      for name in config.statuses {
+        let reserved = [
+          "open",
+          "closed",
+          "remotes",
+          "attachments",
+          "templates",
+          "sprints",
+          "hooks",
+        ];
+        if name.is_empty()
+          || !name.chars().all(char::is_alphanumeric)
+          || reserved.contains(&name.to_lowercase().as_str())
+        {
+          bail!(
+            "The status '{}' in the repo config isn't allowed. Statuses may \
+             only contain letters and numbers and can't be named {}",
+            name,
+            reserved.join(", ")
+          );
+        }
+        statuses.push(Status::Other(name));
+      }
+    }
+    Err(e) => debug!("Unable to read the repo config: {}", e),
+  }
+  statuses.push(Status::Closed);
+  Ok(statuses)
+}
+
+/// Finds the status with the given name, ignoring case
+pub fn resolve_status(name: &str) -> Result<Status> {
+  let statuses = statuses()?;
+  match statuses
+    .iter()
+    .find(|s| s.to_string().eq_ignore_ascii_case(name))
+  {
+    Some(status) => Ok(status.clone()),
+    None => bail!(
+      "'{}' is not a status, expected one of {}",
+      name,
+      statuses
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+    ),
+  }
+}
+
+/// Finds the maintainer from the repo config that `query` means. It can be
+/// their uuid or their name, or the start or part of it as long as only one
+/// maintainer matches.
+pub fn resolve_maintainer(query: &str) -> Result<(Uuid, String)> {
+  let maintainers = get_repo_config()?.maintainers;
+  let list = || {
+    maintainers
+      .iter()
+      .map(|(name, _)| name.as_str())
+      .collect::<Vec<&str>>()
+      .join(", ")
+  };
+  if maintainers.is_empty() {
+    bail!(
+      "There are no maintainers in the repo config. Add yourself with 'ds \
+       config repo add me'"
+    );
+  }
+  let query = query.trim().to_lowercase();
+  let tiers: [&dyn Fn(&str) -> bool; 3] = [
+    &|name| name == query,
+    &|name| name.starts_with(&query),
+    &|name| name.contains(&query),
+  ];
+  if let Some((name, uuid)) = maintainers
+    .iter()
+    .find(|(_, uuid)| uuid.to_string() == query)
+  {
+    return Ok((*uuid, name.clone()));
+  }
+  for matches in tiers.iter() {
+    let found = maintainers
+      .iter()
+      .filter(|(name, _)| matches(&name.to_lowercase()))
+      .collect::<Vec<_>>();
+    match found.as_slice() {
+      [] => continue,
+      [(name, uuid)] => return Ok((*uuid, name.clone())),
+      _ => bail!(
+        "'{}' could be any of {}",
+        query,
+        found
+          .iter()
+          .map(|(name, _)| name.as_str())
+          .collect::<Vec<&str>>()
+          .join(", ")
+      ),
+    }
+  }
+  // Nothing contains what was typed so it might be a typo
+  match maintainers
+    .iter()
+    .map(|(name, uuid)| (distance(&name.to_lowercase(), &query), name, uuid))
+    .filter(|(d, _, _)| *d <= 2)
+    .min_by_key(|(d, _, _)| *d)
+  {
+    Some((_, name, _)) => bail!(
+      "There is no maintainer '{}', did you mean {}? Maintainers are {}",
+      query,
+      name,
+      list()
+    ),
+    None => bail!(
+      "There is no maintainer '{}'. Maintainers are {}",
+      query,
+      list()
+    ),
+  }
+}
+
+/// Who a new ticket is assigned to by `default_assignee` in the repo config,
+/// if anyone
+pub fn default_assignee() -> Result<Option<(Uuid, String)>> {
+  let assignee = match get_repo_config() {
+    Ok(config) => config.default_assignee,
+    Err(e) => {
+      debug!("Unable to read the repo config: {}", e);
+      None
+    }
+  };
+  match assignee.as_deref() {
+    None => Ok(None),
+    Some("creator") => {
+      let config = get_user_config()?;
+      Ok(Some((config.uuid, config.name)))
+    }
+    Some(maintainer) => resolve_maintainer(maintainer).map(Some),
+  }
+}
+
+/// Who a new ticket filed under the component is assigned to, its owners in
+/// the repo config or `default_assignee` if it has none
+pub fn default_assignees(
+  component: Option<&str>,
+) -> Result<Vec<(Uuid, String)>> {
+  let owners = match component {
+    Some(component) => component_owners(component)?,
+    None => Vec::new(),
+  };
+  if !owners.is_empty() {
+    return Ok(owners);
+  }
+  Ok(default_assignee()?.into_iter().collect())
+}
+
+/// The fields the repo config requires that aren't in the fields of a new
+/// ticket. Every way of making a ticket checks this before saving it.
+pub fn missing_fields(fields: &BTreeMap<String, FieldValue>) -> Vec<String> {
+  let config = match get_repo_config() {
+    Ok(config) => config,
+    Err(e) => {
+      debug!("Unable to read the repo config: {}", e);
+      return Vec::new();
+    }
+  };
+  config
+    .fields
+    .into_iter()
+    .filter(|field| field.required && !fields.contains_key(&field.name))
+    .map(|field| field.name)
+    .collect()
+}
+
+/// Fails if a new ticket is missing any of the fields the repo config requires
+pub fn require_fields(fields: &BTreeMap<String, FieldValue>) -> Result<()> {
+  match missing_fields(fields).as_slice() {
+    [] => Ok(()),
+    [field] => bail!("{} is required but isn't set", field),
+    missing => bail!("{} are required but aren't set", missing.join(", ")),
+  }
+}
+
+/// Warns that a ticket made with nobody to ask, e.g. by `ticket scan`, is
+/// missing required fields, since `ticket fsck` fails until they're set
+pub fn warn_missing_fields(ticket: &Ticket) {
+  let missing = missing_fields(&ticket.fields);
+  if !missing.is_empty() {
+    warn!(
+      "{} doesn't have the required fields {}, set them with `ticket set {} \
+       <FIELD> <VALUE>`",
+      ticket.short_id,
+      missing.join(", "),
+      ticket.short_id
+    );
+  }
+}
+
+/// The component from the repo config that `query` names, ignoring case
+pub fn resolve_component(query: &str) -> Result<String> {
+  let components = get_repo_config()?.components;
+  if components.is_empty() {
+    bail!(
+      "There are no components in the repo config. Add them as \
+       [components.<name>] tables"
+    );
+  }
+  let query = query.trim().to_lowercase();
+  match components.keys().find(|name| name.to_lowercase() == query) {
+    Some(name) => Ok(name.clone()),
+    None => bail!(
+      "There is no component '{}'. Components are {}",
+      query,
+      components
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .join(", ")
+    ),
+  }
+}
+
+/// The maintainers who own the component in the repo config
+pub fn component_owners(component: &str) -> Result<Vec<(Uuid, String)>> {
+  get_repo_config()?
+    .components
+    .get(component)
+    .map_or_else(Vec::new, |c| c.owners.clone())
+    .iter()
+    .map(|owner| resolve_maintainer(owner))
+    .collect()
+}
+
+/// The Levenshtein distance between two strings
+fn distance(a: &str, b: &str) -> usize {
+  let b = b.chars().collect::<Vec<char>>();
+  let mut row = (0..=b.len()).collect::<Vec<usize>>();
+  for (i, ca) in a.chars().enumerate() {
+    let mut prev = row[0];
+    row[0] = i + 1;
+    for (j, cb) in b.iter().enumerate() {
+      let cur = row[j + 1];
+      row[j + 1] = if ca == *cb {
+        prev
+      } else {
+        1 + prev.min(row[j]).min(cur)
+      };
+      prev = cur;
+    }
+  }
+  row[b.len()]
+}
+
+/// Reads in the tickets in the directory. Parsing them is most of the time it
+/// takes, so they're parsed in parallel.
+fn get_tickets(path: &Path) -> Result<Vec<Ticket>> {
+  let mut paths = Vec::new();
+  debug!("Looking for ticket.");
+  for entry in fs::read_dir(&path)? {
+    let entry = entry?;
+    let path = entry.path();
+    trace!("Looking at entry {}.", path.display());
+    // Skips anything else, like the temporary file of a write that died
+    if path.is_file() && FileFormat::of(&path).is_some() {
+      trace!("Entry is a file.");
+      paths.push(path);
+    }
+  }
+  let mut out = paths
+    .par_iter()
+    .map(|path| read_ticket(path))
+    .collect::<Result<Vec<Ticket>>>()?;
+  out.sort_by(|a, b| a.id.cmp(&b.id));
+  Ok(out)
+}
+
+/// Reads in the ticket stored at the path
+pub fn read_ticket(path: &Path) -> Result<Ticket> {
+  match format::parse(&fs::read_to_string(path)?) {
+    Ok(mut ticket) => {
+      // Tickets made before short ids existed get one from what they contain
+      // now, which is stored the next time they're saved
+      if ticket.short_id.is_empty() {
+        ticket.short_id =
+          short_id(&ticket.id, &ticket.title, &ticket.description);
+      }
+      if ticket.created_at.is_none() {
+        ticket.created_at = uuid_time(&ticket.id);
+      }
+      Ok(ticket)
+    }
+    Err(e) => {
+      error!("Failed to parse ticket {}", path.canonicalize()?.display());
+      error!("Is the file an old ticket format? You might need to run `ticket migrate`.");
+      bail!("Underlying error was {}", e);
+    }
+  }
+}
+
+/// The fewest characters of a uuid that are looked up as the start of one, so
+/// that something like a single digit isn't taken as a ticket's id
+const MIN_PREFIX: usize = 4;
+
+/// Finds a ticket by its uuid or its short id, then by the name of its file
+/// without the extension, then like git does for commits by the start of its
+/// uuid. Archived tickets are only looked through if no other ticket matches.
+pub fn find_ticket(id: &str) -> Result<Ticket> {
+  let uuid = Uuid::parse_str(id).ok();
+  let exact =
+    |t: &Ticket| Some(t.id) == uuid || t.short_id.eq_ignore_ascii_case(id);
+  let lower = id.to_lowercase();
+  let named = |t: &Ticket| {
+    let name = slug(&t.title);
+    name == lower || format!("{}-{}", name, t.short_id.to_lowercase()) == lower
+  };
+  let starts = |t: &Ticket| {
+    lower.len() >= MIN_PREFIX
+      && (t.id.to_string().starts_with(&lower)
+        || t.id.to_simple().to_string().starts_with(&lower))
+  };
+  let found = find_first(&[&exact, &named, &starts])?;
+  only(found, &format!("id {}", id))
+}
+
+/// Finds a ticket by its title, ignoring case
+pub fn find_ticket_by_title(title: &str) -> Result<Ticket> {
+  let title = title.trim();
+  let titled = |t: &Ticket| t.title.trim().eq_ignore_ascii_case(title);
+  let found = find_first(&[&titled])?;
+  only(found, &format!("the title '{}'", title))
+}
+
+/// The tickets that pass the first test any ticket passes, trying archived
+/// tickets only if none of the others do
+fn find_first(tests: &[&dyn Fn(&Ticket) -> bool]) -> Result<Vec<Ticket>> {
+  let loads: [fn() -> Result<Vec<Ticket>>; 2] =
+    [get_all_tickets, get_archived_tickets];
+  for load in loads {
+    let tickets = load()?;
+    for test in tests {
+      let found = tickets
+        .iter()
+        .filter(|t| test(t))
+        .cloned()
+        .collect::<Vec<Ticket>>();
+      if !found.is_empty() {
+        return Ok(found);
+      }
+    }
+  }
+  Ok(Vec::new())
+}
+
+/// The one ticket found, or an error listing them if there's more than one
+fn only(mut found: Vec<Ticket>, what: &str) -> Result<Ticket> {
+  match found.len() {
+    0 => bail!("No ticket with {} exists.", what),
+    1 => Ok(found.remove(0)),
+    _ => bail!(
+      "More than one ticket matches {}, use the uuid of the one you \
+       want:\n{}",
+      what,
+      found
+        .iter()
+        .map(|t| format!("  {} {} {}", t.id, t.short_id, t.title))
+        .collect::<Vec<String>>()
+        .join("\n")
+    ),
+  }
+}
+
+/// The first 8 characters of the sha256 hash of the ticket's id, title, and
+/// description. The id is included so that tickets with the same title and
+/// description still get different short ids.
+pub fn short_id(id: &Uuid, title: &str, description: &str) -> String {
+  let contents = format!("{}\n{}\n{}", id, title, description);
+  let hash = Sha256::digest(contents.as_bytes());
+  format!("{:x}", hash)[..8].to_owned()
+}
+
+/// The directory everything for tickets is kept in, `.dev-suite/ticket`
+pub fn ticket_root() -> Result<PathBuf> {
+  Ok(find_root()?.join(".dev-suite").join("ticket"))
+}
+
+/// The directory attached files are kept in
+pub fn attachments() -> Result<PathBuf> {
+  Ok(ticket_root()?.join("attachments"))
+}
+
+/// Copies the file into the attachments directory under the sha256 hash of its
+/// contents and returns the hash. Files that are already stored are not
+/// written again so the same file attached to many tickets is only kept once.
+pub fn save_attachment(path: &Path) -> Result<String> {
+  let contents = fs::read(path)?;
+  let hash = format!("{:x}", Sha256::digest(&contents));
+  let dir = attachments()?;
+  fs::create_dir_all(&dir)?;
+  let blob = dir.join(&hash);
+  if blob.exists() {
+    debug!("Attachment {} is already stored.", hash);
+  } else {
+    trace!("Writing attachment {}.", blob.display());
+    write_atomic(&blob, contents)?;
+  }
+  Ok(hash)
+}
+
+/// The directory the templates for new tickets are kept in
+pub fn templates_dir() -> Result<PathBuf> {
+  Ok(ticket_root()?.join("templates"))
+}
+
+/// The names of the repo's ticket templates in alphabetical order
+pub fn templates() -> Result<Vec<String>> {
+  let dir = templates_dir()?;
+  let mut names = Vec::new();
+  if dir.exists() {
+    for entry in fs::read_dir(&dir)? {
+      if let Some(name) = entry?.path().file_stem().and_then(|n| n.to_str()) {
+        names.push(name.to_owned());
+      }
+    }
+  }
+  names.sort();
+  Ok(names)
+}
+
+/// Reads the ticket template with the given name, e.g. `bug` for
+/// `.dev-suite/ticket/templates/bug.md`
+pub fn get_template(name: &str) -> Result<String> {
+  let dir = templates_dir()?;
+  let path = dir.join(name).with_extension("md");
+  if path.exists() {
+    return Ok(fs::read_to_string(path)?);
+  }
+  let names = templates()?;
+  if names.is_empty() {
+    bail!(
+      "There is no template named '{}', the repo doesn't have any templates",
+      name
+    );
+  }
+  bail!(
+    "There is no template named '{}', expected one of {}",
+    name,
+    names.join(", ")
+  );
+}
+
+/// The directory closed tickets are kept in
+pub fn closed_tickets() -> Result<PathBuf> {
+  Ok(ticket_root()?.join("closed"))
+}
+
+/// The directory archived tickets are kept in, which is inside the closed
+/// tickets' directory but only read when asked for
+pub fn archive_dir() -> Result<PathBuf> {
+  Ok(closed_tickets()?.join("archive"))
+}
+
+/// The directory open tickets are kept in
+pub fn open_tickets() -> Result<PathBuf> {
+  Ok(ticket_root()?.join("open"))
+}
+
+/// The directory tickets with the status are kept in
+pub fn status_dir(status: &Status) -> Result<PathBuf> {
+  match status {
+    Status::Open => open_tickets(),
+    Status::Closed => closed_tickets(),
+    Status::Other(name) => Ok(ticket_root()?.join(name.to_lowercase())),
+  }
+}
+
+/// The clock sequence shared by every uuid made in this process, so ones made
+/// at the same time still differ and order by when they were made
+static CONTEXT: Context = Context::new(0);
+
+/// Makes a new uuid for a ticket or comment from the current time
+pub fn uuid_v1() -> Result<Uuid> {
+  let now = Utc::now();
+  Ok(Uuid::new_v1(
+    Timestamp::from_unix(
+      &CONTEXT,
+      now.timestamp().try_into()?,
+      now.timestamp_subsec_nanos(),
+    ),
+    &[random(), random(), random(), random(), random(), random()],
+  )?)
+}
+
+/// The time a v1 uuid was made at, which for tickets is when they were created
+pub fn uuid_time(id: &Uuid) -> Option<DateTime<Utc>> {
+  let (secs, nanos) = id.to_timestamp()?.to_unix();
+  Utc.timestamp_opt(secs.try_into().ok()?, nanos).single()
+}
+
+/// Saves the ticket to the file its status and title say it belongs in. If
+/// it's still in another file, e.g. from before it was renamed or moved to a
+/// new status, that file is moved there first so git sees it as a rename
+/// rather than a new ticket left next to a stale copy.
+pub fn save_ticket(ticket: &Ticket) -> Result<()> {
+  let path = ticket_path(ticket)?;
+  match file_id(&path) {
+    Some(id) if id != ticket.id => bail!(
+      "Can't save ticket {} to {} since another ticket is already there",
+      ticket.short_id,
+      path.display()
+    ),
+    Some(_) => (),
+    None => {
+      if let Some(old_path) = find_ticket_file(&ticket.id)? {
+        move_ticket_file(&old_path, &path)?;
+      }
+    }
+  }
+  fs::create_dir_all(status_dir(&ticket.status)?)?;
+  write_atomic(&path, FileFormat::configured()?.write(ticket)?)?;
+  Ok(())
+}
+
+/// Saves an archived ticket in the archive in the format it's already in,
+/// rather than moving it back out like `save_ticket` would
+pub fn save_archived_ticket(ticket: &Ticket) -> Result<()> {
+  let path = match find_ticket_file(&ticket.id)? {
+    Some(path) if path.parent() == Some(archive_dir()?.as_path()) => path,
+    _ => bail!("{} isn't in the archive", ticket.short_id),
+  };
+  let format = match FileFormat::of(&path) {
+    Some(format) => format,
+    None => FileFormat::configured()?,
+  };
+  write_atomic(&path, format.write(ticket)?)?;
+  Ok(())
+}
+
+/// The uuid of the ticket in the file if there is one
+fn file_id(path: &Path) -> Option<Uuid> {
+  let file = format::parse_table(&fs::read_to_string(path).ok()?).ok()?;
+  file.get("id")?.as_str()?.parse().ok()
+}
+
+/// Finds the file the ticket is saved in wherever it is, including the
+/// archive, so that saving an archived ticket moves it back out
+pub fn find_ticket_file(id: &Uuid) -> Result<Option<PathBuf>> {
+  let mut dirs = statuses()?
+    .iter()
+    .map(status_dir)
+    .collect::<Result<Vec<PathBuf>>>()?;
+  dirs.push(archive_dir()?);
+  for dir in dirs {
+    if !dir.exists() {
+      continue;
+    }
+    for entry in fs::read_dir(dir)? {
+      let path = entry?.path();
+      if FileFormat::of(&path).is_some() && file_id(&path).as_ref() == Some(id)
+      {
+        return Ok(Some(path));
+      }
+    }
+  }
+  Ok(None)
+}
+
+/// Moves a ticket's file like `git mv` would, so that if git is tracking it
+/// the move is staged and its history follows it
+pub fn move_ticket_file(from: &Path, to: &Path) -> Result<()> {
+  debug!("Moving {} to {}", from.display(), to.display());
+  if let Some(parent) = to.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let staged = index_paths(from, to);
+  fs::rename(from, to)?;
+  if let Err(e) = staged.and_then(|(repo, from, to)| {
+    let mut index = repo.index()?;
+    if let Some(mut entry) = index.get_path(&from, 0) {
+      entry.path = to.to_string_lossy().replace('\\', "/").into_bytes();
+      index.add(&entry)?;
+      index.remove_path(&from)?;
+      index.write()?;
+    }
+    Ok(())
+  }) {
+    debug!("Not moving {} in git: {}", from.display(), e);
+  }
+  Ok(())
+}
+
+/// The repo and the paths relative to it, found before the file is moved
+/// since that's when both can be resolved
+fn index_paths(
+  from: &Path,
+  to: &Path,
+) -> Result<(Repository, PathBuf, PathBuf)> {
+  let repo = Repository::discover(find_root()?)?;
+  let workdir = match repo.workdir() {
+    Some(workdir) => workdir.canonicalize()?,
+    None => bail!("The repo is bare"),
+  };
+  let from = from.canonicalize()?.strip_prefix(&workdir)?.to_owned();
+  let to = match (to.parent(), to.file_name()) {
+    (Some(dir), Some(name)) => dir.canonicalize()?.join(name),
+    _ => bail!("{} isn't a path to a file", to.display()),
+  };
+  let to = to.strip_prefix(&workdir)?.to_owned();
+  Ok((repo, from, to))
+}
+
+/// The most characters of a title that go in its file name
+const MAX_SLUG: usize = 64;
+
+/// Names that Windows won't allow as a file name whatever the extension
+const RESERVED: [&str; 22] = [
+  "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6",
+  "com7", "com8", "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6",
+  "lpt7", "lpt8", "lpt9",
+];
+
+/// Turns a title into a name that's safe for a file on any platform. It's
+/// transliterated to lowercase ascii, every run of anything besides letters
+/// and numbers becomes a dash, and it's cut off if it gets too long.
+pub fn slug(title: &str) -> String {
+  let mut slug = String::new();
+  for c in deunicode(title).chars() {
+    if slug.len() == MAX_SLUG {
+      break;
+    }
+    if c.is_ascii_alphanumeric() {
+      slug.push(c.to_ascii_lowercase());
+    } else if c != '\'' && !slug.is_empty() && !slug.ends_with('-') {
+      slug.push('-');
+    }
+  }
+  let slug = slug.trim_end_matches('-');
+  if slug.is_empty() {
+    "ticket".into()
+  } else if RESERVED.contains(&slug) {
+    format!("{}-ticket", slug)
+  } else {
+    slug.into()
+  }
+}
+
+/// Where the ticket belongs, which is a file named after its title with the
+/// extension of the repo's format. If another ticket with the same title
+/// already has that file, the short id is added to the name to tell them apart.
+pub fn ticket_path(ticket: &Ticket) -> Result<PathBuf> {
+  let dir = status_dir(&ticket.status)?;
+  let slug = slug(&ticket.title);
+  let ext = FileFormat::configured()?.extension();
+  let path = dir.join(format!("{}.{}", slug, ext));
+  let with_id = dir.join(format!(
+    "{}-{}.{}",
+    slug,
+    ticket.short_id.to_lowercase(),
+    ext
+  ));
+  // Once a ticket has had to use its short id it keeps it, so that it doesn't
+  // move around when the other ticket does
+  if file_id(&with_id) == Some(ticket.id)
+    || (path.exists() && file_id(&path) != Some(ticket.id))
+  {
+    Ok(with_id)
+  } else {
+    Ok(path)
+  }
+}
@@ -0,0 +1,100 @@
+//! Encrypting the descriptions and comments of confidential tickets with gpg,
+//! so that security issues can be kept in the repo without everyone who can
+//! read the repo being able to read them. They're encrypted for the gpg key of
+//! every maintainer in the repo config's `[keys]` table, and only those
+//! maintainers can decrypt them. The title and everything else about the
+//! ticket stays readable so it can still be listed and found.
+use crate::sign::is_ssh_key;
+use anyhow::{
+  bail,
+  format_err,
+  Result,
+};
+use configamajig::get_repo_config;
+use std::{
+  io::Write,
+  process::{
+    Command,
+    Stdio,
+  },
+};
+
+/// The first line of everything gpg encrypts with `--armor`
+const HEADER: &str = "-----BEGIN PGP MESSAGE-----";
+
+/// The gpg keys of the maintainers, which is who tickets are encrypted for
+pub fn recipients() -> Result<Vec<String>> {
+  let config = get_repo_config()?;
+  let keys = config
+    .maintainers
+    .iter()
+    .filter_map(|(_, uuid)| config.keys.get(uuid))
+    // ssh keys are only for checking signatures
+    .filter(|key| !is_ssh_key(key))
+    .cloned()
+    .collect::<Vec<String>>();
+  if keys.is_empty() {
+    bail!(
+      "None of the maintainers have a gpg key in the [keys] table of the repo \
+       config to encrypt tickets for"
+    );
+  }
+  Ok(keys)
+}
+
+/// Encrypts the text for every maintainer with a key
+pub fn encrypt(text: &str) -> Result<String> {
+  let recipients = recipients()?;
+  let mut args = vec!["--batch", "--armor", "--trust-model", "always"];
+  for recipient in &recipients {
+    args.push("--recipient");
+    args.push(recipient);
+  }
+  args.push("--encrypt");
+  pipe("gpg", &args, text)
+}
+
+/// Decrypts text made by [`encrypt`], which only works for the maintainers it
+/// was encrypted for. Text that isn't encrypted, like a comment redacted after
+/// it was encrypted, is returned as it is.
+pub fn reveal(text: &str) -> Result<String> {
+  if is_encrypted(text) {
+    pipe("gpg", &["--quiet", "--decrypt"], text)
+  } else {
+    Ok(text.to_owned())
+  }
+}
+
+/// Whether the text is something [`encrypt`] made
+pub fn is_encrypted(text: &str) -> bool {
+  text.trim_start().starts_with(HEADER)
+}
+
+/// Runs the program, gpg or ssh-keygen, with the text on stdin and returns
+/// what it printed
+pub(crate) fn pipe(
+  program: &str,
+  args: &[&str],
+  input: &str,
+) -> Result<String> {
+  let mut child = Command::new(program)
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format_err!("Unable to run {}: {}", program, e))?;
+  if let Some(mut stdin) = child.stdin.take() {
+    stdin.write_all(input.as_bytes())?;
+  }
+  let output = child.wait_with_output()?;
+  if !output.status.success() {
+    bail!(
+      "{} failed with {}. {}",
+      program,
+      output.status,
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(String::from_utf8(output.stdout)?)
+}
@@ -0,0 +1,171 @@
+//! The formats ticket files can be saved in. Tickets are toml by default, but a
+//! repo can set `format = 'markdown'` in its config to have the description be
+//! the body of a markdown file, with the rest of the ticket as toml frontmatter
+//! between `+++` lines, so that GitHub and GitLab show tickets as a rendered
+//! page. Tickets in either format are always read, so a repo that switches has
+//! its tickets converted as they're next saved or by `ticket fsck --fix`.
+use crate::Ticket;
+use anyhow::{
+  bail,
+  Result,
+};
+use configamajig::get_repo_config;
+use log::*;
+use std::path::Path;
+use toml::{
+  value::Table,
+  Value,
+};
+
+/// The line above and below the frontmatter of a markdown ticket
+const FENCE: &str = "+++";
+
+/// How a ticket file is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+  /// The whole ticket as toml
+  Toml,
+  /// The description as markdown after the rest of the ticket as toml
+  /// frontmatter
+  Markdown,
+}
+
+impl FileFormat {
+  /// Every format, which are the ones whose files are read as tickets
+  pub const ALL: [FileFormat; 2] = [FileFormat::Toml, FileFormat::Markdown];
+
+  /// The format the repo config says tickets are saved in
+  pub fn configured() -> Result<Self> {
+    let format = match get_repo_config() {
+      Ok(config) => config.format,
+      Err(e) => {
+        debug!("Unable to read the repo config: {}", e);
+        None
+      }
+    };
+    match format.map(|f| f.to_lowercase()).as_deref() {
+      None | Some("toml") => Ok(FileFormat::Toml),
+      Some("markdown") | Some("md") => Ok(FileFormat::Markdown),
+      Some(other) => bail!(
+        "The format '{}' in the repo config isn't one tickets can be saved \
+         in. It can be 'toml' or 'markdown'",
+        other
+      ),
+    }
+  }
+
+  /// The extension of files in the format
+  pub fn extension(self) -> &'static str {
+    match self {
+      FileFormat::Toml => "toml",
+      FileFormat::Markdown => "md",
+    }
+  }
+
+  /// The format of a ticket file going by its extension, or `None` if it
+  /// isn't one
+  pub fn of(path: &Path) -> Option<Self> {
+    let ext = path.extension()?;
+    Self::ALL.iter().copied().find(|f| ext == f.extension())
+  }
+
+  /// The format a ticket is in going by what's in it, since git hands its
+  /// merge driver tickets as temporary files without their extension
+  pub fn detect(contents: &str) -> Self {
+    if contents.starts_with(FENCE) {
+      FileFormat::Markdown
+    } else {
+      FileFormat::Toml
+    }
+  }
+
+  /// Writes out the ticket in the format
+  pub fn write(self, ticket: &Ticket) -> Result<String> {
+    match self {
+      FileFormat::Toml => Ok(toml::to_string_pretty(ticket)?),
+      FileFormat::Markdown => {
+        let mut front = Value::try_from(ticket)?;
+        if let Some(front) = front.as_table_mut() {
+          let _ = front.remove("description");
+        }
+        // Serializing it as a Value rather than a Table puts tables last
+        Ok(format!(
+          "{fence}\n{}{fence}\n\n{}\n",
+          toml::to_string_pretty(&front)?,
+          ticket.description,
+          fence = FENCE
+        ))
+      }
+    }
+  }
+}
+
+/// Reads in a ticket in either format
+pub fn parse(contents: &str) -> Result<Ticket> {
+  match FileFormat::detect(contents) {
+    FileFormat::Toml => Ok(toml::from_str(contents)?),
+    FileFormat::Markdown => {
+      Ok(Value::Table(parse_table(contents)?).try_into()?)
+    }
+  }
+}
+
+/// Reads in a ticket in either format as the toml it's stored as, with the
+/// description of a markdown ticket put back in, for [`crate::migrate`]
+pub fn parse_table(contents: &str) -> Result<Table> {
+  match FileFormat::detect(contents) {
+    FileFormat::Toml => Ok(toml::from_str(contents)?),
+    FileFormat::Markdown => {
+      let (mut front, description) = split(contents)?;
+      let _ = front.insert("description".into(), description.into());
+      Ok(front)
+    }
+  }
+}
+
+/// Splits a markdown ticket into its frontmatter, read as toml, and its body,
+/// without the blank line and last newline that are added around the
+/// description. A fence line can also be inside a multi-line string in the
+/// frontmatter, such as a comment that has one, so the frontmatter ends at the
+/// first fence line that the toml before it is whole at.
+fn split(contents: &str) -> Result<(Table, &str)> {
+  let rest = match contents.strip_prefix(FENCE).and_then(strip_newline) {
+    Some(rest) => rest,
+    None => bail!("The ticket doesn't start with a {} line", FENCE),
+  };
+  let mut at = 0;
+  let mut error = None;
+  for line in rest.split_inclusive('\n') {
+    if line.trim_end() == FENCE {
+      match toml::from_str(&rest[..at]) {
+        Ok(front) => {
+          let body = &rest[at + line.len()..];
+          let body = strip_newline(body).unwrap_or(body);
+          let body = body
+            .strip_suffix("\r\n")
+            .or_else(|| body.strip_suffix('\n'))
+            .unwrap_or(body);
+          return Ok((front, body));
+        }
+        Err(e) => {
+          let _ = error.get_or_insert(e);
+        }
+      }
+    }
+    at += line.len();
+  }
+  match error {
+    Some(e) => Err(e.into()),
+    None => bail!(
+      "The frontmatter of the ticket doesn't end with a {} line",
+      FENCE
+    ),
+  }
+}
+
+/// The text after the newline it starts with
+fn strip_newline(text: &str) -> Option<&str> {
+  text
+    .strip_prefix("\r\n")
+    .or_else(|| text.strip_prefix('\n'))
+}
@@ -0,0 +1,814 @@
+//! The tickets that `ticket` keeps in a repo and the logic for loading,
+//! saving, and upgrading them, so that other tools like editor plugins can work
+//! with tickets without shelling out to `ticket`.
+//!
+//! Tickets are stored as files in `.dev-suite/ticket/<status>`, named after
+//! their title, in one of the formats in [`format`]. [`actions`] has the
+//! functions for finding and reading them in and saving them, [`Ticket`] the
+//! methods for changing them in a way that's recorded in their history, and
+//! [`migrate`] upgrades tickets saved by older versions.
+
+pub mod actions;
+pub mod crypt;
+pub mod format;
+pub mod migrate;
+pub mod sign;
+
+use actions::*;
+use anyhow::{
+  bail,
+  format_err,
+  Result,
+};
+use chrono::prelude::*;
+use configamajig::{
+  get_user_config,
+  log_event,
+  Field,
+  FieldKind,
+};
+use log::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+use std::{
+  collections::BTreeMap,
+  convert::TryFrom,
+  fmt,
+  iter,
+  str::FromStr,
+};
+use uuid::Uuid;
+
+/// Makes a new open ticket in memory with nothing but a title and description
+pub fn new_ticket(title: String, description: String) -> Result<Ticket> {
+  debug!("Creating ticket in memory.");
+  let id = uuid_v1()?;
+  let now = Utc::now();
+  let mut t = Ticket {
+    short_id: short_id(&id, &title, &description),
+    title,
+    status: Status::Open,
+    id,
+    assignees: Vec::new(),
+    watchers: Vec::new(),
+    description,
+    tags: Vec::new(),
+    due: None,
+    estimate: None,
+    component: None,
+    fields: BTreeMap::new(),
+    closed_by: None,
+    closed_at: None,
+    blocked_by: Vec::new(),
+    blocks: Vec::new(),
+    related_to: Vec::new(),
+    duplicate_of: Vec::new(),
+    duplicated_by: Vec::new(),
+    branches: Vec::new(),
+    locations: Vec::new(),
+    tasks: Vec::new(),
+    redactions: Vec::new(),
+    history: Vec::new(),
+    attachments: BTreeMap::new(),
+    comment_times: BTreeMap::new(),
+    signatures: BTreeMap::new(),
+    reactions: BTreeMap::new(),
+    comments: BTreeMap::new(),
+    encrypted: false,
+    locked: false,
+    version: Version::V3,
+    created_at: Some(now),
+    updated_at: Some(now),
+  };
+  let created = t.signed_event("Created the ticket");
+  t.history.push(created);
+  Ok(t)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// The fundamental type this tool revolves around. The ticket represents
+/// everything about an issue or future plan for the code base.
+pub struct Ticket {
+  /// The one line summary of the ticket
+  pub title: String,
+  /// Where the ticket is in its workflow, which is also the directory
+  /// it's stored in
+  pub status: Status,
+  /// The ticket's unique id
+  pub id: Uuid,
+  /// A short id derived from the contents of the ticket when it was made
+  /// that's easier to type than the uuid
+  #[serde(default)]
+  pub short_id: String,
+  /// Who is working on the ticket
+  pub assignees: Vec<(Uuid, Name)>,
+  /// The uuids of who wants to hear about new comments and changes on the
+  /// ticket in their `ticket inbox`
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub watchers: Vec<Uuid>,
+  /// The details of the ticket, in markdown
+  pub description: String,
+  /// Whether the description and comments are encrypted, see [`crypt`]
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub encrypted: bool,
+  /// Whether a maintainer locked the ticket so no more comments can be added
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub locked: bool,
+  /// The version of the format the ticket was saved with
+  pub version: Version,
+  /// When the ticket was made. Tickets from before V3 get this from their id
+  /// when they're read in
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  /// When anything about the ticket last changed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+  /// Free form labels used to group tickets, e.g. 'bug' or 'docs'. V1 tickets
+  /// don't have any
+  #[serde(default)]
+  pub tags: Vec<String>,
+  /// When the ticket should be closed by
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub due: Option<NaiveDate>,
+  /// How much work the ticket is
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub estimate: Option<Estimate>,
+  /// The part of the project from the repo config's components the ticket is
+  /// filed under
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub component: Option<String>,
+  /// Who closed the ticket, if it's closed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub closed_by: Option<(Uuid, Name)>,
+  /// When the ticket was closed, if it's closed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub closed_at: Option<DateTime<Utc>>,
+  /// Tickets that need to be closed before this one can be
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub blocked_by: Vec<Uuid>,
+  /// Tickets that can't be closed until this one is
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub blocks: Vec<Uuid>,
+  /// Tickets that are related in some other way
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub related_to: Vec<Uuid>,
+  /// Tickets this one was opened again for by mistake
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub duplicate_of: Vec<Uuid>,
+  /// Tickets that were opened again for this one by mistake
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub duplicated_by: Vec<Uuid>,
+  /// The git branches made for working on the ticket
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub branches: Vec<String>,
+  /// Where the TODO(ds) comments for the ticket are, e.g. `src/main.rs:12`
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub locations: Vec<String>,
+  /// A checklist of things to do for the ticket
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub tasks: Vec<Task>,
+  /// Comments that had their contents removed, e.g. for leaking a secret
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub redactions: Vec<Redaction>,
+  /// Everything that's been changed about the ticket, oldest first
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub history: Vec<Event>,
+  /// The values of the custom fields from the repo config, by field name
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub fields: BTreeMap<String, FieldValue>,
+  /// Attached files keyed by the sha256 hash of their contents, which is also
+  /// the name they're stored under in the attachments directory, mapped to the
+  /// original file name
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub attachments: BTreeMap<String, String>,
+  /// When each comment was made. Comments from before these were kept use the
+  /// time from their id
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub comment_times: BTreeMap<Uuid, DateTime<Utc>>,
+  /// The emojis each person reacted to a comment with, keyed by the comment
+  /// then the uuid of who reacted
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub reactions: BTreeMap<Uuid, BTreeMap<Uuid, Vec<String>>>,
+  /// The signatures of comments made by people who sign them, see [`sign`]
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub signatures: BTreeMap<Uuid, String>,
+  /// The comments on the ticket keyed by their id, with who made them
+  #[serde(serialize_with = "toml::ser::tables_last")]
+  pub comments: BTreeMap<Uuid, (Uuid, Name, Comment)>,
+}
+
+impl Ticket {
+  /// What's wrong with the ticket's custom fields going by the fields in the
+  /// repo config, e.g. values of the wrong type or missing required fields
+  #[must_use]
+  pub fn field_problems(&self, fields: &[Field]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (name, value) in &self.fields {
+      match fields.iter().find(|field| field.name == *name) {
+        Some(field) => {
+          if let Err(e) = value.check(field) {
+            problems.push(e.to_string());
+          }
+        }
+        None => {
+          problems.push(format!("{} isn't a field in the repo config", name))
+        }
+      }
+    }
+    for field in fields {
+      if field.required && !self.fields.contains_key(&field.name) {
+        problems.push(format!("{} is required but isn't set", field.name));
+      }
+    }
+    problems
+  }
+
+  /// Adds a change the current user made to the history
+  pub fn record(&mut self, action: impl Into<String>) {
+    let event = self.signed_event(action);
+    log_event("ticket", format!("{}: {}", self.short_id, event.action));
+    self.history.push(event);
+    self.touch();
+  }
+
+  /// Adds a newly made ticket to the audit log, once it's been saved
+  pub fn log_created(&self) {
+    log_event(
+      "ticket",
+      format!("{}: Created the ticket '{}'", self.short_id, self.title),
+    );
+  }
+
+  /// A change made by the user to the ticket, signed if they sign
+  fn signed_event(&self, action: impl Into<String>) -> Event {
+    let mut event = Event::new(action);
+    // Failing to sign shouldn't lose the change, it's only left unsigned
+    match sign::sign(&event.payload(&self.id)) {
+      Ok(signature) => event.signature = signature,
+      Err(e) => warn!("Unable to sign the change to {}: {}", self.short_id, e),
+    }
+    event
+  }
+
+  /// Adds a comment and returns its id. Its time is never before the comments
+  /// already on the ticket so they stay in the order they were made even when
+  /// clocks on different machines disagree.
+  pub fn add_comment(
+    &mut self,
+    by: Uuid,
+    name: String,
+    comment: String,
+  ) -> Result<Uuid> {
+    if self.locked {
+      bail!(
+        "{} is locked so it can't be commented on, a maintainer can unlock it \
+         with `ticket unlock`",
+        self.short_id
+      );
+    }
+    let id = uuid_v1()?;
+    let latest = self
+      .comments
+      .keys()
+      .filter_map(|id| self.comment_time(id))
+      .max();
+    let now = Utc::now();
+    let at = match latest {
+      Some(latest) if latest >= now => {
+        latest + chrono::Duration::microseconds(1)
+      }
+      _ => now,
+    };
+    // Encrypted comments are signed as they were written so encrypting or
+    // decrypting the ticket later doesn't break their signature
+    let signature = sign::sign(&payload(&self.id, &id, &by, at, &comment))?;
+    let comment = if self.encrypted {
+      crypt::encrypt(&comment)?
+    } else {
+      comment
+    };
+    let _ = self.comments.insert(id, (by, Name(name), Comment(comment)));
+    let _ = self.comment_times.insert(id, at);
+    if let Some(signature) = signature {
+      let _ = self.signatures.insert(id, signature);
+    }
+    log_event("ticket", format!("{}: Commented", self.short_id));
+    self.touch();
+    Ok(id)
+  }
+
+  /// What the signature of a comment is made over, given its text as it was
+  /// written, or `None` if there's no such comment
+  pub fn comment_payload(&self, id: &Uuid, text: &str) -> Option<String> {
+    let (by, _, _) = self.comments.get(id)?;
+    Some(payload(&self.id, id, by, self.comment_time(id)?, text))
+  }
+
+  /// Replaces the description, encrypting it if the ticket is encrypted
+  pub fn set_description(&mut self, description: String) -> Result<()> {
+    self.description = if self.encrypted {
+      crypt::encrypt(&description)?
+    } else {
+      description
+    };
+    Ok(())
+  }
+
+  /// Encrypts the description and comments for the maintainers with keys
+  pub fn encrypt(&mut self) -> Result<()> {
+    if self.encrypted {
+      bail!("The ticket {} is already encrypted.", self.short_id);
+    }
+    self.description = crypt::encrypt(&self.description)?;
+    for (_, _, comment) in self.comments.values_mut() {
+      comment.0 = crypt::encrypt(&comment.0)?;
+    }
+    self.encrypted = true;
+    Ok(())
+  }
+
+  /// Decrypts the description and comments so they're stored readable again
+  pub fn decrypt(&mut self) -> Result<()> {
+    if !self.encrypted {
+      bail!("The ticket {} isn't encrypted.", self.short_id);
+    }
+    self.description = crypt::reveal(&self.description)?;
+    for (_, _, comment) in self.comments.values_mut() {
+      comment.0 = crypt::reveal(&comment.0)?;
+    }
+    self.encrypted = false;
+    Ok(())
+  }
+
+  /// A copy of the ticket with its description and comments decrypted to be
+  /// shown. It's still marked as encrypted, and saving it would store them
+  /// unencrypted, so it's only for showing.
+  pub fn revealed(&self) -> Result<Ticket> {
+    let mut revealed = self.clone();
+    if self.encrypted {
+      revealed.decrypt()?;
+      revealed.encrypted = true;
+    }
+    Ok(revealed)
+  }
+
+  /// When a comment was made
+  pub fn comment_time(&self, id: &Uuid) -> Option<DateTime<Utc>> {
+    self
+      .comment_times
+      .get(id)
+      .copied()
+      .or_else(|| uuid_time(id))
+  }
+
+  /// The comments from oldest to newest
+  pub fn comments_in_order(&self) -> Vec<(&Uuid, &(Uuid, Name, Comment))> {
+    let mut comments = self.comments.iter().collect::<Vec<_>>();
+    comments.sort_by_key(|(id, _)| (self.comment_time(id), **id));
+    comments
+  }
+
+  /// Each emoji people reacted to a comment with and how many did, e.g.
+  /// '👍 2  🎉 1'
+  pub fn reaction_summary(&self, comment: &Uuid) -> String {
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for emoji in self
+      .reactions
+      .get(comment)
+      .into_iter()
+      .flat_map(|r| r.values().flatten())
+    {
+      *counts.entry(emoji).or_default() += 1;
+    }
+    counts
+      .into_iter()
+      .map(|(emoji, count)| format!("{} {}", emoji, count))
+      .collect::<Vec<String>>()
+      .join("  ")
+  }
+
+  /// The last time anything happened to the ticket, including comments
+  pub fn last_activity(&self) -> Option<DateTime<Utc>> {
+    iter::once(self.created_at)
+      .chain(iter::once(self.updated_at))
+      .chain(self.comments.keys().map(|id| self.comment_time(id)))
+      .max()
+      .flatten()
+  }
+
+  /// Marks the ticket as having just been changed
+  pub fn touch(&mut self) {
+    self.updated_at = Some(Utc::now());
+  }
+
+  /// The tickets this one has the relation to
+  pub fn related(&self, relation: Relation) -> &Vec<Uuid> {
+    match relation {
+      Relation::BlockedBy => &self.blocked_by,
+      Relation::Blocks => &self.blocks,
+      Relation::RelatedTo => &self.related_to,
+      Relation::DuplicateOf => &self.duplicate_of,
+      Relation::DuplicatedBy => &self.duplicated_by,
+    }
+  }
+
+  /// The tickets this one has the relation to, to change them
+  pub fn related_mut(&mut self, relation: Relation) -> &mut Vec<Uuid> {
+    match relation {
+      Relation::BlockedBy => &mut self.blocked_by,
+      Relation::Blocks => &mut self.blocks,
+      Relation::RelatedTo => &mut self.related_to,
+      Relation::DuplicateOf => &mut self.duplicate_of,
+      Relation::DuplicatedBy => &mut self.duplicated_by,
+    }
+  }
+
+  /// Takes the other ticket out of every relation this one has to it and
+  /// returns the relations it was in
+  pub fn unrelate(&mut self, other: &Uuid) -> Vec<Relation> {
+    Relation::ALL
+      .iter()
+      .copied()
+      .filter(|relation| {
+        let related = self.related_mut(*relation);
+        let before = related.len();
+        related.retain(|id| id != other);
+        related.len() != before
+      })
+      .collect()
+  }
+}
+
+/// A record of a comment's contents being removed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Redaction {
+  /// The id of the comment
+  pub comment: Uuid,
+  /// The uuid of who removed it
+  pub by: Uuid,
+  /// The name of who removed it
+  pub name: String,
+  /// Why it was removed
+  pub reason: String,
+  /// When it was removed
+  pub at: DateTime<Utc>,
+}
+
+impl Redaction {
+  /// What the comment's contents are replaced with
+  #[must_use]
+  pub fn marker(&self) -> String {
+    format!(
+      "[Redacted by {} on {}: {}]",
+      self.name,
+      self.at.date_naive(),
+      self.reason
+    )
+  }
+
+  /// The change recorded in the ticket's history for the redaction
+  #[must_use]
+  pub fn action(&self) -> String {
+    format!("Redacted comment {}", &self.comment.to_string()[..8])
+  }
+}
+
+/// A change made to a ticket
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Event {
+  /// When the change was made
+  pub at: DateTime<Utc>,
+  /// Who made the change, if they had a user config
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub by: Option<(Uuid, Name)>,
+  /// What was changed, e.g. 'Added the tag bug'
+  pub action: String,
+  /// The signature of who made the change, if they sign changes
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub signature: Option<String>,
+}
+
+impl Event {
+  /// Records something the current user just did
+  pub fn new(action: impl Into<String>) -> Self {
+    Self {
+      at: Utc::now(),
+      by: get_user_config()
+        .ok()
+        .map(|config| (config.uuid, Name(config.name))),
+      action: action.into(),
+      signature: None,
+    }
+  }
+
+  /// What the signature of the change to the ticket is made over
+  pub fn payload(&self, ticket: &Uuid) -> String {
+    let by = self.by.as_ref().map_or_else(Uuid::nil, |(uuid, _)| *uuid);
+    let at = self.at.to_rfc3339();
+    format!("{}\n{}\n{}\n{}", ticket, by, at, self.action)
+  }
+}
+
+/// What the signature of a comment is made over. The ticket and comment ids
+/// are part of it so the signature can't be passed off as one for another
+/// comment.
+fn payload(
+  ticket: &Uuid,
+  id: &Uuid,
+  by: &Uuid,
+  at: DateTime<Utc>,
+  text: &str,
+) -> String {
+  format!("{}\n{}\n{}\n{}\n{}", ticket, id, by, at.to_rfc3339(), text)
+}
+
+/// One item in a ticket's checklist
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Task {
+  /// What needs doing
+  pub text: String,
+  /// Whether it's been done
+  pub done: bool,
+}
+
+/// How one ticket relates to another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Relation {
+  /// The other ticket needs to be closed first
+  BlockedBy,
+  /// This ticket needs to be closed before the other one
+  Blocks,
+  /// The tickets are related in some other way
+  RelatedTo,
+  /// The ticket is about the same thing as the other one, which was opened
+  /// first
+  DuplicateOf,
+  /// The other ticket is about the same thing as this one
+  DuplicatedBy,
+}
+
+impl Relation {
+  /// Every relation
+  pub const ALL: [Relation; 5] = [
+    Relation::BlockedBy,
+    Relation::Blocks,
+    Relation::RelatedTo,
+    Relation::DuplicateOf,
+    Relation::DuplicatedBy,
+  ];
+
+  /// The relation the other ticket has to this one
+  pub fn inverse(self) -> Self {
+    match self {
+      Relation::BlockedBy => Relation::Blocks,
+      Relation::Blocks => Relation::BlockedBy,
+      Relation::RelatedTo => Relation::RelatedTo,
+      Relation::DuplicateOf => Relation::DuplicatedBy,
+      Relation::DuplicatedBy => Relation::DuplicateOf,
+    }
+  }
+
+  /// How the relation is labeled when showing a ticket
+  pub fn title(self) -> &'static str {
+    match self {
+      Relation::BlockedBy => "Blocked by",
+      Relation::Blocks => "Blocks",
+      Relation::RelatedTo => "Related to",
+      Relation::DuplicateOf => "Duplicate of",
+      Relation::DuplicatedBy => "Duplicated by",
+    }
+  }
+}
+
+impl FromStr for Relation {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "blocked-by" => Ok(Relation::BlockedBy),
+      "blocks" => Ok(Relation::Blocks),
+      "related-to" => Ok(Relation::RelatedTo),
+      "duplicate-of" => Ok(Relation::DuplicateOf),
+      "duplicated-by" => Ok(Relation::DuplicatedBy),
+      _ => bail!(
+        "'{}' is not a relation, expected 'blocked-by', 'blocks', \
+         'related-to', 'duplicate-of', or 'duplicated-by'",
+        s
+      ),
+    }
+  }
+}
+
+impl fmt::Display for Relation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Relation::BlockedBy => write!(f, "blocked by"),
+      Relation::Blocks => write!(f, "blocking"),
+      Relation::RelatedTo => write!(f, "related to"),
+      Relation::DuplicateOf => write!(f, "a duplicate of"),
+      Relation::DuplicatedBy => write!(f, "duplicated by"),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Enum representing what version of the ticket it is and the assumptions that
+/// can be made about it
+pub enum Version {
+  /// The first version
+  V1,
+  /// Added tags
+  V2,
+  /// Added the times tickets were created and last updated
+  V3,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Newtype to represent a users Name
+pub struct Name(pub String);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Newtype to represent a Comment
+pub struct Comment(pub String);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "String", into = "String")]
+/// What is the current state of a ticket
+pub enum Status {
+  /// The ticket has been opened but the issue has not been resolved
+  Open,
+  /// One of the statuses from the repo config that tickets move through
+  /// between being opened and closed, e.g. InProgress
+  Other(String),
+  /// The ticket has a corresponding fix and has been closed
+  Closed,
+}
+
+impl fmt::Display for Status {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Status::Open => write!(f, "Open"),
+      Status::Other(name) => write!(f, "{}", name),
+      Status::Closed => write!(f, "Closed"),
+    }
+  }
+}
+
+impl From<String> for Status {
+  fn from(s: String) -> Self {
+    match s.as_str() {
+      "Open" => Status::Open,
+      "Closed" => Status::Closed,
+      _ => Status::Other(s),
+    }
+  }
+}
+
+impl From<Status> for String {
+  fn from(status: Status) -> Self {
+    status.to_string()
+  }
+}
+
+/// How many hours of work make up a day in an estimate
+const HOURS_PER_DAY: f64 = 8.0;
+/// How many days of work make up a week in an estimate
+const DAYS_PER_WEEK: f64 = 5.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+/// How much work a ticket is, either in story points or in time
+pub enum Estimate {
+  /// Story points, written like `3` or `3pt`
+  Points(f64),
+  /// Time, written like `30m`, `4h`, `2d`, or `1w` where a day is 8 hours of
+  /// work and a week is 5 days
+  Hours(f64),
+}
+
+impl FromStr for Estimate {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    let s = s.trim().to_lowercase();
+    let end = s
+      .find(|c: char| !c.is_ascii_digit() && c != '.')
+      .unwrap_or(s.len());
+    let (number, unit) = s.split_at(end);
+    let number = match number.parse::<f64>() {
+      Ok(number) => number,
+      Err(_) => bail!(
+        "'{}' is not an estimate, expected points like '3' or a time like \
+         '30m', '4h', '2d', or '1w'",
+        s
+      ),
+    };
+    let hours = match unit.trim() {
+      "" | "p" | "pt" | "pts" | "point" | "points" => {
+        return Ok(Estimate::Points(number))
+      }
+      "m" | "min" | "mins" | "minute" | "minutes" => number / 60.0,
+      "h" | "hr" | "hrs" | "hour" | "hours" => number,
+      "d" | "day" | "days" => number * HOURS_PER_DAY,
+      "w" | "wk" | "wks" | "week" | "weeks" => {
+        number * DAYS_PER_WEEK * HOURS_PER_DAY
+      }
+      unit => bail!(
+        "'{}' is not a unit for estimates, expected 'pt', 'm', 'h', 'd', or \
+         'w'",
+        unit
+      ),
+    };
+    Ok(Estimate::Hours(hours))
+  }
+}
+
+impl fmt::Display for Estimate {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Estimate::Points(points) => write!(f, "{}pt", points),
+      Estimate::Hours(hours) => write!(f, "{}h", hours),
+    }
+  }
+}
+
+impl TryFrom<String> for Estimate {
+  type Error = anyhow::Error;
+
+  fn try_from(s: String) -> Result<Self> {
+    s.parse()
+  }
+}
+
+impl From<Estimate> for String {
+  fn from(estimate: Estimate) -> Self {
+    estimate.to_string()
+  }
+}
+
+/// The value of one of a ticket's custom fields
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum FieldValue {
+  /// The value of a number field
+  Number(f64),
+  /// The value of a string, enum, or date field
+  Text(String),
+}
+
+impl FieldValue {
+  /// Reads what was typed in for the field, making sure it fits the field's
+  /// type. Enum values are matched ignoring case.
+  pub fn parse(field: &Field, value: &str) -> Result<Self> {
+    let value = value.trim();
+    match field.kind {
+      FieldKind::String => Ok(FieldValue::Text(value.to_owned())),
+      FieldKind::Enum => field
+        .values
+        .iter()
+        .find(|v| v.eq_ignore_ascii_case(value))
+        .map(|v| FieldValue::Text(v.clone()))
+        .ok_or_else(|| {
+          format_err!(
+            "'{}' isn't a value of {}, expected one of {}",
+            value,
+            field.name,
+            field.values.join(", ")
+          )
+        }),
+      FieldKind::Number => {
+        value.parse().map(FieldValue::Number).map_err(|_| {
+          format_err!("{} is a number, got '{}'", field.name, value)
+        })
+      }
+      FieldKind::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|_| FieldValue::Text(value.to_owned()))
+        .map_err(|_| {
+          format_err!(
+            "{} is a date like 2020-07-01, got '{}'",
+            field.name,
+            value
+          )
+        }),
+    }
+  }
+
+  /// Makes sure a value read from a ticket file fits the field's type
+  pub fn check(&self, field: &Field) -> Result<()> {
+    if FieldValue::parse(field, &self.to_string())? != *self {
+      bail!("{} is a {}, got {}", field.name, field.kind, self);
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for FieldValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FieldValue::Number(number) => write!(f, "{}", number),
+      FieldValue::Text(text) => write!(f, "{}", text),
+    }
+  }
+}
@@ -0,0 +1,251 @@
+//! Upgrading ticket files from older versions of the format. Each version has
+//! a migration from the one before it, and a ticket has every migration after
+//! its version run on it in order, so a V0 ticket goes through V1 and V2 on its
+//! way to V3. The migrations work on the toml as it's stored since older
+//! tickets can't always be read in as a [`Ticket`].
+use crate::{
+  actions::{
+    archive_dir,
+    save_ticket,
+    short_id,
+    status_dir,
+    statuses,
+    ticket_path,
+    uuid_time,
+    uuid_v1,
+  },
+  format::{
+    parse_table,
+    FileFormat,
+  },
+  Ticket,
+};
+use anyhow::{
+  bail,
+  format_err,
+  Result,
+};
+use chrono::prelude::*;
+use log::*;
+use shared::{
+  find_root,
+  write_atomic,
+};
+use std::{
+  fs,
+  iter,
+  path::{
+    Path,
+    PathBuf,
+  },
+};
+use toml::{
+  value::Table,
+  Value,
+};
+
+/// A step from one version of the format to the next
+struct Migration {
+  /// The version the ticket is at after this runs
+  to: &'static str,
+  /// What the migration changes, shown by `--dry-run`
+  what: &'static str,
+  run: fn(&mut Table) -> Result<()>,
+}
+
+/// Every migration, oldest first
+const MIGRATIONS: [Migration; 3] = [
+  Migration {
+    to: "V1",
+    what: "Gives the ticket a uuid, dropping its number and its assignee \
+           since that was only a name",
+    run: v0_to_v1,
+  },
+  Migration {
+    to: "V2",
+    what: "Adds tags",
+    run: v1_to_v2,
+  },
+  Migration {
+    to: "V3",
+    what: "Adds the short id and the times the ticket was created and last \
+           updated",
+    run: v2_to_v3,
+  },
+];
+
+/// A ticket that needs upgrading
+#[derive(Debug)]
+pub struct Upgrade {
+  /// Where the ticket is
+  pub path: PathBuf,
+  /// Where the ticket is after being upgraded, which is only different for
+  /// tickets whose file name had their number in it
+  pub new_path: PathBuf,
+  /// The version it's at
+  pub from: String,
+  /// What each migration run on it does
+  pub steps: Vec<&'static str>,
+}
+
+/// What `migrate` did or, for a dry run, would do
+#[derive(Debug)]
+pub struct Report {
+  /// The tickets that were upgraded
+  pub upgrades: Vec<Upgrade>,
+  /// Where the tickets were copied to before being changed. There's no backup
+  /// for a dry run or if nothing needed upgrading.
+  pub backup: Option<PathBuf>,
+}
+
+/// The newest version of the format
+pub fn latest() -> &'static str {
+  MIGRATIONS[MIGRATIONS.len() - 1].to
+}
+
+/// Upgrades every ticket that isn't at the newest version, archived ones
+/// included. Unless it's a dry run, the tickets are all copied to
+/// `.dev-suite/backups/<time>` first.
+pub fn migrate(dry_run: bool) -> Result<Report> {
+  let archive = archive_dir()?;
+  let mut dirs = statuses()?
+    .iter()
+    .map(status_dir)
+    .collect::<Result<Vec<PathBuf>>>()?;
+  dirs.push(archive.clone());
+  let mut pending = Vec::new();
+  for dir in dirs {
+    if !dir.exists() {
+      continue;
+    }
+    for entry in fs::read_dir(dir)? {
+      let path = entry?.path();
+      if FileFormat::of(&path).is_none() {
+        continue;
+      }
+      let table = parse_table(&fs::read_to_string(&path)?)
+        .map_err(|e| format_err!("{}: {}", path.display(), e))?;
+      let version = version(&table)
+        .map_err(|e| format_err!("{}: {}", path.display(), e))?;
+      if version < MIGRATIONS.len() {
+        pending.push((path, version, table));
+      }
+    }
+  }
+
+  let backup = if dry_run || pending.is_empty() {
+    None
+  } else {
+    Some(backup(pending.iter().map(|(path, _, _)| path.as_path()))?)
+  };
+  let mut upgrades = Vec::new();
+  for (path, version, mut table) in pending {
+    let from = match version {
+      0 => "V0",
+      v => MIGRATIONS[v - 1].to,
+    };
+    debug!("Upgrading {} from {}", path.display(), from);
+    for migration in &MIGRATIONS[version..] {
+      (migration.run)(&mut table)
+        .map_err(|e| format_err!("{}: {}", path.display(), e))?;
+      let _ = table.insert("version".into(), migration.to.into());
+    }
+    let ticket = Value::Table(table).try_into::<Ticket>()?;
+    let archived = path.parent() == Some(archive.as_path());
+    let new_path = if archived {
+      path.clone()
+    } else {
+      ticket_path(&ticket)?
+    };
+    if !dry_run && archived {
+      // Saving it the usual way would move it back out of the archive
+      let format = FileFormat::of(&path).unwrap_or(FileFormat::Toml);
+      write_atomic(&path, format.write(&ticket)?)?;
+    } else if !dry_run {
+      save_ticket(&ticket)?;
+      // Files from before tickets had ids aren't found to be moved by saving
+      if path != new_path && path.exists() {
+        fs::remove_file(&path)?;
+      }
+    }
+    upgrades.push(Upgrade {
+      path,
+      new_path,
+      from: from.into(),
+      steps: MIGRATIONS[version..].iter().map(|m| m.what).collect(),
+    });
+  }
+  Ok(Report { upgrades, backup })
+}
+
+/// How many migrations the ticket has had, which is 0 for V0 tickets since
+/// they don't have a version
+fn version(table: &Table) -> Result<usize> {
+  match table.get("version").and_then(Value::as_str) {
+    None if table.contains_key("number") => Ok(0),
+    None => bail!("The ticket doesn't have a version"),
+    Some(version) => match MIGRATIONS.iter().position(|m| m.to == version) {
+      Some(i) => Ok(i + 1),
+      None => bail!("{} isn't a version of tickets", version),
+    },
+  }
+}
+
+/// Copies the files into a new directory in `.dev-suite/backups`, keeping
+/// where they are in the repo, and returns the directory
+fn backup<'a>(paths: impl Iterator<Item = &'a Path>) -> Result<PathBuf> {
+  let root = find_root()?;
+  let dir = root
+    .join(".dev-suite")
+    .join("backups")
+    .join(Utc::now().format("%Y%m%dT%H%M%S%.f").to_string());
+  for path in paths {
+    let to = dir.join(path.strip_prefix(&root)?);
+    if let Some(parent) = to.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    let _ = fs::copy(path, &to)?;
+  }
+  Ok(dir)
+}
+
+fn v0_to_v1(ticket: &mut Table) -> Result<()> {
+  let _ = ticket.remove("number");
+  let _ = ticket.remove("assignee");
+  let _ = ticket.insert("id".into(), uuid_v1()?.to_string().into());
+  let _ = ticket.insert("assignees".into(), Value::Array(Vec::new()));
+  let _ = ticket.insert("comments".into(), Value::Table(Table::new()));
+  Ok(())
+}
+
+fn v1_to_v2(ticket: &mut Table) -> Result<()> {
+  let _ = ticket
+    .entry("tags")
+    .or_insert_with(|| Value::Array(Vec::new()));
+  Ok(())
+}
+
+/// Tickets without a short id or creation time get one from what they have,
+/// and the last update is the latest change anything recorded
+fn v2_to_v3(table: &mut Table) -> Result<()> {
+  let mut ticket = Value::Table(table.clone()).try_into::<Ticket>()?;
+  if ticket.short_id.is_empty() {
+    ticket.short_id = short_id(&ticket.id, &ticket.title, &ticket.description);
+  }
+  if ticket.created_at.is_none() {
+    ticket.created_at = uuid_time(&ticket.id);
+  }
+  if ticket.updated_at.is_none() {
+    ticket.updated_at = iter::once(ticket.created_at)
+      .chain(iter::once(ticket.closed_at))
+      .chain(ticket.history.iter().map(|e| Some(e.at)))
+      .chain(ticket.comments.keys().map(|id| ticket.comment_time(id)))
+      .max()
+      .flatten();
+  }
+  match Value::try_from(&ticket)? {
+    Value::Table(upgraded) => *table = upgraded,
+    _ => bail!("The ticket didn't serialize to a table"),
+  }
+  Ok(())
+}
@@ -0,0 +1,144 @@
+//! Signing the comments and changes someone makes to tickets with their gpg or
+//! ssh key, so who wrote them can be checked rather than taken from the name
+//! and uuid saved with them. Signing is opted into with a `[signing]` table in
+//! the user config, and the signatures are detached ones saved on the ticket
+//! next to what they sign. They're checked against the keys maintainers have
+//! in the `[keys]` table of the repo config.
+use crate::{
+  actions::uuid_v1,
+  crypt::pipe,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use configamajig::{
+  get_user_config,
+  Signing,
+};
+use std::{
+  env,
+  fs,
+  path::Path,
+};
+use uuid::Uuid;
+
+/// What ssh signatures are made for, so they can't be passed off as
+/// signatures for something else made with the same key, like a git commit
+pub const NAMESPACE: &str = "dev-suite-ticket";
+
+/// Signs the payload with the user's key, or returns `None` if they don't sign
+pub fn sign(payload: &str) -> Result<Option<String>> {
+  let signing = match get_user_config() {
+    Ok(config) => config.signing,
+    Err(_) => None,
+  };
+  let signature = match signing {
+    None => return Ok(None),
+    Some(Signing::Gpg { key }) => pipe(
+      "gpg",
+      &["--batch", "--armor", "--local-user", &key, "--detach-sign"],
+      payload,
+    )?,
+    Some(Signing::Ssh { key }) => pipe(
+      "ssh-keygen",
+      &["-Y", "sign", "-n", NAMESPACE, "-f", &key.to_string_lossy()],
+      payload,
+    )?,
+  };
+  Ok(Some(signature))
+}
+
+/// What's signed to vouch for a maintainer's key in the repo config
+pub fn key_payload(uuid: &Uuid, key: &str) -> String {
+  format!("{} {}\n", uuid, key)
+}
+
+/// Whether the key from the repo config is an ssh public key rather than a
+/// gpg key
+pub fn is_ssh_key(key: &str) -> bool {
+  ["ssh-", "ecdsa-", "sk-"].iter().any(|p| key.starts_with(p))
+}
+
+/// Checks that the signature of the payload was made with the key, either an
+/// ssh public key or a gpg key by its fingerprint, long id, or email. The gpg
+/// key has to be in the keyring to check it.
+pub fn verify(payload: &str, signature: &str, key: &str) -> Result<()> {
+  let temp = env::temp_dir().join(format!("ticket-verify-{}", uuid_v1()?));
+  fs::create_dir(&temp)?;
+  let checked = if is_ssh_key(key) {
+    verify_ssh(&temp, payload, signature, key)
+  } else {
+    verify_gpg(&temp, payload, signature, key)
+  };
+  let _ = fs::remove_dir_all(&temp);
+  checked
+}
+
+fn verify_ssh(
+  temp: &Path,
+  payload: &str,
+  signature: &str,
+  key: &str,
+) -> Result<()> {
+  let allowed = temp.join("allowed_signers");
+  let sig = temp.join("signature");
+  fs::write(&allowed, format!("ticket {}\n", key))?;
+  fs::write(&sig, signature)?;
+  let _ = pipe(
+    "ssh-keygen",
+    &[
+      "-Y",
+      "verify",
+      "-n",
+      NAMESPACE,
+      "-I",
+      "ticket",
+      "-f",
+      &allowed.to_string_lossy(),
+      "-s",
+      &sig.to_string_lossy(),
+    ],
+    payload,
+  )?;
+  Ok(())
+}
+
+fn verify_gpg(
+  temp: &Path,
+  payload: &str,
+  signature: &str,
+  key: &str,
+) -> Result<()> {
+  let sig = temp.join("signature");
+  fs::write(&sig, signature)?;
+  let sig = sig.to_string_lossy();
+  let args = ["--batch", "--status-fd", "1", "--verify", &sig, "-"];
+  let status = pipe("gpg", &args, payload)?;
+  if !signed_by(&status, key) {
+    bail!("It was signed with a key other than {}", key);
+  }
+  Ok(())
+}
+
+/// Whether gpg's status output for a good signature says it was made by the
+/// key
+fn signed_by(status: &str, key: &str) -> bool {
+  let wanted = key.trim_start_matches("0x").replace(' ', "").to_uppercase();
+  let email = format!("<{}>", key.to_lowercase());
+  status
+    .lines()
+    .filter_map(|line| line.strip_prefix("[GNUPG:] "))
+    .any(|line| {
+      let fields = line.split(' ').collect::<Vec<&str>>();
+      match fields.as_slice() {
+        ["VALIDSIG", fingerprint, .., primary] => {
+          *fingerprint == wanted || *primary == wanted
+        }
+        ["GOODSIG", id, ..] => {
+          *id == wanted || line.to_lowercase().contains(&email)
+        }
+        _ => false,
+      }
+    })
+}
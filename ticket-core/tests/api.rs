@@ -0,0 +1,57 @@
+//! Using the library the way a tool embedding it would, without `ticket`
+
+use std::{
+  error::Error,
+  fs,
+};
+use tempfile::tempdir;
+use ticket_core::{
+  actions::{
+    find_ticket,
+    get_all_tickets,
+    save_ticket,
+    ticket_path,
+    uuid_time,
+    uuid_v1,
+  },
+  new_ticket,
+  Status,
+};
+
+#[test]
+fn make_and_load_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = tempdir()?;
+  fs::create_dir_all(dir.path().join(".dev-suite").join("ticket"))?;
+  shared::set_root(dir.path())?;
+
+  let mut ticket = new_ticket("Embedded".into(), "From a plugin".into())?;
+  save_ticket(&ticket)?;
+  assert!(ticket_path(&ticket)?.ends_with("open/embedded.toml"));
+
+  ticket.record("Moved it along");
+  ticket.status = Status::Closed;
+  save_ticket(&ticket)?;
+  let tickets = get_all_tickets()?;
+  assert_eq!(tickets.len(), 1);
+  assert_eq!(tickets[0].status, Status::Closed);
+  assert!(ticket_path(&tickets[0])?.ends_with("closed/embedded.toml"));
+
+  let found = find_ticket(&ticket.short_id)?;
+  assert_eq!(found.description, "From a plugin");
+  assert_eq!(found.history.len(), 2);
+  Ok(())
+}
+
+#[test]
+fn uuids_made_together_differ() -> Result<(), Box<dyn Error>> {
+  let ids = (0..1000)
+    .map(|_| uuid_v1())
+    .collect::<Result<Vec<_>, _>>()?;
+  let mut unique = ids.clone();
+  unique.sort();
+  unique.dedup();
+  assert_eq!(unique.len(), ids.len());
+  let times = ids.iter().map(uuid_time).collect::<Vec<_>>();
+  assert!(times.windows(2).all(|w| w[0] <= w[1]));
+  Ok(())
+}
@@ -0,0 +1,43 @@
+//! Finds the commits that mention a ticket
+use crate::actions::find_ticket;
+use anyhow::Result;
+use chrono::prelude::*;
+use colored::*;
+use git2::{
+  Repository,
+  Sort,
+};
+use shared::find_root;
+
+/// Prints the commits reachable from HEAD whose messages mention the ticket's
+/// uuid or short id, newest first
+pub fn activity(id: &str) -> Result<()> {
+  let ticket = find_ticket(id)?;
+  let repo = Repository::open(find_root()?)?;
+  if repo.head().is_err() {
+    // Nothing's been committed yet
+    return Ok(());
+  }
+  let mut walk = repo.revwalk()?;
+  walk.set_sorting(Sort::TIME);
+  walk.push_head()?;
+
+  let id = ticket.id.to_string();
+  for oid in walk {
+    let commit = repo.find_commit(oid?)?;
+    let message = commit.message().unwrap_or_default().to_lowercase();
+    if !message.contains(&id) && !message.contains(&ticket.short_id) {
+      continue;
+    }
+    let at = Local.timestamp_opt(commit.time().seconds(), 0).single();
+    println!(
+      "{} {} {} {}",
+      commit.id().to_string()[..8].yellow(),
+      at.map_or_else(String::new, |at| at.format("%Y-%m-%d").to_string())
+        .dimmed(),
+      commit.author().name().unwrap_or("Someone").cyan(),
+      commit.summary().unwrap_or_default()
+    );
+  }
+  Ok(())
+}
@@ -0,0 +1,108 @@
+//! Expands the aliases and default flags from the user config before the
+//! arguments are parsed
+
+use configamajig::get_user_config;
+use log::*;
+
+/// Replaces an alias used as the subcommand with what it stands for, then adds
+/// the default flags for the subcommand that weren't passed already. Aliases
+/// aren't expanded recursively so an alias can share a name with the
+/// subcommand it stands for.
+pub fn expand(mut args: Vec<String>) -> Vec<String> {
+  let config = match get_user_config() {
+    Ok(config) => config,
+    Err(e) => {
+      debug!("No user config so aliases aren't expanded: {}", e);
+      return args;
+    }
+  };
+
+  let pos = match subcommand(&args) {
+    Some(pos) => pos,
+    None => return args,
+  };
+  if let Some(alias) = config.aliases.get(&args[pos]) {
+    trace!("Expanding alias {} to {}.", args[pos], alias);
+    let _ = args.splice(pos..=pos, split(alias));
+  }
+
+  let pos = match subcommand(&args) {
+    Some(pos) => pos,
+    None => return args,
+  };
+  if let Some(defaults) = config.defaults.get(&args[pos]) {
+    let mut extra = Vec::new();
+    for group in flag_groups(&split(defaults)) {
+      let name = group[0].split('=').next().unwrap_or_default();
+      let passed = args[pos..]
+        .iter()
+        .any(|arg| arg == name || arg.starts_with(&format!("{}=", name)));
+      if !passed {
+        extra.extend(group);
+      }
+    }
+    trace!("Adding default flags {:?} to {}.", extra, args[pos]);
+    let _ = args.splice(pos + 1..pos + 1, extra);
+  }
+  args
+}
+
+/// The index of the subcommand, which is the first argument that isn't a
+/// global flag or its value
+fn subcommand(args: &[String]) -> Option<usize> {
+  let mut i = 1;
+  while i < args.len() {
+    match args[i].as_str() {
+      "--root" => i += 2,
+      arg if arg.starts_with('-') => i += 1,
+      _ => return Some(i),
+    }
+  }
+  None
+}
+
+/// Splits the arguments into each flag along with any values after it
+fn flag_groups(args: &[String]) -> Vec<Vec<String>> {
+  let mut groups: Vec<Vec<String>> = Vec::new();
+  for arg in args {
+    match groups.last_mut() {
+      Some(group) if !arg.starts_with('-') => group.push(arg.clone()),
+      _ => groups.push(vec![arg.clone()]),
+    }
+  }
+  groups
+}
+
+/// Splits a string into arguments on whitespace the way a shell would, keeping
+/// anything in single or double quotes together
+fn split(s: &str) -> Vec<String> {
+  let mut args = Vec::new();
+  let mut current = String::new();
+  let mut in_arg = false;
+  let mut quote = None;
+  for c in s.chars() {
+    match quote {
+      Some(q) if c == q => quote = None,
+      Some(_) => current.push(c),
+      None if c == '\'' || c == '"' => {
+        quote = Some(c);
+        in_arg = true;
+      }
+      None if c.is_whitespace() => {
+        if in_arg {
+          args.push(current.clone());
+          current.clear();
+          in_arg = false;
+        }
+      }
+      None => {
+        current.push(c);
+        in_arg = true;
+      }
+    }
+  }
+  if in_arg {
+    args.push(current);
+  }
+  args
+}
@@ -0,0 +1,202 @@
+//! The JSON API served by `ticket serve --api` so that bots and CI jobs can
+//! work with the tickets. Tickets are sent as the same json `ticket show
+//! --format json` prints and errors as `{"error": "..."}`.
+//!
+//! - `GET /tickets` lists the tickets, or only those with `?status=<status>`
+//! - `POST /tickets` makes one from `{"title": "...", "description": "..."}`,
+//!   with `"fields": {"<name>": "<value>"}` for any custom fields
+//! - `GET /tickets/<id>` gets one by its uuid or short id
+//! - `POST /tickets/<id>/comments` comments on it with `{"comment": "..."}`
+//! - `POST /tickets/<id>/close` closes it, with an optional `{"reason": "..."}`
+//!
+//! Everything posted has to be sent as `application/json`, which browsers
+//! won't send to another site without asking it first, and when the server
+//! has a token every request has to carry it as `Authorization: Bearer ...`.
+use crate::{
+  actions::{
+    find_ticket,
+    get_all_tickets,
+    resolve_status,
+  },
+  close,
+  comment,
+  serve::{
+    self,
+    body,
+    check_host,
+    header_value,
+    respond,
+    Page,
+  },
+  FieldValue,
+  Ticket,
+};
+use anyhow::format_err;
+use configamajig::get_repo_config;
+use log::*;
+use serde::{
+  de::DeserializeOwned,
+  Deserialize,
+};
+use serde_json::{
+  json,
+  Value,
+};
+use std::collections::BTreeMap;
+use tiny_http::{
+  Method,
+  Request,
+};
+
+#[derive(Deserialize)]
+struct NewTicket {
+  title: String,
+  #[serde(default)]
+  description: String,
+  #[serde(default)]
+  fields: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct NewComment {
+  comment: String,
+}
+
+#[derive(Deserialize)]
+struct Close {
+  #[serde(default)]
+  reason: Option<String>,
+}
+
+/// An error and the status code to send it with
+struct ApiError(u16, anyhow::Error);
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+  fn from(e: E) -> Self {
+    ApiError(400, e.into())
+  }
+}
+
+pub fn handle(request: &mut Request, port: u16, token: Option<&str>) -> Page {
+  let (status, value) = check(request, port, token)
+    .and_then(|_| route(request))
+    .unwrap_or_else(|ApiError(status, e)| {
+      warn!("{} {}: {}", request.method(), request.url(), e);
+      (status, json!({ "error": e.to_string() }))
+    });
+  respond(status, "application/json", value.to_string())
+}
+
+/// Turns away requests from other sites and, if the server has a token,
+/// anyone without it
+fn check(
+  request: &Request,
+  port: u16,
+  token: Option<&str>,
+) -> Result<(), ApiError> {
+  check_host(request, port).map_err(|e| ApiError(403, e))?;
+  if let Some(token) = token {
+    let sent = header_value(request, "Authorization")
+      .and_then(|value| value.strip_prefix("Bearer "));
+    if sent != Some(token) {
+      return Err(ApiError(
+        401,
+        format_err!("The request needs the server's token"),
+      ));
+    }
+  }
+  let json = header_value(request, "Content-Type").is_some_and(|value| {
+    value.split(';').next().map(str::trim) == Some("application/json")
+  });
+  if *request.method() == Method::Post && !json {
+    return Err(ApiError(
+      415,
+      format_err!("Requests have to be sent as application/json"),
+    ));
+  }
+  Ok(())
+}
+
+fn route(request: &mut Request) -> Result<(u16, Value), ApiError> {
+  let url = request.url().to_owned();
+  let mut parts = url.splitn(2, '?');
+  let path = parts
+    .next()
+    .unwrap_or_default()
+    .split('/')
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>();
+  let query = parts.next().unwrap_or_default();
+  let method = request.method().clone();
+  match (method, path.as_slice()) {
+    (Method::Get, ["tickets"]) => {
+      let status = url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == "status")
+        .map(|(_, status)| resolve_status(&status))
+        .transpose()?;
+      let tickets = get_all_tickets()?
+        .into_iter()
+        .filter(|t| match &status {
+          Some(status) => t.status == *status,
+          None => true,
+        })
+        .collect::<Vec<_>>();
+      Ok((200, serde_json::to_value(tickets)?))
+    }
+    (Method::Post, ["tickets"]) => {
+      let new: NewTicket = read(request)?;
+      let fields = parse_fields(new.fields)?;
+      let ticket = serve::new(new.title, new.description, fields)?;
+      Ok((201, serde_json::to_value(ticket)?))
+    }
+    (Method::Get, ["tickets", id]) => {
+      Ok((200, serde_json::to_value(find(id)?)?))
+    }
+    (Method::Post, ["tickets", id, "comments"]) => {
+      let id = find(id)?.id.to_string();
+      let new: NewComment = read(request)?;
+      comment(&id, new.comment)?;
+      Ok((201, serde_json::to_value(find(&id)?)?))
+    }
+    (Method::Post, ["tickets", id, "close"]) => {
+      let id = find(id)?.id.to_string();
+      let Close { reason } = read(request)?;
+      close(&id, reason)?;
+      Ok((200, serde_json::to_value(find(&id)?)?))
+    }
+    (method, _) => Err(ApiError(
+      404,
+      format_err!("There's nothing at {} {}", method, url),
+    )),
+  }
+}
+
+/// The custom fields of a new ticket from the strings they were posted as
+fn parse_fields(
+  fields: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, FieldValue>, ApiError> {
+  if fields.is_empty() {
+    return Ok(BTreeMap::new());
+  }
+  let config = get_repo_config()?;
+  fields
+    .into_iter()
+    .map(|(name, value)| {
+      let field = config
+        .field(&name)
+        .ok_or_else(|| format_err!("There is no field '{}'", name))?;
+      Ok((name, FieldValue::parse(field, &value)?))
+    })
+    .collect()
+}
+
+fn find(id: &str) -> Result<Ticket, ApiError> {
+  find_ticket(id).map_err(|e| ApiError(404, e))
+}
+
+/// Reads the json that was posted, where nothing at all counts as `{}`
+fn read<T: DeserializeOwned>(request: &mut Request) -> Result<T, ApiError> {
+  let body = body(request)?;
+  let body = if body.trim().is_empty() { "{}" } else { &body };
+  Ok(serde_json::from_str(body)?)
+}
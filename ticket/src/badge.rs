@@ -0,0 +1,123 @@
+use crate::{
+  actions::get_all_tickets,
+  Status,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use log::*;
+use std::{
+  fs,
+  path::Path,
+  str::FromStr,
+};
+
+/// What a badge shows
+#[derive(Debug, Clone, Copy)]
+pub enum Badge {
+  /// How many tickets are open
+  Open,
+  /// What percent of the tickets are closed
+  Progress,
+}
+
+impl FromStr for Badge {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "open" => Ok(Badge::Open),
+      "progress" => Ok(Badge::Progress),
+      _ => bail!("'{}' is not a badge, expected 'open' or 'progress'", s),
+    }
+  }
+}
+
+/// Writes an svg badge for the tickets, optionally only those with the given
+/// tag, to the path
+pub fn badge(kind: Badge, path: &Path, tag: Option<String>) -> Result<()> {
+  let tagged = |t: &Ticket| tag.as_ref().is_none_or(|tag| t.tags.contains(tag));
+  let (closed, open): (Vec<Ticket>, Vec<Ticket>) = get_all_tickets()?
+    .into_iter()
+    .filter(tagged)
+    .partition(|t| t.status == Status::Closed);
+  let (open, closed) = (open.len(), closed.len());
+
+  let svg = match kind {
+    Badge::Open => svg(
+      tag.as_deref().unwrap_or("open tickets"),
+      &open.to_string(),
+      if open == 0 { GREEN } else { BLUE },
+    ),
+    Badge::Progress => {
+      // Nothing to do counts as done
+      let percent = (closed * 100).checked_div(open + closed).unwrap_or(100);
+      svg(
+        tag.as_deref().unwrap_or("progress"),
+        &format!("{}%", percent),
+        match percent {
+          100 => GREEN,
+          50..=99 => YELLOW,
+          _ => RED,
+        },
+      )
+    }
+  };
+  trace!("Writing badge to {}.", path.display());
+  fs::write(path, svg)?;
+  info!("Wrote badge to {}", path.display());
+  Ok(())
+}
+
+const GREEN: &str = "#4c1";
+const BLUE: &str = "#007ec6";
+const YELLOW: &str = "#dfb317";
+const RED: &str = "#e05d44";
+
+/// A flat two part badge like the ones from shields.io. There's no font to
+/// measure the text with so widths are a guess based on the number of
+/// characters, which is good enough for short labels.
+fn svg(label: &str, value: &str, color: &str) -> String {
+  let width = |s: &str| s.chars().count() * 7 + 10;
+  let (label_width, value_width) = (width(label), width(value));
+  let total = label_width + value_width;
+  let (label, value) = (escape(label), escape(value));
+  format!(
+    r##"<svg xmlns="http://www.w3.org/2000/svg" width="{total}" height="20" role="img" aria-label="{label}: {value}">
+  <linearGradient id="s" x2="0" y2="100%">
+    <stop offset="0" stop-color="#bbb" stop-opacity=".1"/>
+    <stop offset="1" stop-opacity=".1"/>
+  </linearGradient>
+  <clipPath id="r">
+    <rect width="{total}" height="20" rx="3" fill="#fff"/>
+  </clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="#555"/>
+    <rect x="{label_width}" width="{value_width}" height="20" fill="{color}"/>
+    <rect width="{total}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{value_x}" y="14">{value}</text>
+  </g>
+</svg>
+"##,
+    total = total,
+    label = label,
+    value = value,
+    label_width = label_width,
+    value_width = value_width,
+    color = color,
+    label_x = label_width / 2,
+    value_x = label_width + value_width / 2,
+  )
+}
+
+fn escape(s: &str) -> String {
+  s.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
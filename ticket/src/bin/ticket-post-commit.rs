@@ -0,0 +1,104 @@
+//! Closes the tickets named in the trailers of the commit that was just made,
+//! e.g. `Closes: 1a2b3c4d` or `Fixes: 1a2b3c4d, 5e6f7a8b`
+
+use std::{
+  env,
+  error::Error,
+  path::PathBuf,
+  process::{
+    self,
+    Command,
+  },
+};
+
+/// Trailers that close the tickets they name
+const TRAILERS: [&str; 3] = ["closes", "fixes", "resolves"];
+
+fn git(args: &[&str]) -> Result<String, Box<dyn Error>> {
+  let output = Command::new("git").args(args).output()?;
+  if !output.status.success() {
+    return Err(format!("Failed to run git {}", args.join(" ")).into());
+  }
+  Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+}
+
+/// The ticket ids in the closing trailers of a commit message. Trailers are
+/// the `Key: value` lines of the last paragraph.
+fn closed_tickets(message: &str) -> Vec<String> {
+  let trailers = message.trim().rsplit("\n\n").next().unwrap_or_default();
+  // A message that's only a subject has no trailers
+  if trailers == message.trim() {
+    return Vec::new();
+  }
+  trailers
+    .lines()
+    .filter_map(|line| line.split_once(':'))
+    .filter(|(key, _)| TRAILERS.contains(&key.trim().to_lowercase().as_str()))
+    .flat_map(|(_, ids)| ids.split(','))
+    .map(|id| id.trim().to_owned())
+    .filter(|id| !id.is_empty())
+    .collect()
+}
+
+/// Whether the trailer value is a ticket's uuid or short id rather than
+/// something else, like an issue number on another tracker or a word
+fn is_ticket_id(id: &str) -> bool {
+  let hex = |part: &str, len: usize| {
+    part.len() == len && part.chars().all(|c| c.is_ascii_hexdigit())
+  };
+  let parts = id.split('-').collect::<Vec<&str>>();
+  match parts.as_slice() {
+    [short] => hex(short, 8),
+    [a, b, c, d, e] => {
+      hex(a, 8) && hex(b, 4) && hex(c, 4) && hex(d, 4) && hex(e, 12)
+    }
+    _ => false,
+  }
+}
+
+/// The `ticket` binary installed next to this one, or the one on the PATH
+fn ticket_bin() -> PathBuf {
+  env::current_exe()
+    .ok()
+    .map(|exe| exe.with_file_name(format!("ticket{}", env::consts::EXE_SUFFIX)))
+    .filter(|ticket| ticket.exists())
+    .unwrap_or_else(|| "ticket".into())
+}
+
+fn main() {
+  if let Err(e) = || -> Result<(), Box<dyn Error>> {
+    // .git is a file pointing to the real one in worktrees and submodules
+    let git_dir = PathBuf::from(git(&["rev-parse", "--git-dir"])?);
+    // Rebases and cherry-picks make copies of commits that already closed
+    // their tickets
+    if git_dir.join("rebase-merge").exists()
+      || git_dir.join("rebase-apply").exists()
+      || git_dir.join("CHERRY_PICK_HEAD").exists()
+    {
+      return Ok(());
+    }
+
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let message = git(&["log", "-1", "--format=%B", &commit])?;
+    for id in closed_tickets(&message) {
+      if !is_ticket_id(&id) {
+        eprintln!(
+          "warning: skipping '{}' in commit {} since it isn't a ticket id",
+          id, commit
+        );
+        continue;
+      }
+      let status = Command::new(ticket_bin())
+        .args(["close", &id, "--reason"])
+        .arg(format!("Closed by commit {}", commit))
+        .status()?;
+      if !status.success() {
+        eprintln!("warning: commit {} didn't close ticket {}", commit, id);
+      }
+    }
+    Ok(())
+  }() {
+    eprintln!("{}", e);
+    process::exit(1);
+  }
+}
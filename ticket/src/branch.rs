@@ -0,0 +1,81 @@
+//! Makes git branches to work on tickets in
+use crate::actions::{
+  find_ticket,
+  save_ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use git2::{
+  build::CheckoutBuilder,
+  BranchType,
+  Repository,
+};
+use log::*;
+use shared::find_root;
+
+/// Makes a branch for the ticket off of HEAD, or reuses the one with that name
+/// if it exists, checks it out, and records it on the ticket
+pub fn branch(id: &str, name: Option<String>, no_checkout: bool) -> Result<()> {
+  let ticket = find_ticket(id)?;
+  let name =
+    name.unwrap_or_else(|| branch_name(&ticket.short_id, &ticket.title));
+  let repo = Repository::open(find_root()?)?;
+
+  match repo.find_branch(&name, BranchType::Local) {
+    Ok(_) => info!("Using the existing branch {}", name),
+    Err(_) => {
+      let head = match repo.head() {
+        Ok(head) => head.peel_to_commit()?,
+        Err(_) => bail!("Make a commit before making branches for tickets"),
+      };
+      let _ = repo.branch(&name, &head, false)?;
+      info!("Made the branch {}", name);
+    }
+  }
+
+  if !no_checkout {
+    let refname = format!("refs/heads/{}", name);
+    // Like `git checkout`, changes in the working tree are kept unless the
+    // branch would overwrite them
+    repo.checkout_tree(
+      &repo.revparse_single(&refname)?,
+      Some(CheckoutBuilder::new().safe()),
+    )?;
+    repo.set_head(&refname)?;
+    info!("Switched to {}", name);
+  }
+
+  // The ticket is read again since the branch might have its own copy of it
+  let mut ticket = find_ticket(id)?;
+  if !ticket.branches.contains(&name) {
+    ticket.record(format!("Made the branch {}", name));
+    ticket.branches.push(name);
+    save_ticket(&ticket)?;
+  }
+  Ok(())
+}
+
+/// The short id followed by the title made safe for a branch name, e.g.
+/// `1a2b3c4d-fix-the-login-page`
+fn branch_name(short_id: &str, title: &str) -> String {
+  let mut slug = String::new();
+  for c in title.chars().flat_map(char::to_lowercase) {
+    if c.is_ascii_alphanumeric() {
+      slug.push(c);
+    } else if !slug.is_empty() && !slug.ends_with('-') {
+      slug.push('-');
+    }
+  }
+  let mut slug = slug.trim_end_matches('-').to_owned();
+  if slug.len() > 40 {
+    slug.truncate(40);
+    slug = slug.trim_end_matches('-').to_owned();
+  }
+  if slug.is_empty() {
+    short_id.to_owned()
+  } else {
+    format!("{}-{}", short_id, slug)
+  }
+}
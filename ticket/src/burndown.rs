@@ -0,0 +1,302 @@
+//! Burndown charts of how much work was left on each day of a sprint, and the
+//! velocity of past sprints, i.e. how much work was closed during each one.
+//! Work is counted in points if any of the sprint's tickets are estimated in
+//! points, then in hours, and otherwise in tickets.
+use crate::{
+  sprint::{
+    get_sprints,
+    named_or_current,
+    sprint_tickets,
+    Sprint,
+  },
+  Estimate,
+  Status,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use chrono::prelude::*;
+use colored::*;
+use std::{
+  fmt,
+  iter,
+  str::FromStr,
+};
+
+/// How many rows tall the chart is
+const HEIGHT: usize = 10;
+
+/// How `ticket burndown` prints what it finds
+#[derive(Debug, Clone, Copy)]
+pub enum ChartFormat {
+  /// A chart drawn in the terminal
+  Human,
+  /// Comma separated values to chart in a spreadsheet
+  Csv,
+}
+
+impl FromStr for ChartFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "human" => Ok(ChartFormat::Human),
+      "csv" => Ok(ChartFormat::Csv),
+      _ => bail!("'{}' is not a format, expected 'human' or 'csv'", s),
+    }
+  }
+}
+
+/// What work is counted in
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+  Points,
+  Hours,
+  Tickets,
+}
+
+impl Unit {
+  /// Points if any of the tickets are estimated in them, then hours, then
+  /// tickets
+  fn of(tickets: &[Ticket]) -> Self {
+    let estimated = |unit: Unit| tickets.iter().any(|t| unit.work(t) > 0.0);
+    if estimated(Unit::Points) {
+      Unit::Points
+    } else if estimated(Unit::Hours) {
+      Unit::Hours
+    } else {
+      Unit::Tickets
+    }
+  }
+
+  /// How much work the ticket is. Tickets estimated in the other unit, or not
+  /// estimated, are no work unless work is counted in tickets.
+  fn work(self, ticket: &Ticket) -> f64 {
+    match (self, ticket.estimate) {
+      (Unit::Points, Some(Estimate::Points(points))) => points,
+      (Unit::Hours, Some(Estimate::Hours(hours))) => hours,
+      (Unit::Tickets, _) => 1.0,
+      _ => 0.0,
+    }
+  }
+}
+
+impl fmt::Display for Unit {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Unit::Points => write!(f, "points"),
+      Unit::Hours => write!(f, "hours"),
+      Unit::Tickets => write!(f, "tickets"),
+    }
+  }
+}
+
+/// The work left at the end of a day of the sprint
+struct Day {
+  date: NaiveDate,
+  /// `None` for days that haven't happened yet
+  remaining: Option<f64>,
+  /// Where the work left would be if it were done at a steady pace
+  ideal: f64,
+}
+
+/// Charts the work left in the sprint, the current one if there's no name, or
+/// if `velocity` is set lists how much work was closed in each sprint so far
+pub fn burndown(
+  name: Option<String>,
+  velocity: bool,
+  format: ChartFormat,
+) -> Result<()> {
+  if velocity {
+    return print_velocity(format);
+  }
+  let (name, sprint) = named_or_current(name)?;
+  let tickets = sprint_tickets(&sprint)?;
+  let unit = Unit::of(&tickets);
+  let days = days(&sprint, &tickets, unit);
+  match format {
+    ChartFormat::Csv => {
+      println!("date,remaining,ideal");
+      for day in &days {
+        println!(
+          "{},{},{}",
+          day.date,
+          day.remaining.map_or_else(String::new, |r| r.to_string()),
+          round(day.ideal)
+        );
+      }
+    }
+    ChartFormat::Human => {
+      println!(
+        "{} {} to {}, {} left",
+        name.bold().cyan(),
+        sprint.start,
+        sprint.end,
+        unit
+      );
+      print_chart(&days);
+    }
+  }
+  Ok(())
+}
+
+/// The work left on each day of the sprint
+fn days(sprint: &Sprint, tickets: &[Ticket], unit: Unit) -> Vec<Day> {
+  let today = Local::now().date_naive();
+  let total = tickets.iter().map(|t| unit.work(t)).sum::<f64>();
+  let length = (sprint.end - sprint.start).num_days() as f64;
+  iter::successors(Some(sprint.start), |date| date.succ_opt())
+    .take_while(|date| *date <= sprint.end)
+    .map(|date| {
+      let remaining = tickets
+        .iter()
+        .filter(|t| !closed_by(t, date))
+        .map(|t| unit.work(t))
+        .sum::<f64>();
+      let elapsed = (date - sprint.start).num_days() as f64;
+      Day {
+        date,
+        remaining: Some(remaining).filter(|_| date <= today),
+        ideal: if length > 0.0 {
+          total * (1.0 - elapsed / length)
+        } else {
+          0.0
+        },
+      }
+    })
+    .collect()
+}
+
+/// Whether the ticket was closed by the end of the day
+fn closed_by(ticket: &Ticket, date: NaiveDate) -> bool {
+  ticket.status == Status::Closed
+    && ticket
+      .closed_at
+      .is_some_and(|at| at.with_timezone(&Local).date_naive() <= date)
+}
+
+/// Draws the work left on each day as a column of '#', with '.' where the
+/// ideal line is
+fn print_chart(days: &[Day]) {
+  let max = days
+    .iter()
+    .flat_map(|day| day.remaining.into_iter().chain(iter::once(day.ideal)))
+    .fold(0.0, f64::max);
+  if max == 0.0 {
+    println!("There is no work in the sprint.");
+    return;
+  }
+  let level = |work: f64| (work / max * HEIGHT as f64).round() as usize;
+  let label = round(max).to_string();
+  for row in (1..=HEIGHT).rev() {
+    let mut line = format!(
+      "{:>width$} |",
+      if row == HEIGHT { label.as_str() } else { "" },
+      width = label.len()
+    );
+    for day in days {
+      line.push_str(match day.remaining.map(level) {
+        Some(remaining) if remaining >= row => " #",
+        _ if level(day.ideal) == row => " .",
+        _ => "  ",
+      });
+    }
+    println!("{}", line.trim_end());
+  }
+  println!(
+    "{:>width$} +{}",
+    0,
+    "-".repeat(days.len() * 2),
+    width = label.len()
+  );
+  if let (Some(first), Some(last)) = (days.first(), days.last()) {
+    // The dates line up under the first and last columns
+    println!(
+      "{:width$}  {}{:>gap$}",
+      "",
+      first.date.format("%m-%d"),
+      last.date.format("%m-%d").to_string(),
+      width = label.len(),
+      gap = (days.len() * 2).saturating_sub(5).max(6)
+    );
+  }
+}
+
+/// Prints how much work was closed during each sprint that's started, and the
+/// average of the ones that are over
+fn print_velocity(format: ChartFormat) -> Result<()> {
+  let today = Local::now().date_naive();
+  let mut sprints = get_sprints()?
+    .into_iter()
+    .filter(|(_, sprint)| sprint.start <= today)
+    .collect::<Vec<(String, Sprint)>>();
+  sprints.sort_by_key(|(_, sprint)| sprint.start);
+  if let ChartFormat::Csv = format {
+    println!("sprint,start,end,tickets,points,hours");
+  }
+  let mut finished = Vec::new();
+  for (name, sprint) in &sprints {
+    let closed = sprint_tickets(sprint)?
+      .into_iter()
+      .filter(|t| {
+        closed_by(t, sprint.end)
+          && sprint
+            .start
+            .pred_opt()
+            .is_none_or(|before| !closed_by(t, before))
+      })
+      .collect::<Vec<Ticket>>();
+    let done = [Unit::Tickets, Unit::Points, Unit::Hours]
+      .map(|unit| closed.iter().map(|t| unit.work(t)).sum::<f64>());
+    if sprint.end < today {
+      finished.push(done);
+    }
+    match format {
+      ChartFormat::Csv => println!(
+        "{},{},{},{},{},{}",
+        name, sprint.start, sprint.end, done[0], done[1], done[2]
+      ),
+      ChartFormat::Human => println!(
+        "{:<20} {} to {} {:>4} closed {:>6}pt {:>6}h{}",
+        name.cyan(),
+        sprint.start,
+        sprint.end,
+        done[0],
+        round(done[1]),
+        round(done[2]),
+        if sprint.end < today {
+          ""
+        } else {
+          " (in progress)"
+        }
+      ),
+    }
+  }
+  if let ChartFormat::Human = format {
+    if sprints.is_empty() {
+      println!("No sprints have started yet.");
+    } else if !finished.is_empty() {
+      let average = |i: usize| {
+        round(
+          finished.iter().map(|done| done[i]).sum::<f64>()
+            / finished.len() as f64,
+        )
+      };
+      println!(
+        "\n{} {} tickets, {}pt, {}h closed per sprint",
+        "Average velocity:".bold().purple(),
+        average(0),
+        average(1),
+        average(2)
+      );
+    }
+  }
+  Ok(())
+}
+
+/// Rounds to one decimal place
+fn round(work: f64) -> f64 {
+  (work * 10.0).round() / 10.0
+}
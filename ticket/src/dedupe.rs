@@ -0,0 +1,168 @@
+//! Finds open tickets that are probably about the same thing by how many of
+//! the words in their titles and descriptions they share. Each pair can then
+//! be dealt with by marking the newer ticket a duplicate of the older one and
+//! closing it.
+use crate::{
+  actions::get_all_tickets,
+  close,
+  index::words,
+  link,
+  Relation,
+  Status,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use log::*;
+use rustyline::{
+  error::ReadlineError,
+  Editor,
+};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+/// How much more alike titles count than descriptions
+const TITLE_WEIGHT: f64 = 2.0;
+
+/// Words so common that sharing them says nothing about two tickets
+const STOP_WORDS: [&str; 20] = [
+  "a", "an", "and", "are", "as", "be", "by", "for", "from", "in", "is", "it",
+  "of", "on", "or", "that", "the", "this", "to", "with",
+];
+
+/// What to do with a pair of tickets that look alike
+enum Answer {
+  /// Close the newer one as a duplicate of the older one
+  Close,
+  /// Close the older one as a duplicate of the newer one
+  Swap,
+  Skip,
+  Quit,
+}
+
+/// Lists the pairs of open tickets at least `threshold` alike, most alike
+/// first. If `interactive` it asks what to do with each pair.
+pub fn dedupe(threshold: f64, interactive: bool) -> Result<()> {
+  if !(0.0..=1.0).contains(&threshold) {
+    bail!("The threshold has to be between 0 and 1, not {}", threshold);
+  }
+  let tickets = get_all_tickets()?
+    .into_iter()
+    .filter(|t| t.status != Status::Closed)
+    .collect::<Vec<Ticket>>();
+  let words = tickets
+    .iter()
+    .map(|t| (significant(&t.title), significant(&t.description)))
+    .collect::<Vec<_>>();
+
+  let mut pairs = Vec::new();
+  for (i, a) in tickets.iter().enumerate() {
+    for (j, b) in tickets.iter().enumerate().skip(i + 1) {
+      // Tickets already marked as duplicates have been dealt with
+      if a.duplicate_of.contains(&b.id) || a.duplicated_by.contains(&b.id) {
+        continue;
+      }
+      let score = similarity(&words[i], &words[j]);
+      if score >= threshold {
+        // The newer ticket is the one that duplicates the older one
+        if a.created_at <= b.created_at {
+          pairs.push((score, a, b));
+        } else {
+          pairs.push((score, b, a));
+        }
+      }
+    }
+  }
+  if pairs.is_empty() {
+    info!("No open tickets look like duplicates.");
+    return Ok(());
+  }
+  pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+  let mut closed = BTreeSet::<Uuid>::new();
+  for (score, original, duplicate) in pairs {
+    if closed.contains(&original.id) || closed.contains(&duplicate.id) {
+      continue;
+    }
+    println!("{:.0}% alike", score * 100.0);
+    println!("  {} {}", original.short_id, original.title);
+    println!("  {} {}", duplicate.short_id, duplicate.title);
+    if !interactive {
+      continue;
+    }
+    let (duplicate, original) = match ask(duplicate, original)? {
+      Answer::Close => (duplicate, original),
+      Answer::Swap => (original, duplicate),
+      Answer::Skip => continue,
+      Answer::Quit => break,
+    };
+    let (id, of) = (duplicate.id.to_string(), original.id.to_string());
+    link(&id, Relation::DuplicateOf, &of, false)?;
+    close(&id, Some(format!("Duplicate of {}", original.short_id)))?;
+    let _ = closed.insert(duplicate.id);
+    info!(
+      "Closed {} as a duplicate of {}",
+      duplicate.short_id, original.short_id
+    );
+  }
+  Ok(())
+}
+
+/// Asks whether to close the duplicate, the original instead, or neither
+fn ask(duplicate: &Ticket, original: &Ticket) -> Result<Answer> {
+  let mut rl = Editor::<()>::new();
+  let prompt = format!(
+    "Close {} as a duplicate of {}? [y]es, [s]wap them, [N]o, [q]uit ",
+    duplicate.short_id, original.short_id
+  );
+  match rl.readline(&prompt) {
+    Ok(line) => Ok(match line.trim().to_lowercase().as_str() {
+      "y" | "yes" => Answer::Close,
+      "s" | "swap" => Answer::Swap,
+      "q" | "quit" => Answer::Quit,
+      _ => Answer::Skip,
+    }),
+    Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+      Ok(Answer::Quit)
+    }
+    Err(e) => Err(e.into()),
+  }
+}
+
+/// The words in the text besides the stop words
+fn significant(text: &str) -> BTreeSet<String> {
+  let mut words = words(text);
+  words.retain(|word| !STOP_WORDS.contains(&word.as_str()));
+  words
+}
+
+/// How alike two tickets are from 0 to 1, going by the share of their title
+/// words and of their description words they have in common. If neither has a
+/// description only the titles count.
+fn similarity(
+  (a_title, a_description): &(BTreeSet<String>, BTreeSet<String>),
+  (b_title, b_description): &(BTreeSet<String>, BTreeSet<String>),
+) -> f64 {
+  match (
+    jaccard(a_title, b_title),
+    jaccard(a_description, b_description),
+  ) {
+    (Some(title), Some(description)) => {
+      (TITLE_WEIGHT * title + description) / (TITLE_WEIGHT + 1.0)
+    }
+    (Some(alike), None) | (None, Some(alike)) => alike,
+    (None, None) => 0.0,
+  }
+}
+
+/// How many words the sets share out of all the words in either, or `None`
+/// if both are empty
+fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> Option<f64> {
+  let all = a.union(b).count();
+  if all == 0 {
+    return None;
+  }
+  Some(a.intersection(b).count() as f64 / all as f64)
+}
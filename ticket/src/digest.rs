@@ -0,0 +1,216 @@
+//! A summary of what happened to the tickets between two commits, worked out
+//! by reading the ticket files as they were at each one. It's plain text so a
+//! CI job can post it to a mailing list or chat.
+use crate::{
+  actions::short_id,
+  format::{
+    self,
+    FileFormat,
+  },
+  Status,
+  Ticket,
+};
+use anyhow::{
+  format_err,
+  Result,
+};
+use chrono::prelude::*;
+use git2::{
+  Commit,
+  ObjectType,
+  Repository,
+  Sort,
+  TreeWalkMode,
+  TreeWalkResult,
+};
+use log::*;
+use shared::find_root;
+use std::{
+  collections::BTreeMap,
+  path::Path,
+};
+use uuid::Uuid;
+
+/// Where the tickets are kept in the repo
+const TICKET_ROOT: &str = ".dev-suite/ticket";
+
+/// Prints what changed about the tickets from `since`, a git revision or a
+/// date like 2024-07-01, to the `until` revision
+pub fn digest(since: &str, until: &str) -> Result<()> {
+  let repo = Repository::open(find_root()?)?;
+  let until_commit = repo
+    .revparse_single(until)
+    .and_then(|object| object.peel_to_commit())
+    .map_err(|_| {
+      format_err!("'{}' isn't a tag or commit in the repo", until)
+    })?;
+  let since_commit = since_commit(&repo, since, &until_commit)?;
+  let before = match &since_commit {
+    Some(commit) => tickets_at(&repo, commit)?,
+    None => BTreeMap::new(),
+  };
+  let after = tickets_at(&repo, &until_commit)?;
+  print!("{}", summary(since, until, &before, &after));
+  Ok(())
+}
+
+/// The commit the tickets are compared against. For a date that's the last
+/// commit made before the day started, or `None` if every commit is newer.
+fn since_commit<'r>(
+  repo: &'r Repository,
+  since: &str,
+  until: &Commit<'r>,
+) -> Result<Option<Commit<'r>>> {
+  let date = match NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+    Ok(date) => date,
+    Err(_) => {
+      return repo
+        .revparse_single(since)
+        .and_then(|object| object.peel_to_commit())
+        .map(Some)
+        .map_err(|_| {
+          format_err!(
+            "'{}' isn't a date like 2024-07-01 or a tag or commit in the repo",
+            since
+          )
+        })
+    }
+  };
+  let start = date
+    .and_hms_opt(0, 0, 0)
+    .and_then(|date| Local.from_local_datetime(&date).earliest())
+    .ok_or_else(|| format_err!("{} isn't a valid date", since))?
+    .timestamp();
+  let mut walk = repo.revwalk()?;
+  walk.set_sorting(Sort::TIME);
+  walk.push(until.id())?;
+  for oid in walk {
+    let commit = repo.find_commit(oid?)?;
+    if commit.time().seconds() < start {
+      return Ok(Some(commit));
+    }
+  }
+  Ok(None)
+}
+
+/// Every ticket in the commit by its id. Files that can't be read as a
+/// ticket, like templates or tickets in a format too old to read, are skipped.
+fn tickets_at(
+  repo: &Repository,
+  commit: &Commit,
+) -> Result<BTreeMap<Uuid, Ticket>> {
+  let mut tickets = BTreeMap::new();
+  let tree = match commit.tree()?.get_path(Path::new(TICKET_ROOT)) {
+    Ok(entry) => entry.to_object(repo)?.peel_to_tree()?,
+    Err(_) => return Ok(tickets),
+  };
+  tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+    let name = entry.name().unwrap_or_default();
+    if entry.kind() != Some(ObjectType::Blob)
+      || FileFormat::of(Path::new(name)).is_none()
+    {
+      return TreeWalkResult::Ok;
+    }
+    let contents = repo
+      .find_blob(entry.id())
+      .ok()
+      .and_then(|blob| String::from_utf8(blob.content().to_vec()).ok());
+    match contents.map(|contents| format::parse(&contents)) {
+      Some(Ok(mut ticket)) => {
+        if ticket.short_id.is_empty() {
+          ticket.short_id =
+            short_id(&ticket.id, &ticket.title, &ticket.description);
+        }
+        let _ = tickets.insert(ticket.id, ticket);
+      }
+      _ => trace!("Skipping {}{}, it isn't a ticket.", dir, name),
+    }
+    TreeWalkResult::Ok
+  })?;
+  Ok(tickets)
+}
+
+/// The digest of what changed between the tickets before and after
+fn summary(
+  since: &str,
+  until: &str,
+  before: &BTreeMap<Uuid, Ticket>,
+  after: &BTreeMap<Uuid, Ticket>,
+) -> String {
+  let line = |t: &Ticket| format!("- {} {}", t.short_id, t.title);
+  let was_closed = |t: &Ticket| {
+    before
+      .get(&t.id)
+      .is_some_and(|old| old.status == Status::Closed)
+  };
+  let mut created = Vec::new();
+  let mut closed = Vec::new();
+  let mut reopened = Vec::new();
+  let mut commented = Vec::new();
+  for ticket in after.values() {
+    if !before.contains_key(&ticket.id) {
+      created.push(line(ticket));
+    }
+    match (ticket.status == Status::Closed, was_closed(ticket)) {
+      (true, false) => closed.push(format!(
+        "{}{}",
+        line(ticket),
+        ticket
+          .closed_by
+          .as_ref()
+          .map_or_else(String::new, |(_, name)| format!(" (by {})", name.0))
+      )),
+      (false, true) => reopened.push(line(ticket)),
+      _ => {}
+    }
+    let old = before.get(&ticket.id);
+    let mut names = Vec::new();
+    let mut count = 0;
+    for (id, (_, name, _)) in &ticket.comments {
+      if old.is_some_and(|old| old.comments.contains_key(id)) {
+        continue;
+      }
+      count += 1;
+      if !names.contains(&name.0) {
+        names.push(name.0.clone());
+      }
+    }
+    if count > 0 {
+      commented.push(format!(
+        "{}: {} new comment{} from {}",
+        line(ticket),
+        count,
+        if count == 1 { "" } else { "s" },
+        names.join(", ")
+      ));
+    }
+  }
+  let deleted = before
+    .values()
+    .filter(|t| !after.contains_key(&t.id))
+    .map(line)
+    .collect::<Vec<String>>();
+
+  let mut out = format!("Ticket digest from {} to {}\n", since, until);
+  let sections = [
+    ("Created", created),
+    ("Closed", closed),
+    ("Reopened", reopened),
+    ("Commented on", commented),
+    ("Deleted", deleted),
+  ];
+  if sections.iter().all(|(_, lines)| lines.is_empty()) {
+    out.push_str("\nNothing happened to the tickets.\n");
+  }
+  for (heading, lines) in &sections {
+    if lines.is_empty() {
+      continue;
+    }
+    out.push_str(&format!("\n{} ({})\n", heading, lines.len()));
+    for line in lines {
+      out.push_str(line);
+      out.push('\n');
+    }
+  }
+  out
+}
@@ -0,0 +1,182 @@
+use crate::{
+  actions::{
+    get_all_tickets,
+    resolve_status,
+    statuses,
+  },
+  assignee_names,
+  is_assigned,
+  resolve_assignee,
+  html,
+  task_progress,
+  Relation,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use log::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+use std::{
+  fmt::Write,
+  fs,
+  path::PathBuf,
+  str::FromStr,
+};
+
+/// What tickets can be exported as
+#[derive(Debug, Clone, Copy)]
+pub enum Export {
+  Markdown,
+  Json,
+  Html,
+}
+
+impl FromStr for Export {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "markdown" | "md" => Ok(Export::Markdown),
+      "json" => Ok(Export::Json),
+      "html" => Ok(Export::Html),
+      _ => bail!(
+        "'{}' is not an export format, expected 'markdown', 'json', or \
+         'html'",
+        s
+      ),
+    }
+  }
+}
+
+/// Every part of the tickets exported as json, which `ticket import` can turn
+/// back into tickets
+#[derive(Serialize, Deserialize)]
+pub struct Dump<T> {
+  pub tickets: Vec<T>,
+}
+
+/// Writes the tickets that match the filters to one document, either to the
+/// output file or to stdout
+pub fn export(
+  format: Export,
+  status: Option<String>,
+  tag: Option<String>,
+  assignee: Option<String>,
+  output: Option<PathBuf>,
+) -> Result<()> {
+  let status = status.as_deref().map(resolve_status).transpose()?;
+  let assignee = resolve_assignee(assignee)?;
+  let all = get_all_tickets()?;
+  let tickets = all
+    .iter()
+    .filter(|t| status.as_ref().is_none_or(|s| t.status == *s))
+    .filter(|t| tag.as_ref().is_none_or(|tag| t.tags.contains(tag)))
+    .filter(|t| assignee.as_ref().is_none_or(|who| is_assigned(t, who)))
+    .collect::<Vec<&Ticket>>();
+
+  let document = match format {
+    Export::Html => {
+      let dir = match output {
+        Some(dir) => dir,
+        None => bail!("Exporting html needs --out for the site's directory"),
+      };
+      html::site(&tickets, &all, &dir)?;
+      info!("Exported {} tickets to {}", tickets.len(), dir.display());
+      return Ok(());
+    }
+    Export::Markdown => markdown(&tickets, &all)?,
+    Export::Json => serde_json::to_string_pretty(&Dump {
+      tickets: tickets.clone(),
+    })?,
+  };
+  match output {
+    Some(path) => {
+      fs::write(&path, document)?;
+      info!("Exported {} tickets to {}", tickets.len(), path.display());
+    }
+    None => print!("{}", document),
+  }
+  Ok(())
+}
+
+/// A section for each status with the tickets in it. `all` is every ticket so
+/// that related tickets can be named even if they weren't exported.
+fn markdown(tickets: &[&Ticket], all: &[Ticket]) -> Result<String> {
+  let mut out = String::from("# Tickets\n");
+  for status in statuses()? {
+    let with_status = tickets
+      .iter()
+      .filter(|t| t.status == status)
+      .collect::<Vec<_>>();
+    if with_status.is_empty() {
+      continue;
+    }
+    write!(out, "\n## {}\n", status)?;
+
+    for ticket in with_status {
+      write!(out, "\n### {} - {}\n\n", ticket.short_id, ticket.title)?;
+      if !ticket.assignees.is_empty() {
+        writeln!(out, "- **Assignees:** {}", assignee_names(ticket))?;
+      }
+      if !ticket.tags.is_empty() {
+        writeln!(out, "- **Tags:** {}", ticket.tags.join(", "))?;
+      }
+      if let Some(due) = ticket.due {
+        writeln!(out, "- **Due:** {}", due)?;
+      }
+      if let Some(estimate) = ticket.estimate {
+        writeln!(out, "- **Estimate:** {}", estimate)?;
+      }
+      if let Some(component) = &ticket.component {
+        writeln!(out, "- **Component:** {}", component)?;
+      }
+      for (name, value) in &ticket.fields {
+        writeln!(out, "- **{}:** {}", name, value)?;
+      }
+      for relation in Relation::ALL.iter() {
+        for id in ticket.related(*relation) {
+          let other = all.iter().find(|t| t.id == *id).map_or_else(
+            || id.to_string(),
+            |t| format!("{} {}", t.short_id, t.title),
+          );
+          writeln!(out, "- **{}:** {}", relation.title(), other)?;
+        }
+      }
+      if !ticket.tasks.is_empty() {
+        writeln!(out, "- **Tasks:** {} done", task_progress(ticket))?;
+      }
+
+      let description = ticket.description.trim();
+      if !description.is_empty() {
+        write!(out, "\n{}\n", description)?;
+      }
+      if !ticket.tasks.is_empty() {
+        out.push('\n');
+        for task in &ticket.tasks {
+          writeln!(
+            out,
+            "- [{}] {}",
+            if task.done { "x" } else { " " },
+            task.text
+          )?;
+        }
+      }
+      if !ticket.comments.is_empty() {
+        out.push_str("\n#### Comments\n");
+        for (id, (_, name, comment)) in ticket.comments_in_order() {
+          write!(out, "\n**{}**", name.0)?;
+          if let Some(time) = ticket.comment_time(id) {
+            write!(out, " ({})", time.format("%Y-%m-%d"))?;
+          }
+          write!(out, ":\n\n{}\n", comment.0.trim())?;
+        }
+      }
+    }
+  }
+  Ok(out)
+}
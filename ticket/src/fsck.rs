@@ -0,0 +1,216 @@
+//! Checks that the ticket files are all readable and where they should be
+use crate::{
+  actions::{
+    get_archived_tickets,
+    move_ticket_file,
+    read_ticket,
+    save_ticket,
+    status_dir,
+    statuses,
+    ticket_path,
+    ticket_root,
+  },
+  format::{
+    self,
+    FileFormat,
+  },
+  Relation,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use configamajig::get_repo_config;
+use log::*;
+use std::{
+  collections::BTreeMap,
+  fs,
+  path::PathBuf,
+};
+use uuid::Uuid;
+
+/// Directories in the ticket root that don't hold tickets
+const NOT_STATUSES: [&str; 4] =
+  ["attachments", "remotes", "sprints", "templates"];
+
+/// Reports problems with the ticket files and, if `fix` is set, moves and
+/// renames tickets that are in the wrong place and drops relations to tickets
+/// that don't exist. Anything that needs a person to decide what's right is
+/// only reported.
+pub fn fsck(fix: bool) -> Result<()> {
+  let root = ticket_root()?;
+  let mut problems = 0;
+  let mut dirs = statuses()?
+    .iter()
+    .map(status_dir)
+    .collect::<Result<Vec<PathBuf>>>()?;
+  for entry in fs::read_dir(&root)? {
+    let path = entry?.path();
+    let name = path
+      .file_name()
+      .and_then(|n| n.to_str())
+      .unwrap_or_default();
+    if path.is_dir() && !dirs.contains(&path) && !NOT_STATUSES.contains(&name) {
+      warn!(
+        "{} isn't the directory of any status in the repo config",
+        path.display()
+      );
+      problems += 1;
+      dirs.push(path);
+    }
+  }
+
+  let mut tickets = Vec::new();
+  for dir in dirs.iter().filter(|dir| dir.exists()) {
+    for entry in fs::read_dir(dir)? {
+      let path = entry?.path();
+      if !path.is_file() {
+        continue;
+      }
+      if let Err(e) = format::parse(&fs::read_to_string(&path)?) {
+        warn!("{} can't be read: {}", path.display(), e);
+        problems += 1;
+        continue;
+      }
+      tickets.push((path.clone(), read_ticket(&path)?));
+    }
+  }
+
+  for (path, ticket) in &tickets {
+    let expected = ticket_path(ticket)?;
+    if *path == expected {
+      continue;
+    }
+    let why = if path.parent() != expected.parent() {
+      format!("is in the wrong directory for its status {}", ticket.status)
+    } else if FileFormat::of(path) != FileFormat::of(&expected) {
+      format!(
+        "isn't in the repo's format, {}",
+        FileFormat::configured()?.extension()
+      )
+    } else {
+      format!(
+        "should be named {}",
+        expected.file_name().unwrap_or_default().to_string_lossy()
+      )
+    };
+    if !fix {
+      warn!(
+        "{} {}, run 'ticket fsck --fix' to move it",
+        path.display(),
+        why
+      );
+      problems += 1;
+    } else if expected.exists() {
+      warn!(
+        "{} {} but {} already exists",
+        path.display(),
+        why,
+        expected.display()
+      );
+      problems += 1;
+    } else {
+      move_ticket_file(path, &expected)?;
+      if FileFormat::of(path) != FileFormat::of(&expected) {
+        save_ticket(ticket)?;
+      }
+      info!("Moved {} to {}", path.display(), expected.display());
+    }
+  }
+
+  let mut by_id = BTreeMap::<Uuid, Vec<&PathBuf>>::new();
+  let mut by_short_id = BTreeMap::<&str, Vec<&PathBuf>>::new();
+  for (path, ticket) in &tickets {
+    by_id.entry(ticket.id).or_default().push(path);
+    by_short_id
+      .entry(ticket.short_id.as_str())
+      .or_default()
+      .push(path);
+  }
+  for (id, paths) in by_id.iter().filter(|(_, paths)| paths.len() > 1) {
+    warn!("These tickets all have the uuid {}: {}", id, list(paths));
+    problems += 1;
+  }
+  for (id, paths) in by_short_id.iter().filter(|(_, paths)| paths.len() > 1) {
+    warn!(
+      "These tickets all have the short id {}: {}",
+      id,
+      list(paths)
+    );
+    problems += 1;
+  }
+
+  let archived = get_archived_tickets()?;
+  for (path, ticket) in &tickets {
+    let exists = |id: &Uuid| {
+      by_id.contains_key(id) || archived.iter().any(|t| t.id == *id)
+    };
+    let dangling = Relation::ALL
+      .iter()
+      .flat_map(|r| ticket.related(*r).iter().map(move |id| (*r, *id)))
+      .filter(|(_, id)| !exists(id))
+      .collect::<Vec<(Relation, Uuid)>>();
+    if dangling.is_empty() {
+      continue;
+    }
+    if !fix {
+      for (relation, id) in &dangling {
+        warn!(
+          "{} is {} {} which doesn't exist, run 'ticket fsck --fix' to \
+           remove it",
+          path.display(),
+          relation,
+          id
+        );
+      }
+      problems += dangling.len();
+      continue;
+    }
+    let mut ticket = ticket.clone();
+    for (relation, id) in &dangling {
+      let _ = ticket.unrelate(id);
+      ticket.record(format!(
+        "No longer {} {} since it doesn't exist",
+        relation, id
+      ));
+      info!("Removed {} from the relations of {}", id, path.display());
+    }
+    save_ticket(&ticket)?;
+  }
+
+  // Without a repo config there's nobody to check assignees or fields against
+  if let Ok(config) = get_repo_config() {
+    for (path, ticket) in &tickets {
+      for problem in ticket.field_problems(&config.fields) {
+        warn!("{} has a bad field: {}", path.display(), problem);
+        problems += 1;
+      }
+      for (uuid, name) in &ticket.assignees {
+        if !config.maintainers.iter().any(|(_, m)| m == uuid) {
+          warn!(
+            "{} is assigned to {} ({}) who isn't a maintainer",
+            path.display(),
+            name.0,
+            uuid
+          );
+          problems += 1;
+        }
+      }
+    }
+  }
+
+  if problems > 0 {
+    bail!("Found {} problems with the tickets", problems);
+  }
+  info!("Everything looks good.");
+  Ok(())
+}
+
+/// The paths separated by commas
+fn list(paths: &[&PathBuf]) -> String {
+  paths
+    .iter()
+    .map(|p| p.display().to_string())
+    .collect::<Vec<String>>()
+    .join(", ")
+}
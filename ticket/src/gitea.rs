@@ -0,0 +1,235 @@
+//! Syncs with a Gitea or Forgejo instance using its v1 REST API
+use crate::{
+  remote::Kind,
+  sync::{
+    check,
+    client,
+    send,
+    split_repo_url,
+    Issue,
+    IssueComment,
+    Provider,
+  },
+};
+use anyhow::Result;
+use chrono::prelude::*;
+use reqwest::{
+  blocking::{
+    Client,
+    RequestBuilder,
+  },
+  Method,
+};
+use serde::{
+  de::DeserializeOwned,
+  Deserialize,
+};
+use serde_json::json;
+
+/// How many items to ask for on each page, which is the most Gitea gives out
+/// by default. Instances can be set to give out fewer than asked for.
+const PAGE_SIZE: usize = 50;
+
+#[derive(Deserialize)]
+struct GiteaIssue {
+  number: u64,
+  title: String,
+  #[serde(default)]
+  body: String,
+  state: String,
+  #[serde(default)]
+  labels: Vec<Label>,
+  #[serde(default)]
+  assignees: Option<Vec<User>>,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+  #[serde(default)]
+  closed_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  due_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct Label {
+  name: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaComment {
+  id: u64,
+  body: String,
+  user: User,
+  created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct User {
+  login: String,
+  #[serde(default)]
+  full_name: String,
+}
+
+impl From<User> for crate::sync::User {
+  fn from(user: User) -> Self {
+    Self {
+      // Users who haven't set a full name go by their login
+      name: if user.full_name.is_empty() {
+        user.login.clone()
+      } else {
+        user.full_name
+      },
+      username: user.login,
+    }
+  }
+}
+
+/// A repo on a Gitea or Forgejo instance
+pub struct Gitea {
+  client: Client,
+  /// Where the issues of the repo are in the API
+  api: String,
+  token: Option<String>,
+}
+
+impl Gitea {
+  /// Makes a provider for the repo at the url, e.g.
+  /// 'https://codeberg.org/owner/repo'
+  pub fn new(url: &str, token: Option<String>) -> Result<Self> {
+    let (instance, repo) = split_repo_url(Kind::Gitea, url)?;
+    Ok(Self {
+      client: client()?,
+      api: format!("{}/api/v1/repos/{}/issues", instance, repo),
+      token,
+    })
+  }
+
+  fn request(&self, method: Method, path: &str) -> RequestBuilder {
+    let request = self
+      .client
+      .request(method, &format!("{}{}", self.api, path));
+    match &self.token {
+      Some(token) => {
+        request.header("Authorization", format!("token {}", token))
+      }
+      None => request,
+    }
+  }
+
+  /// Opens or closes an issue, state being 'open' or 'closed'
+  fn set_state(&self, issue: &str, state: &str) -> Result<()> {
+    let _ = check(
+      Kind::Gitea,
+      self
+        .request(Method::PATCH, &format!("/{}", issue))
+        .header("Content-Type", "application/json")
+        .body(json!({ "state": state }).to_string()),
+    )?;
+    Ok(())
+  }
+
+  /// Gets every page of a list from the API. Gitea caps the limit at its
+  /// MAX_RESPONSE_ITEMS setting so a short page isn't always the last one,
+  /// only an empty page is.
+  fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+    let mut all = Vec::new();
+    for page in 1.. {
+      let items: Vec<T> = send(
+        Kind::Gitea,
+        self.request(Method::GET, path).query(&[
+          ("limit", PAGE_SIZE.to_string()),
+          ("page", page.to_string()),
+        ]),
+      )?;
+      if items.is_empty() {
+        break;
+      }
+      all.extend(items);
+    }
+    Ok(all)
+  }
+}
+
+impl Provider for Gitea {
+  fn issues(&self) -> Result<Vec<Issue>> {
+    Ok(
+      self
+        .get_all::<GiteaIssue>("?state=all&type=issues")?
+        .into_iter()
+        .map(|issue| Issue {
+          id: issue.number.to_string(),
+          title: issue.title,
+          description: issue.body,
+          closed: issue.state == "closed",
+          labels: issue.labels.into_iter().map(|label| label.name).collect(),
+          assignees: issue
+            .assignees
+            .unwrap_or_default()
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+          created_at: issue.created_at,
+          updated_at: issue.updated_at,
+          closed_at: issue.closed_at,
+          closed_by: None,
+          due: issue.due_date.map(|due| due.date_naive()),
+        })
+        .collect(),
+    )
+  }
+
+  fn comments(&self, issue: &str) -> Result<Vec<IssueComment>> {
+    Ok(
+      self
+        .get_all::<GiteaComment>(&format!("/{}/comments", issue))?
+        .into_iter()
+        .map(|comment| IssueComment {
+          id: comment.id.to_string(),
+          body: comment.body,
+          author: comment.user.into(),
+          created_at: comment.created_at,
+        })
+        .collect(),
+    )
+  }
+
+  fn create(&self, title: &str, description: &str) -> Result<String> {
+    let issue: GiteaIssue = send(
+      Kind::Gitea,
+      self
+        .request(Method::POST, "")
+        .header("Content-Type", "application/json")
+        .body(json!({ "title": title, "body": description }).to_string()),
+    )?;
+    Ok(issue.number.to_string())
+  }
+
+  fn update(&self, issue: &str, title: &str, description: &str) -> Result<()> {
+    let _ = check(
+      Kind::Gitea,
+      self
+        .request(Method::PATCH, &format!("/{}", issue))
+        .header("Content-Type", "application/json")
+        .body(json!({ "title": title, "body": description }).to_string()),
+    )?;
+    Ok(())
+  }
+
+  fn close(&self, issue: &str) -> Result<()> {
+    self.set_state(issue, "closed")
+  }
+
+  fn reopen(&self, issue: &str) -> Result<()> {
+    self.set_state(issue, "open")
+  }
+
+  fn comment(&self, issue: &str, body: &str) -> Result<String> {
+    let comment: GiteaComment = send(
+      Kind::Gitea,
+      self
+        .request(Method::POST, &format!("/{}/comments", issue))
+        .header("Content-Type", "application/json")
+        .body(json!({ "body": body }).to_string()),
+    )?;
+    Ok(comment.id.to_string())
+  }
+}
@@ -0,0 +1,233 @@
+//! Syncs with GitHub, either github.com or GitHub Enterprise, using its v3 REST
+//! API
+use crate::{
+  remote::Kind,
+  sync::{
+    check,
+    client,
+    send,
+    split_repo_url,
+    Issue,
+    IssueComment,
+    Provider,
+  },
+};
+use anyhow::Result;
+use chrono::prelude::*;
+use reqwest::{
+  blocking::{
+    Client,
+    RequestBuilder,
+  },
+  Method,
+};
+use serde::{
+  de::DeserializeOwned,
+  Deserialize,
+};
+use serde_json::json;
+
+/// How many items to ask for on each page, which is the most GitHub gives out
+const PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+struct GitHubIssue {
+  number: u64,
+  title: String,
+  #[serde(default)]
+  body: Option<String>,
+  state: String,
+  #[serde(default)]
+  labels: Vec<Label>,
+  #[serde(default)]
+  assignees: Vec<User>,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+  #[serde(default)]
+  closed_at: Option<DateTime<Utc>>,
+  /// Set on pull requests, which GitHub lists along with issues
+  #[serde(default)]
+  pull_request: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct Label {
+  name: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubComment {
+  id: u64,
+  body: String,
+  user: User,
+  created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct User {
+  login: String,
+}
+
+impl From<User> for crate::sync::User {
+  fn from(user: User) -> Self {
+    // Lists only have the login of users
+    Self {
+      name: user.login.clone(),
+      username: user.login,
+    }
+  }
+}
+
+/// A repo on GitHub
+pub struct GitHub {
+  client: Client,
+  /// Where the issues of the repo are in the API
+  api: String,
+  token: Option<String>,
+}
+
+impl GitHub {
+  /// Makes a provider for the repo at the url, e.g.
+  /// 'https://github.com/owner/repo'. Repos anywhere but github.com are taken
+  /// to be on GitHub Enterprise.
+  pub fn new(url: &str, token: Option<String>) -> Result<Self> {
+    let (instance, repo) = split_repo_url(Kind::GitHub, url)?;
+    let api = match instance.as_str() {
+      "https://github.com" | "http://github.com" => {
+        "https://api.github.com".to_owned()
+      }
+      _ => format!("{}/api/v3", instance),
+    };
+    Ok(Self {
+      client: client()?,
+      api: format!("{}/repos/{}/issues", api, repo),
+      token,
+    })
+  }
+
+  fn request(&self, method: Method, path: &str) -> RequestBuilder {
+    let request = self
+      .client
+      .request(method, &format!("{}{}", self.api, path))
+      .header("Accept", "application/vnd.github.v3+json");
+    match &self.token {
+      Some(token) => {
+        request.header("Authorization", format!("token {}", token))
+      }
+      None => request,
+    }
+  }
+
+  /// Opens or closes an issue, state being 'open' or 'closed'
+  fn set_state(&self, issue: &str, state: &str) -> Result<()> {
+    let _ = check(
+      Kind::GitHub,
+      self
+        .request(Method::PATCH, &format!("/{}", issue))
+        .header("Content-Type", "application/json")
+        .body(json!({ "state": state }).to_string()),
+    )?;
+    Ok(())
+  }
+
+  /// Gets every page of a list from the API
+  fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+    let mut all = Vec::new();
+    for page in 1.. {
+      let items: Vec<T> = send(
+        Kind::GitHub,
+        self.request(Method::GET, path).query(&[
+          ("per_page", PAGE_SIZE.to_string()),
+          ("page", page.to_string()),
+        ]),
+      )?;
+      let last = items.len() < PAGE_SIZE;
+      all.extend(items);
+      if last {
+        break;
+      }
+    }
+    Ok(all)
+  }
+}
+
+impl Provider for GitHub {
+  fn issues(&self) -> Result<Vec<Issue>> {
+    Ok(
+      self
+        .get_all::<GitHubIssue>("?state=all")?
+        .into_iter()
+        .filter(|issue| issue.pull_request.is_none())
+        .map(|issue| Issue {
+          id: issue.number.to_string(),
+          title: issue.title,
+          description: issue.body.unwrap_or_default(),
+          closed: issue.state == "closed",
+          labels: issue.labels.into_iter().map(|label| label.name).collect(),
+          assignees: issue.assignees.into_iter().map(Into::into).collect(),
+          created_at: issue.created_at,
+          updated_at: issue.updated_at,
+          closed_at: issue.closed_at,
+          closed_by: None,
+          due: None,
+        })
+        .collect(),
+    )
+  }
+
+  fn comments(&self, issue: &str) -> Result<Vec<IssueComment>> {
+    Ok(
+      self
+        .get_all::<GitHubComment>(&format!("/{}/comments", issue))?
+        .into_iter()
+        .map(|comment| IssueComment {
+          id: comment.id.to_string(),
+          body: comment.body,
+          author: comment.user.into(),
+          created_at: comment.created_at,
+        })
+        .collect(),
+    )
+  }
+
+  fn create(&self, title: &str, description: &str) -> Result<String> {
+    let issue: GitHubIssue = send(
+      Kind::GitHub,
+      self
+        .request(Method::POST, "")
+        .header("Content-Type", "application/json")
+        .body(json!({ "title": title, "body": description }).to_string()),
+    )?;
+    Ok(issue.number.to_string())
+  }
+
+  fn update(&self, issue: &str, title: &str, description: &str) -> Result<()> {
+    let _ = check(
+      Kind::GitHub,
+      self
+        .request(Method::PATCH, &format!("/{}", issue))
+        .header("Content-Type", "application/json")
+        .body(json!({ "title": title, "body": description }).to_string()),
+    )?;
+    Ok(())
+  }
+
+  fn close(&self, issue: &str) -> Result<()> {
+    self.set_state(issue, "closed")
+  }
+
+  fn reopen(&self, issue: &str) -> Result<()> {
+    self.set_state(issue, "open")
+  }
+
+  fn comment(&self, issue: &str, body: &str) -> Result<String> {
+    let comment: GitHubComment = send(
+      Kind::GitHub,
+      self
+        .request(Method::POST, &format!("/{}/comments", issue))
+        .header("Content-Type", "application/json")
+        .body(json!({ "body": body }).to_string()),
+    )?;
+    Ok(comment.id.to_string())
+  }
+}
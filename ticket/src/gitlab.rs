@@ -0,0 +1,247 @@
+//! Syncs with GitLab, either gitlab.com or a self-hosted instance, using its
+//! v4 REST API
+use crate::{
+  remote::Kind,
+  sync::{
+    check,
+    client,
+    send,
+    Issue,
+    IssueComment,
+    Provider,
+  },
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use chrono::prelude::*;
+use reqwest::{
+  blocking::{
+    Client,
+    RequestBuilder,
+  },
+  Method,
+};
+use serde::{
+  de::DeserializeOwned,
+  Deserialize,
+};
+use serde_json::json;
+
+#[derive(Deserialize)]
+struct GitLabIssue {
+  iid: u64,
+  title: String,
+  #[serde(default)]
+  description: Option<String>,
+  state: String,
+  #[serde(default)]
+  labels: Vec<String>,
+  #[serde(default)]
+  assignees: Vec<User>,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+  #[serde(default)]
+  closed_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  closed_by: Option<User>,
+  #[serde(default)]
+  due_date: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+struct Note {
+  id: u64,
+  body: String,
+  author: User,
+  created_at: DateTime<Utc>,
+  /// Notes GitLab makes itself, e.g. when a label is added
+  #[serde(default)]
+  system: bool,
+}
+
+#[derive(Deserialize)]
+struct User {
+  name: String,
+  username: String,
+}
+
+impl From<User> for crate::sync::User {
+  fn from(user: User) -> Self {
+    Self {
+      name: user.name,
+      username: user.username,
+    }
+  }
+}
+
+/// A project on a GitLab instance
+pub struct GitLab {
+  client: Client,
+  /// Where the issues of the project are in the API
+  api: String,
+  token: Option<String>,
+}
+
+impl GitLab {
+  /// Makes a provider for the project at the url, e.g.
+  /// 'https://gitlab.com/group/project'. Projects can also be given by their
+  /// id, e.g. 'https://gitlab.com/1234'.
+  pub fn new(url: &str, token: Option<String>) -> Result<Self> {
+    let url = url.trim_end_matches('/');
+    let host = url
+      .find("://")
+      .map(|scheme| scheme + 3)
+      .and_then(|start| url[start..].find('/').map(|slash| start + slash));
+    let (instance, project) = match host {
+      Some(end) if end + 1 < url.len() => (&url[..end], &url[end + 1..]),
+      _ => bail!(
+        "'{}' isn't the url of a GitLab project, expected something like \
+         'https://gitlab.com/group/project'",
+        url
+      ),
+    };
+    Ok(Self {
+      client: client()?,
+      api: format!(
+        "{}/api/v4/projects/{}/issues",
+        instance,
+        project.replace('/', "%2F")
+      ),
+      token,
+    })
+  }
+
+  fn request(&self, method: Method, path: &str) -> RequestBuilder {
+    let request = self
+      .client
+      .request(method, &format!("{}{}", self.api, path));
+    match &self.token {
+      Some(token) => request.header("PRIVATE-TOKEN", token.as_str()),
+      None => request,
+    }
+  }
+
+  /// Closes or reopens an issue, event being 'close' or 'reopen'
+  fn state_event(&self, issue: &str, event: &str) -> Result<()> {
+    let _ = check(
+      Kind::GitLab,
+      self
+        .request(Method::PUT, &format!("/{}", issue))
+        .header("Content-Type", "application/json")
+        .body(json!({ "state_event": event }).to_string()),
+    )?;
+    Ok(())
+  }
+
+  /// Gets every page of a list from the API
+  fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+    let mut all = Vec::new();
+    let mut page = "1".to_owned();
+    loop {
+      let response = check(
+        Kind::GitLab,
+        self
+          .request(Method::GET, path)
+          .query(&[("per_page", "100"), ("page", &page)]),
+      )?;
+      let next = response
+        .headers()
+        .get("x-next-page")
+        .and_then(|next| next.to_str().ok())
+        .map(ToOwned::to_owned)
+        .filter(|next| !next.is_empty());
+      all.extend(serde_json::from_str::<Vec<T>>(&response.text()?)?);
+      match next {
+        Some(next) => page = next,
+        None => return Ok(all),
+      }
+    }
+  }
+}
+
+impl Provider for GitLab {
+  fn issues(&self) -> Result<Vec<Issue>> {
+    Ok(
+      self
+        .get_all::<GitLabIssue>("?scope=all")?
+        .into_iter()
+        .map(|issue| Issue {
+          id: issue.iid.to_string(),
+          title: issue.title,
+          description: issue.description.unwrap_or_default(),
+          closed: issue.state == "closed",
+          labels: issue.labels,
+          assignees: issue.assignees.into_iter().map(Into::into).collect(),
+          created_at: issue.created_at,
+          updated_at: issue.updated_at,
+          closed_at: issue.closed_at,
+          closed_by: issue.closed_by.map(Into::into),
+          due: issue.due_date,
+        })
+        .collect(),
+    )
+  }
+
+  fn comments(&self, issue: &str) -> Result<Vec<IssueComment>> {
+    Ok(
+      self
+        .get_all::<Note>(&format!("/{}/notes?sort=asc", issue))?
+        .into_iter()
+        .filter(|note| !note.system)
+        .map(|note| IssueComment {
+          id: note.id.to_string(),
+          body: note.body,
+          author: note.author.into(),
+          created_at: note.created_at,
+        })
+        .collect(),
+    )
+  }
+
+  fn create(&self, title: &str, description: &str) -> Result<String> {
+    let issue: GitLabIssue = send(
+      Kind::GitLab,
+      self
+        .request(Method::POST, "")
+        .header("Content-Type", "application/json")
+        .body(
+          json!({ "title": title, "description": description }).to_string(),
+        ),
+    )?;
+    Ok(issue.iid.to_string())
+  }
+
+  fn update(&self, issue: &str, title: &str, description: &str) -> Result<()> {
+    let _ = check(
+      Kind::GitLab,
+      self
+        .request(Method::PUT, &format!("/{}", issue))
+        .header("Content-Type", "application/json")
+        .body(
+          json!({ "title": title, "description": description }).to_string(),
+        ),
+    )?;
+    Ok(())
+  }
+
+  fn close(&self, issue: &str) -> Result<()> {
+    self.state_event(issue, "close")
+  }
+
+  fn reopen(&self, issue: &str) -> Result<()> {
+    self.state_event(issue, "reopen")
+  }
+
+  fn comment(&self, issue: &str, body: &str) -> Result<String> {
+    let note: Note = send(
+      Kind::GitLab,
+      self
+        .request(Method::POST, &format!("/{}/notes", issue))
+        .header("Content-Type", "application/json")
+        .body(json!({ "body": body }).to_string()),
+    )?;
+    Ok(note.id.to_string())
+  }
+}
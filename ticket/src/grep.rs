@@ -0,0 +1,71 @@
+//! Prints every line of the descriptions and comments that matches a regex in
+//! the `name:line:text` shape grep uses, so it can be piped into other tools.
+//! Unlike `ticket search` nothing is highlighted or shown around the matches.
+use crate::{
+  actions::get_all_tickets,
+  crypt,
+  Ticket,
+};
+use anyhow::Result;
+use colored::*;
+use regex::{
+  Regex,
+  RegexBuilder,
+};
+use std::process;
+
+/// Prints the matching lines of every open or closed ticket under its short
+/// id and title, or with the short id at the start of each line if it's
+/// `no_heading`. Like grep it exits with 1 if nothing matched.
+pub fn grep(pattern: &str, ignore_case: bool, no_heading: bool) -> Result<()> {
+  let re = RegexBuilder::new(pattern)
+    .case_insensitive(ignore_case)
+    .build()?;
+  let mut found = false;
+  for ticket in get_all_tickets()? {
+    let lines = matching_lines(&re, &ticket);
+    if lines.is_empty() {
+      continue;
+    }
+    if no_heading {
+      for line in lines {
+        println!("{}:{}", ticket.short_id, line);
+      }
+    } else {
+      if found {
+        println!();
+      }
+      println!("{} {}", ticket.short_id.bold().red(), ticket.title.bold());
+      for line in lines {
+        println!("{}", line);
+      }
+    }
+    found = true;
+  }
+  if !found {
+    process::exit(1);
+  }
+  Ok(())
+}
+
+/// The matching lines of the description and comments as `description:3:...`
+/// or `comment-<first 8 of its id>:1:...`. Encrypted text is skipped rather
+/// than decrypting every ticket.
+fn matching_lines(re: &Regex, ticket: &Ticket) -> Vec<String> {
+  let mut out = Vec::new();
+  let mut search = |name: &str, text: &str| {
+    if crypt::is_encrypted(text) {
+      return;
+    }
+    for (n, line) in text.lines().enumerate() {
+      if re.is_match(line) {
+        out.push(format!("{}:{}:{}", name, n + 1, line));
+      }
+    }
+  };
+  search("description", &ticket.description);
+  for (id, (_, _, comment)) in &ticket.comments {
+    search(&format!("comment-{}", &id.to_string()[..8]), &comment.0);
+  }
+  out
+}
@@ -0,0 +1,119 @@
+//! Runs the scripts in `.dev-suite/ticket/hooks` when tickets are created,
+//! closed, or commented on, e.g. to add closed tickets to a CHANGELOG. Each
+//! script is named for its event, `on-create`, `on-close`, or `on-comment`, and
+//! gets the ticket as json on stdin. It runs from the root of the repo with
+//! `TICKET_ID`, `TICKET_SHORT_ID`, `TICKET_TITLE`, and `TICKET_COMMENT` in its
+//! environment, where the comment is the one made or the reason for closing.
+//! Since anyone who can push to the repo can change them they only run in
+//! clones where `ticket init --hooks` has turned them on.
+use crate::{
+  actions::ticket_root,
+  webhook::{
+    self,
+    Event,
+  },
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use git2::{
+  ConfigLevel,
+  Repository,
+};
+use log::*;
+use shared::find_root;
+use std::{
+  io::Write,
+  process::{
+    Command,
+    Stdio,
+  },
+};
+
+/// Runs the hook for the event and sends the webhooks for it. Failures are
+/// only warned about since the ticket has already been saved.
+pub fn notify(event: Event, ticket: &Ticket, comment: Option<&str>) {
+  match run(event, ticket, comment) {
+    Ok(out) => print!("{}", out),
+    Err(e) => warn!("{}", e),
+  }
+  webhook::notify(event, ticket, comment);
+}
+
+/// The git config setting that turns hooks on
+const ENABLED: &str = "ticket.hooks";
+
+/// Turns on running the repo's hooks in this clone by setting it in
+/// .git/config, which isn't committed
+pub fn enable() -> Result<()> {
+  Repository::discover(find_root()?)?
+    .config()?
+    .open_level(ConfigLevel::Local)?
+    .set_bool(ENABLED, true)?;
+  Ok(())
+}
+
+/// Whether hooks have been turned on for this clone, or for every repo in the
+/// user's git config
+fn enabled() -> Result<bool> {
+  let config = Repository::discover(find_root()?)?.config()?;
+  Ok(config.get_bool(ENABLED).unwrap_or(false))
+}
+
+/// The file name of the hook for the event
+fn hook_name(event: Event) -> &'static str {
+  match event {
+    Event::Created => "on-create",
+    Event::Closed => "on-close",
+    Event::Commented => "on-comment",
+  }
+}
+
+/// Runs the hook for the event if there is one and returns what it printed
+pub fn run(
+  event: Event,
+  ticket: &Ticket,
+  comment: Option<&str>,
+) -> Result<String> {
+  let path = ticket_root()?.join("hooks").join(hook_name(event));
+  if !path.is_file() {
+    return Ok(String::new());
+  }
+  if !enabled()? {
+    warn!(
+      "Not running the {} hook since hooks aren't turned on in this clone. \
+       Run `ticket init --hooks` to turn them on if you trust them.",
+      hook_name(event)
+    );
+    return Ok(String::new());
+  }
+  // Comments on encrypted tickets stay between the maintainers
+  let comment = comment.filter(|_| !ticket.encrypted);
+  debug!("Running {}", path.display());
+  let mut child = Command::new(&path)
+    .current_dir(find_root()?)
+    .env("TICKET_ID", ticket.id.to_string())
+    .env("TICKET_SHORT_ID", &ticket.short_id)
+    .env("TICKET_TITLE", &ticket.title)
+    .env("TICKET_COMMENT", comment.unwrap_or_default())
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+  if let Some(mut stdin) = child.stdin.take() {
+    // Hooks that don't read the ticket may exit before it's all written
+    let _ = stdin.write_all(serde_json::to_string(ticket)?.as_bytes());
+  }
+  let output = child.wait_with_output()?;
+  if !output.status.success() {
+    bail!(
+      "The {} hook failed with {}. {}",
+      hook_name(event),
+      output.status,
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,316 @@
+//! Renders the tickets as a static site, an index of every ticket plus a page
+//! for each, that can be published with something like GitHub Pages. `ticket
+//! serve` uses the same pages with forms added to edit the tickets.
+use crate::{
+  actions::statuses,
+  assignee_names,
+  task_progress,
+  Relation,
+  Ticket,
+};
+use anyhow::Result;
+use log::*;
+use std::{
+  fmt::Write,
+  fs,
+  path::Path,
+};
+
+pub const STYLE: &str = "\
+body { font-family: sans-serif; max-width: 60em; margin: 2em auto; \
+padding: 0 1em; color: #24292e; }
+a { color: #0366d6; text-decoration: none; }
+a:hover { text-decoration: underline; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.4em 0.6em; \
+border-bottom: 1px solid #e1e4e8; }
+code { color: #6a737d; }
+.tag { display: inline-block; background: #e1e4e8; border-radius: 1em; \
+padding: 0 0.6em; margin-right: 0.3em; font-size: 0.85em; }
+.text { white-space: pre-wrap; }
+.comment { border: 1px solid #e1e4e8; border-radius: 4px; margin: 1em 0; }
+.comment header { background: #f6f8fa; padding: 0.4em 0.8em; \
+border-bottom: 1px solid #e1e4e8; }
+.comment .text { padding: 0 0.8em; }
+form { margin: 1em 0; }
+input[type=text], textarea { width: 100%; box-sizing: border-box; }
+";
+
+/// Writes index.html, style.css, and a page in tickets/ for each ticket to
+/// `dir`. `all` is every ticket so that related tickets can be named even if
+/// they weren't exported.
+pub fn site(tickets: &[&Ticket], all: &[Ticket], dir: &Path) -> Result<()> {
+  fs::create_dir_all(dir.join("tickets"))?;
+  fs::write(dir.join("style.css"), STYLE)?;
+  fs::write(dir.join("index.html"), index(tickets, None)?)?;
+  for ticket in tickets {
+    let path = dir.join("tickets").join(page_name(ticket));
+    trace!("Writing {}", path.display());
+    fs::write(path, ticket_page(ticket, tickets, all, None)?)?;
+  }
+  Ok(())
+}
+
+/// A table of the tickets with each status linking to their pages. If there's
+/// a `token` for forms to be posted with there's a form to make a new ticket.
+pub fn index(tickets: &[&Ticket], token: Option<&str>) -> Result<String> {
+  let mut body = String::from("<h1>Tickets</h1>\n");
+  if let Some(token) = token {
+    write!(
+      body,
+      "<form method=\"post\" action=\"index.html\">\n{}\
+       <input type=\"hidden\" name=\"action\" value=\"new\">\n\
+       <p><input type=\"text\" name=\"title\" placeholder=\"Title\" \
+       required></p>\n\
+       <p><textarea name=\"description\" rows=\"6\" \
+       placeholder=\"Description\"></textarea></p>\n\
+       <button>New ticket</button>\n</form>\n",
+      token_input(token)
+    )?;
+  }
+  for status in statuses()? {
+    let with_status = tickets
+      .iter()
+      .filter(|t| t.status == status)
+      .collect::<Vec<_>>();
+    if with_status.is_empty() {
+      continue;
+    }
+    write!(
+      body,
+      "<h2>{} ({})</h2>\n<table>\n<tr><th>Id</th><th>Title</th>\
+       <th>Tags</th><th>Assignees</th><th>Due</th></tr>\n",
+      escape(&status.to_string()),
+      with_status.len()
+    )?;
+    for ticket in with_status {
+      writeln!(
+        body,
+        "<tr><td><code>{}</code></td><td><a href=\"tickets/{}\">{}</a></td>\
+         <td>{}</td><td>{}</td><td>{}</td></tr>",
+        escape(&ticket.short_id),
+        page_name(ticket),
+        escape(&ticket.title),
+        tags(ticket),
+        escape(&assignee_names(ticket)),
+        ticket.due.map(|due| due.to_string()).unwrap_or_default()
+      )?;
+    }
+    body.push_str("</table>\n");
+  }
+  Ok(page("Tickets", "", &body))
+}
+
+/// Everything about a ticket: its status, labels, description, and comments.
+/// If there's a `token` for forms to be posted with there are forms to comment
+/// on, move, and edit it.
+pub fn ticket_page(
+  ticket: &Ticket,
+  exported: &[&Ticket],
+  all: &[Ticket],
+  token: Option<&str>,
+) -> Result<String> {
+  let mut body =
+    String::from("<p><a href=\"../index.html\">All tickets</a></p>\n");
+  write!(
+    body,
+    "<h1>{} <code>{}</code></h1>\n<ul>\n<li><b>Status:</b> {}</li>\n",
+    escape(&ticket.title),
+    escape(&ticket.short_id),
+    escape(&ticket.status.to_string())
+  )?;
+  if !ticket.assignees.is_empty() {
+    writeln!(
+      body,
+      "<li><b>Assignees:</b> {}</li>",
+      escape(&assignee_names(ticket))
+    )?;
+  }
+  if !ticket.tags.is_empty() {
+    writeln!(body, "<li><b>Tags:</b> {}</li>", tags(ticket))?;
+  }
+  if let Some(due) = ticket.due {
+    writeln!(body, "<li><b>Due:</b> {}</li>", due)?;
+  }
+  if let Some(estimate) = ticket.estimate {
+    writeln!(body, "<li><b>Estimate:</b> {}</li>", estimate)?;
+  }
+  if let Some(component) = &ticket.component {
+    writeln!(body, "<li><b>Component:</b> {}</li>", escape(component))?;
+  }
+  for (name, value) in &ticket.fields {
+    writeln!(
+      body,
+      "<li><b>{}:</b> {}</li>",
+      escape(name),
+      escape(&value.to_string())
+    )?;
+  }
+  for relation in Relation::ALL.iter() {
+    for id in ticket.related(*relation) {
+      // Only tickets that were exported have a page to link to
+      let other = match (
+        exported.iter().find(|t| t.id == *id),
+        all.iter().find(|t| t.id == *id),
+      ) {
+        (Some(t), _) => format!(
+          "<a href=\"{}\">{} {}</a>",
+          page_name(t),
+          escape(&t.short_id),
+          escape(&t.title)
+        ),
+        (None, Some(t)) => {
+          format!("{} {}", escape(&t.short_id), escape(&t.title))
+        }
+        (None, None) => id.to_string(),
+      };
+      writeln!(body, "<li><b>{}:</b> {}</li>", relation.title(), other)?;
+    }
+  }
+  if !ticket.tasks.is_empty() {
+    writeln!(
+      body,
+      "<li><b>Tasks:</b> {} done</li>",
+      task_progress(ticket)
+    )?;
+  }
+  body.push_str("</ul>\n");
+
+  let description = ticket.description.trim();
+  if !description.is_empty() {
+    writeln!(body, "<div class=\"text\">{}</div>", escape(description))?;
+  }
+  if !ticket.tasks.is_empty() {
+    body.push_str("<ul>\n");
+    for task in &ticket.tasks {
+      writeln!(
+        body,
+        "<li><input type=\"checkbox\" disabled{}> {}</li>",
+        if task.done { " checked" } else { "" },
+        escape(&task.text)
+      )?;
+    }
+    body.push_str("</ul>\n");
+  }
+  if !ticket.comments.is_empty() {
+    writeln!(body, "<h2>Comments ({})</h2>", ticket.comments.len())?;
+    for (id, (_, name, comment)) in ticket.comments_in_order() {
+      write!(
+        body,
+        "<section class=\"comment\">\n<header><b>{}</b>",
+        escape(&name.0)
+      )?;
+      if let Some(time) = ticket.comment_time(id) {
+        write!(body, " on {}", time.format("%Y-%m-%d %H:%M UTC"))?;
+      }
+      writeln!(
+        body,
+        "</header>\n<div class=\"text\">{}</div>\n</section>",
+        escape(comment.0.trim())
+      )?;
+    }
+  }
+  if let Some(token) = token {
+    edit_forms(&mut body, ticket, token)?;
+  }
+  Ok(page(&ticket.title, "../", &body))
+}
+
+/// Forms that post back to the ticket's page, told apart by their `action`
+fn edit_forms(body: &mut String, ticket: &Ticket, token: &str) -> Result<()> {
+  let token = token_input(token);
+  write!(
+    body,
+    "<form method=\"post\">\n{}\
+     <input type=\"hidden\" name=\"action\" value=\"comment\">\n\
+     <p><textarea name=\"comment\" rows=\"4\" required></textarea></p>\n\
+     <button>Comment</button>\n</form>\n",
+    token
+  )?;
+  write!(
+    body,
+    "<form method=\"post\">\n{}\
+     <input type=\"hidden\" name=\"action\" value=\"status\">\n\
+     <select name=\"status\">\n",
+    token
+  )?;
+  for status in statuses()? {
+    let name = escape(&status.to_string());
+    writeln!(
+      body,
+      "<option{}>{}</option>",
+      if status == ticket.status {
+        " selected"
+      } else {
+        ""
+      },
+      name
+    )?;
+  }
+  body.push_str("</select>\n<button>Move</button>\n</form>\n");
+  write!(
+    body,
+    "<h2>Edit</h2>\n<form method=\"post\">\n{}\
+     <input type=\"hidden\" name=\"action\" value=\"edit\">\n\
+     <p><input type=\"text\" name=\"title\" value=\"{}\" required></p>\n\
+     <p><textarea name=\"description\" rows=\"12\">{}</textarea></p>\n\
+     <button>Save</button>\n</form>\n",
+    token,
+    escape(&ticket.title),
+    escape(&ticket.description)
+  )?;
+  Ok(())
+}
+
+/// The hidden field that has `ticket serve` take a form, so that other sites
+/// can't post forms to it
+fn token_input(token: &str) -> String {
+  format!(
+    "<input type=\"hidden\" name=\"token\" value=\"{}\">\n",
+    escape(token)
+  )
+}
+
+/// The file the ticket's page is written to in tickets/
+pub fn page_name(ticket: &Ticket) -> String {
+  format!("{}.html", ticket.short_id.to_lowercase())
+}
+
+/// The ticket's tags as labels
+fn tags(ticket: &Ticket) -> String {
+  ticket
+    .tags
+    .iter()
+    .map(|tag| format!("<span class=\"tag\">{}</span>", escape(tag)))
+    .collect()
+}
+
+/// Wraps the body in a whole html document. `root` is the path from the page
+/// back to index.html's directory.
+pub fn page(title: &str, root: &str, body: &str) -> String {
+  format!(
+    "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+     <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+     <title>{}</title>\n<link rel=\"stylesheet\" href=\"{}style.css\">\n\
+     </head>\n<body>\n{}</body>\n</html>\n",
+    escape(title),
+    root,
+    body
+  )
+}
+
+/// Makes text safe to put in html
+pub fn escape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => out.push_str("&amp;"),
+      '<' => out.push_str("&lt;"),
+      '>' => out.push_str("&gt;"),
+      '"' => out.push_str("&quot;"),
+      '\'' => out.push_str("&#39;"),
+      c => out.push(c),
+    }
+  }
+  out
+}
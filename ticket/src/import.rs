@@ -0,0 +1,200 @@
+//! Brings tickets in from outside the repo, either from what `ticket export
+//! json` wrote or from another issue tracker
+use crate::{
+  actions::{
+    find_ticket_file,
+    get_all_tickets,
+    get_archived_tickets,
+    save_ticket,
+    statuses,
+    ticket_path,
+  },
+  export::Dump,
+  remote::{
+    get_remotes,
+    Direction,
+    Kind,
+    Remote,
+  },
+  sync,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use configamajig::log_event;
+use log::*;
+use std::{
+  collections::BTreeMap,
+  fs,
+  io::{
+    self,
+    Read,
+  },
+  path::{
+    Path,
+    PathBuf,
+  },
+};
+
+#[derive(structopt::StructOpt)]
+pub enum ImportCmd {
+  /// Recreate the tickets in a json export, e.g. from another repo. Tickets
+  /// keep their uuids and ones that already exist are skipped
+  Json {
+    /// The file `ticket export json` wrote, or '-' to read it from stdin
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    /// Replace tickets that already exist with the ones in the export
+    #[structopt(long)]
+    force: bool,
+  },
+  /// Open a ticket for each issue in a GitLab project, along with its
+  /// comments and labels. Issues that were imported before only get the
+  /// comments made on them since
+  Gitlab {
+    /// The project's id or its path, e.g. 'group/project'
+    #[structopt(long)]
+    project: String,
+    /// Where GitLab is, for self-hosted instances
+    #[structopt(long, default_value = "https://gitlab.com")]
+    url: String,
+    /// An access token with the read_api scope, needed for private projects
+    #[structopt(long, env = "GITLAB_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// The remote to remember which issue and comment each ticket and
+    /// comment came from in, made if it doesn't exist
+    #[structopt(long, default_value = "gitlab")]
+    remote: String,
+  },
+  /// Mirror the issues in a Gitea or Forgejo repo into tickets. Issues that
+  /// were mirrored before only get the comments made on them since, and
+  /// `ticket remote sync` sends changes made to the tickets back
+  Gitea {
+    /// The url of the instance, e.g. 'https://codeberg.org'
+    #[structopt(long)]
+    url: String,
+    /// The repo as 'owner/repo'
+    #[structopt(long)]
+    repo: String,
+    /// An access token, needed for private repos
+    #[structopt(long, env = "GITEA_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// The remote to remember which issue and comment each ticket and
+    /// comment came from in, made if it doesn't exist
+    #[structopt(long, default_value = "gitea")]
+    remote: String,
+  },
+}
+
+pub fn import(cmd: ImportCmd) -> Result<()> {
+  match cmd {
+    ImportCmd::Json { path, force } => json(&path, force),
+    ImportCmd::Gitlab {
+      project,
+      url,
+      token,
+      remote,
+    } => from_remote(Kind::GitLab, &remote, url, &project, token),
+    ImportCmd::Gitea {
+      url,
+      repo,
+      token,
+      remote,
+    } => from_remote(Kind::Gitea, &remote, url, &repo, token),
+  }
+}
+
+/// Brings in the issues on the remote with the name, which is made for the
+/// project at the url if it doesn't exist yet
+fn from_remote(
+  kind: Kind,
+  name: &str,
+  url: String,
+  project: &str,
+  token: Option<String>,
+) -> Result<()> {
+  let remote = match get_remotes()?.remove(name) {
+    Some(remote) if remote.kind != kind => bail!(
+      "The remote '{}' is a {} remote, not a {} one",
+      name,
+      remote.kind,
+      kind
+    ),
+    Some(remote) if remote.direction == Direction::Push => bail!(
+      "The remote '{}' is push only so issues aren't imported from it",
+      name
+    ),
+    Some(remote) => remote,
+    None => Remote {
+      kind,
+      url: format!("{}/{}", url.trim_end_matches('/'), project),
+      direction: Direction::Both,
+      ids: BTreeMap::new(),
+      comments: BTreeMap::new(),
+      synced: BTreeMap::new(),
+    },
+  };
+  let provider = sync::provider(&remote, token)?;
+  sync::import(&*provider, name, remote)
+}
+
+/// Saves each ticket in the export at `path`, or stdin if it's `-`, as is so
+/// it keeps its uuid, comments, and history. Tickets that already exist are
+/// skipped unless `force` is set in which case they're replaced.
+fn json(path: &Path, force: bool) -> Result<()> {
+  let json = if path == Path::new("-") {
+    let mut json = String::new();
+    let _ = io::stdin().read_to_string(&mut json)?;
+    json
+  } else {
+    fs::read_to_string(path)?
+  };
+  let dump = serde_json::from_str::<Dump<Ticket>>(&json)?;
+  let mut existing = get_all_tickets()?;
+  existing.extend(get_archived_tickets()?);
+  let statuses = statuses()?;
+
+  let mut imported = 0;
+  for ticket in dump.tickets {
+    if let Some(old) = existing.iter().find(|t| t.id == ticket.id) {
+      if !force {
+        info!(
+          "Skipping {} since it already exists: {}",
+          ticket.short_id, ticket.title
+        );
+        continue;
+      }
+      if let Some(path) = find_ticket_file(&old.id)? {
+        fs::remove_file(path)?;
+      }
+    }
+    let path = ticket_path(&ticket)?;
+    if path.exists() {
+      warn!(
+        "Skipping {} since {} already exists for another ticket",
+        ticket.short_id,
+        path.display()
+      );
+      continue;
+    }
+    if !statuses.contains(&ticket.status) {
+      warn!(
+        "{} has the status {} which isn't in this repo's config",
+        ticket.short_id, ticket.status
+      );
+    }
+    save_ticket(&ticket)?;
+    log_event(
+      "ticket",
+      format!(
+        "{}: Imported the ticket '{}'",
+        ticket.short_id, ticket.title
+      ),
+    );
+    imported += 1;
+  }
+  info!("Imported {} tickets", imported);
+  Ok(())
+}
@@ -0,0 +1,139 @@
+use crate::{
+  actions::{
+    find_ticket,
+    get_all_tickets,
+    save_ticket,
+  },
+  ago,
+  crypt,
+  state::UserState,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use chrono::prelude::*;
+use colored::*;
+use configamajig::get_user_config;
+use log::*;
+use uuid::Uuid;
+
+/// Adds the user to the ticket's watchers so what happens on it shows up in
+/// their inbox from now on
+pub fn watch(id: &str) -> Result<()> {
+  let me = get_user_config()?.uuid;
+  let mut ticket = find_ticket(id)?;
+  if ticket.watchers.contains(&me) {
+    info!("You're already watching {}", ticket.short_id);
+    return Ok(());
+  }
+  ticket.watchers.push(me);
+  save_ticket(&ticket)?;
+  let mut state = UserState::load()?;
+  let _ = state.inbox.insert(ticket.id, Utc::now());
+  state.save()?;
+  info!("Watching {}", ticket.short_id);
+  Ok(())
+}
+
+pub fn unwatch(id: &str) -> Result<()> {
+  let me = get_user_config()?.uuid;
+  let mut ticket = find_ticket(id)?;
+  if !ticket.watchers.contains(&me) {
+    bail!("You aren't watching {}", ticket.short_id);
+  }
+  ticket.watchers.retain(|uuid| *uuid != me);
+  save_ticket(&ticket)?;
+  let mut state = UserState::load()?;
+  let _ = state.inbox.remove(&ticket.id);
+  state.save()?;
+  info!("No longer watching {}", ticket.short_id);
+  Ok(())
+}
+
+/// Something that happened on a watched ticket
+struct Item {
+  at: DateTime<Utc>,
+  by: String,
+  what: String,
+}
+
+/// Prints the comments and changes other people made on the tickets the user
+/// watches since the last time they checked. Unless it's a `peek` they're
+/// marked as seen.
+pub fn inbox(peek: bool) -> Result<()> {
+  let me = get_user_config()?.uuid;
+  let mut state = UserState::load()?;
+  let now = Utc::now();
+  let watched = get_all_tickets()?
+    .into_iter()
+    .filter(|t| t.watchers.contains(&me))
+    .collect::<Vec<Ticket>>();
+
+  let mut empty = true;
+  for ticket in &watched {
+    let since = state.inbox.get(&ticket.id).copied();
+    let items = items(ticket, me, since);
+    if !peek {
+      let _ = state.inbox.insert(ticket.id, now);
+    }
+    if items.is_empty() {
+      continue;
+    }
+    empty = false;
+    println!("{} {}", ticket.short_id.bold().red(), ticket.title.bold());
+    for item in items {
+      println!(
+        "  {} {} {}",
+        ago(item.at).dimmed(),
+        item.by.blue(),
+        item.what
+      );
+    }
+  }
+  if empty {
+    println!("Nothing new on the tickets you're watching.");
+  }
+  // Forget the tickets that aren't watched anymore, e.g. deleted ones
+  state
+    .inbox
+    .retain(|id, _| watched.iter().any(|t| t.id == *id));
+  state.save()
+}
+
+/// The comments and changes made on the ticket by someone other than `me`
+/// after `since`, oldest first
+fn items(ticket: &Ticket, me: Uuid, since: Option<DateTime<Utc>>) -> Vec<Item> {
+  let new = |at: DateTime<Utc>| since.is_none_or(|since| at > since);
+  let mut items = Vec::new();
+  for (id, (by, name, comment)) in &ticket.comments {
+    let at = match ticket.comment_time(id) {
+      Some(at) if *by != me && new(at) => at,
+      _ => continue,
+    };
+    // Encrypted comments stay encrypted for anyone who can't decrypt them
+    let text =
+      crypt::reveal(&comment.0).unwrap_or_else(|_| "(encrypted)".to_owned());
+    items.push(Item {
+      at,
+      by: name.0.clone(),
+      what: format!("commented: {}", text.lines().next().unwrap_or_default()),
+    });
+  }
+  for event in &ticket.history {
+    if !new(event.at) || event.by.as_ref().is_some_and(|(by, _)| *by == me) {
+      continue;
+    }
+    items.push(Item {
+      at: event.at,
+      by: event
+        .by
+        .as_ref()
+        .map_or_else(|| "Someone".to_owned(), |(_, name)| name.0.clone()),
+      what: event.action.clone(),
+    });
+  }
+  items.sort_by_key(|item| item.at);
+  items
+}
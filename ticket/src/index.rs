@@ -0,0 +1,241 @@
+//! A search index kept in `.git/ds-cache` so that `ticket search` doesn't have
+//! to read every ticket in big repos. It maps every word in the tickets to the
+//! tickets containing it and is brought up to date before each search by
+//! reading only the ticket files that changed since it was last written.
+use crate::{
+  actions::{
+    read_ticket,
+    status_dir,
+    statuses,
+    ticket_root,
+  },
+  Ticket,
+};
+use anyhow::Result;
+use log::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+use shared::{
+  find_root,
+  write_atomic,
+};
+use std::{
+  collections::{
+    BTreeMap,
+    BTreeSet,
+  },
+  fs,
+  path::{
+    Path,
+    PathBuf,
+  },
+  time::UNIX_EPOCH,
+};
+use uuid::Uuid;
+
+/// Bumped whenever the format changes so old indexes are rebuilt
+const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Index {
+  version: u32,
+  /// Every ticket file keyed by its path relative to the ticket root
+  files: BTreeMap<String, Entry>,
+  /// The tickets each word shows up in
+  words: BTreeMap<String, BTreeSet<Uuid>>,
+}
+
+/// What the index knows about a ticket file
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+  id: Uuid,
+  /// When the file was last modified in nanoseconds since the epoch, which
+  /// along with its length is used to tell if it changed
+  modified: u128,
+  len: u64,
+  words: BTreeSet<String>,
+}
+
+/// The tickets that could contain the query, ordered like `get_all_tickets`
+/// orders them. Every word of the query has to be part of a word in the
+/// ticket, ignoring case. None means the index couldn't be used and every
+/// ticket needs to be searched instead.
+pub fn candidates(query: &str) -> Option<Vec<Ticket>> {
+  let query = words(query);
+  if query.is_empty() {
+    return None;
+  }
+  match find(&query) {
+    Ok(tickets) => Some(tickets),
+    Err(e) => {
+      debug!(
+        "Unable to use the search index, searching everything: {}",
+        e
+      );
+      None
+    }
+  }
+}
+
+fn find(query: &BTreeSet<String>) -> Result<Vec<Ticket>> {
+  let path = index_path()?;
+  let index = update(&path)?;
+
+  let mut matching: Option<BTreeSet<Uuid>> = None;
+  for part in query {
+    let found = index
+      .words
+      .iter()
+      .filter(|(word, _)| word.contains(part.as_str()))
+      .flat_map(|(_, ids)| ids.iter().copied())
+      .collect::<BTreeSet<Uuid>>();
+    matching = Some(match matching {
+      Some(matching) => matching.intersection(&found).copied().collect(),
+      None => found,
+    });
+  }
+  let matching = matching.unwrap_or_default();
+
+  let root = ticket_root()?;
+  let mut tickets = Vec::new();
+  for (file, entry) in &index.files {
+    if matching.contains(&entry.id) {
+      tickets.push(read_ticket(&root.join(file))?);
+    }
+  }
+  let statuses = statuses()?;
+  tickets.sort_by_key(|t| (statuses.iter().position(|s| *s == t.status), t.id));
+  Ok(tickets)
+}
+
+/// Reads in the index and updates it with the ticket files that were added,
+/// changed, or removed since it was written
+fn update(path: &Path) -> Result<Index> {
+  let mut index = fs::read(path)
+    .ok()
+    .and_then(|bytes| serde_json::from_slice::<Index>(&bytes).ok())
+    .filter(|index| index.version == VERSION)
+    .unwrap_or_else(|| {
+      debug!("Building the search index from scratch.");
+      Index {
+        version: VERSION,
+        ..Index::default()
+      }
+    });
+
+  let root = ticket_root()?;
+  let mut seen = BTreeSet::new();
+  let mut changed = false;
+  for status in statuses()? {
+    let dir = status_dir(&status)?;
+    if !dir.exists() {
+      continue;
+    }
+    for entry in fs::read_dir(&dir)? {
+      let file = entry?.path();
+      if !file.is_file() {
+        continue;
+      }
+      let metadata = fs::metadata(&file)?;
+      let modified =
+        metadata.modified()?.duration_since(UNIX_EPOCH)?.as_nanos();
+      let len = metadata.len();
+      let key = file.strip_prefix(&root)?.to_string_lossy().into_owned();
+      let _ = seen.insert(key.clone());
+      if index
+        .files
+        .get(&key)
+        .is_some_and(|e| e.modified == modified && e.len == len)
+      {
+        continue;
+      }
+
+      trace!("Indexing {}.", file.display());
+      let ticket = read_ticket(&file)?;
+      remove(&mut index, &key);
+      let mut text = vec![ticket.title.as_str()];
+      // Only the title of an encrypted ticket is readable
+      if !ticket.encrypted {
+        text.push(ticket.description.as_str());
+        text.extend(ticket.comments.values().map(|(_, _, c)| c.0.as_str()));
+      }
+      let words = words(&text.join("\n"));
+      for word in &words {
+        let _ = index
+          .words
+          .entry(word.clone())
+          .or_default()
+          .insert(ticket.id);
+      }
+      let _ = index.files.insert(
+        key,
+        Entry {
+          id: ticket.id,
+          modified,
+          len,
+          words,
+        },
+      );
+      changed = true;
+    }
+  }
+
+  let removed = index
+    .files
+    .keys()
+    .filter(|key| !seen.contains(*key))
+    .cloned()
+    .collect::<Vec<String>>();
+  for key in removed {
+    remove(&mut index, &key);
+    changed = true;
+  }
+
+  if changed {
+    save(path, &index)?;
+  }
+  Ok(index)
+}
+
+/// Takes the file and its words out of the index
+fn remove(index: &mut Index, key: &str) {
+  if let Some(entry) = index.files.remove(key) {
+    for word in entry.words {
+      if let Some(ids) = index.words.get_mut(&word) {
+        let _ = ids.remove(&entry.id);
+        if ids.is_empty() {
+          let _ = index.words.remove(&word);
+        }
+      }
+    }
+  }
+}
+
+/// Writes the index atomically so a search running at the same time never
+/// reads half of one
+fn save(path: &Path, index: &Index) -> Result<()> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  write_atomic(path, serde_json::to_vec(index)?)
+}
+
+fn index_path() -> Result<PathBuf> {
+  Ok(
+    find_root()?
+      .join(".git")
+      .join("ds-cache")
+      .join("search-index.json"),
+  )
+}
+
+/// The lowercased words in the text
+pub fn words(text: &str) -> BTreeSet<String> {
+  text
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(str::to_lowercase)
+    .collect()
+}
@@ -1,8 +1,42 @@
 //! ticket is a cli tool to create, delete, and manage tickets as part of
 //! repository, rather than a separate service outside the history of the
 //! code.
-mod actions;
+mod activity;
+mod alias;
+mod api;
+mod badge;
+mod branch;
+mod burndown;
+mod dedupe;
+mod digest;
+mod export;
+mod fsck;
+mod gitea;
+mod github;
+mod gitlab;
+mod grep;
+mod hooks;
+mod html;
+mod import;
+mod inbox;
+mod index;
+mod merge;
+mod release_notes;
+mod remind;
+mod remote;
+mod report;
+mod scan;
+mod search;
+mod serve;
+mod sprint;
+mod starter;
+mod state;
+mod sync;
+mod tree;
 mod tui;
+mod verify;
+mod webhook;
+mod workflow;
 
 use actions::*;
 use anyhow::{
@@ -10,350 +44,2459 @@ use anyhow::{
   format_err,
   Result,
 };
+use badge::Badge;
+use burndown::ChartFormat;
+use chrono::prelude::*;
 use colored::*;
 use configamajig::*;
+use export::Export;
+use import::ImportCmd;
 use log::*;
+use remote::RemoteCmd;
+use report::Report;
 use rustyline::{
   error::ReadlineError,
   Editor,
 };
-use serde::{
-  Deserialize,
-  Serialize,
+use search::Field;
+use sprint::SprintCmd;
+use shared::{
+  find_root,
+  set_root,
+  write_atomic,
 };
+use state::UserState;
 use std::{
-  collections::BTreeMap,
+  cmp,
+  collections::{
+    BTreeMap,
+    BTreeSet,
+  },
   env,
   fs,
+  io::{
+    self,
+    Read,
+  },
+  iter,
+  path::{
+    Path,
+    PathBuf,
+  },
   process,
   process::Command,
-  thread,
-  time,
+  str::FromStr,
+};
+use structopt::StructOpt;
+use ticket_core::{
+  actions,
+  crypt,
+  format,
+  migrate::latest,
+  new_ticket,
+  sign,
+  Comment,
+  Estimate,
+  FieldValue,
+  Name,
+  Redaction,
+  Relation,
+  Status,
+  Task,
+  Ticket,
 };
+use tree::TreeFormat;
 use uuid::Uuid;
+use workflow::{
+  Transition,
+  Workflow,
+};
 
 #[derive(structopt::StructOpt)]
 struct Args {
+  /// Use this directory as the repo root instead of searching for it
+  #[structopt(long, global = true, parse(from_os_str))]
+  root: Option<PathBuf>,
+  /// Show snoozed tickets in the tui
+  #[structopt(long)]
+  include_snoozed: bool,
+  /// Order the tickets in the tui, the same way as `ticket list --sort`
+  #[structopt(long)]
+  sort: Option<Sort>,
+  /// Show the tickets in the tui in the opposite order
+  #[structopt(long, requires = "sort")]
+  reverse: bool,
   #[structopt(subcommand)]
   cmd: Option<Cmd>,
 }
 
 #[derive(structopt::StructOpt)]
 enum Cmd {
-  /// Initialize the repo to use ticket
-  Init,
+  /// Initialize the repo to use ticket with starter templates
+  Init {
+    /// Also open a ticket introducing how to use ticket
+    #[structopt(long)]
+    welcome: bool,
+    /// Run the scripts in .dev-suite/ticket/hooks in this clone. Only turn
+    /// this on for repos whose hooks you trust.
+    #[structopt(long)]
+    hooks: bool,
+  },
   /// Update tickets to newer formats
-  Migrate,
-  /// Create a new ticket
-  New,
+  Migrate {
+    /// Show which tickets would be upgraded and how without changing them
+    #[structopt(long)]
+    dry_run: bool,
+  },
+  /// Check for tickets that can't be read, are in the wrong place, share an
+  /// id, are related to tickets that don't exist, or are assigned to someone
+  /// who isn't a maintainer
+  Fsck {
+    /// Move and rename tickets that are in the wrong place and drop relations
+    /// to tickets that don't exist
+    #[structopt(long)]
+    fix: bool,
+  },
+  /// Check that comments and changes to tickets were signed by who made them
+  /// with their key from the repo config, failing if any weren't
+  Verify {
+    /// Only check the ones made on or after this date, e.g. 2020-07-01
+    #[structopt(long)]
+    since: Option<NaiveDate>,
+    /// Check against the keys in the repo config at this revision rather
+    /// than the current one, e.g. the old value of a ref being pushed to
+    #[structopt(long)]
+    keys_from: Option<String>,
+  },
+  /// Set a maintainer's key in the repo config, signed with your own key so
+  /// `ticket verify --keys-from` trusts it
+  Trust { uuid: Uuid, key: String },
+  /// Create a new ticket, asking for anything not passed as a flag
+  New {
+    /// The title of the ticket
+    #[structopt(long)]
+    title: Option<String>,
+    /// The description of the ticket
+    #[structopt(long, conflicts_with_all = &["description-file", "template"])]
+    description: Option<String>,
+    /// Read the description from this file, or from stdin if it's '-'
+    #[structopt(long, parse(from_os_str), conflicts_with = "template")]
+    description_file: Option<PathBuf>,
+    /// Start the description off with a template from
+    /// .dev-suite/ticket/templates
+    #[structopt(long)]
+    template: Option<String>,
+    /// Don't open up the editor for the description
+    #[structopt(long)]
+    no_edit: bool,
+    /// Encrypt the description and comments for the maintainers with keys in
+    /// the repo config
+    #[structopt(long)]
+    encrypt: bool,
+    /// The component from the repo config to file the ticket under. It's
+    /// asked for along with the title if there are components.
+    #[structopt(long)]
+    component: Option<String>,
+    /// Set a custom field from the repo config, e.g. --field severity=high.
+    /// Any that aren't set are asked for along with the title.
+    #[structopt(long = "field", number_of_values = 1)]
+    fields: Vec<String>,
+  },
+  /// List tickets on the command line, only open ones by default
+  List {
+    /// List closed tickets instead of open ones
+    #[structopt(long, conflicts_with_all = &["all", "status"])]
+    closed: bool,
+    /// List tickets no matter their status
+    #[structopt(long, conflicts_with = "status")]
+    all: bool,
+    /// Only list tickets with this status
+    #[structopt(long)]
+    status: Option<String>,
+    /// Only list tickets assigned to this person, by name, uuid, or 'me'
+    #[structopt(long)]
+    assignee: Option<String>,
+    /// Include tickets you've snoozed
+    #[structopt(long)]
+    include_snoozed: bool,
+    /// Only list tickets you've snoozed, with the date each one comes back,
+    /// to review what was put off
+    #[structopt(long)]
+    snoozed: bool,
+    /// Only list tickets made or commented on since this date, e.g. 2020-07-01
+    #[structopt(long, conflicts_with = "unread")]
+    comments_since: Option<NaiveDate>,
+    /// Only list tickets made or commented on since you last showed them
+    #[structopt(long)]
+    unread: bool,
+    /// Show each ticket under the tickets it blocks
+    #[structopt(long)]
+    tree: bool,
+    /// How to draw the tree, either 'human' or 'dot' for Graphviz
+    #[structopt(long, requires = "tree")]
+    format: Option<TreeFormat>,
+    /// Order the tickets by 'activity', most recently changed or commented on
+    /// first, 'created' or 'updated', newest first, 'title', 'priority' from
+    /// the repo config's priority field, or 'assignee'. They're ordered by
+    /// uuid otherwise.
+    #[structopt(long)]
+    sort: Option<Sort>,
+    /// List the tickets in the opposite order
+    #[structopt(long, requires = "sort")]
+    reverse: bool,
+    /// Only list tickets with a custom field set to a value, e.g. --field
+    /// severity=high. Can be passed more than once.
+    #[structopt(long = "field", number_of_values = 1)]
+    fields: Vec<String>,
+  },
+  /// Search the titles, descriptions, and comments of open and closed tickets
+  Search {
+    query: String,
+    /// Match without caring about case
+    #[structopt(long, short)]
+    ignore_case: bool,
+    /// Treat the query as a regular expression
+    #[structopt(long)]
+    regex: bool,
+    /// Only search this field, either 'title', 'description', or 'comments'.
+    /// Can be passed more than once.
+    #[structopt(long = "field", number_of_values = 1)]
+    fields: Vec<Field>,
+    /// How many lines to show around each matching line
+    #[structopt(long, short = "C", default_value = "1")]
+    context: usize,
+  },
+  /// Print the lines of descriptions and comments that match a regex in the
+  /// format grep uses, for piping into other tools
+  Grep {
+    pattern: String,
+    /// Match without caring about case
+    #[structopt(long, short)]
+    ignore_case: bool,
+    /// Start each line with the ticket's short id instead of printing it and
+    /// the title above the ticket's matches
+    #[structopt(long)]
+    no_heading: bool,
+  },
   /// Show a ticket on the command line
-  Show { id: Uuid },
+  Show {
+    /// The ticket's uuid, short id, or file name without its extension
+    #[structopt(required_unless = "title")]
+    id: Option<String>,
+    /// Find the ticket by its title instead, ignoring case
+    #[structopt(long, conflicts_with = "id")]
+    title: Option<String>,
+    /// Only show comments made since this date, e.g. 2020-07-01
+    #[structopt(long, conflicts_with = "unread")]
+    comments_since: Option<NaiveDate>,
+    /// Only show comments made since you last showed the ticket
+    #[structopt(long)]
+    unread: bool,
+    /// Output format, either 'human', 'json', or 'toml'
+    #[structopt(long, default_value = "human")]
+    format: Format,
+  },
+  /// Show who changed what about a ticket and when
+  History { id: String },
+  /// List the commits that mention a ticket by its uuid or short id
+  Activity { id: String },
+  /// Open tickets for TODO(ds) and FIXME(ds) comments in the code and update
+  /// where the ones that already have tickets are
+  Scan {
+    /// Show what would change without changing anything
+    #[structopt(long)]
+    dry_run: bool,
+  },
+  /// Edit the description of a ticket in $EDITOR
+  Edit {
+    id: String,
+    /// Change the title of the ticket as well
+    #[structopt(long)]
+    title: Option<String>,
+    /// Don't open up the editor, e.g. if you only want to change the title
+    #[structopt(long)]
+    no_edit: bool,
+  },
+  /// Open a ticket's file in $EDITOR to change anything about it by hand. The
+  /// file is checked afterwards and put back if it isn't a valid ticket.
+  Open { id: String },
   /// Close a ticket from the command line
-  Close { id: Uuid },
+  Close {
+    id: String,
+    /// Why the ticket is being closed, which is added as a comment
+    #[structopt(long)]
+    reason: Option<String>,
+  },
+  /// Reopen a closed ticket from the command line
+  Reopen {
+    id: String,
+    /// Why the ticket is being reopened, which is added as a comment
+    #[structopt(long)]
+    reason: Option<String>,
+  },
+  /// Change the status of a ticket, e.g. to one from the repo config
+  Move {
+    id: String,
+    status: String,
+    /// Why the ticket is moving, which is added as a comment
+    #[structopt(long)]
+    reason: Option<String>,
+  },
+  /// Delete a ticket that shouldn't exist, e.g. one made by mistake
+  Delete {
+    id: String,
+    /// Don't ask for confirmation first
+    #[structopt(long)]
+    force: bool,
+  },
   /// Comment on a ticket from the command line
-  Comment { id: Uuid, message: String },
-  /// Assing someone to a ticket from the command line
+  Comment { id: String, message: String },
+  /// Encrypt the description and comments of a confidential ticket for the
+  /// maintainers with gpg keys in the repo config
+  Encrypt { id: String },
+  /// Store an encrypted ticket's description and comments readable again
+  Decrypt { id: String },
+  /// Stop any more comments from being added to a ticket, e.g. one that's
+  /// drawing heated or off topic comments. Only maintainers can lock tickets.
+  Lock { id: String },
+  /// Let a locked ticket be commented on again
+  Unlock { id: String },
+  /// Make a git branch named after a ticket, check it out, and record it on
+  /// the ticket
+  Branch {
+    id: String,
+    /// Use this name instead of the short id and title
+    #[structopt(long)]
+    name: Option<String>,
+    /// Only make the branch without checking it out
+    #[structopt(long)]
+    no_checkout: bool,
+  },
+  /// Attach a file to a ticket
+  Attach {
+    id: String,
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+  },
+  /// Find attachments no longer referenced by any ticket
+  Gc {
+    /// Delete the unreferenced attachments rather than just listing them
+    #[structopt(long)]
+    delete: bool,
+  },
+  /// Move old closed tickets into the archive, which is left out of lists and
+  /// the tui but still found when a ticket is asked for by its id
+  Archive {
+    /// Archive the tickets closed before this date, e.g. 2020-07-01
+    #[structopt(long)]
+    before: NaiveDate,
+  },
+  /// Hide a ticket for yourself until the given date
+  Snooze {
+    id: String,
+    /// The date to show the ticket again on, e.g. 2020-07-01
+    #[structopt(long)]
+    until: NaiveDate,
+  },
+  /// Stop hiding a snoozed ticket
+  Unsnooze { id: String },
+  /// Hear about new comments and changes on a ticket in your inbox
+  Watch { id: String },
+  /// Stop hearing about a ticket in your inbox
+  Unwatch { id: String },
+  /// Show the comments and changes others made on the tickets you watch since
+  /// you last checked
+  Inbox {
+    /// Leave what's shown as new for next time
+    #[structopt(long)]
+    peek: bool,
+  },
+  /// Set the date a ticket is due, e.g. 2020-07-01
+  Due {
+    id: String,
+    #[structopt(required_unless = "clear")]
+    date: Option<NaiveDate>,
+    /// Remove the due date instead
+    #[structopt(long, conflicts_with = "date")]
+    clear: bool,
+  },
+  /// Set how much work a ticket is, in points like 3 or a time like 4h, 2d,
+  /// or 1w
+  Estimate {
+    id: String,
+    #[structopt(required_unless = "clear")]
+    estimate: Option<Estimate>,
+    /// Remove the estimate instead
+    #[structopt(long, conflicts_with = "estimate")]
+    clear: bool,
+  },
+  /// Set one of the custom fields from the repo config on a ticket
+  Set {
+    id: String,
+    field: String,
+    #[structopt(required_unless = "clear")]
+    value: Option<String>,
+    /// Remove the field's value instead
+    #[structopt(long, conflicts_with = "value")]
+    clear: bool,
+  },
+  /// File a ticket under one of the components in the repo config
+  Component {
+    id: String,
+    #[structopt(required_unless = "clear")]
+    component: Option<String>,
+    /// Take the ticket out of its component instead
+    #[structopt(long, conflicts_with = "component")]
+    clear: bool,
+  },
+  /// Remind you of tickets assigned to you that are due soon or were blocked
+  /// since the last reminder. Meant to be run from the post-checkout and
+  /// post-merge hooks.
+  Remind {
+    /// How many days ahead counts as due soon
+    #[structopt(long, default_value = "3")]
+    days: i64,
+  },
+  /// List the open tickets nobody has changed or commented on in a while,
+  /// least recently active first
+  Stale {
+    /// How many days without activity makes a ticket stale
+    #[structopt(long, default_value = "30")]
+    days: i64,
+    /// Output format, either 'human', 'json', or 'toml'
+    #[structopt(long, default_value = "human")]
+    format: Format,
+  },
+  /// List open tickets that look like duplicates of each other, going by the
+  /// words in their titles and descriptions
+  Dedupe {
+    /// How alike tickets have to be to be listed, from 0 to 1
+    #[structopt(long, default_value = "0.6")]
+    threshold: f64,
+    /// Ask whether to close the newer ticket of each pair as a duplicate of
+    /// the older one
+    #[structopt(long, short)]
+    interactive: bool,
+  },
+  /// Counts of open and closed tickets, who and what they're for, how long
+  /// they take to close, and which were active recently
+  Stats {
+    /// How many days back counts as recently active
+    #[structopt(long, default_value = "7")]
+    days: i64,
+    /// Output format, either 'human', 'json', or 'toml'
+    #[structopt(long, default_value = "human")]
+    format: Format,
+  },
+  /// Print Markdown for a changelog listing the tickets closed since a git tag
+  /// or date, grouped by their tags
+  ReleaseNotes {
+    /// A tag or other git revision, or a date like 2024-07-01
+    #[structopt(long)]
+    since: String,
+    /// Start with a heading for the release, e.g. v0.2.0
+    #[structopt(long)]
+    heading: Option<String>,
+  },
+  /// Print a plain text summary of the tickets made, closed, reopened,
+  /// commented on, and deleted between two commits, e.g. for CI to post to a
+  /// mailing list or chat
+  Digest {
+    /// A tag or other git revision, or a date like 2024-07-01 for the last
+    /// commit before it
+    #[structopt(long)]
+    since: String,
+    /// The git revision to compare with
+    #[structopt(long, default_value = "HEAD")]
+    until: String,
+  },
+  /// Summaries of the tickets in the repo
+  Report(Report),
+  /// Write an svg badge with the state of the tickets, e.g. for a README
+  Badge {
+    /// What the badge shows, either 'open' for the number of open tickets or
+    /// 'progress' for the percent of tickets that are closed
+    kind: Badge,
+    /// Where to write the badge to
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    /// Only count tickets with this tag, e.g. the one for the current
+    /// milestone
+    #[structopt(long)]
+    tag: Option<String>,
+  },
+  /// Write tickets to a single document, e.g. for a wiki or release notes
+  Export {
+    /// What to write the tickets as, 'markdown', 'json', or 'html'. Only json
+    /// has everything about the tickets and can be imported with `ticket
+    /// import`, while html is a static site of the tickets
+    format: Export,
+    /// Only export tickets with this status
+    #[structopt(long)]
+    status: Option<String>,
+    /// Only export tickets with this tag
+    #[structopt(long)]
+    tag: Option<String>,
+    /// Only export tickets assigned to this person, by name, uuid, or 'me'
+    #[structopt(long)]
+    assignee: Option<String>,
+    /// Write the document to this file rather than to stdout. For html this
+    /// is the directory to write the site to.
+    #[structopt(long, short, alias = "out", parse(from_os_str))]
+    output: Option<PathBuf>,
+  },
+  /// Bring in tickets from a json export or another issue tracker
+  Import(ImportCmd),
+  /// Merge two versions of a ticket file, which git runs when merging
+  /// branches once `ticket init` has registered it
+  MergeDriver {
+    /// The version both sides came from
+    #[structopt(parse(from_os_str))]
+    base: PathBuf,
+    /// Our version, where the merged ticket is written
+    #[structopt(parse(from_os_str))]
+    ours: PathBuf,
+    /// The version being merged in
+    #[structopt(parse(from_os_str))]
+    theirs: PathBuf,
+  },
+  /// Serve a web page to browse and edit the tickets on
+  Serve {
+    /// The port to listen on. Only connections from this machine are taken.
+    #[structopt(long, short, default_value = "8080")]
+    port: u16,
+    /// Serve a JSON API for bots and CI jobs instead of the web page
+    #[structopt(long)]
+    api: bool,
+    /// Only take API requests sent with `Authorization: Bearer <token>`
+    #[structopt(long, env = "TICKET_API_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+  },
+  /// Manage the issue trackers tickets are synced with
+  Remote(RemoteCmd),
+  /// Add or remove tags on a ticket
+  Tag(TagCmd),
+  /// Manage the checklist of tasks on a ticket
+  Task(TaskCmd),
+  /// Plan sprints, pull tickets into them, and see how the current one is
+  /// going
+  Sprint(SprintCmd),
+  /// Chart the work left on each day of a sprint, the current one if no name
+  /// is given
+  Burndown {
+    name: Option<String>,
+    /// List how much work was closed in each sprint instead
+    #[structopt(long, conflicts_with = "name")]
+    velocity: bool,
+    /// Output format, either 'human' or 'csv'
+    #[structopt(long, default_value = "human")]
+    format: ChartFormat,
+  },
+  /// Relate a ticket to another one
+  Link {
+    id: String,
+    /// How the ticket relates to the other one: 'blocked-by', 'blocks',
+    /// 'related-to', 'duplicate-of', or 'duplicated-by'
+    relation: Relation,
+    other: String,
+    /// Remove the relation instead of adding it
+    #[structopt(long)]
+    remove: bool,
+  },
+  /// Replace the contents of a comment, e.g. one with a secret pasted in it
+  Redact {
+    id: String,
+    /// The id of the comment shown next to its author by `ticket show`
+    comment: String,
+    /// Why the comment is being redacted
+    #[structopt(long)]
+    reason: String,
+  },
+  /// React to a comment with an emoji, e.g. 👍 or +1
+  React {
+    id: String,
+    /// The id of the comment shown next to its author by `ticket show`
+    comment: String,
+    emoji: String,
+    /// Take the reaction back instead
+    #[structopt(long)]
+    remove: bool,
+  },
+  /// Assign a maintainer to a ticket from the command line
   Assign {
-    id: Uuid,
-    #[structopt(subcommand)]
-    to: Placeholder,
+    id: String,
+    /// The name or uuid of a maintainer in the repo config, or 'me'. The older
+    /// `to me` and `to them <uuid> <name>` still work too.
+    #[structopt(required = true)]
+    maintainer: Vec<String>,
   },
 }
 
 #[derive(structopt::StructOpt)]
-enum Placeholder {
-  To(Who),
+enum TagCmd {
+  /// Tag a ticket
+  Add { id: String, tag: String },
+  /// Remove a tag from a ticket
+  Remove { id: String, tag: String },
 }
 
 #[derive(structopt::StructOpt)]
-enum Who {
-  Me,
-  Them { id: Uuid, name: String },
+enum TaskCmd {
+  /// Add a task to the end of a ticket's checklist
+  Add { id: String, text: String },
+  /// Mark a task as done by its number in the checklist
+  Check { id: String, number: usize },
+  /// Mark a task as not done by its number in the checklist
+  Uncheck { id: String, number: usize },
 }
 
-#[paw::main]
-fn main(args: Args) {
+fn main() {
   env::var("RUST_LOG")
     .ok()
     .map_or_else(|| env::set_var("RUST_LOG", "info"), drop);
   pretty_env_logger::init();
+  let args = Args::from_iter(alias::expand(env::args().collect()));
+
+  if let Err(e) = args.root.map_or(Ok(()), set_root) {
+    error!("{}", e);
+    process::exit(1);
+  }
 
   if let Some(cmd) = args.cmd {
     if let Err(e) = match cmd {
-      Cmd::Init => init(),
-      Cmd::New => new(),
-      Cmd::Migrate => migrate(),
-      Cmd::Show { id } => show(id),
-      Cmd::Close { id } => close(id),
-      Cmd::Comment { id, message } => comment(id, message),
-      Cmd::Assign { id, to } => assign(id, to),
+      Cmd::Init { welcome, hooks } => init(welcome, hooks),
+      Cmd::New {
+        title,
+        description,
+        description_file,
+        template,
+        no_edit,
+        encrypt,
+        component,
+        fields,
+      } => new(
+        title,
+        description,
+        description_file,
+        template,
+        no_edit,
+        encrypt,
+        component,
+        &fields,
+      ),
+      Cmd::Migrate { dry_run } => migrate(dry_run),
+      Cmd::Fsck { fix } => fsck::fsck(fix),
+      Cmd::Verify { since, keys_from } => {
+        verify::verify(since, keys_from.as_deref())
+      }
+      Cmd::Trust { uuid, key } => verify::trust(uuid, key),
+      Cmd::List {
+        closed,
+        all,
+        status,
+        assignee,
+        include_snoozed,
+        snoozed,
+        comments_since,
+        unread,
+        tree,
+        format,
+        sort,
+        reverse,
+        fields,
+      } => list(
+        closed,
+        all,
+        status,
+        assignee,
+        include_snoozed,
+        snoozed,
+        comments_since,
+        unread,
+        tree.then(|| format.unwrap_or(TreeFormat::Human)),
+        sort,
+        reverse,
+        &fields,
+      ),
+      Cmd::Search {
+        query,
+        ignore_case,
+        regex,
+        fields,
+        context,
+      } => search::search(&query, ignore_case, regex, &fields, context),
+      Cmd::Grep {
+        pattern,
+        ignore_case,
+        no_heading,
+      } => grep::grep(&pattern, ignore_case, no_heading),
+      Cmd::Show {
+        id,
+        title,
+        comments_since,
+        unread,
+        format,
+      } => show(id, title, comments_since, unread, format),
+      Cmd::History { id } => history(&id),
+      Cmd::Activity { id } => activity::activity(&id),
+      Cmd::Scan { dry_run } => scan::scan(dry_run),
+      Cmd::Edit { id, title, no_edit } => edit(&id, title, no_edit),
+      Cmd::Open { id } => open(&id),
+      Cmd::Close { id, reason } => close(&id, reason),
+      Cmd::Reopen { id, reason } => reopen(&id, reason),
+      Cmd::Move { id, status, reason } => move_ticket(&id, &status, reason),
+      Cmd::Delete { id, force } => delete(&id, force),
+      Cmd::Comment { id, message } => comment(&id, message),
+      Cmd::Encrypt { id } => encrypt(&id),
+      Cmd::Decrypt { id } => decrypt(&id),
+      Cmd::Lock { id } => lock(&id, true),
+      Cmd::Unlock { id } => lock(&id, false),
+      Cmd::Assign { id, maintainer } => assign(&id, &maintainer),
+      Cmd::Redact {
+        id,
+        comment,
+        reason,
+      } => redact(&id, &comment, reason),
+      Cmd::React {
+        id,
+        comment,
+        emoji,
+        remove,
+      } => react(&id, &comment, &emoji, remove),
+      Cmd::Tag(t) => tag(t),
+      Cmd::Task(t) => task(t),
+      Cmd::Link {
+        id,
+        relation,
+        other,
+        remove,
+      } => link(&id, relation, &other, remove),
+      Cmd::Branch {
+        id,
+        name,
+        no_checkout,
+      } => branch::branch(&id, name, no_checkout),
+      Cmd::Attach { id, file } => attach(&id, &file),
+      Cmd::Gc { delete } => gc(delete),
+      Cmd::Archive { before } => archive(before),
+      Cmd::Report(r) => report::report(r),
+      Cmd::Remote(r) => remote::remote(r),
+      Cmd::Sprint(s) => sprint::sprint(s),
+      Cmd::Burndown {
+        name,
+        velocity,
+        format,
+      } => burndown::burndown(name, velocity, format),
+      Cmd::Badge { kind, path, tag } => badge::badge(kind, &path, tag),
+      Cmd::Export {
+        format,
+        status,
+        tag,
+        assignee,
+        output,
+      } => export::export(format, status, tag, assignee, output),
+      Cmd::Import(i) => import::import(i),
+      Cmd::MergeDriver { base, ours, theirs } => {
+        merge::merge_driver(&base, &ours, &theirs)
+      }
+      Cmd::Serve { port, api, token } => serve::serve(port, api, token),
+      Cmd::Snooze { id, until } => snooze(&id, until),
+      Cmd::Unsnooze { id } => unsnooze(&id),
+      Cmd::Watch { id } => inbox::watch(&id),
+      Cmd::Unwatch { id } => inbox::unwatch(&id),
+      Cmd::Inbox { peek } => inbox::inbox(peek),
+      Cmd::Due { id, date, clear } => due(&id, date.filter(|_| !clear)),
+      Cmd::Estimate {
+        id,
+        estimate: value,
+        clear,
+      } => estimate(&id, value.filter(|_| !clear)),
+      Cmd::Set {
+        id,
+        field,
+        value,
+        clear,
+      } => set_field(&id, &field, value.filter(|_| !clear)),
+      Cmd::Component {
+        id,
+        component: value,
+        clear,
+      } => component(&id, value.filter(|_| !clear)),
+      Cmd::Remind { days } => remind::remind(days),
+      Cmd::Stale { days, format } => stale(days, format),
+      Cmd::Dedupe {
+        threshold,
+        interactive,
+      } => dedupe::dedupe(threshold, interactive),
+      Cmd::Stats { days, format } => report::stats(days, format),
+      Cmd::ReleaseNotes { since, heading } => {
+        release_notes::release_notes(&since, heading)
+      }
+      Cmd::Digest { since, until } => digest::digest(&since, &until),
     } {
       error!("{}", e);
       std::process::exit(1);
     }
-  } else if let Err(e) = tui::run() {
+  } else if let Err(e) = tui::run(args.include_snoozed, args.sort, args.reverse)
+  {
     error!("{}", e);
     std::process::exit(1);
   }
 }
 
-fn init() -> Result<()> {
+fn init(welcome: bool, hooks: bool) -> Result<()> {
   debug!("Creating open ticket directory.");
   fs::create_dir_all(&open_tickets()?)?;
   debug!("Creating closed ticket directory");
   fs::create_dir_all(&closed_tickets()?)?;
+
+  // Repos that already have templates, e.g. from `ds config repo adopt`, keep
+  // just their own
+  if templates()?.is_empty() {
+    debug!("Creating the default templates.");
+    let dir = templates_dir()?;
+    fs::create_dir_all(&dir)?;
+    for (name, contents) in &starter::TEMPLATES {
+      fs::write(dir.join(name).with_extension("md"), contents)?;
+    }
+  }
+
+  debug!("Ignoring local only files.");
+  let gitignore = ticket_root()?.join(".gitignore");
+  let mut ignored = fs::read_to_string(&gitignore).unwrap_or_default();
+  let missing = starter::GITIGNORE
+    .lines()
+    .filter(|line| !ignored.lines().any(|l| l == *line))
+    .collect::<Vec<&str>>();
+  if !missing.is_empty() {
+    if !ignored.is_empty() && !ignored.ends_with('\n') {
+      ignored.push('\n');
+    }
+    for line in missing {
+      ignored.push_str(line);
+      ignored.push('\n');
+    }
+    fs::write(&gitignore, ignored)?;
+  }
+
+  debug!("Registering the merge driver for ticket files.");
+  if let Err(e) = merge::register() {
+    warn!("Unable to register the merge driver for tickets: {}", e);
+  }
+
+  if hooks {
+    debug!("Turning on hooks for this clone.");
+    hooks::enable()?;
+  }
+
+  if welcome {
+    debug!("Opening the welcome ticket.");
+    let ticket = new_ticket(
+      starter::WELCOME_TITLE.to_owned(),
+      starter::WELCOME.to_owned(),
+    )?;
+    save_ticket(&ticket)?;
+    ticket.log_created();
+    info!("Opened ticket {}: {}", ticket.short_id, ticket.title);
+  }
   trace!("Done initializing tickets.");
   info!("Initialized repo to use ticket");
   Ok(())
 }
 
-fn new() -> Result<()> {
-  debug!("Getting ticket root.");
-  let ticket_root = ticket_root()?;
-  trace!("Got ticket root: {}", ticket_root.display());
-  let description = ticket_root.join("description");
+#[allow(clippy::too_many_arguments)]
+fn new(
+  title: Option<String>,
+  description: Option<String>,
+  description_file: Option<PathBuf>,
+  template: Option<String>,
+  no_edit: bool,
+  encrypt: bool,
+  component: Option<String>,
+  fields: &[String],
+) -> Result<()> {
+  let interactive = title.is_none();
+  let title = match title {
+    Some(title) => {
+      if title.trim().is_empty() {
+        bail!("Title may not be empty");
+      }
+      title
+    }
+    None => {
+      let mut rl = Editor::<()>::new();
+      match rl.readline("Title: ") {
+        Ok(line) => {
+          if line.is_empty() {
+            bail!("Title may not be empty");
+          }
+          line
+        }
+        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+          debug!("Exiting due to Ctrl-C or Ctrl-D.");
+          process::exit(0);
+        }
+        Err(e) => return Err(e.into()),
+      }
+    }
+  };
+
+  let component = match component {
+    Some(component) => Some(resolve_component(&component)?),
+    None if interactive => ask_component()?,
+    None => None,
+  };
+  let fields = new_fields(fields, interactive)?;
 
+  let description = match description_file {
+    Some(ref path) if path == Path::new("-") => {
+      debug!("Reading the description from stdin.");
+      let mut description = String::new();
+      let _ = io::stdin().read_to_string(&mut description)?;
+      description
+    }
+    Some(path) => fs::read_to_string(&path)
+      .map_err(|e| format_err!("Unable to read {}: {}", path.display(), e))?,
+    None => match template {
+      Some(template) => get_template(&template)?,
+      None => description.unwrap_or_default(),
+    },
+  };
+  let description_contents = if no_edit {
+    description
+  } else {
+    edit_description(&description)?
+  };
+
+  let mut t = new_ticket(title, description_contents)?;
+  for (uuid, name) in default_assignees(component.as_deref())? {
+    t.record(format!("Assigned {}", name));
+    t.assignees.push((uuid, Name(name)));
+  }
+  t.component = component;
+  t.fields = fields;
+  if encrypt {
+    t.encrypt()?;
+  }
+  save_ticket(&t)?;
+  t.log_created();
+  hooks::notify(webhook::Event::Created, &t, None);
+  println!("Ticket Created: {}", t.short_id);
+  Ok(())
+}
+
+/// Asks which of the repo config's components a new ticket is filed under, if
+/// there are any
+fn ask_component() -> Result<Option<String>> {
+  let components = match get_repo_config() {
+    Ok(config) => config.components,
+    Err(e) => {
+      debug!("Unable to read the repo config: {}", e);
+      return Ok(None);
+    }
+  };
+  if components.is_empty() {
+    return Ok(None);
+  }
+  let prompt = format!(
+    "Component ({}, or blank for none): ",
+    components
+      .keys()
+      .map(String::as_str)
+      .collect::<Vec<&str>>()
+      .join(", ")
+  );
   let mut rl = Editor::<()>::new();
-  let title = match rl.readline("Title: ") {
-    Ok(line) => {
-      if line.is_empty() {
-        bail!("Title may not be empty");
+  loop {
+    match rl.readline(&prompt) {
+      Ok(line) if line.trim().is_empty() => return Ok(None),
+      Ok(line) => match resolve_component(&line) {
+        Ok(component) => return Ok(Some(component)),
+        Err(e) => error!("{}", e),
+      },
+      Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+        debug!("Exiting due to Ctrl-C or Ctrl-D.");
+        process::exit(0);
       }
-      line
+      Err(e) => return Err(e.into()),
     }
-    Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-      debug!("Exiting due to Ctrl-C or Ctrl-D.");
-      process::exit(0);
+  }
+}
+
+/// The custom fields of a new ticket from the `--field name=value` arguments.
+/// The rest are asked for if it's `interactive`, otherwise it's an error for
+/// any required ones to be left out.
+fn new_fields(
+  args: &[String],
+  interactive: bool,
+) -> Result<BTreeMap<String, FieldValue>> {
+  let config = match get_repo_config() {
+    Ok(config) => config,
+    Err(e) if args.is_empty() => {
+      debug!("Unable to read the repo config: {}", e);
+      return Ok(BTreeMap::new());
     }
-    Err(e) => return Err(e.into()),
+    Err(e) => return Err(e),
   };
+  let mut values = BTreeMap::new();
+  for (name, value) in args
+    .iter()
+    .map(|arg| field_arg(arg))
+    .collect::<Result<Vec<_>>>()?
+  {
+    let field = find_field(&config, &name)?;
+    let _ = values.insert(name, FieldValue::parse(field, &value)?);
+  }
+  for field in &config.fields {
+    if values.contains_key(&field.name) {
+      continue;
+    }
+    if interactive {
+      if let Some(value) = ask_field(field)? {
+        let _ = values.insert(field.name.clone(), value);
+      }
+    }
+  }
+  require_fields(&values)
+    .map_err(|e| format_err!("{}, set it with --field <NAME>=<VALUE>", e))?;
+  Ok(values)
+}
+
+/// Splits a `name=value` argument for a custom field
+fn field_arg(arg: &str) -> Result<(String, String)> {
+  match arg.split_once('=') {
+    Some((name, value)) => Ok((name.trim().to_owned(), value.to_owned())),
+    None => bail!("Expected a field like name=value, got '{}'", arg),
+  }
+}
+
+/// The custom field from the repo config with the name
+fn find_field<'c>(
+  config: &'c RepoConfig,
+  name: &str,
+) -> Result<&'c configamajig::Field> {
+  if config.fields.is_empty() {
+    bail!(
+      "There is no field '{}', the repo config doesn't have any [[fields]]",
+      name
+    );
+  }
+  config.field(name).ok_or_else(|| {
+    format_err!(
+      "There is no field '{}' in the repo config. Fields are {}",
+      name,
+      config
+        .fields
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
+    )
+  })
+}
+
+/// Asks for the value of a custom field until it's one that fits, or blank if
+/// the field isn't required
+fn ask_field(field: &configamajig::Field) -> Result<Option<FieldValue>> {
+  let prompt = format!(
+    "{} ({}{}): ",
+    field.name,
+    if field.values.is_empty() {
+      field.kind.to_string()
+    } else {
+      field.values.join("/")
+    },
+    if field.required { "" } else { ", optional" }
+  );
+  let mut rl = Editor::<()>::new();
+  loop {
+    match rl.readline(&prompt) {
+      Ok(line) if line.trim().is_empty() && field.required => {
+        error!("{} is required", field.name)
+      }
+      Ok(line) if line.trim().is_empty() => return Ok(None),
+      Ok(line) => match FieldValue::parse(field, &line) {
+        Ok(value) => return Ok(Some(value)),
+        Err(e) => error!("{}", e),
+      },
+      Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+        debug!("Exiting due to Ctrl-C or Ctrl-D.");
+        process::exit(0);
+      }
+      Err(e) => return Err(e.into()),
+    }
+  }
+}
+
+/// Opens up the user's editor with the given description and returns what they
+/// left in it
+fn edit_description(description: &str) -> Result<String> {
+  debug!("Getting ticket root.");
+  let ticket_root = ticket_root()?;
+  trace!("Got ticket root: {}", ticket_root.display());
+  let buffer = ticket_root.join("description");
 
   debug!("Opening up editor.");
   trace!(
     "Create buffer file for the description at {}.",
-    description.display()
+    buffer.display()
   );
-  let _ = fs::File::create(&description)?;
+  fs::write(&buffer, description)?;
   let _ = Command::new(&env::var("EDITOR").unwrap_or_else(|_| "vi".into()))
-    .arg(&description)
+    .arg(&buffer)
     .spawn()?
     .wait()?;
   trace!("Read the file into memory.");
-  let description_contents = fs::read_to_string(&description)?;
+  let contents = fs::read_to_string(&buffer)?;
   trace!("Removing the file.");
-  fs::remove_file(&description)?;
-
-  debug!("Creating ticket in memory.");
-  let t = Ticket {
-    title,
-    status: Status::Open,
-    id: uuid_v1()?,
-    assignees: Vec::new(),
-    description: description_contents,
-    comments: BTreeMap::new(),
-    version: Version::V1,
+  fs::remove_file(&buffer)?;
+  Ok(contents)
+}
+
+fn edit(id: &str, title: Option<String>, no_edit: bool) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+
+  if let Some(title) = title {
+    if title.trim().is_empty() {
+      bail!("Title may not be empty");
+    }
+    if title != ticket.title {
+      ticket.record(format!(
+        "Renamed the ticket from '{}' to '{}'",
+        ticket.title, title
+      ));
+      ticket.title = title;
+    }
+  }
+  if !no_edit {
+    let old = crypt::reveal(&ticket.description)?;
+    let description = edit_description(&old)?;
+    if description != old {
+      ticket.record("Edited the description");
+      ticket.set_description(description)?;
+    }
+  }
+
+  save_ticket(&ticket)
+}
+
+/// Opens the ticket's file in $EDITOR. If what's left in it isn't a valid
+/// ticket it can be edited again or put back the way it was. Otherwise it's
+/// saved again so it's named after its title and is in the directory for its
+/// status.
+fn open(id: &str) -> Result<()> {
+  let ticket = find_ticket(id)?;
+  let path = find_ticket_file(&ticket.id)?
+    .ok_or_else(|| format_err!("{} isn't saved in a file", ticket.short_id))?;
+  let original = fs::read_to_string(&path)?;
+  loop {
+    let _ = Command::new(env::var("EDITOR").unwrap_or_else(|_| "vi".into()))
+      .arg(&path)
+      .spawn()?
+      .wait()?;
+    let contents = fs::read_to_string(&path)?;
+    if contents == original {
+      info!("{} wasn't changed", ticket.short_id);
+      return Ok(());
+    }
+    match check_edit(&ticket, &contents) {
+      Ok(mut edited) => {
+        edited.record("Edited the ticket file");
+        save_ticket(&edited)?;
+        info!("Saved {}", edited.short_id);
+        return Ok(());
+      }
+      Err(e) => {
+        error!("{} isn't a valid ticket anymore: {}", path.display(), e);
+        let mut rl = Editor::<()>::new();
+        let again = match rl.readline("Edit it again? [Y/n] ") {
+          Ok(line) => matches!(line.trim().to_lowercase().as_str(), "" | "y"),
+          Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => false,
+          Err(e) => return Err(e.into()),
+        };
+        if !again {
+          write_atomic(&path, &original)?;
+          bail!("Put {} back the way it was", path.display());
+        }
+      }
+    }
+  }
+}
+
+/// The ticket in the file's contents after it was edited by hand, as long as
+/// it's still the same ticket and in a status from the repo config
+fn check_edit(ticket: &Ticket, contents: &str) -> Result<Ticket> {
+  let mut edited = format::parse(contents)?;
+  if edited.id != ticket.id {
+    bail!("The id can't be changed from {}", ticket.id);
+  }
+  if edited.title.trim().is_empty() {
+    bail!("Title may not be empty");
+  }
+  if !statuses()?.contains(&edited.status) {
+    bail!("{} isn't a status in the repo config", edited.status);
+  }
+  if edited.short_id.is_empty() {
+    edited.short_id = ticket.short_id.clone();
+  }
+  if edited.created_at.is_none() {
+    edited.created_at = ticket.created_at;
+  }
+  Ok(edited)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn list(
+  closed: bool,
+  all: bool,
+  status: Option<String>,
+  assignee: Option<String>,
+  include_snoozed: bool,
+  snoozed: bool,
+  comments_since: Option<NaiveDate>,
+  unread: bool,
+  tree: Option<TreeFormat>,
+  sort: Option<Sort>,
+  reverse: bool,
+  fields: &[String],
+) -> Result<()> {
+  let tickets = if all {
+    get_all_tickets()?
+  } else if closed {
+    get_closed_tickets()?
+  } else if let Some(status) = status {
+    get_tickets_with(&resolve_status(&status)?)?
+  } else {
+    get_all_tickets()?
+      .into_iter()
+      .filter(|t| t.status != Status::Closed)
+      .collect()
+  };
+  let assignee = resolve_assignee(assignee)?;
+  let state = UserState::load()?;
+  let fields = match fields {
+    [] => Vec::new(),
+    fields => {
+      let config = get_repo_config()?;
+      fields
+        .iter()
+        .map(|arg| {
+          let (name, value) = field_arg(arg)?;
+          let value = FieldValue::parse(find_field(&config, &name)?, &value)?;
+          Ok((name, value))
+        })
+        .collect::<Result<Vec<_>>>()?
+    }
   };
 
-  save_ticket(&t)?;
-  println!("Ticket Created: {}", t.id);
+  let mut listed = Vec::new();
+  for ticket in tickets {
+    let hidden = state.is_snoozed(ticket.id);
+    if (snoozed && !hidden) || (!snoozed && !include_snoozed && hidden) {
+      continue;
+    }
+    if let Some(cutoff) = cutoff(&ticket, comments_since, unread, &state) {
+      let created = uuid_time(&ticket.id);
+      let commented = ticket.comments.keys().map(|id| ticket.comment_time(id));
+      if !iter::once(created)
+        .chain(commented)
+        .any(|t| t > Some(cutoff))
+      {
+        continue;
+      }
+    }
+    if !assignee
+      .as_ref()
+      .is_none_or(|who| is_assigned(&ticket, who))
+    {
+      continue;
+    }
+    if !fields
+      .iter()
+      .all(|(name, value)| ticket.fields.get(name) == Some(value))
+    {
+      continue;
+    }
+    listed.push(ticket);
+  }
+  if let Some(sort) = sort {
+    sort_tickets(&mut listed, sort, reverse)?;
+  }
+
+  match tree {
+    Some(format) => tree::tree(&listed, format, all),
+    None => {
+      for ticket in &listed {
+        match state.snoozed.get(&ticket.id).filter(|_| snoozed) {
+          Some(until) => println!(
+            "{}{}",
+            list_line(ticket, all),
+            format!(" snoozed until {}", until).dimmed()
+          ),
+          None => println!("{}", list_line(ticket, all)),
+        }
+      }
+    }
+  }
   Ok(())
 }
 
-fn show(id: Uuid) -> Result<()> {
-  let mut found = false;
-  for ticket in get_all_tickets()? {
-    if ticket.id == id {
-      println!(
-        "{}\n{}{}\n{}{}\n\n{}\n{}",
-        format!("{} - {}\n", ticket.id, ticket.title).bold().red(),
-        "Status: ".bold().purple(),
-        match ticket.status {
-          Status::Open => "Open".bold().green(),
-          Status::Closed => "Closed".bold().red(),
-        },
-        "Assignees: ".bold().purple(),
-        if ticket.assignees.is_empty() {
-          "None".to_owned().blue()
-        } else {
-          let mut commas = ticket.assignees.len();
-          ticket
-            .assignees
-            .into_iter()
-            .fold(String::new(), |mut acc, (_, name)| {
-              acc.push_str(&name.0);
-              if commas > 1 {
-                acc.push_str(", ");
-                commas -= 1;
-              }
-              acc
-            })
-            .blue()
-        },
-        ticket.description,
-        ticket.comments.values().fold(
-          String::new(),
-          |mut acc, (_, name, comment)| {
-            acc.push_str(&format!("{}\n{}", name.0.cyan(), comment.0));
-            acc
-          }
-        )
-      );
-      found = true;
-      break;
+/// How a ticket is shown in `ticket list`
+fn list_line(ticket: &Ticket, all: bool) -> String {
+  format!(
+    "{} {}{}{}{}{}",
+    ticket.short_id.bold().red(),
+    if all || matches!(ticket.status, Status::Other(_)) {
+      format!("[{}] ", ticket.status).purple()
+    } else {
+      "".normal()
+    },
+    ticket.title,
+    if ticket.tasks.is_empty() {
+      "".normal()
+    } else {
+      format!(" [{}]", task_progress(ticket)).yellow()
+    },
+    if ticket.assignees.is_empty() {
+      "".normal()
+    } else {
+      format!(" ({})", assignee_names(ticket)).blue()
+    },
+    ticket
+      .last_activity()
+      .map_or_else(String::new, |at| format!(" updated {}", ago(at)))
+      .dimmed()
+  )
+}
+
+fn stale(days: i64, format: Format) -> Result<()> {
+  let cutoff = Utc::now() - chrono::Duration::days(days);
+  let mut tickets = get_all_tickets()?
+    .into_iter()
+    .filter(|t| t.status != Status::Closed)
+    .filter(|t| t.last_activity().is_none_or(|at| at < cutoff))
+    .collect::<Vec<Ticket>>();
+  tickets.sort_by_key(Ticket::last_activity);
+  match format {
+    Format::Json => println!("{}", serde_json::to_string_pretty(&tickets)?),
+    // TOML documents can't be a bare list so the tickets go under a key
+    Format::Toml => print!(
+      "{}",
+      toml::to_string_pretty(
+        &iter::once(("stale", tickets)).collect::<BTreeMap<_, _>>()
+      )?
+    ),
+    Format::Human => {
+      for ticket in &tickets {
+        println!("{}", list_line(ticket, false));
+      }
     }
   }
-  if found {
-    Ok(())
+  Ok(())
+}
+
+/// How long ago a time was in words, e.g. '3 days ago'
+fn ago(at: DateTime<Utc>) -> String {
+  let secs = (Utc::now() - at).num_seconds();
+  let (count, unit) = match secs {
+    i64::MIN..=59 => return "just now".to_owned(),
+    60..=3599 => (secs / 60, "minute"),
+    3600..=86_399 => (secs / 3600, "hour"),
+    86_400..=2_591_999 => (secs / 86_400, "day"),
+    2_592_000..=31_535_999 => (secs / 2_592_000, "month"),
+    _ => (secs / 31_536_000, "year"),
+  };
+  format!(
+    "{} {}{} ago",
+    count,
+    unit,
+    if count == 1 { "" } else { "s" }
+  )
+}
+
+/// Turns 'me' into the uuid from the user config so it can be matched against
+/// assignees
+fn resolve_assignee(assignee: Option<String>) -> Result<Option<String>> {
+  match assignee {
+    Some(ref who) if who == "me" => {
+      Ok(Some(get_user_config()?.uuid.to_string()))
+    }
+    other => Ok(other),
+  }
+}
+
+/// Whether the ticket is assigned to someone by their name or uuid
+fn is_assigned(ticket: &Ticket, who: &str) -> bool {
+  ticket
+    .assignees
+    .iter()
+    .any(|(id, name)| id.to_string() == who || name.0.eq_ignore_ascii_case(who))
+}
+
+/// How many of the ticket's tasks are done out of how many there are
+fn task_progress(ticket: &Ticket) -> String {
+  let done = ticket.tasks.iter().filter(|t| t.done).count();
+  format!("{}/{}", done, ticket.tasks.len())
+}
+
+/// Comma separated list of everyone assigned to the ticket
+fn assignee_names(ticket: &Ticket) -> String {
+  ticket
+    .assignees
+    .iter()
+    .map(|(_, name)| name.0.as_str())
+    .collect::<Vec<&str>>()
+    .join(", ")
+}
+
+/// The time after which comments on the ticket count as new, if only new ones
+/// should be shown
+fn cutoff(
+  ticket: &Ticket,
+  comments_since: Option<NaiveDate>,
+  unread: bool,
+  state: &UserState,
+) -> Option<DateTime<Utc>> {
+  if unread {
+    // Everything is new on a ticket that's never been looked at
+    Some(
+      state
+        .viewed
+        .get(&ticket.id)
+        .copied()
+        .unwrap_or(DateTime::<Utc>::MIN_UTC),
+    )
   } else {
-    bail!("No ticket with id {} exists.", id);
+    comments_since
+      .and_then(|date| {
+        Local
+          .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+          .earliest()
+      })
+      .map(|date| date.with_timezone(&Utc))
   }
 }
 
-fn close(id: Uuid) -> Result<()> {
-  let mut found = false;
-  for mut ticket in get_open_tickets()? {
-    if ticket.id == id {
-      let path = ticket_path(&ticket)?;
-      ticket.status = Status::Closed;
-      save_ticket(&ticket)?;
-      fs::remove_file(path)?;
-      found = true;
-      break;
+fn show(
+  id: Option<String>,
+  title: Option<String>,
+  comments_since: Option<NaiveDate>,
+  unread: bool,
+  format: Format,
+) -> Result<()> {
+  let mut ticket = match title {
+    Some(title) => find_ticket_by_title(&title)?,
+    None => find_ticket(id.as_deref().unwrap_or_default())?,
+  };
+  let mut state = UserState::load()?;
+  let cutoff = cutoff(&ticket, comments_since, unread, &state);
+  let old = ticket
+    .comments
+    .keys()
+    .filter(|id| cutoff.is_some_and(|c| ticket.comment_time(id) <= Some(c)))
+    .copied()
+    .collect::<Vec<Uuid>>();
+  for id in old {
+    let _ = ticket.comments.remove(&id);
+    let _ = ticket.comment_times.remove(&id);
+  }
+  if ticket.encrypted {
+    match ticket.revealed() {
+      Ok(revealed) => ticket = revealed,
+      Err(e) => {
+        let id = &ticket.short_id;
+        warn!("Unable to decrypt {}, so it's shown encrypted: {}", id, e)
+      }
     }
   }
+  match format {
+    Format::Human => print_ticket(&ticket)?,
+    Format::Json => println!("{}", serde_json::to_string_pretty(&ticket)?),
+    Format::Toml => print!("{}", toml::to_string_pretty(&ticket)?),
+  }
+  let _ = state.viewed.insert(ticket.id, Utc::now());
+  state.save()
+}
+
+/// Prints the ticket in color for the terminal
+fn print_ticket(ticket: &Ticket) -> Result<()> {
+  let tickets = get_all_tickets()?;
+  println!(
+    "{}\n{}{}{}\n{}{}\n{}{}\n{}{}{}{}{}{}{}{}{}{}\n{}\n{}{}",
+    format!("{} ({}) - {}\n", ticket.short_id, ticket.id, ticket.title)
+      .bold()
+      .red(),
+    "Status: ".bold().purple(),
+    match ticket.status {
+      Status::Open => "Open".bold().green(),
+      Status::Other(ref name) => name.bold().yellow(),
+      Status::Closed => "Closed".bold().red(),
+    },
+    if ticket.locked {
+      " (locked)".dimmed()
+    } else {
+      "".normal()
+    },
+    "Assignees: ".bold().purple(),
+    if ticket.assignees.is_empty() {
+      "None".to_owned().blue()
+    } else {
+      assignee_names(ticket).blue()
+    },
+    "Tags: ".bold().purple(),
+    if ticket.tags.is_empty() {
+      "None".normal()
+    } else {
+      ticket.tags.join(", ").yellow()
+    },
+    [
+      ("Created: ", ticket.created_at),
+      ("Updated: ", ticket.updated_at)
+    ]
+    .iter()
+    .filter_map(|(label, at)| Some((label, at.as_ref()?)))
+    .fold(String::new(), |mut acc, (label, at)| {
+      acc.push_str(&format!(
+        "{}{}\n",
+        label.bold().purple(),
+        at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+      ));
+      acc
+    }),
+    ticket.due.map_or_else(String::new, |due| format!(
+      "{}{}\n",
+      "Due: ".bold().purple(),
+      due
+    )),
+    ticket.estimate.map_or_else(String::new, |estimate| format!(
+      "{}{}\n",
+      "Estimate: ".bold().purple(),
+      estimate
+    )),
+    ticket
+      .component
+      .as_ref()
+      .map_or_else(String::new, |component| format!(
+        "{}{}\n",
+        "Component: ".bold().purple(),
+        component
+      )),
+    ticket
+      .fields
+      .iter()
+      .fold(String::new(), |mut acc, (name, value)| {
+        acc.push_str(&format!(
+          "{}{}\n",
+          format!("{}: ", name).bold().purple(),
+          value
+        ));
+        acc
+      }),
+    ticket.closed_at.map_or_else(String::new, |at| format!(
+      "{}{}{}\n",
+      "Closed: ".bold().purple(),
+      at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+      ticket
+        .closed_by
+        .as_ref()
+        .map_or_else(String::new, |(_, name)| format!(" by {}", name.0))
+    )),
+    if ticket.branches.is_empty() {
+      String::new()
+    } else {
+      format!(
+        "{}{}\n",
+        "Branches: ".bold().purple(),
+        ticket.branches.join(", ")
+      )
+    },
+    if ticket.locations.is_empty() {
+      String::new()
+    } else {
+      format!(
+        "{}{}\n",
+        "Found in: ".bold().purple(),
+        ticket.locations.join(", ")
+      )
+    },
+    Relation::ALL
+      .iter()
+      .flat_map(|r| ticket.related(*r).iter().map(move |id| (r, id)))
+      .fold(String::new(), |mut acc, (relation, id)| {
+        let other = tickets.iter().find(|t| t.id == *id);
+        acc.push_str(&format!(
+          "{}{}\n",
+          format!("{}: ", relation.title()).bold().purple(),
+          other.map_or_else(
+            || id.to_string(),
+            |t| format!("{} {}", t.short_id, t.title)
+          )
+        ));
+        acc
+      }),
+    ticket
+      .attachments
+      .iter()
+      .fold(String::new(), |mut acc, (hash, name)| {
+        acc.push_str(&format!(
+          "{}{} ({})\n",
+          "Attachment: ".bold().purple(),
+          name,
+          hash.get(..8).unwrap_or(hash)
+        ));
+        acc
+      }),
+    ticket.description,
+    if ticket.tasks.is_empty() {
+      String::new()
+    } else {
+      ticket.tasks.iter().enumerate().fold(
+        format!(
+          "\n{}\n",
+          format!("Tasks ({})", task_progress(ticket)).bold().purple()
+        ),
+        |mut acc, (i, task)| {
+          acc.push_str(&format!(
+            "{}. [{}] {}\n",
+            i + 1,
+            if task.done { "x" } else { " " },
+            task.text
+          ));
+          acc
+        },
+      ) + "\n"
+    },
+    ticket.comments_in_order().into_iter().fold(
+      String::new(),
+      |mut acc, (id, (_, name, comment))| {
+        acc.push_str(&format!(
+          "{} {}{}\n{}\n",
+          name.0.cyan(),
+          format!("({})", &id.to_string()[..8]).dimmed(),
+          ticket
+            .comment_time(id)
+            .map_or_else(String::new, |at| format!(
+              " {}",
+              at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            )),
+          comment.0.trim_end()
+        ));
+        let reactions = ticket.reaction_summary(id);
+        if !reactions.is_empty() {
+          acc.push_str(&format!("{}\n", reactions));
+        }
+        acc
+      }
+    )
+  );
+  Ok(())
+}
+
+fn history(id: &str) -> Result<()> {
+  let ticket = find_ticket(id)?;
+  if ticket.history.is_empty() {
+    info!("No changes to {} have been recorded.", id);
+  }
+  for event in &ticket.history {
+    println!(
+      "{} {} {}",
+      event
+        .at
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+        .dimmed(),
+      event
+        .by
+        .as_ref()
+        .map_or("Someone", |(_, name)| name.0.as_str())
+        .cyan(),
+      event.action
+    );
+  }
+  Ok(())
+}
+
+fn close(id: &str, reason: Option<String>) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  if let Status::Closed = ticket.status {
+    bail!("The ticket {} is already closed.", id);
+  }
+  set_status(&mut ticket, Status::Closed, reason)
+}
+
+fn reopen(id: &str, reason: Option<String>) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  if ticket.status != Status::Closed {
+    bail!("The ticket {} is not closed.", id);
+  }
+  set_status(&mut ticket, Status::Open, reason)
+}
 
-  if found {
+fn move_ticket(id: &str, status: &str, reason: Option<String>) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  let status = resolve_status(status)?;
+  if ticket.status == status {
+    bail!("The ticket {} is already {}.", id, status);
+  }
+  set_status(&mut ticket, status, reason)
+}
+
+/// Checks the move with the repo's workflow then moves the ticket into the
+/// directory for its new status. The reason is added as a comment.
+fn set_status(
+  ticket: &mut Ticket,
+  status: Status,
+  reason: Option<String>,
+) -> Result<()> {
+  let mut workflow = Workflow::load()?;
+  workflow.on_transition(|t| {
+    let open = t.ticket.tasks.iter().filter(|task| !task.done).count();
+    if *t.to == Status::Closed && open > 0 {
+      warn!("Closing the ticket with {} unfinished task(s)", open);
+    }
     Ok(())
+  });
+  workflow.check(&Transition {
+    ticket,
+    from: &ticket.status,
+    to: &status,
+    reason: reason.as_deref(),
+  })?;
+  let mut moved =
+    format!("Moved the ticket from {} to {}", ticket.status, status);
+  match &reason {
+    // Locked tickets can't be commented on so the reason goes with the move
+    Some(reason) if ticket.locked => {
+      moved = format!("{} because: {}", moved, reason);
+    }
+    Some(reason) => {
+      let user_config = get_user_config()?;
+      let _ = ticket.add_comment(
+        user_config.uuid,
+        user_config.name,
+        reason.clone(),
+      )?;
+    }
+    None => (),
+  }
+  if status == Status::Closed {
+    ticket.closed_by = get_user_config()
+      .ok()
+      .map(|config| (config.uuid, Name(config.name)));
+    ticket.closed_at = Some(Utc::now());
   } else {
-    bail!("No ticket with id {} exists.", id);
+    ticket.closed_by = None;
+    ticket.closed_at = None;
   }
+  ticket.record(moved);
+  ticket.status = status;
+  save_ticket(ticket)?;
+  if ticket.status == Status::Closed {
+    hooks::notify(webhook::Event::Closed, ticket, reason.as_deref());
+  }
+  Ok(())
 }
 
-/// Upgrade from V0 to V1 of the ticket
-fn migrate() -> Result<()> {
-  let tickets = get_all_ticketsv0()?;
+fn delete(id: &str, force: bool) -> Result<()> {
+  let ticket = find_ticket(id)?;
 
-  for t in tickets {
-    let ticket = Ticket {
-      title: t.title,
-      status: t.status,
-      id: uuid_v1()?,
-      assignees: Vec::new(),
-      description: t.description,
-      comments: BTreeMap::new(),
-      version: Version::V1,
-    };
-    let mut path = ticket_path(&ticket)?;
-    let _ = path.pop();
-    fs::remove_file(path.join(format!(
-      "{}-{}",
-      t.number,
-      ticket_file_name(&ticket)
-    )))?;
-    save_ticket(&ticket)?;
-    // We need to make sure we get different times for each ticket
-    // Possible future migrations might not have this issue
-    thread::sleep(time::Duration::from_millis(1000));
+  if !force {
+    let mut rl = Editor::<()>::new();
+    let prompt = format!("Delete ticket '{}'? [y/N] ", ticket.title);
+    match rl.readline(&prompt) {
+      Ok(line) if line.trim().eq_ignore_ascii_case("y") => (),
+      Ok(_) | Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+        info!("Not deleting the ticket.");
+        return Ok(());
+      }
+      Err(e) => return Err(e.into()),
+    }
+  }
+
+  if let Some(path) = find_ticket_file(&ticket.id)? {
+    fs::remove_file(path)?;
+  }
+  for mut other in get_all_tickets()? {
+    if unrelate_deleted(&mut other, &ticket) {
+      save_ticket(&other)?;
+    }
+  }
+  for mut other in get_archived_tickets()? {
+    if unrelate_deleted(&mut other, &ticket) {
+      save_archived_ticket(&other)?;
+    }
   }
+  log_event(
+    "ticket",
+    format!("{}: Deleted the ticket '{}'", ticket.short_id, ticket.title),
+  );
+  info!("Deleted ticket {}", id);
   Ok(())
 }
 
-fn comment(id: Uuid, message: String) -> Result<()> {
-  let mut ticket = get_all_tickets()?
-    .into_iter()
-    .find(|t| t.id == id)
-    .ok_or_else(|| {
-      format_err!("The uuid '{}' is not associated with any ticket")
-    })?;
+/// Takes the deleted ticket out of the other ticket's relations, returning
+/// whether it was in any
+fn unrelate_deleted(other: &mut Ticket, deleted: &Ticket) -> bool {
+  let relations = other.unrelate(&deleted.id);
+  for relation in &relations {
+    other.record(format!(
+      "No longer {} {} since it was deleted",
+      relation, deleted.short_id
+    ));
+  }
+  !relations.is_empty()
+}
+
+fn migrate(dry_run: bool) -> Result<()> {
+  let report = ticket_core::migrate::migrate(dry_run)?;
+  if report.upgrades.is_empty() {
+    info!("Every ticket is already {}.", latest());
+    return Ok(());
+  }
+  let root = find_root()?;
+  let relative =
+    |path: &Path| path.strip_prefix(&root).unwrap_or(path).to_owned();
+  for upgrade in &report.upgrades {
+    let verb = if dry_run { "Would upgrade" } else { "Upgraded" };
+    print!(
+      "{} {} from {} to {}",
+      verb,
+      relative(&upgrade.path).display(),
+      upgrade.from,
+      latest()
+    );
+    if upgrade.new_path != upgrade.path {
+      print!(", moving it to {}", relative(&upgrade.new_path).display());
+    }
+    println!();
+    for step in &upgrade.steps {
+      println!("  - {}", step);
+    }
+  }
+  if let Some(backup) = report.backup {
+    println!(
+      "The tickets from before they were upgraded are in {}",
+      relative(&backup).display()
+    );
+  }
+  Ok(())
+}
+
+fn comment(id: &str, message: String) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  let user_config = get_user_config()?;
+  let _ =
+    ticket.add_comment(user_config.uuid, user_config.name, message.clone())?;
+  save_ticket(&ticket)?;
+  hooks::notify(webhook::Event::Commented, &ticket, Some(&message));
+  Ok(())
+}
+
+/// Finds the comment on a ticket whose id starts with `comment`
+fn find_comment(ticket: &Ticket, id: &str, comment: &str) -> Result<Uuid> {
+  let found = ticket
+    .comments
+    .keys()
+    .filter(|c| c.to_string().starts_with(&comment.to_lowercase()))
+    .copied()
+    .collect::<Vec<Uuid>>();
+  match found.as_slice() {
+    [] => bail!("The ticket {} has no comment {}.", id, comment),
+    [comment_id] => Ok(*comment_id),
+    _ => bail!(
+      "More than one comment on {} starts with {}, use more of its id.",
+      id,
+      comment
+    ),
+  }
+}
+
+fn react(id: &str, comment: &str, emoji: &str, remove: bool) -> Result<()> {
+  let emoji = match emoji {
+    "+1" => "👍",
+    "-1" => "👎",
+    "tada" => "🎉",
+    "heart" => "❤️",
+    "eyes" => "👀",
+    "rocket" => "🚀",
+    emoji if emoji.is_ascii() || emoji.contains(char::is_whitespace) => bail!(
+      "'{}' is not an emoji, use one or +1, -1, tada, heart, eyes, or rocket",
+      emoji
+    ),
+    emoji => emoji,
+  };
+  let mut ticket = find_ticket(id)?;
+  let user_config = get_user_config()?;
+  let comment_id = find_comment(&ticket, id, comment)?;
+  let reactions = ticket
+    .reactions
+    .entry(comment_id)
+    .or_default()
+    .entry(user_config.uuid)
+    .or_default();
+  if remove {
+    let before = reactions.len();
+    reactions.retain(|r| r != emoji);
+    if reactions.len() == before {
+      bail!("You haven't reacted to {} with {}.", comment, emoji);
+    }
+  } else if !reactions.iter().any(|r| r == emoji) {
+    reactions.push(emoji.to_owned());
+  }
+  // Don't leave empty tables behind once everyone's taken theirs back
+  for by_user in ticket.reactions.values_mut() {
+    by_user.retain(|_, emojis| !emojis.is_empty());
+  }
+  ticket.reactions.retain(|_, by_user| !by_user.is_empty());
+  ticket.touch();
+  save_ticket(&ticket)
+}
+
+fn redact(id: &str, comment: &str, reason: String) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
   let user_config = get_user_config()?;
-  let _ = ticket.comments.insert(
-    uuid_v1()?,
-    (user_config.uuid, Name(user_config.name), Comment(message)),
+  let comment_id = find_comment(&ticket, id, comment)?;
+  if ticket.redactions.iter().any(|r| r.comment == comment_id) {
+    bail!("The comment {} was already redacted.", comment);
+  }
+
+  let redaction = Redaction {
+    comment: comment_id,
+    by: user_config.uuid,
+    name: user_config.name,
+    reason,
+    at: Utc::now(),
+  };
+  if let Some((_, _, contents)) = ticket.comments.get_mut(&comment_id) {
+    contents.0 = redaction.marker();
+  }
+  // The signature was of what the comment said before
+  let _ = ticket.signatures.remove(&comment_id);
+  ticket.record(redaction.action());
+  ticket.redactions.push(redaction);
+  save_ticket(&ticket)?;
+
+  info!("Redacted comment {} on {}.", comment, id);
+  warn!(
+    "The comment is still in the repo's git history. If it held a secret, \
+     revoke or rotate it, since anyone with a clone can still read it. Then \
+     rewrite the history that has it with a tool like git filter-repo or BFG \
+     Repo-Cleaner and force push, and ask everyone to reclone."
   );
+  Ok(())
+}
+
+fn assign(id: &str, maintainer: &[String]) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  let words = maintainer.iter().map(String::as_str).collect::<Vec<_>>();
+  let (uuid, name) = match words.as_slice() {
+    ["me"] | ["to", "me"] => {
+      let config = get_user_config()?;
+      (config.uuid, config.name)
+    }
+    ["to", "them", uuid, name] => (uuid.parse()?, (*name).to_owned()),
+    [maintainer] | ["to", maintainer] => resolve_maintainer(maintainer)?,
+    _ => bail!(
+      "Expected a maintainer's name or uuid, 'me', or 'to them <uuid> <name>' \
+       but got '{}'",
+      words.join(" ")
+    ),
+  };
+  if !ticket.assignees.iter().any(|(id, _)| *id == uuid) {
+    ticket.record(format!("Assigned {}", name));
+    ticket.assignees.push((uuid, Name(name)));
+  }
   save_ticket(&ticket)?;
   Ok(())
 }
 
-fn assign(id: Uuid, to: Placeholder) -> Result<()> {
-  let mut ticket = get_all_tickets()?
-    .into_iter()
-    .find(|t| t.id == id)
+fn tag(cmd: TagCmd) -> Result<()> {
+  let (id, tag) = match &cmd {
+    TagCmd::Add { id, tag } | TagCmd::Remove { id, tag } => (id, tag.trim()),
+  };
+  if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+    bail!(
+      "Tags may not be empty or contain spaces or commas, got '{}'",
+      tag
+    );
+  }
+  let mut ticket = find_ticket(id)?;
+  match cmd {
+    TagCmd::Add { .. } => {
+      if !ticket.tags.iter().any(|t| t == tag) {
+        ticket.record(format!("Added the tag '{}'", tag));
+        ticket.tags.push(tag.to_owned());
+        ticket.tags.sort();
+      }
+    }
+    TagCmd::Remove { .. } => {
+      let before = ticket.tags.len();
+      ticket.tags.retain(|t| t != tag);
+      if ticket.tags.len() == before {
+        bail!("The ticket {} is not tagged '{}'.", id, tag);
+      }
+      ticket.record(format!("Removed the tag '{}'", tag));
+    }
+  }
+  save_ticket(&ticket)?;
+  Ok(())
+}
+
+fn task(cmd: TaskCmd) -> Result<()> {
+  match cmd {
+    TaskCmd::Add { id, text } => {
+      if text.trim().is_empty() {
+        bail!("Tasks may not be empty");
+      }
+      let mut ticket = find_ticket(&id)?;
+      ticket.record(format!("Added task {}: {}", ticket.tasks.len() + 1, text));
+      ticket.tasks.push(Task { text, done: false });
+      save_ticket(&ticket)
+    }
+    TaskCmd::Check { id, number } => set_task(&id, number, true),
+    TaskCmd::Uncheck { id, number } => set_task(&id, number, false),
+  }
+}
+
+fn set_task(id: &str, number: usize, done: bool) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  let len = ticket.tasks.len();
+  let task = number
+    .checked_sub(1)
+    .and_then(|i| ticket.tasks.get_mut(i))
     .ok_or_else(|| {
-      format_err!("The uuid '{}' is not associated with any ticket")
+      format_err!(
+        "The ticket {} has no task {}, it has {} tasks.",
+        id,
+        number,
+        len
+      )
     })?;
-  match to {
-    Placeholder::To(who) => match who {
-      Who::Me => {
-        let config = get_user_config()?;
-        if !ticket
-          .assignees
-          .iter()
-          .any(|(id, name)| config.uuid == *id && config.name == name.0)
-        {
-          ticket.assignees.push((config.uuid, Name(config.name)));
-        }
-        save_ticket(&ticket)?;
+  task.done = done;
+  ticket.record(format!(
+    "{} task {}",
+    if done { "Checked" } else { "Unchecked" },
+    number
+  ));
+  save_ticket(&ticket)
+}
+
+fn link(id: &str, relation: Relation, other: &str, remove: bool) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  let mut other = find_ticket(other)?;
+  if ticket.id == other.id {
+    bail!("A ticket can't be related to itself.");
+  }
+
+  if remove {
+    if !ticket.related(relation).contains(&other.id) {
+      bail!("The ticket {} is not {} {}.", id, relation, other.short_id);
+    }
+    ticket.related_mut(relation).retain(|i| *i != other.id);
+    other
+      .related_mut(relation.inverse())
+      .retain(|i| *i != ticket.id);
+    ticket.record(format!("No longer {} {}", relation, other.short_id));
+    other.record(format!(
+      "No longer {} {}",
+      relation.inverse(),
+      ticket.short_id
+    ));
+  } else {
+    if ticket.related(relation).contains(&other.id) {
+      bail!(
+        "The ticket {} is already {} {}.",
+        id,
+        relation,
+        other.short_id
+      );
+    }
+    let blocking = match relation {
+      Relation::BlockedBy => Some((&ticket, &other)),
+      Relation::Blocks => Some((&other, &ticket)),
+      _ => None,
+    };
+    if let Some((blocked, blocker)) = blocking {
+      let tickets = get_all_tickets()?
+        .into_iter()
+        .map(|t| (t.id, t))
+        .collect::<BTreeMap<Uuid, Ticket>>();
+      if is_blocked_by(&tickets, blocker.id, blocked.id) {
+        bail!(
+          "{} is already blocked by {} so it can't block it too.",
+          blocker.short_id,
+          blocked.short_id
+        );
       }
-      Who::Them { id, name } => {
-        let assignee_does_not_exist =
-          !ticket.assignees.iter().any(|(id_inside, name_inside)| {
-            id == *id_inside && name == name_inside.0
-          });
-        if assignee_does_not_exist {
-          ticket.assignees.push((id, Name(name)));
-        }
-        save_ticket(&ticket)?;
+    }
+    ticket.related_mut(relation).push(other.id);
+    other.related_mut(relation.inverse()).push(ticket.id);
+    ticket.record(format!("Marked as {} {}", relation, other.short_id));
+    other.record(format!(
+      "Marked as {} {}",
+      relation.inverse(),
+      ticket.short_id
+    ));
+  }
+
+  // Both sides of the relation are stored so either ticket can show it
+  save_ticket(&ticket)?;
+  save_ticket(&other)?;
+  Ok(())
+}
+
+/// Whether `from` is blocked by `to` either directly or through other tickets
+fn is_blocked_by(
+  tickets: &BTreeMap<Uuid, Ticket>,
+  from: Uuid,
+  to: Uuid,
+) -> bool {
+  let mut seen = BTreeSet::new();
+  let mut stack = vec![from];
+  while let Some(id) = stack.pop() {
+    if id == to {
+      return true;
+    }
+    if seen.insert(id) {
+      if let Some(ticket) = tickets.get(&id) {
+        stack.extend(ticket.blocked_by.iter().copied());
       }
-    },
+    }
   }
+  false
+}
+
+fn attach(id: &str, file: &Path) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  let name = file
+    .file_name()
+    .ok_or_else(|| format_err!("{} is not a file", file.display()))?
+    .to_string_lossy()
+    .into_owned();
+  let hash = save_attachment(file)?;
+  ticket.record(format!("Attached {}", name));
+  let _ = ticket.attachments.insert(hash, name);
+  save_ticket(&ticket)?;
   Ok(())
 }
-#[derive(Serialize, Deserialize, Debug)]
-/// The fundamental type this tool revolves around. The ticket represents
-/// everything about an issue or future plan for the code base.
-pub struct Ticket {
-  title: String,
-  status: Status,
-  id: Uuid,
-  assignees: Vec<(Uuid, Name)>,
-  description: String,
-  version: Version,
-  #[serde(serialize_with = "toml::ser::tables_last")]
-  comments: BTreeMap<Uuid, (Uuid, Name, Comment)>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-/// Enum representing what version of the ticket it is and the assumptions that
-/// can be made about it
-pub enum Version {
-  /// The first version
-  V1,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-/// Newtype to represent a users Name
-pub struct Name(String);
-
-#[derive(Serialize, Deserialize, Debug)]
-/// Newtype to represent a Comment
-pub struct Comment(String);
-
-#[derive(Serialize, Deserialize, Debug)]
-/// Original version of the tickets on disk. This exists for historical reasons
-/// but is deprecated and likely to be removed.
-pub struct TicketV0 {
-  title: String,
-  status: Status,
-  number: usize,
-  assignee: Option<String>,
-  description: String,
+
+fn gc(delete: bool) -> Result<()> {
+  let dir = attachments()?;
+  if !dir.exists() {
+    return Ok(());
+  }
+  let referenced = get_all_tickets()?
+    .into_iter()
+    .chain(get_archived_tickets()?)
+    .flat_map(|t| t.attachments.into_keys())
+    .collect::<BTreeSet<String>>();
+  for entry in fs::read_dir(&dir)? {
+    let path = entry?.path();
+    let hash = path.file_name().unwrap_or_default().to_string_lossy();
+    if path.is_file() && !referenced.contains(hash.as_ref()) {
+      if delete {
+        fs::remove_file(&path)?;
+        println!("Deleted {}", hash);
+      } else {
+        println!("Unreferenced {}", hash);
+      }
+    }
+  }
+  Ok(())
+}
+
+fn archive(before: NaiveDate) -> Result<()> {
+  let dir = closed_tickets()?;
+  if !dir.exists() {
+    return Ok(());
+  }
+  let archive = archive_dir()?;
+  let mut archived = 0;
+  for entry in fs::read_dir(&dir)? {
+    let path = entry?.path();
+    if !path.is_file() || format::FileFormat::of(&path).is_none() {
+      continue;
+    }
+    let ticket = read_ticket(&path)?;
+    // Tickets closed before closing times were recorded go by the last thing
+    // that happened to them
+    let closed = ticket.closed_at.or_else(|| ticket.last_activity());
+    if closed.is_none_or(|at| at.with_timezone(&Local).date_naive() >= before) {
+      continue;
+    }
+    let to = archive.join(path.file_name().unwrap_or_default());
+    if to.exists() {
+      warn!(
+        "Not archiving {} since {} already exists",
+        ticket.short_id,
+        to.display()
+      );
+      continue;
+    }
+    move_ticket_file(&path, &to)?;
+    archived += 1;
+  }
+  info!("Archived {} tickets", archived);
+  Ok(())
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-/// What is the current state of a ticket
-pub enum Status {
-  /// The ticket has been opened but the issue has not been resolved
-  Open,
-  /// The ticket has a corresponding fix and has been closed
-  Closed,
+fn encrypt(id: &str) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  ticket.encrypt()?;
+  ticket.record("Encrypted the ticket");
+  save_ticket(&ticket)?;
+  info!("Encrypted {}", ticket.short_id);
+  Ok(())
+}
+
+fn decrypt(id: &str) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  ticket.decrypt()?;
+  ticket.record("Decrypted the ticket");
+  save_ticket(&ticket)?;
+  info!("Decrypted {}", ticket.short_id);
+  Ok(())
+}
+
+/// Locks or unlocks the ticket, which only maintainers in the repo config can
+/// do
+fn lock(id: &str, locked: bool) -> Result<()> {
+  let me = get_user_config()?.uuid;
+  if !get_repo_config()?
+    .maintainers
+    .iter()
+    .any(|(_, uuid)| *uuid == me)
+  {
+    bail!("Only maintainers in the repo config can lock and unlock tickets");
+  }
+  let mut ticket = find_ticket(id)?;
+  if ticket.locked == locked {
+    info!(
+      "{} is already {}",
+      ticket.short_id,
+      if locked { "locked" } else { "unlocked" }
+    );
+    return Ok(());
+  }
+  ticket.locked = locked;
+  ticket.record(if locked {
+    "Locked the ticket"
+  } else {
+    "Unlocked the ticket"
+  });
+  save_ticket(&ticket)?;
+  if locked {
+    info!("Locked {}", ticket.short_id);
+  } else {
+    info!("Unlocked {}", ticket.short_id);
+  }
+  Ok(())
+}
+
+fn snooze(id: &str, until: NaiveDate) -> Result<()> {
+  let ticket = find_ticket(id)?;
+  let mut state = UserState::load()?;
+  let _ = state.snoozed.insert(ticket.id, until);
+  // Drop snoozes that have run out so the file doesn't grow forever
+  let expired = state
+    .snoozed
+    .keys()
+    .filter(|id| !state.is_snoozed(**id))
+    .copied()
+    .collect::<Vec<Uuid>>();
+  for id in expired {
+    let _ = state.snoozed.remove(&id);
+  }
+  state.save()?;
+  info!("Snoozed {} until {}", id, until);
+  Ok(())
+}
+
+fn due(id: &str, date: Option<NaiveDate>) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  ticket.record(match date {
+    Some(date) => format!("Set the due date to {}", date),
+    None => "Removed the due date".to_owned(),
+  });
+  ticket.due = date;
+  save_ticket(&ticket)?;
+  match date {
+    Some(date) => info!("{} is due on {}", id, date),
+    None => info!("{} no longer has a due date", id),
+  }
+  Ok(())
+}
+
+fn estimate(id: &str, estimate: Option<Estimate>) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  ticket.record(match estimate {
+    Some(estimate) => format!("Set the estimate to {}", estimate),
+    None => "Removed the estimate".to_owned(),
+  });
+  ticket.estimate = estimate;
+  save_ticket(&ticket)?;
+  match estimate {
+    Some(estimate) => info!("{} is estimated at {}", id, estimate),
+    None => info!("{} no longer has an estimate", id),
+  }
+  Ok(())
+}
+
+fn set_field(id: &str, name: &str, value: Option<String>) -> Result<()> {
+  let config = get_repo_config()?;
+  let field = find_field(&config, name)?;
+  let mut ticket = find_ticket(id)?;
+  match value {
+    Some(value) => {
+      let value = FieldValue::parse(field, &value)?;
+      ticket.record(format!("Set {} to {}", field.name, value));
+      info!("Set {} of {} to {}", field.name, id, value);
+      let _ = ticket.fields.insert(field.name.clone(), value);
+    }
+    None if field.required => {
+      bail!("{} is required so it can't be cleared", field.name)
+    }
+    None => {
+      if ticket.fields.remove(&field.name).is_none() {
+        bail!("{} doesn't have {} set", ticket.short_id, field.name);
+      }
+      ticket.record(format!("Cleared {}", field.name));
+      info!("Cleared {} of {}", field.name, id);
+    }
+  }
+  save_ticket(&ticket)
+}
+
+fn component(id: &str, component: Option<String>) -> Result<()> {
+  let mut ticket = find_ticket(id)?;
+  let component = component
+    .map(|component| resolve_component(&component))
+    .transpose()?;
+  ticket.record(match &component {
+    Some(component) => format!("Filed the ticket under {}", component),
+    None => "Took the ticket out of its component".to_owned(),
+  });
+  match &component {
+    Some(component) => info!("Filed {} under {}", id, component),
+    None => info!("{} is no longer in a component", id),
+  }
+  ticket.component = component;
+  save_ticket(&ticket)
+}
+
+fn unsnooze(id: &str) -> Result<()> {
+  let ticket = find_ticket(id)?;
+  let mut state = UserState::load()?;
+  if state.snoozed.remove(&ticket.id).is_none() {
+    bail!("The ticket {} is not snoozed.", id);
+  }
+  state.save()
+}
+
+/// What order `ticket list` and the tui show tickets in
+#[derive(Debug, Clone, Copy)]
+pub enum Sort {
+  /// The most recently active first
+  Activity,
+  /// The newest first
+  Created,
+  /// The most recently changed first
+  Updated,
+  /// By title, ignoring case
+  Title,
+  /// By the `priority` field from the repo config, in the order of its values
+  /// for an enum or the lowest number first
+  Priority,
+  /// By the name of the first assignee
+  Assignee,
+}
+
+impl FromStr for Sort {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "activity" => Ok(Sort::Activity),
+      "created" => Ok(Sort::Created),
+      "updated" => Ok(Sort::Updated),
+      "title" => Ok(Sort::Title),
+      "priority" => Ok(Sort::Priority),
+      "assignee" => Ok(Sort::Assignee),
+      _ => bail!(
+        "'{}' is not a sort order, expected 'activity', 'created', \
+         'updated', 'title', 'priority', or 'assignee'",
+        s
+      ),
+    }
+  }
+}
+
+/// Puts the tickets in the order, or the opposite one if it's `reverse`.
+/// Tickets without a priority or an assignee go after the ones with one.
+fn sort_tickets(
+  tickets: &mut [Ticket],
+  sort: Sort,
+  reverse: bool,
+) -> Result<()> {
+  match sort {
+    Sort::Activity => tickets.sort_by_key(|t| cmp::Reverse(t.last_activity())),
+    Sort::Created => tickets.sort_by_key(|t| cmp::Reverse(t.created_at)),
+    Sort::Updated => tickets.sort_by_key(|t| cmp::Reverse(t.updated_at)),
+    Sort::Title => tickets.sort_by_cached_key(|t| t.title.to_lowercase()),
+    Sort::Priority => {
+      let config = get_repo_config()?;
+      let field = find_field(&config, "priority")?;
+      let rank = |t: &Ticket| match t.fields.get(&field.name) {
+        Some(FieldValue::Number(number)) => Some(*number),
+        Some(FieldValue::Text(text)) => field
+          .values
+          .iter()
+          .position(|value| value == text)
+          .map(|i| i as f64),
+        None => None,
+      };
+      tickets.sort_by(|a, b| match (rank(a), rank(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+      });
+    }
+    Sort::Assignee => tickets.sort_by_cached_key(|t| {
+      t.assignees
+        .first()
+        .map(|(_, name)| name.0.to_lowercase())
+        .map_or((1, String::new()), |name| (0, name))
+    }),
+  }
+  if reverse {
+    tickets.reverse();
+  }
+  Ok(())
+}
+
+/// How commands that can be used by other tools should print their output
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+  /// Colored output meant to be read in a terminal
+  Human,
+  /// JSON for other programs to consume
+  Json,
+  /// TOML for other programs to consume
+  Toml,
+}
+
+impl FromStr for Format {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "human" => Ok(Format::Human),
+      "json" => Ok(Format::Json),
+      "toml" => Ok(Format::Toml),
+      _ => bail!(
+        "'{}' is not a format, expected 'human', 'json', or 'toml'",
+        s
+      ),
+    }
+  }
 }
@@ -0,0 +1,413 @@
+//! A git merge driver for ticket files so that branches changing the same
+//! ticket, e.g. both commenting on it, merge cleanly instead of conflicting on
+//! the toml. `ticket init` registers it for the files in .dev-suite/ticket.
+use crate::{
+  actions::statuses,
+  format::{
+    self,
+    FileFormat,
+  },
+  Status,
+  Task,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use git2::{
+  ConfigLevel,
+  Repository,
+};
+use log::*;
+use shared::{
+  find_root,
+  write_atomic,
+};
+use std::{
+  collections::BTreeSet,
+  fs,
+  mem,
+  path::Path,
+};
+
+/// The lines in .gitattributes that have git use the driver for ticket files
+const ATTRIBUTES: [&str; 2] = [
+  ".dev-suite/ticket/**/*.toml merge=ticket",
+  ".dev-suite/ticket/**/*.md merge=ticket",
+];
+
+/// Has git use `ticket merge-driver` for ticket files by adding it to the
+/// repo's .gitattributes and .git/config. The config isn't committed so each
+/// clone needs to run `ticket init` to use it.
+pub fn register() -> Result<()> {
+  let root = find_root()?;
+  let attributes = root.join(".gitattributes");
+  let mut contents = fs::read_to_string(&attributes).unwrap_or_default();
+  let missing = ATTRIBUTES
+    .iter()
+    .filter(|attribute| {
+      !contents.lines().any(|line| line.trim() == **attribute)
+    })
+    .collect::<Vec<_>>();
+  if !missing.is_empty() {
+    if !contents.is_empty() && !contents.ends_with('\n') {
+      contents.push('\n');
+    }
+    for attribute in missing {
+      contents.push_str(attribute);
+      contents.push('\n');
+    }
+    fs::write(&attributes, contents)?;
+  }
+  let mut config = Repository::discover(&root)?
+    .config()?
+    .open_level(ConfigLevel::Local)?;
+  config.set_str("merge.ticket.name", "ticket's merge of ticket files")?;
+  config.set_str("merge.ticket.driver", "ticket merge-driver %O %A %B")?;
+  Ok(())
+}
+
+/// Merges `theirs` into `ours`, where `base` is the version both came from,
+/// and writes the result to `ours` as git expects, in the format `ours` is in.
+/// Files that aren't tickets are merged line by line. Anything that can't be merged is left with
+/// conflict markers and the driver fails so git reports the conflict.
+pub fn merge_driver(base: &Path, ours: &Path, theirs: &Path) -> Result<()> {
+  let parse = |path: &Path| -> Option<Ticket> {
+    format::parse(&fs::read_to_string(path).ok()?).ok()
+  };
+  let (merged, conflicted) = match (parse(ours), parse(theirs)) {
+    (Some(o), Some(t)) => {
+      let (ticket, conflicted) = merge(parse(base), o, t);
+      let format = FileFormat::detect(&fs::read_to_string(ours)?);
+      (format.write(&ticket)?, conflicted)
+    }
+    _ => {
+      debug!("Not a ticket, merging {} line by line", ours.display());
+      let base = fs::read_to_string(base).unwrap_or_default();
+      match diffy::merge(
+        &base,
+        &fs::read_to_string(ours)?,
+        &fs::read_to_string(theirs)?,
+      ) {
+        Ok(merged) => (merged, false),
+        Err(merged) => (merged, true),
+      }
+    }
+  };
+  write_atomic(ours, merged)?;
+  if conflicted {
+    bail!(
+      "Both sides changed the same lines of {}, which are marked in the file",
+      ours.display()
+    );
+  }
+  Ok(())
+}
+
+/// Merges two versions of a ticket. Lists like the tags or assignees keep
+/// what either side added and drop what either side removed, comments and
+/// history from both are kept, and for fields both sides changed differently
+/// the status furthest along and otherwise the side changed last wins. The
+/// description is merged line by line and is the only thing that can
+/// conflict, which the returned bool says.
+fn merge(
+  base: Option<Ticket>,
+  mut ours: Ticket,
+  theirs: Ticket,
+) -> (Ticket, bool) {
+  let base = base.as_ref();
+  let theirs_newer = theirs.updated_at > ours.updated_at;
+  let Ticket {
+    title,
+    status,
+    assignees,
+    watchers,
+    description,
+    updated_at,
+    tags,
+    due,
+    estimate,
+    component,
+    locked,
+    closed_by,
+    closed_at,
+    blocked_by,
+    blocks,
+    related_to,
+    duplicate_of,
+    duplicated_by,
+    branches,
+    locations,
+    tasks,
+    redactions,
+    history,
+    fields,
+    attachments,
+    comment_times,
+    reactions,
+    signatures,
+    comments,
+    ..
+  } = theirs;
+
+  ours.title = resolve(
+    base.map(|b| &b.title),
+    mem::take(&mut ours.title),
+    title,
+    theirs_newer,
+  );
+  ours.due = resolve(base.map(|b| &b.due), ours.due, due, theirs_newer);
+  ours.estimate = resolve(
+    base.map(|b| &b.estimate),
+    ours.estimate,
+    estimate,
+    theirs_newer,
+  );
+  ours.component = resolve(
+    base.map(|b| &b.component),
+    mem::take(&mut ours.component),
+    component,
+    theirs_newer,
+  );
+  ours.locked =
+    resolve(base.map(|b| &b.locked), ours.locked, locked, theirs_newer);
+  let names = ours
+    .fields
+    .keys()
+    .chain(fields.keys())
+    .cloned()
+    .collect::<BTreeSet<String>>();
+  for name in names {
+    let base = base.map(|b| b.fields.get(&name).cloned());
+    let theirs = fields.get(&name).cloned();
+    let mine = ours.fields.remove(&name);
+    if let Some(value) = resolve(base.as_ref(), mine, theirs, theirs_newer) {
+      let _ = ours.fields.insert(name, value);
+    }
+  }
+  let order = statuses().unwrap_or_default();
+  let rank = |s: &Status| order.iter().position(|o| o == s);
+  let merged = resolve(
+    base.map(|b| &b.status),
+    ours.status.clone(),
+    status.clone(),
+    rank(&status) > rank(&ours.status),
+  );
+  if merged != ours.status {
+    ours.closed_by = closed_by;
+    ours.closed_at = closed_at;
+  }
+  ours.status = merged;
+
+  let mut conflicted = false;
+  ours.description = match base.map(|b| b.description.as_str()) {
+    _ if ours.description == description => ours.description,
+    Some(base) if base == description => ours.description,
+    Some(base) if base == ours.description => description,
+    base => {
+      let base = base.unwrap_or_default();
+      diffy::merge(base, &ours.description, &description).unwrap_or_else(
+        |marked| {
+          conflicted = true;
+          marked
+        },
+      )
+    }
+  };
+
+  ours.tasks = merge_tasks(
+    base.map(|b| b.tasks.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.tasks),
+    tasks,
+  );
+  ours.assignees = merge_list(
+    base.map(|b| b.assignees.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.assignees),
+    assignees,
+    |(uuid, _)| *uuid,
+  );
+  ours.watchers = merge_list(
+    base.map(|b| b.watchers.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.watchers),
+    watchers,
+    Clone::clone,
+  );
+  ours.tags = merge_list(
+    base.map(|b| b.tags.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.tags),
+    tags,
+    Clone::clone,
+  );
+  ours.blocked_by = merge_list(
+    base.map(|b| b.blocked_by.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.blocked_by),
+    blocked_by,
+    Clone::clone,
+  );
+  ours.blocks = merge_list(
+    base.map(|b| b.blocks.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.blocks),
+    blocks,
+    Clone::clone,
+  );
+  ours.related_to = merge_list(
+    base.map(|b| b.related_to.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.related_to),
+    related_to,
+    Clone::clone,
+  );
+  ours.duplicate_of = merge_list(
+    base.map(|b| b.duplicate_of.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.duplicate_of),
+    duplicate_of,
+    Clone::clone,
+  );
+  ours.duplicated_by = merge_list(
+    base.map(|b| b.duplicated_by.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.duplicated_by),
+    duplicated_by,
+    Clone::clone,
+  );
+  ours.branches = merge_list(
+    base.map(|b| b.branches.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.branches),
+    branches,
+    Clone::clone,
+  );
+  ours.locations = merge_list(
+    base.map(|b| b.locations.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.locations),
+    locations,
+    Clone::clone,
+  );
+  ours.redactions = merge_list(
+    base.map(|b| b.redactions.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.redactions),
+    redactions,
+    |r| (r.comment, r.at),
+  );
+  ours.history = merge_list(
+    base.map(|b| b.history.as_slice()).unwrap_or_default(),
+    mem::take(&mut ours.history),
+    history,
+    |e| (e.at, e.action.clone()),
+  );
+  ours.history.sort_by_key(|e| e.at);
+
+  for (id, comment) in comments {
+    match ours.comments.get(&id) {
+      None => {
+        let _ = ours.comments.insert(id, comment);
+      }
+      // Comments only change when they're redacted
+      Some(mine) if (mine.2).0 != (comment.2).0 => {
+        let unchanged = base
+          .and_then(|b| b.comments.get(&id))
+          .is_some_and(|b| (b.2).0 == (mine.2).0);
+        if unchanged {
+          let _ = ours.comments.insert(id, comment);
+        }
+      }
+      Some(_) => {}
+    }
+  }
+  for (id, at) in comment_times {
+    let _ = ours.comment_times.entry(id).or_insert(at);
+  }
+  for (id, signature) in signatures {
+    let _ = ours.signatures.entry(id).or_insert(signature);
+  }
+  // A redacted comment's signature no longer matches it
+  let redactions = &ours.redactions;
+  ours
+    .signatures
+    .retain(|id, _| !redactions.iter().any(|r| r.comment == *id));
+  for (hash, name) in attachments {
+    let _ = ours.attachments.entry(hash).or_insert(name);
+  }
+  for (comment, by_user) in reactions {
+    for (user, emojis) in by_user {
+      let base = base
+        .and_then(|b| b.reactions.get(&comment))
+        .and_then(|by_user| by_user.get(&user))
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+      let mine = ours
+        .reactions
+        .entry(comment)
+        .or_default()
+        .entry(user)
+        .or_default();
+      *mine = merge_list(base, mem::take(mine), emojis, Clone::clone);
+    }
+  }
+  ours.reactions.retain(|_, by_user| {
+    by_user.retain(|_, emojis| !emojis.is_empty());
+    !by_user.is_empty()
+  });
+  ours.updated_at = ours.updated_at.max(updated_at);
+  (ours, conflicted)
+}
+
+/// Picks the value of a field that's the same on both sides or was only
+/// changed on one. If both changed it, `theirs` wins if `prefer_theirs`.
+fn resolve<T: PartialEq>(
+  base: Option<&T>,
+  ours: T,
+  theirs: T,
+  prefer_theirs: bool,
+) -> T {
+  if ours == theirs || base == Some(&theirs) {
+    ours
+  } else if base == Some(&ours) || prefer_theirs {
+    theirs
+  } else {
+    ours
+  }
+}
+
+/// Merges a list as a set of `key`s, keeping anything either side added and
+/// dropping anything either side removed. Items on both sides are kept as
+/// they are in `ours`.
+fn merge_list<T, K: PartialEq>(
+  base: &[T],
+  ours: Vec<T>,
+  theirs: Vec<T>,
+  key: impl Fn(&T) -> K,
+) -> Vec<T> {
+  let in_base = |k: &K| base.iter().any(|b| key(b) == *k);
+  let theirs_keys = theirs.iter().map(&key).collect::<Vec<_>>();
+  let mut merged = ours
+    .into_iter()
+    .filter(|item| {
+      let k = key(item);
+      theirs_keys.contains(&k) || !in_base(&k)
+    })
+    .collect::<Vec<_>>();
+  for item in theirs {
+    let k = key(&item);
+    if !in_base(&k) && !merged.iter().any(|m| key(m) == k) {
+      merged.push(item);
+    }
+  }
+  merged
+}
+
+/// Merges the checklists by their text, where a task checked or unchecked on
+/// one side keeps that change
+fn merge_tasks(base: &[Task], ours: Vec<Task>, theirs: Vec<Task>) -> Vec<Task> {
+  let theirs_done = theirs
+    .iter()
+    .map(|t| (t.text.clone(), t.done))
+    .collect::<Vec<_>>();
+  let mut merged = merge_list(base, ours, theirs, |t| t.text.clone());
+  for task in &mut merged {
+    let base = base.iter().find(|b| b.text == task.text).map(|b| b.done);
+    let theirs = theirs_done.iter().find(|(text, _)| *text == task.text);
+    if let Some((_, done)) = theirs {
+      task.done = resolve(base.as_ref(), task.done, *done, false);
+    }
+  }
+  merged
+}
@@ -0,0 +1,116 @@
+//! Turns the tickets closed since a release into a changelog section
+use crate::{
+  actions::get_tickets_with,
+  Status,
+  Ticket,
+};
+use anyhow::{
+  format_err,
+  Result,
+};
+use chrono::prelude::*;
+use git2::Repository;
+use shared::find_root;
+use std::collections::BTreeMap;
+
+/// Tags that go under a shared heading, checked in order so a ticket tagged
+/// both `bug` and `docs` is listed as a bug fix
+const GROUPS: [(&str, &str); 6] = [
+  ("feature", "Added Features"),
+  ("enhancement", "Added Features"),
+  ("bug", "Bug Fixes"),
+  ("fix", "Bug Fixes"),
+  ("fixme", "Bug Fixes"),
+  ("docs", "Documentation"),
+];
+
+/// Prints Markdown for the tickets closed after `since`, a date like
+/// 2024-07-01 or a git tag or other revision, grouped by their tags
+pub fn release_notes(since: &str, heading: Option<String>) -> Result<()> {
+  let since = since_time(since)?;
+  let mut tickets = get_tickets_with(&Status::Closed)?
+    .into_iter()
+    .filter(|t| t.closed_at.is_some_and(|at| at > since))
+    .collect::<Vec<Ticket>>();
+  tickets.sort_by_key(|t| t.closed_at);
+
+  let mut groups = BTreeMap::<String, Vec<&Ticket>>::new();
+  for ticket in &tickets {
+    groups.entry(group(ticket)).or_default().push(ticket);
+  }
+
+  if let Some(heading) = heading {
+    println!("# {}\n", heading);
+  }
+  if tickets.is_empty() {
+    println!("No tickets were closed in this release.\n");
+    return Ok(());
+  }
+  // Features and fixes are what most people read release notes for so they
+  // go first and anything untagged goes last
+  let mut names = groups.keys().cloned().collect::<Vec<String>>();
+  names.sort_by_key(|name| {
+    (
+      match name.as_str() {
+        "Added Features" => 0,
+        "Bug Fixes" => 1,
+        "Other Changes" => 3,
+        _ => 2,
+      },
+      name.clone(),
+    )
+  });
+  for name in names {
+    println!("## {}\n", name);
+    for ticket in &groups[&name] {
+      println!("- {} ({})", ticket.title, ticket.short_id);
+    }
+    println!();
+  }
+  Ok(())
+}
+
+/// Which heading a ticket goes under
+fn group(ticket: &Ticket) -> String {
+  for (tag, heading) in &GROUPS {
+    if ticket.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+      return (*heading).to_owned();
+    }
+  }
+  match ticket.tags.first() {
+    Some(tag) => {
+      let mut chars = tag.chars();
+      chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+    }
+    None => "Other Changes".to_owned(),
+  }
+}
+
+/// When a date starts in local time, or when the commit a revision points to
+/// was made
+fn since_time(since: &str) -> Result<DateTime<Utc>> {
+  if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+    return date
+      .and_hms_opt(0, 0, 0)
+      .and_then(|date| Local.from_local_datetime(&date).earliest())
+      .map(|date| date.with_timezone(&Utc))
+      .ok_or_else(|| format_err!("{} isn't a valid date", since));
+  }
+  let repo = Repository::open(find_root()?)?;
+  let commit = repo
+    .revparse_single(since)
+    .and_then(|object| object.peel_to_commit())
+    .map_err(|_| {
+      format_err!(
+        "'{}' isn't a date like 2024-07-01 or a tag or commit in the repo",
+        since
+      )
+    })?;
+  Utc
+    .timestamp_opt(commit.time().seconds(), 0)
+    .single()
+    .ok_or_else(|| format_err!("The commit for {} has an invalid time", since))
+}
@@ -0,0 +1,95 @@
+use crate::{
+  actions::get_all_tickets,
+  state::UserState,
+  Status,
+  Ticket,
+};
+use anyhow::Result;
+use chrono::prelude::*;
+use colored::*;
+use configamajig::get_user_config;
+use uuid::Uuid;
+
+/// Prints the tickets assigned to the user that are due within `days` days,
+/// or overdue, and the ones that have been blocked by another ticket since
+/// the last time this ran. Nothing is printed if there's nothing to remind
+/// them of so it can run from hooks without getting in the way.
+pub fn remind(days: i64) -> Result<()> {
+  let me = get_user_config()?.uuid;
+  let today = Local::now().date_naive();
+  let mut state = UserState::load()?;
+  let tickets = get_all_tickets()?;
+  let is_open = |id: &Uuid| {
+    tickets
+      .iter()
+      .any(|t| t.id == *id && t.status != Status::Closed)
+  };
+  let mine = tickets
+    .iter()
+    .filter(|t| t.status != Status::Closed)
+    .filter(|t| t.assignees.iter().any(|(id, _)| *id == me))
+    .collect::<Vec<&Ticket>>();
+
+  let mut due = mine
+    .iter()
+    .filter_map(|t| Some((t.due?, t)))
+    .filter(|(due, _)| (*due - today).num_days() <= days)
+    .collect::<Vec<_>>();
+  due.sort_by_key(|(due, _)| *due);
+  if !due.is_empty() {
+    println!("{}", "Tickets assigned to you that are due soon:".bold());
+    for (date, ticket) in due {
+      let left = (date - today).num_days();
+      println!(
+        "  {} {} ({})",
+        ticket.short_id.bold().red(),
+        ticket.title,
+        match left {
+          0 => "due today".yellow(),
+          1 => "due tomorrow".yellow(),
+          n if n < 0 => format!("overdue by {} days", -n).red(),
+          n => format!("due in {} days", n).yellow(),
+        }
+      );
+    }
+  }
+
+  let mut printed = false;
+  for ticket in &mine {
+    let seen = state.blockers.get(&ticket.id);
+    let new = ticket
+      .blocked_by
+      .iter()
+      .filter(|id| is_open(id) && seen.is_none_or(|seen| !seen.contains(id)))
+      .filter_map(|id| tickets.iter().find(|t| t.id == *id))
+      .collect::<Vec<&Ticket>>();
+    if new.is_empty() {
+      continue;
+    }
+    if !printed {
+      println!(
+        "{}",
+        "Tickets assigned to you that are newly blocked:".bold()
+      );
+      printed = true;
+    }
+    println!(
+      "  {} {} is blocked by {}",
+      ticket.short_id.bold().red(),
+      ticket.title,
+      new
+        .iter()
+        .map(|t| format!("{} {}", t.short_id, t.title))
+        .collect::<Vec<String>>()
+        .join(", ")
+    );
+  }
+
+  // Only the tickets that are still assigned to the user need to be kept
+  state.blockers = mine
+    .iter()
+    .filter(|t| !t.blocked_by.is_empty())
+    .map(|t| (t.id, t.blocked_by.clone()))
+    .collect();
+  state.save()
+}
@@ -0,0 +1,262 @@
+use crate::{
+  actions::ticket_root,
+  sync::{
+    self,
+    Side,
+  },
+  Name,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use colored::*;
+use log::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+use shared::write_atomic;
+use std::{
+  collections::BTreeMap,
+  fmt,
+  fs,
+  path::PathBuf,
+  str::FromStr,
+};
+use uuid::Uuid;
+
+#[derive(structopt::StructOpt)]
+pub enum RemoteCmd {
+  /// Add an issue tracker to sync tickets with
+  Add {
+    /// What to call the remote
+    name: String,
+    /// What kind of tracker it is: github, gitlab, gitea, or sourcehut
+    kind: Kind,
+    /// The url of the project on the tracker
+    url: String,
+    /// Only push tickets to the remote rather than syncing both ways
+    #[structopt(long)]
+    push_only: bool,
+  },
+  /// List the remotes tickets are synced with
+  List,
+  /// Remove a remote along with which issues tickets were synced to
+  Remove { name: String },
+  /// Sync tickets with the issues on a remote. Titles, descriptions, and
+  /// whether they're closed changed on both sides are merged, comments are
+  /// copied both ways, and tickets and issues that only exist on one side are
+  /// opened on the other. Changes that conflict are reported and left alone
+  Sync {
+    /// The remote to sync with
+    name: String,
+    /// An access token allowed to write to issues on the remote. Defaults to
+    /// GITHUB_TOKEN, GITLAB_TOKEN, or GITEA_TOKEN depending on the remote
+    #[structopt(long)]
+    token: Option<String>,
+    /// Which side to take when both changed the same thing, 'local' or
+    /// 'remote'
+    #[structopt(long)]
+    prefer: Option<Side>,
+  },
+}
+
+/// An issue tracker outside of the repo that tickets are synced with
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Remote {
+  pub kind: Kind,
+  pub url: String,
+  pub direction: Direction,
+  /// The id of the issue on the remote for each ticket synced with it
+  #[serde(default)]
+  pub ids: BTreeMap<Uuid, String>,
+  /// The id of the comment on the remote for each ticket comment synced with
+  /// it
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub comments: BTreeMap<Uuid, String>,
+  /// What each ticket looked like the last time it was synced
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub synced: BTreeMap<Uuid, Synced>,
+}
+
+/// The title, description, and state a ticket and its issue agreed on the
+/// last time they were synced, which changes made on either side since are
+/// merged from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Synced {
+  pub title: String,
+  pub description: String,
+  /// Whether they were closed, which remotes synced before this was kept
+  /// don't know
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub closed: Option<bool>,
+}
+
+/// Which service the remote is
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+  GitHub,
+  GitLab,
+  Gitea,
+  SourceHut,
+}
+
+/// Which way changes flow between the tickets and a remote
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+  /// The remote is a mirror of the tickets and changes to it are ignored
+  Push,
+  /// Changes are synced both ways
+  Both,
+}
+
+impl FromStr for Kind {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "github" => Ok(Kind::GitHub),
+      "gitlab" => Ok(Kind::GitLab),
+      "gitea" => Ok(Kind::Gitea),
+      "sourcehut" => Ok(Kind::SourceHut),
+      _ => bail!(
+        "'{}' is not a kind of remote, expected 'github', 'gitlab', 'gitea', \
+         or 'sourcehut'",
+        s
+      ),
+    }
+  }
+}
+
+impl fmt::Display for Kind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Kind::GitHub => write!(f, "GitHub"),
+      Kind::GitLab => write!(f, "GitLab"),
+      Kind::Gitea => write!(f, "Gitea"),
+      Kind::SourceHut => write!(f, "SourceHut"),
+    }
+  }
+}
+
+pub fn remote(cmd: RemoteCmd) -> Result<()> {
+  match cmd {
+    RemoteCmd::Add {
+      name,
+      kind,
+      url,
+      push_only,
+    } => {
+      if get_remotes()?.contains_key(&name) {
+        bail!("There is already a remote named '{}'", name);
+      }
+      save_remote(
+        &name,
+        &Remote {
+          kind,
+          url,
+          direction: if push_only {
+            Direction::Push
+          } else {
+            Direction::Both
+          },
+          ids: BTreeMap::new(),
+          comments: BTreeMap::new(),
+          synced: BTreeMap::new(),
+        },
+      )?;
+      info!("Added remote {}", name);
+    }
+    RemoteCmd::List => {
+      for (name, remote) in get_remotes()? {
+        println!(
+          "{} {} {} ({}, {} tickets synced)",
+          name.bold().cyan(),
+          remote.kind.to_string().purple(),
+          remote.url,
+          match remote.direction {
+            Direction::Push => "push only",
+            Direction::Both => "both ways",
+          },
+          remote.ids.len()
+        );
+      }
+    }
+    RemoteCmd::Remove { name } => {
+      let path = remote_path(&name)?;
+      if !path.exists() {
+        bail!("There is no remote named '{}'", name);
+      }
+      fs::remove_file(path)?;
+      info!("Removed remote {}", name);
+    }
+    RemoteCmd::Sync {
+      name,
+      token,
+      prefer,
+    } => sync::sync(&name, token, prefer)?,
+  }
+  Ok(())
+}
+
+/// Matches a user on a remote to one of the maintainers in the repo config by
+/// their display name or username
+pub fn find_maintainer(
+  maintainers: &[(String, Uuid)],
+  name: &str,
+  username: &str,
+) -> Option<(Uuid, Name)> {
+  maintainers
+    .iter()
+    .find(|(maintainer, _)| {
+      maintainer.eq_ignore_ascii_case(name)
+        || maintainer.eq_ignore_ascii_case(username)
+    })
+    .map(|(maintainer, uuid)| (*uuid, Name(maintainer.clone())))
+}
+
+pub fn remotes_dir() -> Result<PathBuf> {
+  Ok(ticket_root()?.join("remotes"))
+}
+
+fn remote_path(name: &str) -> Result<PathBuf> {
+  if name.is_empty()
+    || !name
+      .chars()
+      .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+  {
+    bail!(
+      "Remote names may only contain letters, numbers, '-', and '_', got '{}'",
+      name
+    );
+  }
+  Ok(remotes_dir()?.join(name).with_extension("toml"))
+}
+
+/// All of the configured remotes by name
+pub fn get_remotes() -> Result<BTreeMap<String, Remote>> {
+  let mut remotes = BTreeMap::new();
+  let dir = remotes_dir()?;
+  if !dir.exists() {
+    return Ok(remotes);
+  }
+  for entry in fs::read_dir(&dir)? {
+    let path = entry?.path();
+    if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+      continue;
+    }
+    if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+      trace!("Reading remote {}.", path.display());
+      let _ =
+        remotes.insert(name.to_owned(), toml::from_slice(&fs::read(&path)?)?);
+    }
+  }
+  Ok(remotes)
+}
+
+pub fn save_remote(name: &str, remote: &Remote) -> Result<()> {
+  fs::create_dir_all(remotes_dir()?)?;
+  write_atomic(&remote_path(name)?, toml::to_string_pretty(remote)?)?;
+  Ok(())
+}
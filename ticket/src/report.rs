@@ -0,0 +1,342 @@
+use crate::{
+  actions::get_all_tickets,
+  Estimate,
+  Format,
+  Status,
+  Ticket,
+};
+use anyhow::Result;
+use chrono::prelude::*;
+use colored::*;
+use configamajig::get_repo_config;
+use log::*;
+use serde::Serialize;
+use std::{
+  cmp::Reverse,
+  collections::BTreeMap,
+  iter,
+};
+use uuid::Uuid;
+
+#[derive(structopt::StructOpt)]
+pub enum Report {
+  /// How many open tickets each assignee has and how much work they add up to
+  Workload {
+    /// Output format, either 'human', 'json', or 'toml'
+    #[structopt(long, default_value = "human")]
+    format: Format,
+  },
+}
+
+pub fn report(report: Report) -> Result<()> {
+  match report {
+    Report::Workload { format } => workload(format),
+  }
+}
+
+#[derive(Serialize, Debug)]
+struct Workload {
+  name: String,
+  uuid: Option<Uuid>,
+  open: usize,
+  oldest_days: Option<i64>,
+  /// How much work the estimated open tickets add up to
+  estimated: Estimated,
+}
+
+fn workload(format: Format) -> Result<()> {
+  let now = Utc::now();
+  let mut rows = BTreeMap::new();
+
+  // Maintainers without any tickets should still show up as having nothing to
+  // do rather than being left out
+  match get_repo_config() {
+    Ok(config) => {
+      for (name, uuid) in config.maintainers {
+        let _ = rows.insert(
+          Some(uuid),
+          Workload {
+            name,
+            uuid: Some(uuid),
+            open: 0,
+            oldest_days: None,
+            estimated: Estimated::default(),
+          },
+        );
+      }
+    }
+    Err(e) => debug!("Unable to read the repo config: {}", e),
+  }
+
+  for ticket in get_all_tickets()?
+    .into_iter()
+    .filter(|t| t.status != Status::Closed)
+  {
+    let age = ticket.created_at.map(|t| (now - t).num_days());
+    let assignees = if ticket.assignees.is_empty() {
+      vec![(None, "Unassigned".to_owned())]
+    } else {
+      ticket
+        .assignees
+        .iter()
+        .map(|(uuid, name)| (Some(*uuid), name.0.clone()))
+        .collect()
+    };
+    for (uuid, name) in assignees {
+      let row = rows.entry(uuid).or_insert_with(|| Workload {
+        name,
+        uuid,
+        open: 0,
+        oldest_days: None,
+        estimated: Estimated::default(),
+      });
+      row.open += 1;
+      row.oldest_days = row.oldest_days.max(age);
+      row.estimated.add(&ticket);
+    }
+  }
+
+  let mut rows = rows.into_values().collect::<Vec<_>>();
+  rows.sort_by(|a, b| b.open.cmp(&a.open).then_with(|| a.name.cmp(&b.name)));
+
+  match format {
+    Format::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+    // TOML documents can't be a bare list so the rows go under a key
+    Format::Toml => print!(
+      "{}",
+      toml::to_string_pretty(
+        &iter::once(("workload", rows)).collect::<BTreeMap<_, _>>()
+      )?
+    ),
+    Format::Human => {
+      println!(
+        "{}",
+        format!(
+          "{:<30} {:>5} {:>12} {:>12}",
+          "Assignee", "Open", "Oldest", "Estimated"
+        )
+        .bold()
+      );
+      for row in rows {
+        println!(
+          "{:<30} {:>5} {:>12} {:>12}",
+          row.name.cyan(),
+          row.open,
+          row
+            .oldest_days
+            .map_or_else(|| "-".to_owned(), |days| format!("{} days", days)),
+          total(row.estimated.open_points, row.estimated.open_hours)
+        );
+      }
+    }
+  }
+  Ok(())
+}
+
+#[derive(Serialize, Debug)]
+struct Stats {
+  open: usize,
+  closed: usize,
+  average_days_to_close: Option<f64>,
+  /// Every ticket by its status
+  by_status: BTreeMap<String, usize>,
+  /// Open tickets by who they're assigned to
+  by_assignee: BTreeMap<String, usize>,
+  /// Open tickets by tag
+  by_tag: BTreeMap<String, usize>,
+  /// How much work the estimated tickets add up to
+  estimated: Estimated,
+  /// How much work the estimated tickets with each tag add up to, which for a
+  /// tag used as a milestone is how much of the milestone is done
+  estimated_by_tag: BTreeMap<String, Estimated>,
+  recently_active: Vec<Recent>,
+}
+
+/// Totals of ticket estimates. Points and time are kept apart since there's
+/// no way to turn one into the other.
+#[derive(Serialize, Debug, Default)]
+struct Estimated {
+  open_points: f64,
+  open_hours: f64,
+  closed_points: f64,
+  closed_hours: f64,
+}
+
+impl Estimated {
+  fn add(&mut self, ticket: &Ticket) {
+    let closed = ticket.status == Status::Closed;
+    match (ticket.estimate, closed) {
+      (Some(Estimate::Points(points)), false) => self.open_points += points,
+      (Some(Estimate::Hours(hours)), false) => self.open_hours += hours,
+      (Some(Estimate::Points(points)), true) => self.closed_points += points,
+      (Some(Estimate::Hours(hours)), true) => self.closed_hours += hours,
+      (None, _) => {}
+    }
+  }
+
+  /// The totals like `3pt + 4h open, 2pt closed`
+  fn summary(&self) -> String {
+    format!(
+      "{} open, {} closed",
+      total(self.open_points, self.open_hours),
+      total(self.closed_points, self.closed_hours)
+    )
+  }
+}
+
+/// Points and hours added up like `3pt + 4h`
+fn total(points: f64, hours: f64) -> String {
+  match (points > 0.0, hours > 0.0) {
+    (true, true) => format!("{}pt + {}h", points, hours),
+    (true, false) => format!("{}pt", points),
+    (false, true) => format!("{}h", hours),
+    (false, false) => "0".to_owned(),
+  }
+}
+
+#[derive(Serialize, Debug)]
+struct Recent {
+  short_id: String,
+  title: String,
+  last_activity: DateTime<Utc>,
+}
+
+/// Counts of the tickets in the repo, how long they take to close, and which
+/// have been active in the last `days` days
+pub fn stats(days: i64, format: Format) -> Result<()> {
+  let tickets = get_all_tickets()?;
+  let (closed, open): (Vec<&Ticket>, Vec<&Ticket>) =
+    tickets.iter().partition(|t| t.status == Status::Closed);
+
+  let mut by_status = BTreeMap::new();
+  for ticket in &tickets {
+    *by_status.entry(ticket.status.to_string()).or_insert(0) += 1;
+  }
+  let mut by_assignee = BTreeMap::new();
+  let mut by_tag = BTreeMap::new();
+  for ticket in &open {
+    if ticket.assignees.is_empty() {
+      *by_assignee.entry("Unassigned".to_owned()).or_insert(0) += 1;
+    }
+    for (_, name) in &ticket.assignees {
+      *by_assignee.entry(name.0.clone()).or_insert(0) += 1;
+    }
+    for tag in &ticket.tags {
+      *by_tag.entry(tag.clone()).or_insert(0) += 1;
+    }
+  }
+
+  let mut estimated = Estimated::default();
+  let mut estimated_by_tag = BTreeMap::<String, Estimated>::new();
+  for ticket in tickets.iter().filter(|t| t.estimate.is_some()) {
+    estimated.add(ticket);
+    for tag in &ticket.tags {
+      estimated_by_tag.entry(tag.clone()).or_default().add(ticket);
+    }
+  }
+
+  // Only tickets that know when they were both opened and closed count
+  let times = closed
+    .iter()
+    .filter_map(|t| Some(t.closed_at? - t.created_at?))
+    .map(|taken| taken.num_minutes() as f64 / (60.0 * 24.0))
+    .collect::<Vec<f64>>();
+  let average_days_to_close = if times.is_empty() {
+    None
+  } else {
+    Some((times.iter().sum::<f64>() / times.len() as f64 * 10.0).round() / 10.0)
+  };
+
+  let since = Utc::now() - chrono::Duration::days(days);
+  let mut recently_active = tickets
+    .iter()
+    .filter_map(|t| {
+      let last_activity = t.last_activity().filter(|at| *at >= since)?;
+      Some(Recent {
+        short_id: t.short_id.clone(),
+        title: t.title.clone(),
+        last_activity,
+      })
+    })
+    .collect::<Vec<Recent>>();
+  recently_active.sort_by_key(|recent| Reverse(recent.last_activity));
+  recently_active.truncate(10);
+
+  let stats = Stats {
+    open: open.len(),
+    closed: closed.len(),
+    average_days_to_close,
+    by_status,
+    by_assignee,
+    by_tag,
+    estimated,
+    estimated_by_tag,
+    recently_active,
+  };
+  match format {
+    Format::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    Format::Toml => print!("{}", toml::to_string_pretty(&stats)?),
+    Format::Human => print_stats(&stats, days),
+  }
+  Ok(())
+}
+
+fn print_stats(stats: &Stats, days: i64) {
+  println!(
+    "{} {}  {} {}",
+    "Open:".bold().purple(),
+    stats.open,
+    "Closed:".bold().purple(),
+    stats.closed
+  );
+  println!(
+    "{} {}",
+    "Average time to close:".bold().purple(),
+    stats
+      .average_days_to_close
+      .map_or_else(|| "-".to_owned(), |days| format!("{} days", days))
+  );
+  println!(
+    "{} {}",
+    "Estimated:".bold().purple(),
+    stats.estimated.summary()
+  );
+  for (heading, counts) in &[
+    ("By status", &stats.by_status),
+    ("Open by assignee", &stats.by_assignee),
+    ("Open by tag", &stats.by_tag),
+  ] {
+    if counts.is_empty() {
+      continue;
+    }
+    println!("\n{}", heading.bold().purple());
+    for (name, count) in counts.iter() {
+      println!("{:<30} {:>5}", name.cyan(), count);
+    }
+  }
+  if !stats.estimated_by_tag.is_empty() {
+    println!("\n{}", "Estimated by tag".bold().purple());
+    for (tag, estimated) in &stats.estimated_by_tag {
+      println!("{:<30} {}", tag.cyan(), estimated.summary());
+    }
+  }
+  if !stats.recently_active.is_empty() {
+    println!(
+      "\n{}",
+      format!("Active in the last {} days", days).bold().purple()
+    );
+    for recent in &stats.recently_active {
+      println!(
+        "{} {} {}",
+        recent.short_id.bold().red(),
+        recent.title,
+        recent
+          .last_activity
+          .with_timezone(&Local)
+          .format("%Y-%m-%d %H:%M")
+          .to_string()
+          .dimmed()
+      );
+    }
+  }
+}
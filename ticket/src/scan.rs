@@ -0,0 +1,177 @@
+//! Files tickets for `TODO(ds)` and `FIXME(ds)` comments in the code. Each one
+//! gets the short id of its ticket written back into it, e.g.
+//! `TODO(ds-1a2b3c4d)`, so scanning again updates the ticket instead of opening
+//! another one.
+use crate::{
+  actions::{
+    get_all_tickets,
+    save_ticket,
+    warn_missing_fields,
+  },
+  new_ticket,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use log::*;
+use regex::Regex;
+use shared::find_root;
+use std::{
+  collections::BTreeMap,
+  fs,
+  path::Path,
+  process::Command,
+};
+
+/// Scans the files git knows about, or would if they were added, for comments
+/// to file tickets for
+pub fn scan(dry_run: bool) -> Result<()> {
+  let root = find_root()?;
+  let pattern = Regex::new(r"\b(TODO|FIXME)\(ds(?:-([0-9a-f]{8}))?\):?[ \t]*")?;
+  let mut tickets = get_all_tickets()?;
+  // Where each ticket's comments are, keyed by short id
+  let mut found = BTreeMap::<String, Vec<String>>::new();
+
+  for path in files(&root)? {
+    let contents = match fs::read_to_string(root.join(&path)) {
+      Ok(contents) => contents,
+      // Binary files can't have comments in them
+      Err(_) => continue,
+    };
+    let mut changed = false;
+    let mut out = String::with_capacity(contents.len());
+    for (i, line) in contents.split_inclusive('\n').enumerate() {
+      let location = format!("{}:{}", path, i + 1);
+      let captures = match pattern.captures(line) {
+        Some(captures) => captures,
+        None => {
+          out.push_str(line);
+          continue;
+        }
+      };
+      if let Some(short_id) = captures.get(2) {
+        found
+          .entry(short_id.as_str().to_owned())
+          .or_default()
+          .push(location);
+        out.push_str(line);
+        continue;
+      }
+
+      let whole = captures.get(0).map_or(0..0, |m| m.range());
+      let kind = &captures[1];
+      let text = comment_text(&line[whole.end..]);
+      let title = if text.is_empty() {
+        format!("{} in {}", kind, location)
+      } else {
+        text.to_owned()
+      };
+      if dry_run {
+        println!("Would open a ticket for {}: {}", location, title);
+        out.push_str(line);
+        continue;
+      }
+      let mut ticket = new_ticket(title, String::new())?;
+      ticket.tags.push(kind.to_lowercase());
+      ticket.locations = vec![location.clone()];
+      save_ticket(&ticket)?;
+      ticket.log_created();
+      println!(
+        "Opened {} for {}: {}",
+        ticket.short_id, location, ticket.title
+      );
+      warn_missing_fields(&ticket);
+      out.push_str(&line[..whole.start]);
+      out.push_str(&format!("{}(ds-{}): ", kind, ticket.short_id));
+      out.push_str(&line[whole.end..]);
+      changed = true;
+      let _ = found.insert(ticket.short_id.clone(), vec![location]);
+      tickets.push(ticket);
+    }
+    if changed {
+      fs::write(root.join(&path), out)?;
+    }
+  }
+
+  for ticket in &mut tickets {
+    let locations = found.remove(&ticket.short_id).unwrap_or_default();
+    if ticket.locations == locations {
+      continue;
+    }
+    if locations.is_empty() {
+      println!(
+        "{} isn't mentioned in the code anymore: {}",
+        ticket.short_id, ticket.title
+      );
+    } else {
+      println!(
+        "{} is now in {}: {}",
+        ticket.short_id,
+        locations.join(", "),
+        ticket.title
+      );
+    }
+    if !dry_run {
+      update_locations(ticket, locations)?;
+    }
+  }
+  for (short_id, locations) in found {
+    warn!(
+      "{} mentions ticket {} which doesn't exist",
+      locations.join(", "),
+      short_id
+    );
+  }
+  Ok(())
+}
+
+/// Records where the ticket's comments are now
+fn update_locations(ticket: &mut Ticket, locations: Vec<String>) -> Result<()> {
+  ticket.record(if locations.is_empty() {
+    "Removed from the code".to_owned()
+  } else {
+    format!("Found in {}", locations.join(", "))
+  });
+  ticket.locations = locations;
+  save_ticket(ticket)
+}
+
+/// What a comment says, without whatever closes it, e.g. `*/` or `-->`
+fn comment_text(rest: &str) -> &str {
+  let mut text = rest.trim();
+  for end in &["*/", "-->", "#}", "%>"] {
+    text = text.trim_end_matches(end).trim_end();
+  }
+  text
+}
+
+/// The files in the repo that aren't ignored, besides dev-suite's own
+fn files(root: &Path) -> Result<Vec<String>> {
+  let output = Command::new("git")
+    .args([
+      "ls-files",
+      "-z",
+      "--cached",
+      "--others",
+      "--exclude-standard",
+    ])
+    .current_dir(root)
+    .output()?;
+  if !output.status.success() {
+    bail!(
+      "Unable to list the files in the repo: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  let mut files = String::from_utf8(output.stdout)?
+    .split('\0')
+    .filter(|f| !f.is_empty() && !f.starts_with(".dev-suite/"))
+    .map(ToOwned::to_owned)
+    .collect::<Vec<String>>();
+  // Files that are staged and also changed show up twice
+  files.sort();
+  files.dedup();
+  Ok(files)
+}
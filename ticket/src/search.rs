@@ -0,0 +1,150 @@
+use crate::{
+  actions::get_all_tickets,
+  index,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use colored::*;
+use regex::{
+  Regex,
+  RegexBuilder,
+};
+use std::str::FromStr;
+
+/// Parts of a ticket that can be searched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+  Title,
+  Description,
+  Comments,
+}
+
+impl FromStr for Field {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "title" => Ok(Field::Title),
+      "description" => Ok(Field::Description),
+      "comments" => Ok(Field::Comments),
+      _ => bail!(
+        "'{}' is not a field, expected 'title', 'description', or 'comments'",
+        s
+      ),
+    }
+  }
+}
+
+/// Prints every open or closed ticket with a match for the query, along with
+/// the lines that matched and `context` lines around them
+pub fn search(
+  query: &str,
+  ignore_case: bool,
+  regex: bool,
+  fields: &[Field],
+  context: usize,
+) -> Result<()> {
+  let pattern = if regex {
+    query.to_owned()
+  } else {
+    regex::escape(query)
+  };
+  let re = RegexBuilder::new(&pattern)
+    .case_insensitive(ignore_case)
+    .build()?;
+  let searched = |field| fields.is_empty() || fields.contains(&field);
+
+  // Regexes can match in ways the index can't know about ahead of time
+  let candidates = if regex {
+    None
+  } else {
+    index::candidates(query)
+  };
+  let tickets = match candidates {
+    Some(tickets) => tickets,
+    None => get_all_tickets()?,
+  };
+  for ticket in tickets {
+    let mut found = Vec::new();
+    if searched(Field::Title) && re.is_match(&ticket.title) {
+      found.push(format!(
+        "  {} {}",
+        "title:".purple(),
+        highlight(&re, &ticket.title)
+      ));
+    }
+    if searched(Field::Description) {
+      let lines = matching_lines(&re, &ticket.description, context);
+      if !lines.is_empty() {
+        found.push(format!("  {}", "description:".purple()));
+        found.extend(lines);
+      }
+    }
+    if searched(Field::Comments) {
+      for (_, name, comment) in ticket.comments.values() {
+        let lines = matching_lines(&re, &comment.0, context);
+        if !lines.is_empty() {
+          found
+            .push(format!("  {}", format!("comment by {}:", name.0).purple()));
+          found.extend(lines);
+        }
+      }
+    }
+    if !found.is_empty() {
+      print_header(&ticket);
+      for line in found {
+        println!("{}", line);
+      }
+    }
+  }
+  Ok(())
+}
+
+fn print_header(ticket: &Ticket) {
+  println!("{} {}", ticket.short_id.bold().red(), ticket.title.bold());
+}
+
+/// The numbered lines of the text that match along with the lines of context
+/// around them. Matches are marked with ':' and context with '-' like grep
+/// does, and '--' separates groups of lines that aren't next to each other.
+fn matching_lines(re: &Regex, text: &str, context: usize) -> Vec<String> {
+  let lines = text.lines().collect::<Vec<&str>>();
+  let matches = lines
+    .iter()
+    .enumerate()
+    .filter(|(_, line)| re.is_match(line))
+    .map(|(i, _)| i)
+    .collect::<Vec<usize>>();
+
+  let mut out = Vec::new();
+  let mut last = None;
+  for (n, line) in lines.iter().enumerate() {
+    let near = matches
+      .iter()
+      .any(|m| n + context >= *m && n <= m + context);
+    if !near {
+      continue;
+    }
+    if last.is_some_and(|last| last + 1 != n) {
+      out.push("    --".to_owned());
+    }
+    last = Some(n);
+    if matches.contains(&n) {
+      out.push(format!("    {}: {}", n + 1, highlight(re, line)));
+    } else {
+      out.push(format!("    {}- {}", n + 1, line));
+    }
+  }
+  out
+}
+
+/// Colors every match in the line
+fn highlight(re: &Regex, line: &str) -> String {
+  re.replace_all(line, |caps: &regex::Captures| {
+    caps[0].bold().yellow().to_string()
+  })
+  .into_owned()
+}
@@ -0,0 +1,285 @@
+//! A small web server for people who'd rather not use the terminal. It serves
+//! the same pages as `ticket export html` but with forms on them, and edits
+//! to the tickets are written straight back to their files. With `--api` it
+//! serves the JSON API in api.rs instead.
+use crate::{
+  actions::{
+    default_assignee,
+    get_all_tickets,
+    require_fields,
+    resolve_status,
+    save_ticket,
+  },
+  api,
+  hooks,
+  html,
+  new_ticket,
+  set_status,
+  webhook,
+  FieldValue,
+  Name,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  format_err,
+  Result,
+};
+use configamajig::get_user_config;
+use log::*;
+use rand::prelude::*;
+use std::{
+  collections::{
+    BTreeMap,
+    HashMap,
+  },
+  io::Cursor,
+};
+use tiny_http::{
+  Header,
+  Method,
+  Request,
+  Response,
+  Server,
+};
+
+pub type Page = Response<Cursor<Vec<u8>>>;
+
+/// Serves the tickets on `port` until the process is killed. Only connections
+/// from this machine are taken since anyone who can reach it can edit the
+/// tickets, and only forms carrying the token made for this run are taken so
+/// that other sites open in the browser can't post to it. The API can also be
+/// made to require `api_token` from whoever uses it.
+pub fn serve(port: u16, api: bool, api_token: Option<String>) -> Result<()> {
+  let server = Server::http(("127.0.0.1", port))
+    .map_err(|e| format_err!("Unable to listen on port {}: {}", port, e))?;
+  let token = (0..16)
+    .map(|_| format!("{:02x}", random::<u8>()))
+    .collect::<String>();
+  if api {
+    println!("Serving the ticket API at http://localhost:{}", port);
+  } else {
+    println!("Serving the tickets at http://localhost:{}", port);
+  }
+  for mut request in server.incoming_requests() {
+    debug!("{} {}", request.method(), request.url());
+    let response = if api {
+      api::handle(&mut request, port, api_token.as_deref())
+    } else {
+      check_host(&request, port)
+        .map_err(|e| (403, e))
+        .and_then(|_| handle(&mut request, &token).map_err(|e| (400, e)))
+        .unwrap_or_else(|(status, e)| {
+          warn!("{} {}: {}", request.method(), request.url(), e);
+          let body = format!(
+            "<p><a href=\"/index.html\">All tickets</a></p>\n<p>{}</p>\n",
+            html::escape(&e.to_string())
+          );
+          respond(status, "text/html", html::page("Error", "/", &body))
+        })
+    };
+    if let Err(e) = request.respond(response) {
+      warn!("Unable to respond to a request: {}", e);
+    }
+  }
+  Ok(())
+}
+
+/// Turns away requests sent to any name for the server but this machine's,
+/// such as from a site that rebound its domain to 127.0.0.1, and ones that a
+/// page on another site sent
+pub fn check_host(request: &Request, port: u16) -> Result<()> {
+  let allowed = [format!("localhost:{}", port), format!("127.0.0.1:{}", port)];
+  match header_value(request, "Host") {
+    Some(host) if allowed.iter().any(|a| a == host) => (),
+    host => bail!("Requests to {} aren't taken", host.unwrap_or("no host")),
+  }
+  if let Some(origin) = header_value(request, "Origin") {
+    if !allowed.iter().any(|a| origin == format!("http://{}", a)) {
+      bail!("Requests from {} aren't taken", origin);
+    }
+  }
+  Ok(())
+}
+
+/// The value of the request's header called `name`, if it has one
+pub fn header_value<'a>(
+  request: &'a Request,
+  name: &'static str,
+) -> Option<&'a str> {
+  request
+    .headers()
+    .iter()
+    .find(|header| header.field.equiv(name))
+    .map(|header| header.value.as_str())
+}
+
+fn handle(request: &mut Request, token: &str) -> Result<Page> {
+  let url = request.url().to_owned();
+  let path = url.split('?').next().unwrap_or_default();
+  let page = path.trim_start_matches("/tickets/");
+  let method = request.method().clone();
+  let mut tickets = get_all_tickets()?;
+  Ok(match (method, path) {
+    (Method::Get, "/") | (Method::Get, "/index.html") => {
+      let tickets = tickets.iter().collect::<Vec<_>>();
+      respond(200, "text/html", html::index(&tickets, Some(token))?)
+    }
+    (Method::Get, "/style.css") => respond(200, "text/css", html::STYLE),
+    (Method::Post, "/") | (Method::Post, "/index.html") => {
+      let form = form(request, token)?;
+      let ticket = new(
+        field(&form, "title")?,
+        field(&form, "description")?,
+        BTreeMap::new(),
+      )?;
+      redirect(&format!("/tickets/{}", html::page_name(&ticket)))
+    }
+    (method, _) if page != path => {
+      let index = match tickets.iter().position(|t| html::page_name(t) == page)
+      {
+        Some(index) => index,
+        None => return Ok(not_found()),
+      };
+      if method == Method::Post {
+        let mut ticket = tickets.swap_remove(index);
+        update(&mut ticket, &form(request, token)?)?;
+        redirect(&format!("/tickets/{}", html::page_name(&ticket)))
+      } else {
+        let all = tickets.iter().collect::<Vec<_>>();
+        let page =
+          html::ticket_page(&tickets[index], &all, &tickets, Some(token))?;
+        respond(200, "text/html", page)
+      }
+    }
+    _ => not_found(),
+  })
+}
+
+/// Makes a ticket like `ticket new` does
+pub fn new(
+  title: String,
+  description: String,
+  fields: BTreeMap<String, FieldValue>,
+) -> Result<Ticket> {
+  if title.trim().is_empty() {
+    bail!("Title may not be empty");
+  }
+  require_fields(&fields)?;
+  let mut ticket = new_ticket(title, description)?;
+  ticket.fields = fields;
+  if let Some((uuid, name)) = default_assignee()? {
+    ticket.record(format!("Assigned {}", name));
+    ticket.assignees.push((uuid, Name(name)));
+  }
+  save_ticket(&ticket)?;
+  ticket.log_created();
+  hooks::notify(webhook::Event::Created, &ticket, None);
+  Ok(ticket)
+}
+
+/// Does what one of the forms on a ticket's page asked for
+fn update(ticket: &mut Ticket, form: &HashMap<String, String>) -> Result<()> {
+  match field(form, "action")?.as_str() {
+    "comment" => {
+      let user_config = get_user_config()?;
+      let comment = field(form, "comment")?;
+      let _ = ticket.add_comment(
+        user_config.uuid,
+        user_config.name,
+        comment.clone(),
+      )?;
+      save_ticket(ticket)?;
+      hooks::notify(webhook::Event::Commented, ticket, Some(&comment));
+      Ok(())
+    }
+    "status" => {
+      let status = resolve_status(&field(form, "status")?)?;
+      if status == ticket.status {
+        return Ok(());
+      }
+      set_status(ticket, status, None)
+    }
+    "edit" => {
+      let title = field(form, "title")?;
+      let description = field(form, "description")?;
+      if title.trim().is_empty() {
+        bail!("Title may not be empty");
+      }
+      if title != ticket.title {
+        ticket.record(format!(
+          "Renamed the ticket from '{}' to '{}'",
+          ticket.title, title
+        ));
+        ticket.title = title;
+      }
+      if description != ticket.description {
+        ticket.record("Edited the description");
+        ticket.set_description(description)?;
+      }
+      save_ticket(ticket)
+    }
+    action => {
+      bail!("'{}' isn't something that can be done to a ticket", action)
+    }
+  }
+}
+
+/// Reads the url encoded form that was posted, which has to carry the token
+/// the pages' forms were made with
+fn form(request: &mut Request, token: &str) -> Result<HashMap<String, String>> {
+  let body = body(request)?;
+  let form = url::form_urlencoded::parse(body.as_bytes())
+    .into_owned()
+    .collect::<HashMap<_, _>>();
+  if form.get("token").map(String::as_str) != Some(token) {
+    bail!("The form didn't come from a page this server made");
+  }
+  Ok(form)
+}
+
+/// Reads everything that was sent with the request
+pub fn body(request: &mut Request) -> Result<String> {
+  let mut body = String::new();
+  let _ = request.as_reader().read_to_string(&mut body)?;
+  Ok(body)
+}
+
+/// A field of a form. Browsers send newlines as \r\n so they're turned back
+/// into the \n the rest of the tickets use.
+fn field(form: &HashMap<String, String>, name: &str) -> Result<String> {
+  form
+    .get(name)
+    .map(|value| value.replace("\r\n", "\n"))
+    .ok_or_else(|| format_err!("The form is missing '{}'", name))
+}
+
+pub fn respond(
+  status: u16,
+  content_type: &str,
+  body: impl Into<Vec<u8>>,
+) -> Page {
+  Response::from_data(body)
+    .with_status_code(status)
+    .with_header(header("Content-Type", content_type))
+}
+
+/// Sends the browser to another page after a form is posted so that
+/// refreshing doesn't post it again
+fn redirect(to: &str) -> Page {
+  Response::from_data(Vec::new())
+    .with_status_code(303)
+    .with_header(header("Location", to))
+}
+
+fn not_found() -> Page {
+  let body =
+    "<p><a href=\"/index.html\">All tickets</a></p>\n<p>Not found</p>\n";
+  respond(404, "text/html", html::page("Not found", "/", body))
+}
+
+fn header(name: &str, value: &str) -> Header {
+  // Only ever called with ascii names and values, which make valid headers
+  Header::from_bytes(name.as_bytes(), value.as_bytes())
+    .expect("Header is valid")
+}
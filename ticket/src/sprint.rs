@@ -0,0 +1,384 @@
+//! Sprints are stretches of time tickets are pulled into to be worked on.
+//! Each one is kept in `.dev-suite/ticket/sprints/<name>.toml` with its start
+//! and end dates and the tickets in it, and the current sprint is the one
+//! today falls in.
+use crate::{
+  actions::{
+    find_ticket,
+    get_all_tickets,
+    get_archived_tickets,
+    save_ticket,
+    ticket_root,
+  },
+  Estimate,
+  Format,
+  Status,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  format_err,
+  Result,
+};
+use chrono::prelude::*;
+use colored::*;
+use log::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+use shared::write_atomic;
+use std::{
+  collections::BTreeMap,
+  fs,
+  path::PathBuf,
+};
+use uuid::Uuid;
+
+#[derive(structopt::StructOpt)]
+pub enum SprintCmd {
+  /// Plan a new sprint
+  New {
+    /// What to call the sprint, e.g. 2020-w27
+    name: String,
+    /// The first day of the sprint, e.g. 2020-07-01
+    #[structopt(long)]
+    start: NaiveDate,
+    /// The last day of the sprint, e.g. 2020-07-14
+    #[structopt(long)]
+    end: NaiveDate,
+  },
+  /// Pull tickets into a sprint
+  Add {
+    #[structopt(required = true)]
+    ids: Vec<String>,
+    /// The sprint to add them to, the current one if not given
+    #[structopt(long)]
+    sprint: Option<String>,
+  },
+  /// Take tickets out of a sprint
+  Remove {
+    #[structopt(required = true)]
+    ids: Vec<String>,
+    /// The sprint to take them out of, the current one if not given
+    #[structopt(long)]
+    sprint: Option<String>,
+  },
+  /// List the sprints, the current one marked with a '*'
+  List,
+  /// Show how far along a sprint is, the current one if no name is given
+  Status {
+    name: Option<String>,
+    /// Output format, either 'human', 'json', or 'toml'
+    #[structopt(long, default_value = "human")]
+    format: Format,
+  },
+}
+
+/// A stretch of time and the tickets to be done in it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Sprint {
+  pub start: NaiveDate,
+  pub end: NaiveDate,
+  /// The tickets pulled into the sprint, in the order they were added
+  #[serde(default)]
+  pub tickets: Vec<Uuid>,
+}
+
+impl Sprint {
+  /// Whether the day falls in the sprint
+  pub fn contains(&self, day: NaiveDate) -> bool {
+    self.start <= day && day <= self.end
+  }
+}
+
+/// How far along a sprint is
+#[derive(Serialize, Debug)]
+struct Progress {
+  name: String,
+  start: NaiveDate,
+  end: NaiveDate,
+  days_left: i64,
+  open: usize,
+  closed: usize,
+  closed_points: f64,
+  total_points: f64,
+  closed_hours: f64,
+  total_hours: f64,
+  tickets: Vec<Entry>,
+}
+
+#[derive(Serialize, Debug)]
+struct Entry {
+  short_id: String,
+  title: String,
+  status: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  estimate: Option<Estimate>,
+}
+
+pub fn sprint(cmd: SprintCmd) -> Result<()> {
+  match cmd {
+    SprintCmd::New { name, start, end } => {
+      if end < start {
+        bail!(
+          "The sprint can't end on {} before it starts on {}",
+          end,
+          start
+        );
+      }
+      if get_sprints()?.contains_key(&name) {
+        bail!("There is already a sprint named '{}'", name);
+      }
+      save_sprint(
+        &name,
+        &Sprint {
+          start,
+          end,
+          tickets: Vec::new(),
+        },
+      )?;
+      info!("Planned sprint {} from {} to {}", name, start, end);
+    }
+    SprintCmd::Add { ids, sprint } => {
+      let (name, mut sprint) = named_or_current(sprint)?;
+      for id in ids {
+        let mut ticket = find_ticket(&id)?;
+        if sprint.tickets.contains(&ticket.id) {
+          info!("{} is already in sprint {}", ticket.short_id, name);
+          continue;
+        }
+        ticket.record(format!("Added to the sprint '{}'", name));
+        save_ticket(&ticket)?;
+        sprint.tickets.push(ticket.id);
+        info!("Added {} to sprint {}", ticket.short_id, name);
+      }
+      save_sprint(&name, &sprint)?;
+    }
+    SprintCmd::Remove { ids, sprint } => {
+      let (name, mut sprint) = named_or_current(sprint)?;
+      for id in ids {
+        let mut ticket = find_ticket(&id)?;
+        if !sprint.tickets.contains(&ticket.id) {
+          bail!("{} isn't in sprint {}", ticket.short_id, name);
+        }
+        ticket.record(format!("Removed from the sprint '{}'", name));
+        save_ticket(&ticket)?;
+        sprint.tickets.retain(|t| *t != ticket.id);
+        info!("Removed {} from sprint {}", ticket.short_id, name);
+      }
+      save_sprint(&name, &sprint)?;
+    }
+    SprintCmd::List => {
+      let today = Local::now().date_naive();
+      for (name, sprint) in get_sprints()? {
+        println!(
+          "{} {} {} to {} ({} tickets)",
+          if sprint.contains(today) { "*" } else { " " },
+          name.bold().cyan(),
+          sprint.start,
+          sprint.end,
+          sprint.tickets.len()
+        );
+      }
+    }
+    SprintCmd::Status { name, format } => {
+      let (name, sprint) = named_or_current(name)?;
+      let progress = progress(name, &sprint)?;
+      match format {
+        Format::Json => {
+          println!("{}", serde_json::to_string_pretty(&progress)?)
+        }
+        Format::Toml => print!("{}", toml::to_string_pretty(&progress)?),
+        Format::Human => print_progress(&progress),
+      }
+    }
+  }
+  Ok(())
+}
+
+fn progress(name: String, sprint: &Sprint) -> Result<Progress> {
+  let tickets = sprint_tickets(sprint)?;
+  let today = Local::now().date_naive();
+  let mut progress = Progress {
+    name,
+    start: sprint.start,
+    end: sprint.end,
+    days_left: ((sprint.end - today.max(sprint.start)).num_days() + 1).max(0),
+    open: 0,
+    closed: 0,
+    closed_points: 0.0,
+    total_points: 0.0,
+    closed_hours: 0.0,
+    total_hours: 0.0,
+    tickets: Vec::new(),
+  };
+  for ticket in &tickets {
+    let closed = ticket.status == Status::Closed;
+    if closed {
+      progress.closed += 1;
+    } else {
+      progress.open += 1;
+    }
+    match ticket.estimate {
+      Some(Estimate::Points(points)) => {
+        progress.total_points += points;
+        if closed {
+          progress.closed_points += points;
+        }
+      }
+      Some(Estimate::Hours(hours)) => {
+        progress.total_hours += hours;
+        if closed {
+          progress.closed_hours += hours;
+        }
+      }
+      None => {}
+    }
+    progress.tickets.push(Entry {
+      short_id: ticket.short_id.clone(),
+      title: ticket.title.clone(),
+      status: ticket.status.to_string(),
+      estimate: ticket.estimate,
+    });
+  }
+  Ok(progress)
+}
+
+fn print_progress(progress: &Progress) {
+  println!(
+    "{} {} to {}, {} days left",
+    progress.name.bold().cyan(),
+    progress.start,
+    progress.end,
+    progress.days_left
+  );
+  let total = progress.open + progress.closed;
+  let percent = (progress.closed * 100).checked_div(total).unwrap_or(100);
+  println!(
+    "{} {} of {} tickets closed ({}%)",
+    "Progress:".bold().purple(),
+    progress.closed,
+    total,
+    percent
+  );
+  if progress.total_points > 0.0 {
+    println!(
+      "{} {} of {}pt done",
+      "Points:".bold().purple(),
+      progress.closed_points,
+      progress.total_points
+    );
+  }
+  if progress.total_hours > 0.0 {
+    println!(
+      "{} {} of {}h done",
+      "Hours:".bold().purple(),
+      progress.closed_hours,
+      progress.total_hours
+    );
+  }
+  if !progress.tickets.is_empty() {
+    println!();
+  }
+  for entry in &progress.tickets {
+    println!(
+      "{} {} {}{}",
+      entry.short_id.bold().red(),
+      entry.title,
+      entry.status.yellow(),
+      entry
+        .estimate
+        .map_or_else(String::new, |e| format!(" ({})", e).dimmed().to_string())
+    );
+  }
+}
+
+/// The tickets in the sprint, archived ones included, in the order they were
+/// added. Tickets that were deleted since are left out.
+pub fn sprint_tickets(sprint: &Sprint) -> Result<Vec<Ticket>> {
+  let mut all = get_all_tickets()?;
+  let missing = |id: &Uuid| !all.iter().any(|t| t.id == *id);
+  if sprint.tickets.iter().any(missing) {
+    all.extend(get_archived_tickets()?);
+  }
+  Ok(
+    sprint
+      .tickets
+      .iter()
+      .filter_map(|id| all.iter().find(|t| t.id == *id).cloned())
+      .collect(),
+  )
+}
+
+/// The sprint with the name, or the current one if there's no name
+pub fn named_or_current(name: Option<String>) -> Result<(String, Sprint)> {
+  let mut sprints = get_sprints()?;
+  match name {
+    Some(name) => match sprints.remove(&name) {
+      Some(sprint) => Ok((name, sprint)),
+      None => bail!("There is no sprint named '{}'", name),
+    },
+    None => current_sprint(sprints),
+  }
+}
+
+/// The sprint today falls in. If sprints overlap it's the one that started
+/// last.
+pub fn current_sprint(
+  sprints: BTreeMap<String, Sprint>,
+) -> Result<(String, Sprint)> {
+  let today = Local::now().date_naive();
+  sprints
+    .into_iter()
+    .filter(|(_, sprint)| sprint.contains(today))
+    .max_by_key(|(_, sprint)| sprint.start)
+    .ok_or_else(|| {
+      format_err!("There is no current sprint, name the one you want")
+    })
+}
+
+pub fn sprints_dir() -> Result<PathBuf> {
+  Ok(ticket_root()?.join("sprints"))
+}
+
+fn sprint_path(name: &str) -> Result<PathBuf> {
+  if name.is_empty()
+    || !name
+      .chars()
+      .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+  {
+    bail!(
+      "Sprint names may only contain letters, numbers, '-', and '_', got '{}'",
+      name
+    );
+  }
+  Ok(sprints_dir()?.join(name).with_extension("toml"))
+}
+
+/// All of the sprints by name
+pub fn get_sprints() -> Result<BTreeMap<String, Sprint>> {
+  let mut sprints = BTreeMap::new();
+  let dir = sprints_dir()?;
+  if !dir.exists() {
+    return Ok(sprints);
+  }
+  for entry in fs::read_dir(&dir)? {
+    let path = entry?.path();
+    if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+      continue;
+    }
+    if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+      trace!("Reading sprint {}.", path.display());
+      let _ =
+        sprints.insert(name.to_owned(), toml::from_slice(&fs::read(&path)?)?);
+    }
+  }
+  Ok(sprints)
+}
+
+pub fn save_sprint(name: &str, sprint: &Sprint) -> Result<()> {
+  fs::create_dir_all(sprints_dir()?)?;
+  write_atomic(&sprint_path(name)?, toml::to_string_pretty(sprint)?)?;
+  Ok(())
+}
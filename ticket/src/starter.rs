@@ -0,0 +1,44 @@
+//! What `ticket init` fills a new tracker with so it's ready to use
+
+/// Ticket templates written into `.dev-suite/ticket/templates` by name
+pub const TEMPLATES: [(&str, &str); 2] = [
+  (
+    "bug",
+    "## What happened\n\n\
+     ## What should have happened\n\n\
+     ## Steps to reproduce\n\n1. \n\n\
+     ## Version and platform\n",
+  ),
+  (
+    "feature",
+    "## The problem\n\n\
+     ## The proposed solution\n\n\
+     ## Alternatives considered\n",
+  ),
+];
+
+/// Files only ever made on someone's machine that shouldn't be committed
+pub const GITIGNORE: &str = "\
+# The scratch file descriptions are edited in and the editor's swap files
+/description
+/.description.sw?
+/description~
+";
+
+/// The title of the ticket `ticket init --welcome` opens
+pub const WELCOME_TITLE: &str = "Welcome to ticket";
+
+/// The description of the ticket `ticket init --welcome` opens
+pub const WELCOME: &str = "\
+Tickets live in .dev-suite/ticket next to the code and are committed with it.
+Try these out on this ticket, then close it:
+
+- ticket list
+- ticket show <id>
+- ticket comment <id> \"Trying out comments\"
+- ticket new --template bug
+- ticket close <id> --reason \"Done with the tour\"
+
+Run ticket with no arguments for the tui, or ticket --help for everything
+else.
+";
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use chrono::prelude::*;
+use configamajig::user_dir;
+use log::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+use shared::write_atomic;
+use std::{
+  collections::BTreeMap,
+  fs,
+  path::PathBuf,
+};
+use uuid::Uuid;
+
+/// State for ticket that belongs to the current user rather than to the repo,
+/// so it's kept in the user's config dir instead of next to the tickets
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct UserState {
+  /// Tickets that are hidden until the given date
+  #[serde(default)]
+  pub snoozed: BTreeMap<Uuid, NaiveDate>,
+  /// The last time each ticket was shown, used to find what's new on it
+  #[serde(default)]
+  pub viewed: BTreeMap<Uuid, DateTime<Utc>>,
+  /// What each ticket assigned to the user was blocked by the last time they
+  /// were reminded about it, so that only new blockers are mentioned
+  #[serde(default)]
+  pub blockers: BTreeMap<Uuid, Vec<Uuid>>,
+  /// When the inbox was last checked for each watched ticket, so only what's
+  /// happened on it since is shown
+  #[serde(default)]
+  pub inbox: BTreeMap<Uuid, DateTime<Utc>>,
+}
+
+impl UserState {
+  /// Reads in the state, or an empty one if nothing has been saved yet
+  pub fn load() -> Result<Self> {
+    let path = state_path()?;
+    if path.exists() {
+      Ok(toml::from_slice(&fs::read(&path)?)?)
+    } else {
+      debug!("No user state at {}, using the default.", path.display());
+      Ok(Self::default())
+    }
+  }
+
+  pub fn save(&self) -> Result<()> {
+    let path = state_path()?;
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    write_atomic(&path, toml::to_string_pretty(&self)?)?;
+    Ok(())
+  }
+
+  /// Whether the ticket is still hidden as of today
+  pub fn is_snoozed(&self, id: Uuid) -> bool {
+    self
+      .snoozed
+      .get(&id)
+      .is_some_and(|until| *until > Local::now().date_naive())
+  }
+}
+
+fn state_path() -> Result<PathBuf> {
+  Ok(user_dir()?.join("ticket-state.toml"))
+}
@@ -0,0 +1,690 @@
+//! Keeps tickets in step with the issues on a remote. Each kind of remote is a
+//! `Provider` that only has to list, open, and change issues and comments, and
+//! the title, description, and state every ticket had the last time it was
+//! synced are kept in the remote so changes made on both sides since can be
+//! merged.
+use crate::{
+  actions::{
+    get_all_tickets,
+    save_ticket,
+    uuid_v1,
+    warn_missing_fields,
+  },
+  gitea::Gitea,
+  github::GitHub,
+  gitlab::GitLab,
+  new_ticket,
+  remote::{
+    find_maintainer,
+    get_remotes,
+    save_remote,
+    Direction,
+    Kind,
+    Remote,
+    Synced,
+  },
+  Comment,
+  Name,
+  Status,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use chrono::prelude::*;
+use colored::*;
+use configamajig::get_repo_config;
+use log::*;
+use reqwest::blocking::{
+  Client,
+  RequestBuilder,
+  Response,
+};
+use serde::de::DeserializeOwned;
+use std::{
+  collections::BTreeMap,
+  env,
+  str::FromStr,
+};
+use uuid::Uuid;
+
+/// An issue on a remote, whichever kind of tracker it came from
+pub struct Issue {
+  pub id: String,
+  pub title: String,
+  pub description: String,
+  pub closed: bool,
+  pub labels: Vec<String>,
+  pub assignees: Vec<User>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+  pub closed_at: Option<DateTime<Utc>>,
+  pub closed_by: Option<User>,
+  pub due: Option<NaiveDate>,
+}
+
+/// A comment on an issue on a remote
+pub struct IssueComment {
+  pub id: String,
+  pub body: String,
+  pub author: User,
+  pub created_at: DateTime<Utc>,
+}
+
+/// Someone with an account on a remote
+pub struct User {
+  /// The name they go by, which is their username if they haven't set one
+  pub name: String,
+  pub username: String,
+}
+
+/// An issue tracker tickets can be synced with
+pub trait Provider {
+  /// Every issue, open or closed
+  fn issues(&self) -> Result<Vec<Issue>>;
+  /// The comments on an issue from oldest to newest
+  fn comments(&self, issue: &str) -> Result<Vec<IssueComment>>;
+  /// Opens an issue and returns its id
+  fn create(&self, title: &str, description: &str) -> Result<String>;
+  /// Changes the title and description of an issue
+  fn update(&self, issue: &str, title: &str, description: &str) -> Result<()>;
+  /// Closes an issue
+  fn close(&self, issue: &str) -> Result<()>;
+  /// Reopens a closed issue
+  fn reopen(&self, issue: &str) -> Result<()>;
+  /// Comments on an issue and returns the comment's id
+  fn comment(&self, issue: &str, body: &str) -> Result<String>;
+}
+
+/// Which side of a sync to take when both changed the same thing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+  Local,
+  Remote,
+}
+
+impl FromStr for Side {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "local" => Ok(Side::Local),
+      "remote" => Ok(Side::Remote),
+      _ => bail!("'{}' is not a side, expected 'local' or 'remote'", s),
+    }
+  }
+}
+
+/// Makes the provider for the remote, using the token from the environment
+/// variable for its kind if one isn't given
+pub fn provider(
+  remote: &Remote,
+  token: Option<String>,
+) -> Result<Box<dyn Provider>> {
+  let var = match remote.kind {
+    Kind::GitHub => "GITHUB_TOKEN",
+    Kind::GitLab => "GITLAB_TOKEN",
+    Kind::Gitea => "GITEA_TOKEN",
+    Kind::SourceHut => bail!("Syncing with SourceHut isn't supported yet"),
+  };
+  let token = token.or_else(|| env::var(var).ok());
+  Ok(match remote.kind {
+    Kind::GitHub => Box::new(GitHub::new(&remote.url, token)?),
+    Kind::GitLab => Box::new(GitLab::new(&remote.url, token)?),
+    _ => Box::new(Gitea::new(&remote.url, token)?),
+  })
+}
+
+/// A client for talking to the APIs of remotes. GitHub turns away requests
+/// without a user agent so every request has one
+pub fn client() -> Result<Client> {
+  Ok(
+    Client::builder()
+      .user_agent(concat!("ticket/", env!("CARGO_PKG_VERSION")))
+      .build()?,
+  )
+}
+
+/// Sends the request and fails if the remote didn't accept it
+pub fn check(kind: Kind, request: RequestBuilder) -> Result<Response> {
+  let response = request.send()?;
+  if !response.status().is_success() {
+    bail!(
+      "{} responded to {} with {}",
+      kind,
+      response.url(),
+      response.status()
+    );
+  }
+  Ok(response)
+}
+
+/// Sends the request and parses what comes back as json
+pub fn send<T: DeserializeOwned>(
+  kind: Kind,
+  request: RequestBuilder,
+) -> Result<T> {
+  Ok(serde_json::from_str(&check(kind, request)?.text()?)?)
+}
+
+/// Splits the url of a repo on GitHub or Gitea into the url of the instance
+/// and the 'owner/repo' part
+pub fn split_repo_url(kind: Kind, url: &str) -> Result<(String, String)> {
+  let mut parts = url.trim_end_matches('/').rsplitn(3, '/');
+  match (parts.next(), parts.next(), parts.next()) {
+    (Some(repo), Some(owner), Some(url)) if url.contains("://") => {
+      Ok((url.to_owned(), format!("{}/{}", owner, repo)))
+    }
+    _ => bail!(
+      "'{}' isn't the url of a {} repo, expected something like \
+       'https://example.com/owner/repo'",
+      url,
+      kind
+    ),
+  }
+}
+
+/// Opens a ticket for each issue on the remote that isn't synced with one
+/// yet and adds comments made on issues that were since then
+pub fn import(
+  provider: &dyn Provider,
+  remote_name: &str,
+  mut remote: Remote,
+) -> Result<()> {
+  let maintainers = maintainers();
+  let mut tickets = get_all_tickets()?
+    .into_iter()
+    .map(|ticket| (ticket.id, ticket))
+    .collect::<BTreeMap<Uuid, Ticket>>();
+  for issue in provider.issues()? {
+    let id = issue.id.clone();
+    let mut ticket = match linked(&remote, &id) {
+      Some(uuid) => match tickets.remove(&uuid) {
+        Some(ticket) if ticket.encrypted => {
+          warn!("Not syncing {} since it's encrypted", ticket.short_id);
+          continue;
+        }
+        Some(ticket) => ticket,
+        None => {
+          warn!("Issue #{} was synced with {} which is gone", id, uuid);
+          continue;
+        }
+      },
+      None => {
+        import_issue(provider, issue, &mut remote, &maintainers)?;
+        save_remote(remote_name, &remote)?;
+        continue;
+      }
+    };
+    let mut changed = false;
+    let comments = pull_comments(provider, &id, &ticket, &mut remote)?;
+    if !comments.is_empty() {
+      let added = comments.len();
+      for (uuid, comment) in comments {
+        add_comment(&mut ticket, uuid, comment, &maintainers);
+      }
+      ticket.record(format!(
+        "Mirrored {} comments from {} issue #{}",
+        added, remote.kind, id
+      ));
+      println!(
+        "Mirrored {} comments from #{} to {}",
+        added, id, ticket.short_id
+      );
+      changed = true;
+    }
+    // Only issues closed or reopened since the last sync are brought in,
+    // tickets that changed are left for sync to push
+    let closed = ticket.status == Status::Closed;
+    let base = remote.synced.get(&ticket.id).and_then(|base| base.closed);
+    match merge_state(base, closed, issue.closed, None) {
+      Some(state) => {
+        if state != closed {
+          match_state(&mut ticket, &issue, remote.kind, &maintainers);
+          changed = true;
+        }
+        if let Some(synced) = remote.synced.get_mut(&ticket.id) {
+          synced.closed = Some(state);
+        }
+      }
+      None => warn!(
+        "{} and #{} were both opened or closed since they were last synced, \
+         sync with '--prefer' to pick one",
+        ticket.short_id, id
+      ),
+    }
+    if changed {
+      save_ticket(&ticket)?;
+    }
+    // Saved after every issue so an error partway through doesn't lead to the
+    // same issues or comments being brought in twice
+    save_remote(remote_name, &remote)?;
+  }
+  info!("Imported issues from {}", remote.url);
+  Ok(())
+}
+
+/// Syncs the tickets with the issues on the remote. Only changes made to
+/// tickets are pushed to remotes that are push only.
+pub fn sync(
+  remote_name: &str,
+  token: Option<String>,
+  prefer: Option<Side>,
+) -> Result<()> {
+  let mut remote = match get_remotes()?.remove(remote_name) {
+    Some(remote) => remote,
+    None => bail!("There is no remote named '{}'", remote_name),
+  };
+  let provider = provider(&remote, token)?;
+  let both = remote.direction == Direction::Both;
+  let prefer = if both { prefer } else { Some(Side::Local) };
+  let maintainers = maintainers();
+  let mut issues = provider
+    .issues()?
+    .into_iter()
+    .map(|issue| (issue.id.clone(), issue))
+    .collect::<BTreeMap<String, Issue>>();
+
+  let mut conflicts = 0;
+  for mut ticket in get_all_tickets()? {
+    // Encrypted tickets aren't put anywhere they'd have to be decrypted
+    if ticket.encrypted {
+      if remote.ids.contains_key(&ticket.id) {
+        warn!("Not syncing {} since it's encrypted", ticket.short_id);
+      }
+      continue;
+    }
+    let (id, issue) = match remote.ids.get(&ticket.id).cloned() {
+      Some(id) => match issues.remove(&id) {
+        Some(issue) => (id, Some(issue)),
+        None => {
+          warn!("{} was synced with #{} which is gone", ticket.short_id, id);
+          continue;
+        }
+      },
+      // Closed tickets that were never synced would only be noise on the
+      // remote
+      None if ticket.status == Status::Closed => continue,
+      None => {
+        let id = provider.create(&ticket.title, &ticket.description)?;
+        println!("Opened #{} for {}: {}", id, ticket.short_id, ticket.title);
+        let _ = remote.ids.insert(ticket.id, id.clone());
+        (id, None)
+      }
+    };
+
+    let mut changed = false;
+    if let Some(issue) = issue {
+      // Without a base neither side looks unchanged so push only remotes
+      // always take the ticket's side
+      let base = remote.synced.get(&ticket.id).filter(|_| both).cloned();
+      let title = merge(
+        base.as_ref().map(|base| base.title.as_str()),
+        &ticket.title,
+        &issue.title,
+        prefer,
+        false,
+      );
+      let description = merge(
+        base.as_ref().map(|base| base.description.as_str()),
+        &ticket.description,
+        &issue.description,
+        prefer,
+        true,
+      );
+      let closed = ticket.status == Status::Closed;
+      let state = merge_state(
+        base.as_ref().and_then(|base| base.closed),
+        closed,
+        issue.closed,
+        prefer,
+      );
+      for (field, merged) in &[
+        ("title", title.is_some()),
+        ("description", description.is_some()),
+        ("state", state.is_some()),
+      ] {
+        if !merged {
+          conflicts += 1;
+          println!(
+            "{} The {} of {} and #{} were both changed and can't be merged",
+            "Conflict:".red().bold(),
+            field,
+            ticket.short_id,
+            id
+          );
+        }
+      }
+      if let Some(title) = title.as_ref().filter(|t| **t != ticket.title) {
+        ticket.record(format!(
+          "Renamed the ticket from '{}' to '{}' to match {} issue #{}",
+          ticket.title, title, remote.kind, id
+        ));
+        ticket.title = title.clone();
+        changed = true;
+      }
+      if let Some(description) = description
+        .as_ref()
+        .filter(|description| **description != ticket.description)
+      {
+        ticket.record(format!(
+          "Took the description from {} issue #{}",
+          remote.kind, id
+        ));
+        ticket.description = description.clone();
+        changed = true;
+      }
+      if state.is_some_and(|state| state != closed) {
+        match_state(&mut ticket, &issue, remote.kind, &maintainers);
+        changed = true;
+      }
+      // Fields that conflict keep what they were last synced as so they
+      // still conflict next time rather than one side quietly winning
+      let synced = match (&title, &description, base) {
+        (Some(title), Some(description), base) => Some(Synced {
+          title: title.clone(),
+          description: description.clone(),
+          closed: state.or(base.and_then(|base| base.closed)),
+        }),
+        (title, description, Some(base)) => Some(Synced {
+          title: title.clone().unwrap_or(base.title),
+          description: description.clone().unwrap_or(base.description),
+          closed: state.or(base.closed),
+        }),
+        _ => None,
+      };
+      if let Some(synced) = synced {
+        let _ = remote.synced.insert(ticket.id, synced);
+      }
+      // The remote keeps its side of anything that conflicts
+      let title = title.as_ref().unwrap_or(&issue.title);
+      let description = description.as_ref().unwrap_or(&issue.description);
+      if *title != issue.title || *description != issue.description {
+        provider.update(&id, title, description)?;
+        println!("Updated #{} from {}", id, ticket.short_id);
+      }
+      match state {
+        Some(true) if !issue.closed => {
+          provider.close(&id)?;
+          println!("Closed #{} to match {}", id, ticket.short_id);
+        }
+        Some(false) if issue.closed => {
+          provider.reopen(&id)?;
+          println!("Reopened #{} to match {}", id, ticket.short_id);
+        }
+        _ => (),
+      }
+    } else {
+      let _ = remote.synced.insert(
+        ticket.id,
+        Synced {
+          title: ticket.title.clone(),
+          description: ticket.description.clone(),
+          closed: Some(ticket.status == Status::Closed),
+        },
+      );
+    }
+
+    if both {
+      let comments = pull_comments(&*provider, &id, &ticket, &mut remote)?;
+      if !comments.is_empty() {
+        println!(
+          "Pulled {} comments from #{} to {}",
+          comments.len(),
+          id,
+          ticket.short_id
+        );
+        ticket.record(format!(
+          "Mirrored {} comments from {} issue #{}",
+          comments.len(),
+          remote.kind,
+          id
+        ));
+        for (uuid, comment) in comments {
+          add_comment(&mut ticket, uuid, comment, &maintainers);
+        }
+        changed = true;
+      }
+    }
+    let pushed = push_comments(&*provider, &id, &ticket, &mut remote)?;
+    if pushed > 0 {
+      println!(
+        "Pushed {} comments from {} to #{}",
+        pushed, ticket.short_id, id
+      );
+    }
+
+    if changed {
+      save_ticket(&ticket)?;
+    }
+    // Saved after every ticket so an error partway through doesn't lead to
+    // the same changes being made twice
+    save_remote(remote_name, &remote)?;
+  }
+
+  if both {
+    for (id, issue) in issues {
+      if linked(&remote, &id).is_some() {
+        continue;
+      }
+      import_issue(&*provider, issue, &mut remote, &maintainers)?;
+      save_remote(remote_name, &remote)?;
+    }
+  }
+
+  if conflicts > 0 {
+    bail!(
+      "{} conflicting changes were left alone. Change the tickets to what \
+       they should be and sync again with '--prefer local', or pass \
+       '--prefer remote' to take what's on the remote",
+      conflicts
+    );
+  }
+  info!("Synced with {}", remote.url);
+  Ok(())
+}
+
+/// Merges the changes made to one field of a ticket and its issue since they
+/// were last synced. If they can't be merged the side to prefer is taken, and
+/// if there isn't one nothing is returned. Fields that are many lines, like
+/// the description, are merged line by line so changes to different parts of
+/// it don't conflict.
+fn merge(
+  base: Option<&str>,
+  local: &str,
+  remote: &str,
+  prefer: Option<Side>,
+  lines: bool,
+) -> Option<String> {
+  let merged = match base {
+    _ if local == remote => Some(local.to_owned()),
+    Some(base) if base == local => Some(remote.to_owned()),
+    Some(base) if base == remote => Some(local.to_owned()),
+    Some(base) if lines => diffy::merge(base, local, remote).ok(),
+    _ => None,
+  };
+  merged.or_else(|| {
+    prefer.map(|side| match side {
+      Side::Local => local.to_owned(),
+      Side::Remote => remote.to_owned(),
+    })
+  })
+}
+
+/// Merges whether a ticket and its issue are closed the same way [`merge`]
+/// does a field
+fn merge_state(
+  base: Option<bool>,
+  local: bool,
+  remote: bool,
+  prefer: Option<Side>,
+) -> Option<bool> {
+  match base {
+    _ if local == remote => Some(local),
+    Some(base) if base == local => Some(remote),
+    // Being a bool the only other way it changed is on the local side
+    Some(_) => Some(local),
+    None => prefer.map(|side| match side {
+      Side::Local => local,
+      Side::Remote => remote,
+    }),
+  }
+}
+
+/// Opens or closes the ticket to match its issue, closed by whoever closed
+/// the issue if they're a maintainer
+fn match_state(
+  ticket: &mut Ticket,
+  issue: &Issue,
+  kind: Kind,
+  maintainers: &[(String, Uuid)],
+) {
+  let status = if issue.closed {
+    Status::Closed
+  } else {
+    Status::Open
+  };
+  ticket.record(format!(
+    "Moved the ticket from {} to {} to match {} issue #{}",
+    ticket.status, status, kind, issue.id
+  ));
+  if issue.closed {
+    ticket.closed_at = Some(issue.closed_at.unwrap_or_else(Utc::now));
+    ticket.closed_by = issue.closed_by.as_ref().and_then(|user| {
+      find_maintainer(maintainers, &user.name, &user.username)
+    });
+  } else {
+    ticket.closed_at = None;
+    ticket.closed_by = None;
+  }
+  println!(
+    "Moved {} to {} to match #{}",
+    ticket.short_id, status, issue.id
+  );
+  ticket.status = status;
+}
+
+/// The ticket the issue is synced with
+fn linked(remote: &Remote, issue: &str) -> Option<Uuid> {
+  remote
+    .ids
+    .iter()
+    .find(|(_, id)| *id == issue)
+    .map(|(uuid, _)| *uuid)
+}
+
+/// The maintainers in the repo config, who people on remotes are matched to
+fn maintainers() -> Vec<(String, Uuid)> {
+  get_repo_config()
+    .map(|config| config.maintainers)
+    .unwrap_or_default()
+}
+
+/// Turns an issue and its comments into a ticket and links the two in the
+/// remote. Users on the remote are matched to the maintainers in the repo
+/// config by their name or username, and anyone who isn't a maintainer is left
+/// off of the assignees.
+fn import_issue(
+  provider: &dyn Provider,
+  issue: Issue,
+  remote: &mut Remote,
+  maintainers: &[(String, Uuid)],
+) -> Result<()> {
+  let find =
+    |user: &User| find_maintainer(maintainers, &user.name, &user.username);
+
+  let mut ticket = new_ticket(issue.title, issue.description)?;
+  ticket.record(format!("Imported from {} issue #{}", remote.kind, issue.id));
+  ticket.tags = issue.labels;
+  ticket.due = issue.due;
+  ticket.assignees = issue.assignees.iter().filter_map(find).collect();
+  if issue.closed {
+    ticket.status = Status::Closed;
+    ticket.closed_at = issue.closed_at;
+    ticket.closed_by = issue.closed_by.as_ref().and_then(find);
+  }
+  for (id, comment) in pull_comments(provider, &issue.id, &ticket, remote)? {
+    add_comment(&mut ticket, id, comment, maintainers);
+  }
+  ticket.created_at = Some(issue.created_at);
+  ticket.updated_at = Some(issue.updated_at);
+  save_ticket(&ticket)?;
+  ticket.log_created();
+  println!(
+    "Imported #{} as {}: {}",
+    issue.id, ticket.short_id, ticket.title
+  );
+  warn_missing_fields(&ticket);
+  let _ = remote.ids.insert(ticket.id, issue.id);
+  let _ = remote.synced.insert(
+    ticket.id,
+    Synced {
+      title: ticket.title.clone(),
+      description: ticket.description.clone(),
+      closed: Some(issue.closed),
+    },
+  );
+  Ok(())
+}
+
+/// The comments on the issue that aren't on the ticket yet, each with the id
+/// it will have on the ticket, which are recorded in the remote as synced
+fn pull_comments(
+  provider: &dyn Provider,
+  issue: &str,
+  ticket: &Ticket,
+  remote: &mut Remote,
+) -> Result<Vec<(Uuid, IssueComment)>> {
+  let mut pulled = Vec::new();
+  for comment in provider.comments(issue)? {
+    if remote.comments.values().any(|id| *id == comment.id) {
+      continue;
+    }
+    let uuid = uuid_v1()?;
+    trace!("Pulling comment {} into {}", comment.id, ticket.short_id);
+    let _ = remote.comments.insert(uuid, comment.id.clone());
+    pulled.push((uuid, comment));
+  }
+  Ok(pulled)
+}
+
+/// Adds a comment from a remote to the ticket. Anyone who isn't a maintainer
+/// gets a nil uuid.
+fn add_comment(
+  ticket: &mut Ticket,
+  id: Uuid,
+  comment: IssueComment,
+  maintainers: &[(String, Uuid)],
+) {
+  let (by, name) = find_maintainer(
+    maintainers,
+    &comment.author.name,
+    &comment.author.username,
+  )
+  .unwrap_or_else(|| (Uuid::nil(), Name(comment.author.name.clone())));
+  let _ = ticket
+    .comments
+    .insert(id, (by, name, Comment(comment.body)));
+  let _ = ticket.comment_times.insert(id, comment.created_at);
+}
+
+/// Posts each comment on the ticket that isn't on its issue yet, oldest first,
+/// and returns how many there were
+fn push_comments(
+  provider: &dyn Provider,
+  issue: &str,
+  ticket: &Ticket,
+  remote: &mut Remote,
+) -> Result<usize> {
+  let mut pushed = 0;
+  for (id, (_, name, comment)) in ticket.comments_in_order() {
+    if remote.comments.contains_key(id) {
+      continue;
+    }
+    let body = format!("**{}** commented:\n\n{}", name.0, comment.0);
+    let _ = remote.comments.insert(*id, provider.comment(issue, &body)?);
+    pushed += 1;
+  }
+  Ok(pushed)
+}
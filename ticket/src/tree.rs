@@ -0,0 +1,113 @@
+//! Draws the tickets `ticket list` found as a tree of what blocks what
+use crate::{
+  list_line,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use std::{
+  collections::BTreeMap,
+  str::FromStr,
+};
+use uuid::Uuid;
+
+/// How `ticket list --tree` draws the tree
+#[derive(Debug, Clone, Copy)]
+pub enum TreeFormat {
+  /// A tree drawn with box drawing characters for the terminal
+  Human,
+  /// A graph in Graphviz's DOT language
+  Dot,
+}
+
+impl FromStr for TreeFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.to_lowercase().as_str() {
+      "human" => Ok(TreeFormat::Human),
+      "dot" => Ok(TreeFormat::Dot),
+      _ => bail!("'{}' is not a tree format, expected 'human' or 'dot'", s),
+    }
+  }
+}
+
+/// Prints the tickets with each one under the tickets it blocks. Blockers that
+/// aren't in `tickets`, like closed ones, are left out.
+pub fn tree(tickets: &[Ticket], format: TreeFormat, all: bool) {
+  let by_id = tickets
+    .iter()
+    .map(|t| (t.id, t))
+    .collect::<BTreeMap<Uuid, &Ticket>>();
+  match format {
+    TreeFormat::Human => {
+      // Tickets that don't block anything that's shown are the roots
+      for ticket in tickets
+        .iter()
+        .filter(|t| !t.blocks.iter().any(|id| by_id.contains_key(id)))
+      {
+        println!("{}", list_line(ticket, all));
+        branches(ticket, &by_id, all, "", &mut vec![ticket.id]);
+      }
+    }
+    TreeFormat::Dot => {
+      println!("digraph tickets {{");
+      for ticket in tickets {
+        println!(
+          "  \"{}\" [label=\"{} {}\"];",
+          ticket.short_id,
+          ticket.short_id,
+          escape(&ticket.title)
+        );
+      }
+      for ticket in tickets {
+        for blocker in ticket.blocked_by.iter().filter_map(|id| by_id.get(id)) {
+          println!("  \"{}\" -> \"{}\";", blocker.short_id, ticket.short_id);
+        }
+      }
+      println!("}}");
+    }
+  }
+}
+
+/// Prints the blockers of a ticket below it. `path` is every ticket above it
+/// so a cycle from hand edited tickets can't recurse forever.
+fn branches(
+  ticket: &Ticket,
+  by_id: &BTreeMap<Uuid, &Ticket>,
+  all: bool,
+  prefix: &str,
+  path: &mut Vec<Uuid>,
+) {
+  let blockers = ticket
+    .blocked_by
+    .iter()
+    .filter(|id| !path.contains(id))
+    .filter_map(|id| by_id.get(id))
+    .collect::<Vec<_>>();
+  for (i, blocker) in blockers.iter().enumerate() {
+    let last = i + 1 == blockers.len();
+    println!(
+      "{}{}{}",
+      prefix,
+      if last { "└── " } else { "├── " },
+      list_line(blocker, all)
+    );
+    path.push(blocker.id);
+    branches(
+      blocker,
+      by_id,
+      all,
+      &format!("{}{}", prefix, if last { "    " } else { "│   " }),
+      path,
+    );
+    let _ = path.pop();
+  }
+}
+
+/// Escapes a string to go between quotes in DOT
+fn escape(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"")
+}
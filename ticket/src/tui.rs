@@ -1,18 +1,38 @@
 use crate::{
   actions::{
-    get_closed_tickets,
-    get_open_tickets,
+    component_owners,
+    default_assignee,
+    get_template,
+    get_tickets_with,
+    require_fields,
     save_ticket,
-    uuid_v1,
+    statuses,
+    templates,
   },
+  ago,
+  edit_description,
+  hooks,
+  new_ticket,
+  sort_tickets,
+  state::UserState,
+  webhook,
   Comment,
+  Estimate,
+  FieldValue,
   Name,
-  Status,
+  Relation,
+  Sort,
   Ticket,
 };
-use anyhow::Result;
+use anyhow::{
+  bail,
+  Result,
+};
+use chrono::Local;
 use configamajig::{
+  get_repo_config,
   get_user_config,
+  Field,
   UserConfig,
 };
 use crossterm::{
@@ -24,10 +44,13 @@ use crossterm::{
     Event as CEvent,
     KeyCode,
     KeyEvent,
+    KeyModifiers,
   },
+  execute,
   queue,
   terminal::*,
 };
+use log::*;
 use std::{
   collections::BTreeMap,
   io::{
@@ -35,14 +58,24 @@ use std::{
     BufWriter,
     Write,
   },
+  iter,
   panic,
-  sync::mpsc::{
-    self,
-    Receiver,
-    Sender,
+  sync::{
+    atomic::{
+      AtomicBool,
+      Ordering,
+    },
+    mpsc::{
+      self,
+      Receiver,
+    },
+    Arc,
   },
   thread,
-  time::Duration,
+  time::{
+    Duration,
+    Instant,
+  },
 };
 use tui::{
   backend::{
@@ -66,6 +99,7 @@ use tui::{
     Borders,
     Paragraph,
     Row,
+    SelectableList,
     Table,
     Tabs,
     Text,
@@ -74,6 +108,7 @@ use tui::{
   Frame,
   Terminal,
 };
+use uuid::Uuid;
 
 pub struct TabsState<'a> {
   pub titles: Vec<&'a str>,
@@ -97,15 +132,22 @@ impl<'a> TabsState<'a> {
     }
   }
 }
+/// How long toasts stay up for
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
 pub enum Event<I> {
   Input(I),
   Tick,
+  /// The input thread stopped reading input so another program can use the
+  /// terminal
+  Paused,
 }
 
 pub struct TicketState {
   pub tickets: BTreeMap<String, Vec<(Ticket, String)>>,
   pub index: usize,
-  pub status: Status,
+  /// The name of the status whose tab is selected
+  pub status: String,
 }
 
 impl TicketState {
@@ -113,15 +155,12 @@ impl TicketState {
     Self {
       tickets,
       index: 0,
-      status: Status::Open,
+      status: "Open".into(),
     }
   }
 
   fn len(&self) -> usize {
-    match self.status {
-      Status::Open => self.tickets.get("Open").unwrap().len(),
-      Status::Closed => self.tickets.get("Closed").unwrap().len(),
-    }
+    self.tickets.get(&self.status).map_or(0, Vec::len)
   }
 
   pub fn next(&mut self) {
@@ -140,6 +179,197 @@ struct App<'a> {
   tabs: TabsState<'a>,
   tickets: TicketState,
   should_quit: bool,
+  /// Set after saving on exit failed so that exiting again doesn't try to save
+  discard_changes: bool,
+  toast: Option<Toast>,
+  /// The new ticket being made, if the wizard is open
+  wizard: Option<Wizard>,
+  /// Decrypted copies of the encrypted tickets to show in their place. They're
+  /// never saved.
+  revealed: BTreeMap<Uuid, Ticket>,
+}
+
+/// The steps of the new ticket wizard in the order they're shown
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+  Template,
+  Title,
+  Tags,
+  Estimate,
+  /// Only shown if the repo config has components
+  Component,
+  Assignee,
+  /// Each custom field from the repo config by its index, if there are any
+  Field(usize),
+  /// The description is written in $EDITOR outside of the tui
+  Description,
+}
+
+/// What's been filled in for a ticket made with the wizard
+struct Wizard {
+  step: Step,
+  /// The templates to start the description from, with 'None' first
+  templates: Vec<String>,
+  /// The components from the repo config, with 'None' first
+  components: Vec<String>,
+  /// Who the ticket can be assigned to, with 'Unassigned' first
+  assignees: Vec<String>,
+  assignee_ids: Vec<Option<Uuid>>,
+  /// Where the repo's default assignee is in `assignees`
+  default_assignee: usize,
+  /// The selected item on the steps that pick from a list
+  selected: usize,
+  template: Option<String>,
+  title: String,
+  tags: String,
+  /// The estimate typed in, which is checked before moving on
+  estimate: String,
+  component: Option<String>,
+  assignee: Option<(Uuid, String)>,
+  /// The custom fields from the repo config
+  fields: Vec<Field>,
+  /// The value typed in for the current field, which is checked before moving
+  /// on
+  field: String,
+  values: BTreeMap<String, FieldValue>,
+}
+
+impl Wizard {
+  fn new(user_config: &UserConfig) -> Result<Self> {
+    let (mut people, components, fields) = get_repo_config()
+      .map(|config| (config.maintainers, config.components, config.fields))
+      .unwrap_or_default();
+    if !people.iter().any(|(_, uuid)| *uuid == user_config.uuid) {
+      people.push((user_config.name.clone(), user_config.uuid));
+    }
+    let default_assignee = default_assignee()?.map_or(0, |(default, _)| {
+      people
+        .iter()
+        .position(|(_, uuid)| *uuid == default)
+        .map_or(0, |i| i + 1)
+    });
+    Ok(Self {
+      step: Step::Template,
+      templates: iter::once("None".to_owned()).chain(templates()?).collect(),
+      components: iter::once("None".to_owned())
+        .chain(components.into_keys())
+        .collect(),
+      assignees: iter::once("Unassigned".to_owned())
+        .chain(people.iter().map(|(name, _)| name.clone()))
+        .collect(),
+      assignee_ids: iter::once(None)
+        .chain(people.iter().map(|(_, uuid)| Some(*uuid)))
+        .collect(),
+      default_assignee,
+      selected: 0,
+      template: None,
+      title: String::new(),
+      tags: String::new(),
+      estimate: String::new(),
+      component: None,
+      assignee: None,
+      fields,
+      field: String::new(),
+      values: BTreeMap::new(),
+    })
+  }
+
+  /// The step for the custom field at the index, or the description once
+  /// every field has been asked for
+  fn field_step(&self, index: usize) -> Step {
+    if index < self.fields.len() {
+      Step::Field(index)
+    } else {
+      Step::Description
+    }
+  }
+
+  /// What to ask for the custom field, like `ticket new` does
+  fn field_prompt(&self, index: usize) -> String {
+    let field = &self.fields[index];
+    format!(
+      "{} ({}{})",
+      field.name,
+      if field.values.is_empty() {
+        field.kind.to_string()
+      } else {
+        field.values.join("/")
+      },
+      if field.required { "" } else { ", optional" }
+    )
+  }
+
+  /// Checks the value typed in for the custom field at the index and keeps it
+  fn set_field(&mut self, index: usize) -> Result<()> {
+    let field = &self.fields[index];
+    if !self.field.trim().is_empty() {
+      let value = FieldValue::parse(field, &self.field)?;
+      let _ = self.values.insert(field.name.clone(), value);
+    } else if field.required {
+      bail!("{} is required", field.name);
+    }
+    self.field.clear();
+    Ok(())
+  }
+
+  /// The tags typed in, split on commas or spaces
+  fn tags(&self) -> Vec<String> {
+    let mut tags = self
+      .tags
+      .split(|c: char| c.is_whitespace() || c == ',')
+      .filter(|t| !t.is_empty())
+      .map(ToOwned::to_owned)
+      .collect::<Vec<String>>();
+    tags.sort();
+    tags.dedup();
+    tags
+  }
+
+  /// The estimate typed in, if there was one
+  fn estimate(&self) -> Result<Option<Estimate>> {
+    match self.estimate.trim() {
+      "" => Ok(None),
+      estimate => estimate.parse().map(Some),
+    }
+  }
+
+  /// Where the assignee to preselect is in `assignees`, the first owner of
+  /// the component if it has one and otherwise the repo's default assignee
+  fn suggested_assignee(&self) -> usize {
+    self
+      .component
+      .as_deref()
+      .and_then(|component| component_owners(component).ok())
+      .and_then(|owners| {
+        let (owner, _) = owners.first()?;
+        self.assignee_ids.iter().position(|id| *id == Some(*owner))
+      })
+      .unwrap_or(self.default_assignee)
+  }
+}
+
+/// A message shown in place of the instructions for a few seconds, used for
+/// errors the tui can recover from
+struct Toast {
+  message: String,
+  shown_at: Instant,
+}
+
+impl<'a> App<'a> {
+  fn toast(&mut self, message: impl Into<String>) {
+    self.toast = Some(Toast {
+      message: message.into(),
+      shown_at: Instant::now(),
+    });
+  }
+
+  /// Writes every ticket back to disk
+  fn save(&self) -> Result<()> {
+    for t in self.tickets.tickets.values().flatten() {
+      save_ticket(&t.0)?;
+    }
+    Ok(())
+  }
 }
 #[derive(Debug, Clone, Copy)]
 pub struct Config {
@@ -156,8 +386,63 @@ impl Default for Config {
   }
 }
 
-#[allow(clippy::too_many_lines)]
-pub fn run() -> Result<()> {
+pub fn run(
+  include_snoozed: bool,
+  sort: Option<Sort>,
+  reverse: bool,
+) -> Result<()> {
+  let user_state = UserState::load()?;
+  let visible = |t: &Ticket| include_snoozed || !user_state.is_snoozed(t.id);
+
+  // There's a tab for each status, including the ones from the repo config
+  let statuses = statuses()?;
+  let names = statuses
+    .iter()
+    .map(ToString::to_string)
+    .collect::<Vec<String>>();
+
+  // App
+  let mut app = App {
+    tabs: TabsState::new(names.iter().map(String::as_str).collect()),
+    tickets: {
+      let mut map = BTreeMap::new();
+      for status in &statuses {
+        let mut tickets = get_tickets_with(status)?
+          .into_iter()
+          .filter(|t| visible(t))
+          .collect::<Vec<Ticket>>();
+        if let Some(sort) = sort {
+          sort_tickets(&mut tickets, sort, reverse)?;
+        }
+        let _ = map.insert(
+          status.to_string(),
+          tickets.into_iter().map(|i| (i, String::new())).collect(),
+        );
+      }
+      TicketState::new(map)
+    },
+    should_quit: false,
+    discard_changes: false,
+    toast: None,
+    wizard: None,
+    revealed: BTreeMap::new(),
+  };
+  // Decrypted before the terminal is taken over in case gpg asks for a
+  // passphrase
+  for (ticket, _) in app.tickets.tickets.values().flatten() {
+    if ticket.encrypted {
+      match ticket.revealed() {
+        Ok(revealed) => {
+          let _ = app.revealed.insert(ticket.id, revealed);
+        }
+        Err(e) => warn!("Unable to decrypt {}: {}", ticket.short_id, e),
+      }
+    }
+  }
+
+  // Cached Values
+  let user_config = get_user_config()?;
+
   let stdout = io::stdout();
   let mut lock = BufWriter::new(stdout.lock());
   // Terminal initialization
@@ -170,15 +455,15 @@ pub fn run() -> Result<()> {
   // Setup panic handler so that screen gets reset properly
   let old_hook = panic::take_hook();
   panic::set_hook(Box::new(move |panic_info| {
-    // Clean up terminal
-    queue!(
-      io::stdout().lock(),
+    // Clean up terminal. Errors are ignored since panicking again in here
+    // would abort and leave the terminal broken
+    let _ = execute!(
+      io::stdout(),
       LeaveAlternateScreen,
       DisableMouseCapture,
       cursor::Show
-    )
-    .unwrap();
-    disable_raw_mode().unwrap();
+    );
+    let _ = disable_raw_mode();
     // Load bearing println's without them the actual panic from the old hook
     // Isn't flushed to the terminal
     if let Some(location) = panic_info.location() {
@@ -193,35 +478,38 @@ pub fn run() -> Result<()> {
     old_hook(panic_info);
   }));
 
-  // App
-  let mut app = App {
-    tabs: TabsState::new(vec!["Open", "Closed"]),
-    tickets: {
-      let mut map = BTreeMap::new();
-      let _ = map.insert(
-        "Open".into(),
-        get_open_tickets()?
-          .into_iter()
-          .map(|i| (i, String::new()))
-          .collect(),
-      );
-      let _ = map.insert(
-        "Closed".into(),
-        get_closed_tickets()?
-          .into_iter()
-          .map(|i| (i, String::new()))
-          .collect(),
-      );
-      TicketState::new(map)
-    },
-    should_quit: false,
-  };
+  let result = event_loop(&mut terminal, &mut app, &user_config);
+
+  // Clean up terminal even if the loop failed so it isn't left in raw mode
+  Backend::flush(terminal.backend_mut())?;
+  execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+  terminal.backend_mut().show_cursor()?;
+  disable_raw_mode()?;
 
+  result
+}
+
+#[allow(clippy::too_many_lines)]
+fn event_loop(
+  terminal: &mut Terminal<impl Backend + Write>,
+  app: &mut App,
+  user_config: &UserConfig,
+) -> Result<()> {
   // Spawn event sender thread
   let (tx, rx) = mpsc::channel();
   let (tx_close, rx_close) = mpsc::channel();
+  let paused = Arc::new(AtomicBool::new(false));
+  let input_paused = Arc::clone(&paused);
   let _ = thread::spawn(move || -> Result<()> {
     loop {
+      // Reading input while $EDITOR is open would steal its key presses
+      if input_paused.load(Ordering::SeqCst) {
+        tx.send(Event::Paused)?;
+        while input_paused.load(Ordering::SeqCst) {
+          thread::sleep(Duration::from_millis(50));
+        }
+      }
+
       // poll for tick rate duration, if no events, sent tick event.
       if event::poll(Duration::from_millis(250))? {
         if let CEvent::Key(key) = event::read()? {
@@ -238,15 +526,9 @@ pub fn run() -> Result<()> {
     Ok(())
   });
 
-  // Cached Values
-  let user_config = get_user_config()?;
-
   // Main drawing and event receiving loop
   loop {
-    let status = match app.tickets.status {
-      Status::Open => "Open",
-      Status::Closed => "Closed",
-    };
+    let status = app.tabs.titles[app.tabs.index];
 
     terminal.draw(|mut f| {
       let size = f.size();
@@ -272,65 +554,92 @@ pub fn run() -> Result<()> {
           x: size.x,
           y: size.y,
           width: size.width,
-          height: size.height - 3,
+          height: size.height.saturating_sub(3),
         });
       app.tabs(&mut f, vertical[0]);
       app.table(status, &mut f, horizontal[0]);
-      app.description(status, &mut f, horizontal[1]);
+      match &app.wizard {
+        Some(wizard) => App::wizard(wizard, &mut f, horizontal[1]),
+        None => app.description(status, &mut f, horizontal[1]),
+      }
       app.comment(status, &mut f, vertical[2]);
-      App::instructions(&mut f, vertical[3]);
+      match &app.toast {
+        Some(toast) => App::toast_message(&toast.message, &mut f, vertical[3]),
+        None if app.wizard.is_some() => {
+          App::wizard_instructions(&mut f, vertical[3])
+        }
+        None => App::instructions(&mut f, vertical[3]),
+      }
     })?;
 
-    handle_event(&rx, &tx_close, &mut app, &user_config, &status)?;
+    handle_event(&rx, app, user_config, status)?;
+
+    if app
+      .wizard
+      .as_ref()
+      .is_some_and(|w| w.step == Step::Description)
+    {
+      paused.store(true, Ordering::SeqCst);
+      while let Ok(event) = rx.recv() {
+        if let Event::Paused = event {
+          break;
+        }
+      }
+      let result = finish_wizard(terminal, app);
+      paused.store(false, Ordering::SeqCst);
+      if let Err(e) = result {
+        app.toast(format!("Failed to create the ticket: {}", e));
+      }
+    }
 
     if app.should_quit {
-      let open = app.tickets.tickets["Open"].iter();
-      let closed = app.tickets.tickets["Closed"].iter();
-      for t in open.chain(closed) {
-        save_ticket(&t.0)?;
+      if app.discard_changes {
+        break;
+      }
+      match app.save() {
+        Ok(()) => break,
+        Err(e) => {
+          app.should_quit = false;
+          app.discard_changes = true;
+          app.toast(format!(
+            "Failed to save the tickets: {}. Press ESC again to exit without \
+             saving",
+            e
+          ));
+        }
       }
-      break;
     }
   }
-
-  // Clean up terminal
-  queue!(
-    io::stdout().lock(),
-    LeaveAlternateScreen,
-    DisableMouseCapture
-  )?;
-  terminal.backend_mut().show_cursor()?;
-  disable_raw_mode()?;
+  tx_close.send(true)?;
 
   Ok(())
 }
 
 fn handle_event(
   rx: &Receiver<Event<KeyEvent>>,
-  tx: &Sender<bool>,
   app: &mut App,
   user_config: &UserConfig,
   status: &str,
 ) -> Result<()> {
   match rx.recv()? {
+    Event::Input(event) if app.wizard.is_some() => wizard_input(app, event),
     Event::Input(event) => match event.code {
-      KeyCode::Esc => {
-        app.should_quit = true;
-        tx.send(true)?;
-      }
-      KeyCode::Right => {
-        if app.tabs.index == 0 {
-          app.tickets.status = Status::Closed;
-          app.tickets.index = 0;
+      KeyCode::Char('n') if event.modifiers == KeyModifiers::CONTROL => {
+        match Wizard::new(user_config) {
+          Ok(wizard) => app.wizard = Some(wizard),
+          Err(e) => app.toast(format!("Unable to make a ticket: {}", e)),
         }
+      }
+      KeyCode::Esc => app.should_quit = true,
+      KeyCode::Right if app.tabs.index + 1 < app.tabs.titles.len() => {
         app.tabs.next();
+        app.tickets.status = app.tabs.titles[app.tabs.index].to_owned();
+        app.tickets.index = 0;
       }
-      KeyCode::Left => {
-        if app.tabs.index > 0 {
-          app.tickets.status = Status::Open;
-          app.tickets.index = 0;
-        }
+      KeyCode::Left if app.tabs.index > 0 => {
         app.tabs.previous();
+        app.tickets.status = app.tabs.titles[app.tabs.index].to_owned();
+        app.tickets.index = 0;
       }
       KeyCode::Up => app.tickets.previous(),
       KeyCode::Down => app.tickets.next(),
@@ -354,30 +663,212 @@ fn handle_event(
           let ticket = &mut app.tickets.tickets.get_mut(status).unwrap()
             [app.tickets.index];
           if !ticket.1.is_empty() {
-            let _ = ticket.0.comments.insert(
-              uuid_v1()?,
-              (
-                user_config.uuid,
-                Name(user_config.name.clone()),
-                Comment(ticket.1.clone()),
-              ),
-            );
-            ticket.1.clear();
+            match ticket.0.add_comment(
+              user_config.uuid,
+              user_config.name.clone(),
+              ticket.1.clone(),
+            ) {
+              Ok(id) => {
+                // The decrypted copy gets the comment as it was written
+                let comment = ticket.0.comments.get(&id).cloned();
+                let shown = app.revealed.get_mut(&ticket.0.id);
+                if let (Some(shown), Some((by, name, _))) = (shown, comment) {
+                  let _ = shown
+                    .comments
+                    .insert(id, (by, name, Comment(ticket.1.clone())));
+                  shown.comment_times = ticket.0.comment_times.clone();
+                }
+                let failed =
+                  notify(webhook::Event::Commented, &ticket.0, Some(&ticket.1));
+                ticket.1.clear();
+                if let Some(failed) = failed {
+                  app.toast(failed);
+                }
+              }
+              Err(e) => app.toast(format!("Failed to add the comment: {}", e)),
+            }
           }
         }
       }
       _ => {}
     },
-    Event::Tick => (),
+    Event::Paused => {}
+    Event::Tick => {
+      if app
+        .toast
+        .as_ref()
+        .is_some_and(|t| t.shown_at.elapsed() > TOAST_DURATION)
+      {
+        app.toast = None;
+      }
+    }
   }
   Ok(())
 }
 
+/// Moves the wizard along with each key press until it's time to write the
+/// description
+fn wizard_input(app: &mut App, event: KeyEvent) {
+  let wizard = match &mut app.wizard {
+    Some(wizard) => wizard,
+    None => return,
+  };
+  let list_len = match wizard.step {
+    Step::Template => wizard.templates.len(),
+    Step::Component => wizard.components.len(),
+    Step::Assignee => wizard.assignees.len(),
+    _ => 0,
+  };
+  let input = match wizard.step {
+    Step::Title => Some(&mut wizard.title),
+    Step::Tags => Some(&mut wizard.tags),
+    Step::Estimate => Some(&mut wizard.estimate),
+    Step::Field(_) => Some(&mut wizard.field),
+    _ => None,
+  };
+  match (event.code, input) {
+    (KeyCode::Esc, _) => app.wizard = None,
+    (KeyCode::Char(c), Some(input)) => input.push(c),
+    (KeyCode::Backspace, Some(input)) => {
+      let _ = input.pop();
+    }
+    (KeyCode::Up, None) if wizard.selected > 0 => wizard.selected -= 1,
+    (KeyCode::Down, None) if wizard.selected + 1 < list_len => {
+      wizard.selected += 1
+    }
+    (KeyCode::Enter, _) => match wizard.step {
+      Step::Template => {
+        wizard.template = Some(wizard.templates[wizard.selected].clone())
+          .filter(|_| wizard.selected > 0);
+        wizard.selected = 0;
+        wizard.step = Step::Title;
+      }
+      Step::Title if wizard.title.trim().is_empty() => {
+        app.toast("Title may not be empty")
+      }
+      Step::Title => wizard.step = Step::Tags,
+      Step::Tags => wizard.step = Step::Estimate,
+      Step::Estimate => match wizard.estimate() {
+        Ok(_) if wizard.components.len() > 1 => {
+          wizard.selected = 0;
+          wizard.step = Step::Component;
+        }
+        Ok(_) => {
+          wizard.selected = wizard.suggested_assignee();
+          wizard.step = Step::Assignee;
+        }
+        Err(e) => app.toast(e.to_string()),
+      },
+      Step::Component => {
+        wizard.component = Some(wizard.components[wizard.selected].clone())
+          .filter(|_| wizard.selected > 0);
+        wizard.selected = wizard.suggested_assignee();
+        wizard.step = Step::Assignee;
+      }
+      Step::Assignee => {
+        wizard.assignee = wizard.assignee_ids[wizard.selected]
+          .map(|uuid| (uuid, wizard.assignees[wizard.selected].clone()));
+        wizard.step = wizard.field_step(0);
+      }
+      Step::Field(index) => match wizard.set_field(index) {
+        Ok(()) => wizard.step = wizard.field_step(index + 1),
+        Err(e) => app.toast(e.to_string()),
+      },
+      Step::Description => {}
+    },
+    _ => {}
+  }
+}
+
+/// Leaves the tui to write the description in $EDITOR then saves the ticket
+/// from the wizard and selects it
+fn finish_wizard(
+  terminal: &mut Terminal<impl Backend + Write>,
+  app: &mut App,
+) -> Result<()> {
+  let wizard = match app.wizard.take() {
+    Some(wizard) => wizard,
+    None => return Ok(()),
+  };
+  require_fields(&wizard.values)?;
+  let template = match &wizard.template {
+    Some(template) => get_template(template)?,
+    None => String::new(),
+  };
+
+  queue!(
+    terminal.backend_mut(),
+    LeaveAlternateScreen,
+    DisableMouseCapture
+  )?;
+  Write::flush(terminal.backend_mut())?;
+  terminal.show_cursor()?;
+  disable_raw_mode()?;
+  let description = edit_description(&template);
+  enable_raw_mode()?;
+  queue!(
+    terminal.backend_mut(),
+    EnterAlternateScreen,
+    EnableMouseCapture
+  )?;
+  Write::flush(terminal.backend_mut())?;
+  terminal.hide_cursor()?;
+  terminal.clear()?;
+
+  let tags = wizard.tags();
+  let estimate = wizard.estimate()?;
+  let mut ticket = new_ticket(wizard.title, description?)?;
+  ticket.tags = tags;
+  ticket.estimate = estimate;
+  ticket.component = wizard.component;
+  ticket.fields = wizard.values;
+  if let Some((uuid, name)) = wizard.assignee {
+    ticket.assignees.push((uuid, Name(name)));
+  }
+  save_ticket(&ticket)?;
+  ticket.log_created();
+  if let Some(failed) = notify(webhook::Event::Created, &ticket, None) {
+    app.toast(failed);
+  }
+
+  if let Some(index) = app.tabs.titles.iter().position(|t| *t == "Open") {
+    app.tabs.index = index;
+  }
+  app.tickets.status = "Open".into();
+  let open = app.tickets.tickets.entry("Open".into()).or_default();
+  open.push((ticket, String::new()));
+  app.tickets.index = open.len() - 1;
+  Ok(())
+}
+
+/// Runs the hook for the event and sends its webhooks, each whether or not
+/// the other failed. Hooks print to the terminal so only what failed is
+/// returned, to be shown as a toast.
+fn notify(
+  event: webhook::Event,
+  ticket: &Ticket,
+  comment: Option<&str>,
+) -> Option<String> {
+  let failed = [
+    hooks::run(event, ticket, comment).err(),
+    webhook::send(event, ticket, comment).err(),
+  ]
+  .iter()
+  .flatten()
+  .map(ToString::to_string)
+  .collect::<Vec<_>>();
+  if failed.is_empty() {
+    None
+  } else {
+    Some(failed.join("\n"))
+  }
+}
+
 impl<'a> App<'a> {
   #[inline]
   fn table(&self, tab: &'a str, f: &mut Frame<impl Backend>, rect: Rect) {
     Table::new(
-      ["Id", "Title"].iter(),
+      ["Id", "Title", "Updated"].iter(),
       self
         .tickets
         .tickets
@@ -386,8 +877,12 @@ impl<'a> App<'a> {
         .iter()
         .enumerate()
         .map(move |(idx, i)| {
-          let data =
-            vec![i.0.id.to_string(), i.0.title.to_string()].into_iter();
+          let data = vec![
+            i.0.short_id.clone(),
+            i.0.title.to_string(),
+            i.0.last_activity().map_or_else(String::new, ago),
+          ]
+          .into_iter();
           let normal_style = Style::default().fg(Color::Yellow);
           let selected_style =
             Style::default().fg(Color::White).modifier(Modifier::BOLD);
@@ -400,7 +895,11 @@ impl<'a> App<'a> {
     )
     .block(Block::default().title(tab).borders(Borders::ALL))
     .header_style(Style::default().fg(Color::Yellow))
-    .widths(&[Constraint::Percentage(30), Constraint::Percentage(70)])
+    .widths(&[
+      Constraint::Percentage(20),
+      Constraint::Percentage(55),
+      Constraint::Percentage(25),
+    ])
     .style(Style::default().fg(Color::White))
     .column_spacing(1)
     .render(f, rect)
@@ -411,11 +910,12 @@ impl<'a> App<'a> {
     let mut description = vec![];
     for (idx, i) in self.tickets.tickets.get(tab).unwrap().iter().enumerate() {
       if idx == self.tickets.index {
+        let shown = self.revealed.get(&i.0.id).unwrap_or(&i.0);
         description = {
           let header = Style::default().fg(Color::Red).modifier(Modifier::BOLD);
           let mut desc = vec![
             Text::styled("Description\n-------------\n", header),
-            Text::raw(i.0.description.to_owned()),
+            Text::raw(shown.description.to_owned()),
           ];
           let name_style =
             Style::default().fg(Color::Cyan).modifier(Modifier::BOLD);
@@ -437,13 +937,96 @@ impl<'a> App<'a> {
             }
           }
 
-          if i.0.comments.is_empty() {
+          desc.push(Text::styled("\nTags\n----\n", header));
+          if !i.0.tags.is_empty() {
+            desc.push(Text::styled(
+              i.0.tags.join(", "),
+              Style::default().fg(Color::Yellow),
+            ));
+          }
+
+          if i.0.locked {
+            desc.push(Text::styled(
+              "\nLocked, so no more comments can be added\n",
+              Style::default().fg(Color::Red),
+            ));
+          }
+
+          if let Some(estimate) = i.0.estimate {
+            desc.push(Text::styled("\nEstimate\n--------\n", header));
+            desc.push(Text::raw(estimate.to_string()));
+          }
+
+          if let Some(component) = &i.0.component {
+            desc.push(Text::styled("\nComponent\n---------\n", header));
+            desc.push(Text::raw(component.clone()));
+          }
+
+          if !i.0.fields.is_empty() {
+            desc.push(Text::styled("\nFields\n------\n", header));
+            for (name, value) in &i.0.fields {
+              desc.push(Text::raw(format!("{}: {}\n", name, value)));
+            }
+          }
+
+          if !i.0.tasks.is_empty() {
+            let done = i.0.tasks.iter().filter(|t| t.done).count();
+            desc.push(Text::styled(
+              format!("\nTasks ({}/{})\n-----\n", done, i.0.tasks.len()),
+              header,
+            ));
+            for task in &i.0.tasks {
+              desc.push(Text::raw(format!(
+                "[{}] {}\n",
+                if task.done { "x" } else { " " },
+                task.text
+              )));
+            }
+          }
+
+          let related = Relation::ALL
+            .iter()
+            .flat_map(|r| i.0.related(*r).iter().map(move |id| (r, id)))
+            .collect::<Vec<_>>();
+          if !related.is_empty() {
+            desc.push(Text::styled("\nRelations\n---------\n", header));
+            for (relation, id) in related {
+              let other = self
+                .tickets
+                .tickets
+                .values()
+                .flatten()
+                .find(|(t, _)| t.id == *id)
+                .map_or_else(
+                  || id.to_string(),
+                  |(t, _)| format!("{} {}", t.short_id, t.title),
+                );
+              desc.push(Text::raw(format!("{} {}\n", relation.title(), other)));
+            }
+          }
+
+          if shown.comments.is_empty() {
             desc.push(Text::styled("\nComments\n--------\n", header));
           } else {
             desc.push(Text::styled("\nComments\n--------\n", header));
-            for (_, name, comment) in i.0.comments.values() {
-              desc.push(Text::styled(format!("{}\n", name.0), name_style));
-              desc.push(Text::raw(format!("{}\n\n", comment.0)));
+            for (id, (_, name, comment)) in shown.comments_in_order() {
+              desc.push(Text::styled(name.0.clone(), name_style));
+              if let Some(at) = shown.comment_time(id) {
+                desc.push(Text::styled(
+                  format!(
+                    " {}",
+                    at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                  ),
+                  Style::default().fg(Color::DarkGray),
+                ));
+              }
+              desc.push(Text::raw("\n"));
+              desc.push(Text::raw(format!("{}\n", comment.0)));
+              let reactions = i.0.reaction_summary(id);
+              if !reactions.is_empty() {
+                desc.push(Text::raw(format!("{}\n", reactions)));
+              }
+              desc.push(Text::raw("\n"));
             }
           }
           desc
@@ -475,6 +1058,84 @@ impl<'a> App<'a> {
       .render(f, rect);
   }
 
+  #[inline]
+  fn wizard(wizard: &Wizard, f: &mut Frame<impl Backend>, rect: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("New Ticket");
+    let highlight = Style::default().fg(Color::Yellow).modifier(Modifier::BOLD);
+    let field_prompt = match wizard.step {
+      Step::Field(index) => wizard.field_prompt(index),
+      _ => String::new(),
+    };
+    let (prompt, items) = match wizard.step {
+      Step::Template => (
+        "Start the description from a template",
+        Some(&wizard.templates),
+      ),
+      Step::Component => ("File the ticket under", Some(&wizard.components)),
+      Step::Assignee => ("Assign the ticket to", Some(&wizard.assignees)),
+      Step::Title => ("Title", None),
+      Step::Tags => ("Tags, separated by commas or spaces", None),
+      Step::Estimate => (
+        "Estimate in points like 3 or a time like 4h, 2d, or 1w, if any",
+        None,
+      ),
+      Step::Field(_) => (field_prompt.as_str(), None),
+      Step::Description => ("Writing the description in $EDITOR", None),
+    };
+    match items {
+      Some(items) => SelectableList::default()
+        .block(block.title(prompt))
+        .items(items)
+        .select(Some(wizard.selected))
+        .style(Style::default().fg(Color::White))
+        .highlight_style(highlight)
+        .highlight_symbol(">")
+        .render(f, rect),
+      None => {
+        let input = match wizard.step {
+          Step::Title => &wizard.title,
+          Step::Tags => &wizard.tags,
+          Step::Estimate => &wizard.estimate,
+          Step::Field(_) => &wizard.field,
+          _ => "",
+        };
+        Paragraph::new(
+          [
+            Text::styled(format!("{}\n", prompt), highlight),
+            Text::raw(format!("> {}", input)),
+          ]
+          .iter(),
+        )
+        .block(block)
+        .alignment(Alignment::Left)
+        .wrap(true)
+        .render(f, rect)
+      }
+    }
+  }
+
+  #[inline]
+  fn wizard_instructions(f: &mut Frame<impl Backend>, rect: Rect) {
+    let blue = Style::default().fg(Color::Blue).modifier(Modifier::BOLD);
+    Paragraph::new(
+      [
+        Text::Styled("[ESC] ".into(), blue),
+        Text::Raw("- Cancel ".into()),
+        Text::Styled("[Enter] ".into(), blue),
+        Text::Raw("- Next ".into()),
+        Text::Styled("[Up/Down] ".into(), blue),
+        Text::Raw("- Choose ".into()),
+        Text::Styled("[Char] ".into(), blue),
+        Text::Raw("- Type".into()),
+      ]
+      .iter(),
+    )
+    .block(Block::default().borders(Borders::ALL).title("Instructions"))
+    .alignment(Alignment::Left)
+    .wrap(true)
+    .render(f, rect);
+  }
+
   #[inline]
   fn tabs(&self, f: &mut Frame<impl Backend>, rect: Rect) {
     Tabs::default()
@@ -486,6 +1147,21 @@ impl<'a> App<'a> {
       .render(f, rect);
   }
 
+  #[inline]
+  fn toast_message(message: &str, f: &mut Frame<impl Backend>, rect: Rect) {
+    Paragraph::new(
+      [Text::styled(
+        message,
+        Style::default().fg(Color::Red).modifier(Modifier::BOLD),
+      )]
+      .iter(),
+    )
+    .block(Block::default().borders(Borders::ALL).title("Error"))
+    .alignment(Alignment::Left)
+    .wrap(true)
+    .render(f, rect);
+  }
+
   #[inline]
   fn instructions(f: &mut Frame<impl Backend>, rect: Rect) {
     let blue = Style::default().fg(Color::Blue).modifier(Modifier::BOLD);
@@ -498,7 +1174,9 @@ impl<'a> App<'a> {
         Text::Styled("[Char] ".into(), blue),
         Text::Raw("- Write a comment ".into()),
         Text::Styled("[Backspace] ".into(), blue),
-        Text::Raw("- Delete a character".into()),
+        Text::Raw("- Delete a character ".into()),
+        Text::Styled("[Ctrl-N] ".into(), blue),
+        Text::Raw("- New ticket".into()),
       ]
       .iter(),
     )
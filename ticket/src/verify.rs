@@ -0,0 +1,254 @@
+//! Checks that the comments and changes on tickets were signed by who they say
+//! made them, with the key the repo config has for them. It fails if anything
+//! is unsigned or badly signed so it can be run by a pre-receive hook to turn
+//! away pushes with forged comments. Since the pushed repo config can't be
+//! trusted to say what the keys are, they can be taken from a commit that's
+//! already been accepted instead, with any key added or changed since needing
+//! a signature from a key in it.
+use crate::{
+  actions::{
+    get_all_tickets,
+    get_archived_tickets,
+  },
+  crypt,
+  sign,
+  Redaction,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use chrono::prelude::*;
+use configamajig::{
+  get_repo_config,
+  get_user_config,
+  set_repo_config,
+  KeySignature,
+  RepoConfig,
+};
+use log::*;
+use std::{
+  collections::BTreeMap,
+  process::Command,
+};
+use uuid::Uuid;
+
+/// Checks the signatures of every comment and change made on or after
+/// `since`, or all of them if it's `None`. Redacted comments are skipped since
+/// their signature is dropped along with what they said, but only if they say
+/// they were redacted and the change redacting them is signed. Comments that
+/// can't be decrypted count as problems since there's no way to check them.
+/// With `keys_from` the keys are the ones from the repo config at that
+/// revision, plus the ones added or changed since that were vouched for by one
+/// of them.
+pub fn verify(since: Option<NaiveDate>, keys_from: Option<&str>) -> Result<()> {
+  let config = get_repo_config()?;
+  let (keys, mut problems) = match keys_from {
+    Some(rev) => keyring(&config, &trusted_config(rev)?.keys),
+    None => (config.keys, 0),
+  };
+  let checked = |at: Option<DateTime<Utc>>| {
+    since.is_none_or(|since| {
+      at.is_some_and(|at| at.with_timezone(&Local).date_naive() >= since)
+    })
+  };
+  let mut good = 0;
+  let tickets = get_all_tickets()?
+    .into_iter()
+    .chain(get_archived_tickets()?)
+    .collect::<Vec<Ticket>>();
+  for ticket in &tickets {
+    for (id, (by, _, comment)) in &ticket.comments {
+      if !checked(ticket.comment_time(id)) {
+        continue;
+      }
+      let what =
+        format!("Comment {} on {}", &id.to_string()[..8], ticket.short_id);
+      if let Some(redaction) =
+        ticket.redactions.iter().find(|r| r.comment == *id)
+      {
+        if let Some(problem) =
+          check_redaction(&keys, ticket, redaction, &comment.0)
+        {
+          warn!("{} {}", what, problem);
+          problems += 1;
+        }
+        continue;
+      }
+      // Encrypted comments were signed before they were encrypted
+      let text = match crypt::reveal(&comment.0) {
+        Ok(text) => text,
+        Err(e) => {
+          warn!(
+            "{} can't be checked since it can't be decrypted: {}",
+            what, e
+          );
+          problems += 1;
+          continue;
+        }
+      };
+      let payload = ticket.comment_payload(id, &text).unwrap_or_default();
+      match check(&keys, Some(by), &payload, ticket.signatures.get(id)) {
+        Some(problem) => {
+          warn!("{} {}", what, problem);
+          problems += 1;
+        }
+        None => good += 1,
+      }
+    }
+    for event in ticket.history.iter().filter(|e| checked(Some(e.at))) {
+      let by = event.by.as_ref().map(|(uuid, _)| uuid);
+      let payload = event.payload(&ticket.id);
+      match check(&keys, by, &payload, event.signature.as_ref()) {
+        Some(problem) => {
+          warn!(
+            "The change '{}' to {} {}",
+            event.action, ticket.short_id, problem
+          );
+          problems += 1;
+        }
+        None => good += 1,
+      }
+    }
+  }
+
+  if problems > 0 {
+    bail!(
+      "Found {} unsigned, badly signed or unchecked entries",
+      problems
+    );
+  }
+  info!("All {} signatures are good.", good);
+  Ok(())
+}
+
+/// The repo config as it was at the revision, e.g. the old value of a ref
+/// being pushed to
+fn trusted_config(rev: &str) -> Result<RepoConfig> {
+  let output = Command::new("git")
+    .arg("show")
+    .arg(format!("{}:.dev-suite/repo-config.toml", rev))
+    .output()?;
+  if !output.status.success() {
+    bail!(
+      "Unable to read the repo config at {}: {}",
+      rev,
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(toml::from_slice(&output.stdout)?)
+}
+
+/// The keys from the repo config that can be trusted along with how many
+/// weren't. A key is trusted if it's the same as in the trusted keys or a
+/// maintainer with a trusted key signed it.
+fn keyring(
+  config: &RepoConfig,
+  trusted: &BTreeMap<Uuid, String>,
+) -> (BTreeMap<Uuid, String>, usize) {
+  let mut keys = BTreeMap::new();
+  let mut problems = 0;
+  for (uuid, key) in &config.keys {
+    let vouched = trusted.get(uuid) == Some(key)
+      || config.key_signatures.get(uuid).is_some_and(|signed| {
+        check(
+          trusted,
+          Some(&signed.by),
+          &sign::key_payload(uuid, key),
+          Some(&signed.signature),
+        )
+        .is_none()
+      });
+    if vouched {
+      let _ = keys.insert(*uuid, key.clone());
+    } else {
+      warn!(
+        "The key for {} was added or changed without being signed by a \
+         trusted key",
+        uuid
+      );
+      problems += 1;
+    }
+  }
+  (keys, problems)
+}
+
+/// Sets a maintainer's key in the repo config and vouches for it with the
+/// user's own key, so it's trusted by `verify` with keys from before it was
+/// set
+pub fn trust(uuid: Uuid, key: String) -> Result<()> {
+  let mut config = get_repo_config()?;
+  if !config.maintainers.iter().any(|(_, m)| *m == uuid) {
+    bail!("{} isn't a maintainer in the repo config", uuid);
+  }
+  let signature = match sign::sign(&sign::key_payload(&uuid, &key))? {
+    Some(signature) => signature,
+    None => bail!("Set up signing in your user config to vouch for keys"),
+  };
+  let _ = config.key_signatures.insert(
+    uuid,
+    KeySignature {
+      by: get_user_config()?.uuid,
+      signature,
+    },
+  );
+  let _ = config.keys.insert(uuid, key);
+  set_repo_config(config)?;
+  info!("Set and signed the key for {}", uuid);
+  Ok(())
+}
+
+/// What's wrong with a comment's redaction, or `None` if the comment is just
+/// the redaction marker and a signed change by whoever redacted it says so
+fn check_redaction(
+  keys: &BTreeMap<Uuid, String>,
+  ticket: &Ticket,
+  redaction: &Redaction,
+  text: &str,
+) -> Option<String> {
+  if text != redaction.marker() {
+    return Some("is listed as redacted but still has its contents".into());
+  }
+  let signed = ticket.history.iter().any(|event| {
+    event.action == redaction.action()
+      && event.by.as_ref().map(|(uuid, _)| uuid) == Some(&redaction.by)
+      && check(
+        keys,
+        Some(&redaction.by),
+        &event.payload(&ticket.id),
+        event.signature.as_ref(),
+      )
+      .is_none()
+  });
+  if signed {
+    None
+  } else {
+    Some("is listed as redacted without a signed change redacting it".into())
+  }
+}
+
+/// What's wrong with the signature, or `None` if it was made by the key of
+/// who the entry says made it
+fn check(
+  keys: &BTreeMap<Uuid, String>,
+  by: Option<&Uuid>,
+  payload: &str,
+  signature: Option<&String>,
+) -> Option<String> {
+  let signature = match signature {
+    Some(signature) => signature,
+    None => return Some("isn't signed".into()),
+  };
+  let key = match by.and_then(|by| keys.get(by)) {
+    Some(key) => key,
+    None => {
+      return Some(
+        "is signed by someone without a key in the repo config".into(),
+      )
+    }
+  };
+  sign::verify(payload, signature, key)
+    .err()
+    .map(|e| format!("has a bad signature: {}", e))
+}
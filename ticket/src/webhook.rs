@@ -0,0 +1,126 @@
+//! Tells the urls in the `[[webhooks]]` of the repo config when tickets are
+//! created, closed, or commented on so teams can get notified in chat. The
+//! json sent has a `text` field summing up the event, which is what Slack and
+//! Matrix incoming webhooks show, along with the details for anything else.
+use crate::Ticket;
+use anyhow::{
+  format_err,
+  Result,
+};
+use configamajig::{
+  get_repo_config,
+  get_user_config,
+};
+use log::*;
+use reqwest::blocking::Client;
+use serde_json::json;
+use std::{
+  fmt,
+  time::Duration,
+};
+
+/// How long to wait on each webhook so a slow one doesn't hang the command
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+  Created,
+  Closed,
+  Commented,
+}
+
+impl fmt::Display for Event {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Event::Created => write!(f, "created"),
+      Event::Closed => write!(f, "closed"),
+      Event::Commented => write!(f, "commented"),
+    }
+  }
+}
+
+/// Sends the event to the webhooks, logging any that fail rather than failing
+/// the command since the ticket has already been saved
+pub fn notify(event: Event, ticket: &Ticket, comment: Option<&str>) {
+  if let Err(e) = send(event, ticket, comment) {
+    warn!("{}", e);
+  }
+}
+
+/// Sends the event to every webhook that wants it. They're all tried even if
+/// one fails.
+pub fn send(
+  event: Event,
+  ticket: &Ticket,
+  comment: Option<&str>,
+) -> Result<()> {
+  let webhooks = match get_repo_config() {
+    Ok(config) => config.webhooks,
+    Err(_) => return Ok(()),
+  };
+  let webhooks = webhooks
+    .into_iter()
+    .filter(|hook| {
+      hook.events.is_empty()
+        || hook
+          .events
+          .iter()
+          .any(|e| e.eq_ignore_ascii_case(&event.to_string()))
+    })
+    .collect::<Vec<_>>();
+  if webhooks.is_empty() {
+    return Ok(());
+  }
+  // Comments on encrypted tickets stay between the maintainers
+  let comment = comment.filter(|_| !ticket.encrypted);
+
+  let by = get_user_config().ok().map(|config| config.name);
+  let mut text = format!(
+    "{} {} ticket {}: {}",
+    by.as_deref().unwrap_or("Someone"),
+    event,
+    ticket.short_id,
+    ticket.title
+  );
+  if let Some(comment) = comment {
+    text.push_str("\n\n");
+    text.push_str(comment);
+  }
+  let payload = json!({
+    "event": event.to_string(),
+    "text": text,
+    "by": by,
+    "comment": comment,
+    "ticket": {
+      "id": ticket.id,
+      "short_id": ticket.short_id,
+      "title": ticket.title,
+      "status": ticket.status.to_string(),
+    },
+  })
+  .to_string();
+
+  let client = Client::builder().timeout(TIMEOUT).build()?;
+  let mut failed = Vec::new();
+  for hook in webhooks {
+    debug!("Sending the {} event to {}", event, hook.url);
+    let sent = client
+      .post(&hook.url)
+      .header("Content-Type", "application/json")
+      .body(payload.clone())
+      .send()
+      .and_then(|response| response.error_for_status());
+    if let Err(e) = sent {
+      failed.push(format!("{}: {}", hook.url, e));
+    }
+  }
+  if failed.is_empty() {
+    Ok(())
+  } else {
+    Err(format_err!(
+      "Unable to send the {} event to some webhooks:\n{}",
+      event,
+      failed.join("\n")
+    ))
+  }
+}
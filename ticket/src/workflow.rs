@@ -0,0 +1,165 @@
+//! Which statuses a ticket can move between and what has to happen when it does
+use crate::{
+  actions::{
+    resolve_status,
+    statuses,
+  },
+  Status,
+  Ticket,
+};
+use anyhow::{
+  bail,
+  Result,
+};
+use configamajig::{
+  get_repo_config,
+  WorkflowConfig,
+};
+use log::*;
+use shared::find_root;
+use std::{
+  collections::BTreeMap,
+  process::Command,
+};
+
+/// A ticket about to move from one status to another
+pub struct Transition<'a> {
+  /// The ticket that's moving
+  pub ticket: &'a Ticket,
+  /// The status the ticket is in now
+  pub from: &'a Status,
+  /// The status the ticket is moving to
+  pub to: &'a Status,
+  /// Why the ticket is moving, if a reason was given
+  pub reason: Option<&'a str>,
+}
+
+/// Something run before every transition. Returning an error stops the move.
+pub type Callback = Box<dyn Fn(&Transition) -> Result<()>>;
+
+/// The statuses from the repo config and the rules for moving between them.
+/// Anything that changes a ticket's status checks the move with this first.
+pub struct Workflow {
+  statuses: Vec<Status>,
+  transitions: BTreeMap<String, Vec<Status>>,
+  require_reason: Vec<Status>,
+  on_enter: BTreeMap<String, String>,
+  callbacks: Vec<Callback>,
+}
+
+impl Workflow {
+  /// Loads the workflow from the repo config. Without a `[workflow]` table
+  /// tickets can move from any status to any other.
+  pub fn load() -> Result<Self> {
+    let config = match get_repo_config() {
+      Ok(config) => config.workflow,
+      Err(e) => {
+        debug!("Unable to read the repo config: {}", e);
+        WorkflowConfig::default()
+      }
+    };
+    let mut transitions = BTreeMap::new();
+    for (from, to) in config.transitions {
+      let from = resolve_status(&from)?;
+      let to = to
+        .iter()
+        .map(|s| resolve_status(s))
+        .collect::<Result<Vec<Status>>>()?;
+      let _ = transitions.insert(from.to_string(), to);
+    }
+    let mut on_enter = BTreeMap::new();
+    for (to, command) in config.on_enter {
+      let _ = on_enter.insert(resolve_status(&to)?.to_string(), command);
+    }
+    Ok(Self {
+      statuses: statuses()?,
+      transitions,
+      require_reason: config
+        .require_reason
+        .iter()
+        .map(|s| resolve_status(s))
+        .collect::<Result<Vec<Status>>>()?,
+      on_enter,
+      callbacks: Vec::new(),
+    })
+  }
+
+  /// Registers a callback that's run before every transition, after the
+  /// rules from the repo config have passed
+  pub fn on_transition(
+    &mut self,
+    callback: impl Fn(&Transition) -> Result<()> + 'static,
+  ) {
+    self.callbacks.push(Box::new(callback));
+  }
+
+  /// The statuses a ticket in `from` is allowed to move to
+  pub fn allowed(&self, from: &Status) -> Vec<&Status> {
+    match self.transitions.get(&from.to_string()) {
+      Some(to) => to.iter().filter(|s| *s != from).collect(),
+      None => self.statuses.iter().filter(|s| *s != from).collect(),
+    }
+  }
+
+  /// Checks that a ticket can make the move, running the `on_enter` command
+  /// for the new status and any registered callbacks
+  pub fn check(&self, transition: &Transition) -> Result<()> {
+    let Transition {
+      ticket,
+      from,
+      to,
+      reason,
+    } = transition;
+    if !self.allowed(from).contains(to) {
+      bail!(
+        "Tickets can't move from {} to {}. They can move to {}",
+        from,
+        to,
+        self
+          .allowed(from)
+          .iter()
+          .map(ToString::to_string)
+          .collect::<Vec<String>>()
+          .join(", ")
+      );
+    }
+    if self.require_reason.contains(to) && reason.is_none() {
+      bail!(
+        "Tickets need a reason to move to {}. Pass one with --reason",
+        to
+      );
+    }
+    for callback in &self.callbacks {
+      callback(transition)?;
+    }
+    if let Some(command) = self.on_enter.get(&to.to_string()) {
+      debug!("Running '{}' before moving to {}", command, to);
+      #[cfg(not(windows))]
+      let mut cmd = Command::new("sh");
+      #[cfg(not(windows))]
+      let _ = cmd.arg("-c").arg(command);
+      #[cfg(windows)]
+      let mut cmd = Command::new("cmd");
+      #[cfg(windows)]
+      let _ = cmd.arg("/C").arg(command);
+      let status = cmd
+        .current_dir(find_root()?)
+        .env("TICKET_ID", ticket.id.to_string())
+        .env("TICKET_SHORT_ID", &ticket.short_id)
+        .env("TICKET_TITLE", &ticket.title)
+        .env("TICKET_FROM", from.to_string())
+        .env("TICKET_TO", to.to_string())
+        .env("TICKET_REASON", reason.unwrap_or_default())
+        .status()?;
+      if !status.success() {
+        bail!(
+          "Not moving the ticket to {} because '{}' failed with {}",
+          to,
+          command,
+          status
+        );
+      }
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,2810 @@
+mod common;
+
+use assert_cmd::prelude::*;
+use common::*;
+#[cfg(not(windows))]
+use std::os::unix::fs::PermissionsExt;
+use std::{
+  collections::BTreeMap,
+  error::Error,
+  fs,
+  path::Path,
+  process::{
+    Command,
+    Output,
+  },
+};
+
+#[test]
+fn list_open_by_default() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let out = list(&dir, &[])?;
+  assert!(out.contains("Fix the build"));
+  assert!(out.contains("Write docs"));
+  assert!(!out.contains("Old bug"));
+  Ok(())
+}
+
+#[test]
+fn list_closed_and_all() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let out = list(&dir, &["--closed"])?;
+  assert!(!out.contains("Fix the build"));
+  assert!(out.contains("Old bug"));
+  assert_eq!(list(&dir, &["--all"])?.lines().count(), 3);
+  assert_eq!(list(&dir, &["--status", "closed"])?.lines().count(), 1);
+  Ok(())
+}
+
+#[test]
+fn list_many_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  for i in 0..300 {
+    write_ticket(
+      dir.path(),
+      if i % 3 == 0 { "Closed" } else { "Open" },
+      &format!("Ticket {}", i),
+      &format!("{:08x}-1553-11ea-8001-000502040903", i),
+      "",
+    )?;
+  }
+  let all = list(&dir, &["--all"])?;
+  assert_eq!(all.lines().count(), 300);
+  assert_eq!(all, list(&dir, &["--all"])?);
+  assert_eq!(list(&dir, &[])?.lines().count(), 200);
+  Ok(())
+}
+
+#[test]
+fn list_by_assignee() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  for who in &["me", "tester", USER] {
+    let out = list(&dir, &["--assignee", who])?;
+    assert!(out.contains("Fix the build"));
+    assert!(!out.contains("Write docs"));
+  }
+  Ok(())
+}
+
+#[test]
+fn list_hides_snoozed() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let _ = ticket(&dir)?
+    .args(["snooze", "1d6df400-1553-11ea-8001-000502040903"])
+    .args(["--until", "9999-01-01"])
+    .assert()
+    .success();
+  assert!(!list(&dir, &[])?.contains("Write docs"));
+  assert!(list(&dir, &["--include-snoozed"])?.contains("Write docs"));
+  let out = list(&dir, &["--snoozed"])?;
+  assert!(out.contains("Write docs") && out.contains("until 9999-01-01"));
+  assert!(!out.contains("Fix the build"));
+  Ok(())
+}
+
+#[test]
+fn writes_leave_no_temp_files() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Started"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?.args(["close", id]).assert().success();
+  let tickets = dir.path().join(".dev-suite").join("ticket");
+  for status in &["open", "closed"] {
+    for entry in fs::read_dir(tickets.join(status))? {
+      let name = entry?.file_name().to_string_lossy().into_owned();
+      assert!(
+        name.ends_with(".toml") && !name.starts_with('.'),
+        "{}",
+        name
+      );
+    }
+  }
+
+  // What's left by a write that died partway through is ignored
+  fs::write(
+    tickets.join("open").join(".fix-the-build.toml.tmp-1"),
+    "id = ",
+  )?;
+  assert!(list(&dir, &[])?.contains("Fix the build"));
+  Ok(())
+}
+
+#[test]
+fn rename_moves_file_in_git() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let repo = git2::Repository::open(dir.path())?;
+  let mut index = repo.index()?;
+  index.add_all(["."], git2::IndexAddOption::DEFAULT, None)?;
+  index.write()?;
+
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let open = dir.path().join(".dev-suite").join("ticket").join("open");
+  let _ = ticket(&dir)?
+    .args(["edit", id, "--title", "Write the docs", "--no-edit"])
+    .assert()
+    .success();
+  assert!(!open.join("write-docs.toml").exists());
+  assert!(open.join("write-the-docs.toml").exists());
+  index.read(true)?;
+  let staged = |name: &str| {
+    let path = format!(".dev-suite/ticket/open/{}", name);
+    index.get_path(Path::new(&path), 0).is_some()
+  };
+  assert!(!staged("write-docs.toml"));
+  assert!(staged("write-the-docs.toml"));
+
+  // A file whose name no longer matches its title is moved the next time the
+  // ticket is saved rather than a second copy being made
+  fs::rename(open.join("write-the-docs.toml"), open.join("stale.toml"))?;
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Drifted"])
+    .assert()
+    .success();
+  assert!(!open.join("stale.toml").exists());
+  assert!(
+    fs::read_to_string(open.join("write-the-docs.toml"))?.contains("Drifted")
+  );
+
+  Ok(())
+}
+
+#[test]
+fn same_titles_get_different_files() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["edit", id, "--title", "Fix the build", "--no-edit"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Fix the build", "--description", ""])
+    .arg("--no-edit")
+    .assert()
+    .success();
+  let open = dir.path().join(".dev-suite").join("ticket").join("open");
+  let files = fs::read_dir(&open)?
+    .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+    .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+  assert_eq!(files.len(), 3);
+  assert!(files.contains(&"fix-the-build.toml".to_owned()));
+  assert!(files.iter().all(|f| f.starts_with("fix-the-build")));
+  assert_eq!(list(&dir, &[])?.matches("Fix the build").count(), 3);
+
+  // Each is found where it is when it's deleted
+  let _ = ticket(&dir)?
+    .args(["delete", id, "--force"])
+    .assert()
+    .success();
+  assert_eq!(fs::read_dir(&open)?.count(), 2);
+  assert!(open.join("fix-the-build.toml").exists());
+  let _ = ticket(&dir)?.arg("fsck").assert().success();
+  Ok(())
+}
+
+#[test]
+fn file_names_are_portable() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let long = "Word ".repeat(20);
+  for (title, file) in [
+    (
+      "Crash: /usr/bin is \u{201c}missing\u{201d}!",
+      "crash-usr-bin-is-missing",
+    ),
+    (
+      "Caf\u{e9} ni\u{f1}o doesn\u{2019}t <render>",
+      "cafe-nino-doesnt-render",
+    ),
+    ("CON", "con-ticket"),
+    ("???", "ticket"),
+    (long.as_str(), &long.to_lowercase().replace(' ', "-")[..64]),
+  ] {
+    let _ = ticket(&dir)?
+      .args(["new", "--title", title, "--description", "", "--no-edit"])
+      .assert()
+      .success();
+    let path = dir
+      .path()
+      .join(".dev-suite/ticket/open")
+      .join(format!("{}.toml", file));
+    assert!(path.exists(), "{}", path.display());
+  }
+  Ok(())
+}
+
+#[test]
+fn assign_maintainer() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    "maintainers = [['Jane Doe', '5b9e2c3e-0f8a-4c44-9a4b-0a6f1e0b2a77'], \
+     ['Janet Roe', '7c1d2e3f-4a5b-4c6d-8e9f-0a1b2c3d4e5f']]\n",
+  )?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["assign", id, "jane doe"])
+    .assert()
+    .success();
+  assert!(list(&dir, &["--assignee", "Jane Doe"])?.contains("Write docs"));
+  let _ = ticket(&dir)?.args(["assign", id, "roe"]).assert().success();
+  assert!(list(&dir, &["--assignee", "Janet Roe"])?.contains("Write docs"));
+  let output = ticket(&dir)?.args(["assign", id, "jan"]).output()?;
+  assert!(String::from_utf8(output.stderr)?.contains("Jane Doe, Janet Roe"));
+  let output = ticket(&dir)?.args(["assign", id, "jame doe"]).output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("did you mean Jane Doe"));
+
+  // The syntax from before maintainers could be named still works
+  let _ = ticket(&dir)?
+    .args(["assign", id, "to", "me"])
+    .assert()
+    .success();
+  assert!(list(&dir, &["--assignee", "Tester"])?.contains("Write docs"));
+  let them = "9d8e7f6a-5b4c-4d3e-8f2a-1b0c9d8e7f6a";
+  let _ = ticket(&dir)?
+    .args(["assign", id, "to", "them", them, "Sam"])
+    .assert()
+    .success();
+  assert!(list(&dir, &["--assignee", "Sam"])?.contains("Write docs"));
+  let _ = ticket(&dir)?
+    .args(["assign", id, "to", "them", "Sam"])
+    .assert()
+    .failure();
+  Ok(())
+}
+
+#[test]
+fn init_scaffolding() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let root = dir.path().join(".dev-suite").join("ticket");
+  assert!(root.join("templates").join("bug.md").exists());
+  assert!(root.join("templates").join("feature.md").exists());
+  fs::write(root.join(".gitignore"), "/mine")?;
+  let _ = ticket(&dir)?.args(["init", "--welcome"]).assert().success();
+  let ignored = fs::read_to_string(root.join(".gitignore"))?;
+  assert!(ignored.starts_with("/mine\n"));
+  assert!(ignored.contains("/description\n"));
+  assert!(list(&dir, &[])?.contains("Welcome to ticket"));
+  let _ = ticket(&dir)?.arg("init").assert().success();
+  assert_eq!(fs::read_to_string(root.join(".gitignore"))?, ignored);
+  Ok(())
+}
+
+#[test]
+fn reopen_closed_ticket() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "2d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?.args(["reopen", id]).assert().success();
+  assert!(list(&dir, &[])?.contains("Old bug"));
+  assert!(!list(&dir, &["--closed"])?.contains("Old bug"));
+  let _ = ticket(&dir)?.args(["reopen", id]).assert().failure();
+  Ok(())
+}
+
+#[test]
+fn tag_and_migrate() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let _ = ticket(&dir)?
+    .args(["tag", "add", id, "docs"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["tag", "add", id, "bad tag"])
+    .assert()
+    .failure();
+  assert!(fs::read_to_string(&path)?.contains("tags = ['docs']"));
+  let _ = ticket(&dir)?
+    .args(["tag", "remove", id, "docs"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["tag", "remove", id, "docs"])
+    .assert()
+    .failure();
+
+  let _ = ticket(&dir)?.arg("migrate").assert().success();
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  assert_eq!(saved["version"].as_str(), Some("V3"));
+  // The fixture's id is from 2019 but it was tagged just now
+  let created = saved["created_at"].as_str().ok_or("No created_at")?;
+  let updated = saved["updated_at"].as_str().ok_or("No updated_at")?;
+  assert!(created.starts_with("2019-"));
+  assert!(updated > created);
+  assert_eq!(list(&dir, &["--all"])?.lines().count(), 3);
+  Ok(())
+}
+
+#[test]
+fn migrate_from_v0() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let open = dir.path().join(".dev-suite/ticket/open");
+  let old = "title = 'Ancient'\nstatus = 'Open'\nnumber = 1\n\
+             assignee = 'Someone'\ndescription = 'From V0'\n";
+  fs::write(open.join("1-ancient.toml"), old)?;
+
+  let output = ticket(&dir)?.args(["migrate", "--dry-run"]).output()?;
+  assert!(output.status.success());
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains(
+    "Would upgrade .dev-suite/ticket/open/1-ancient.toml from V0 to V3, \
+     moving it to .dev-suite/ticket/open/ancient.toml"
+  ));
+  assert!(out.contains("write-docs.toml from V1 to V3"));
+  assert!(out.contains("Adds tags"));
+  assert_eq!(fs::read_to_string(open.join("1-ancient.toml"))?, old);
+  assert!(!dir.path().join(".dev-suite/backups").exists());
+
+  let output = ticket(&dir)?.arg("migrate").output()?;
+  assert!(output.status.success());
+  assert!(!open.join("1-ancient.toml").exists());
+  let saved = fs::read_to_string(open.join("ancient.toml"))?;
+  let saved = saved.parse::<toml::Value>()?;
+  assert_eq!(saved["version"].as_str(), Some("V3"));
+  assert_eq!(saved["description"].as_str(), Some("From V0"));
+  assert!(saved.get("number").is_none());
+  let backups = fs::read_dir(dir.path().join(".dev-suite/backups"))?
+    .collect::<Result<Vec<_>, _>>()?;
+  assert_eq!(backups.len(), 1);
+  let backup = backups[0].path().join(".dev-suite/ticket/open");
+  assert_eq!(fs::read_to_string(backup.join("1-ancient.toml"))?, old);
+  assert!(backup.join("write-docs.toml").exists());
+  assert!(list(&dir, &[])?.contains("Ancient"));
+
+  let output = ticket(&dir)?.args(["migrate", "--dry-run"]).output()?;
+  assert!(String::from_utf8(output.stderr)?.contains("already V3"));
+  Ok(())
+}
+
+#[test]
+fn unread_since_last_shown() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  assert_eq!(list(&dir, &["--unread"])?.lines().count(), 2);
+  let _ = ticket(&dir)?.args(["show", id]).assert().success();
+  let out = list(&dir, &["--unread"])?;
+  assert!(out.contains("Fix the build"));
+  assert!(!out.contains("Write docs"));
+  assert!(list(&dir, &["--comments-since", "9999-01-01"])?.is_empty());
+  Ok(())
+}
+
+#[test]
+fn badges() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let path = dir.path().join("badge.svg");
+  let _ = ticket(&dir)?
+    .args(["badge", "open"])
+    .arg(&path)
+    .assert()
+    .success();
+  assert!(fs::read_to_string(&path)?.contains(">2</text>"));
+  let _ = ticket(&dir)?
+    .args(["badge", "progress"])
+    .arg(&path)
+    .assert()
+    .success();
+  assert!(fs::read_to_string(&path)?.contains(">33%</text>"));
+  Ok(())
+}
+
+#[test]
+fn short_ids() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let out = list(&dir, &["--closed"])?;
+  let short = out.split_whitespace().next().ok_or("Nothing was listed")?;
+  assert_eq!(short.len(), 8);
+  let output = ticket(&dir)?.args(["show", short]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("Old bug"));
+  let _ = ticket(&dir)?.args(["reopen", short]).assert().success();
+  let _ = ticket(&dir)?.args(["show", "ffffffff"]).assert().failure();
+  Ok(())
+}
+
+#[test]
+fn move_between_statuses() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    "maintainers = []\nstatuses = ['InProgress', 'Blocked']\n",
+  )?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["move", id, "inprogress"])
+    .assert()
+    .success();
+  assert!(dir
+    .path()
+    .join(".dev-suite/ticket/inprogress/write-docs.toml")
+    .exists());
+  assert!(list(&dir, &[])?.contains("[InProgress] Write docs"));
+  assert_eq!(list(&dir, &["--status", "InProgress"])?.lines().count(), 1);
+  let _ = ticket(&dir)?.args(["move", id, "nope"]).assert().failure();
+  let _ = ticket(&dir)?.args(["close", id]).assert().success();
+  assert!(list(&dir, &["--closed"])?.contains("Write docs"));
+  Ok(())
+}
+
+#[test]
+fn workflow_rules() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    "maintainers = []\nstatuses = ['InProgress']\n\n[workflow]\n\
+     require_reason = ['Closed']\n\n[workflow.transitions]\n\
+     Open = ['InProgress']\n\n[workflow.on_enter]\n\
+     Closed = 'test \"$TICKET_FROM\" = InProgress'\n",
+  )?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["close", id, "--reason", "Done"])
+    .assert()
+    .failure();
+  let _ = ticket(&dir)?
+    .args(["move", id, "inprogress"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?.args(["close", id]).assert().failure();
+  let _ = ticket(&dir)?
+    .args(["close", id, "--reason", "Done"])
+    .assert()
+    .success();
+  assert!(list(&dir, &["--closed"])?.contains("Write docs"));
+  Ok(())
+}
+
+#[test]
+fn branch_for_ticket() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?.args(["branch", id]).assert().failure();
+
+  let repo = git2::Repository::open(dir.path())?;
+  let mut index = repo.index()?;
+  index.add_all(["."], git2::IndexAddOption::DEFAULT, None)?;
+  index.write()?;
+  let tree = repo.find_tree(index.write_tree()?)?;
+  let sig = git2::Signature::now("Tester", "tester@example.com")?;
+  let _ = repo.commit(Some("HEAD"), &sig, &sig, "Tickets", &tree, &[])?;
+
+  let _ = ticket(&dir)?.args(["branch", id]).assert().success();
+  let head = repo.head()?;
+  let name = head.shorthand().ok_or("No branch")?;
+  assert!(name.ends_with("-write-docs"));
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains(name));
+
+  let _ = ticket(&dir)?
+    .args(["branch", id, "--name", "docs", "--no-checkout"])
+    .assert()
+    .success();
+  assert!(repo.find_branch("docs", git2::BranchType::Local).is_ok());
+  assert_eq!(repo.head()?.shorthand(), Some(name));
+  Ok(())
+}
+
+#[test]
+fn close_from_commit_trailers() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let repo = git2::Repository::open(dir.path())?;
+  let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+  let sig = git2::Signature::now("Tester", "tester@example.com")?;
+  let commit = repo.commit(
+    Some("HEAD"),
+    &sig,
+    &sig,
+    "Write the docs\n\nThey're in the wiki.\n\n\
+     Fixes: 1d6df400-1553-11ea-8001-000502040903, #1234\n\
+     Closes: login, deadbeef\n",
+    &tree,
+    &[],
+  )?;
+
+  let output = Command::cargo_bin("ticket-post-commit")?
+    .current_dir(&dir)
+    .env("XDG_CONFIG_HOME", dir.path().join("config"))
+    .output()?;
+  assert!(output.status.success());
+  // Anything that isn't a ticket id is left alone rather than searched for
+  let err = String::from_utf8(output.stderr)?;
+  assert!(err.contains("skipping '#1234'"));
+  assert!(err.contains("skipping 'login'"));
+  assert!(err.contains("didn't close ticket deadbeef"));
+  assert!(list(&dir, &["--closed"])?.contains("Write docs"));
+  assert!(!list(&dir, &["--closed"])?.contains("Fix the build"));
+  let output = ticket(&dir)?
+    .args(["show", "1d6df400-1553-11ea-8001-000502040903"])
+    .output()?;
+  assert!(String::from_utf8(output.stdout)?
+    .contains(&format!("Closed by commit {}", commit)));
+  Ok(())
+}
+
+#[test]
+fn commits_mentioning_ticket() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let repo = git2::Repository::open(dir.path())?;
+  let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+  let sig = git2::Signature::now("Tester", "tester@example.com")?;
+  let first = repo.commit(Some("HEAD"), &sig, &sig, "Unrelated", &tree, &[])?;
+  let first = repo.find_commit(first)?;
+  let message = format!("Start the docs\n\nFor {}", id);
+  let _ = repo.commit(Some("HEAD"), &sig, &sig, &message, &tree, &[&first])?;
+
+  let output = ticket(&dir)?.args(["activity", id]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert_eq!(out.lines().count(), 1);
+  assert!(out.contains("Tester Start the docs"));
+  Ok(())
+}
+
+#[test]
+fn scan_todo_comments() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let file = dir.path().join("lib.rs");
+  fs::write(
+    &file,
+    "fn main() {}\n// TODO(ds): Handle errors\n/* FIXME(ds) Leaks memory */\n",
+  )?;
+  let _ = ticket(&dir)?.args(["scan", "--dry-run"]).assert().success();
+  assert!(list(&dir, &[])?.is_empty());
+
+  let _ = ticket(&dir)?.arg("scan").assert().success();
+  let out = list(&dir, &[])?;
+  assert!(out.contains("Handle errors"));
+  assert!(out.contains("Leaks memory"));
+  let source = fs::read_to_string(&file)?;
+  let id = source
+    .split("TODO(ds-")
+    .nth(1)
+    .and_then(|rest| rest.get(..8))
+    .ok_or("No short id written back")?
+    .to_owned();
+  assert!(source.contains(&format!("// TODO(ds-{}): Handle errors\n", id)));
+  let output = ticket(&dir)?.args(["show", &id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("lib.rs:2"));
+
+  // Scanning again only updates where the comments moved to
+  fs::write(&file, format!("\n{}", source))?;
+  let _ = ticket(&dir)?.arg("scan").assert().success();
+  assert_eq!(list(&dir, &[])?.lines().count(), 2);
+  let output = ticket(&dir)?.args(["show", &id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("lib.rs:3"));
+  Ok(())
+}
+
+#[test]
+fn fsck_finds_and_fixes_problems() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let root = dir.path().join(".dev-suite").join("ticket");
+  let _ = ticket(&dir)?.arg("fsck").assert().success();
+
+  fs::rename(
+    root.join("open").join("write-docs.toml"),
+    root.join("closed").join("docs.toml"),
+  )?;
+  let _ = ticket(&dir)?.arg("fsck").assert().failure();
+  let _ = ticket(&dir)?.args(["fsck", "--fix"]).assert().success();
+  assert!(root.join("open").join("write-docs.toml").exists());
+  let _ = ticket(&dir)?.arg("fsck").assert().success();
+
+  // Relations to tickets that were removed by hand are dropped by --fix
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["link", docs, "blocked-by", build])
+    .assert()
+    .success();
+  fs::remove_file(root.join("open").join("fix-the-build.toml"))?;
+  let output = ticket(&dir)?.arg("fsck").output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?
+    .contains(&format!("blocked by {} which doesn't exist", build)));
+  let _ = ticket(&dir)?.args(["fsck", "--fix"]).assert().success();
+  let output = ticket(&dir)?.args(["show", docs]).output()?;
+  assert!(!String::from_utf8(output.stdout)?.contains("Blocked by"));
+  let _ = ticket(&dir)?.arg("fsck").assert().success();
+
+  let _ = fs::copy(
+    root.join("open").join("write-docs.toml"),
+    root.join("open").join("copy.toml"),
+  )?;
+  fs::write(root.join("closed").join("broken.toml"), "title = ")?;
+  let output = ticket(&dir)?.args(["fsck", "--fix"]).output()?;
+  assert!(!output.status.success());
+  let err = String::from_utf8(output.stderr)?;
+  assert!(err.contains("broken.toml can't be read"));
+  assert!(err.contains("all have the uuid 1d6df400"));
+  Ok(())
+}
+
+#[test]
+fn stale_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Fresh", "--description", "", "--no-edit"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["close", "0d6df400-1553-11ea-8001-000502040903"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?.args(["stale", "--days", "30"]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert_eq!(out.lines().count(), 1);
+  assert!(out.contains("Write docs"));
+  let output = ticket(&dir)?
+    .args(["stale", "--days", "0", "--format", "json"])
+    .output()?;
+  let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
+  assert_eq!(json.as_array().map(Vec::len), Some(2));
+  assert_eq!(json[1]["title"], "Fresh");
+  Ok(())
+}
+
+#[test]
+fn release_notes() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "0d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["tag", "add", id, "bug"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?.args(["close", id]).assert().success();
+  let output = ticket(&dir)?
+    .args([
+      "release-notes",
+      "--since",
+      "2020-01-01",
+      "--heading",
+      "v1.0",
+    ])
+    .output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.starts_with("# v1.0\n\n## Bug Fixes\n\n- Fix the build ("));
+  // The fixture's closed ticket has no closed_at so it can't be placed
+  assert!(!out.contains("Old bug"));
+  let _ = ticket(&dir)?
+    .args(["release-notes", "--since", "v9.9"])
+    .assert()
+    .failure();
+  Ok(())
+}
+
+#[test]
+fn digest_between_commits() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let repo = git2::Repository::open(dir.path())?;
+  let sig = git2::Signature::now("Tester", "tester@example.com")?;
+  let commit = |message: &str| -> Result<git2::Oid, Box<dyn Error>> {
+    let mut index = repo.index()?;
+    index.add_all(["."], git2::IndexAddOption::DEFAULT, None)?;
+    index.update_all(["."], None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parents = match repo.head() {
+      Ok(head) => vec![head.peel_to_commit()?],
+      Err(_) => Vec::new(),
+    };
+    let parents = parents.iter().collect::<Vec<_>>();
+    Ok(repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)?)
+  };
+  let first = commit("Tickets")?;
+  let _ = repo.tag_lightweight("v1", &repo.find_object(first, None)?, false)?;
+
+  let _ = ticket(&dir)?.args(["close", build]).assert().success();
+  let _ = ticket(&dir)?
+    .args(["comment", docs, "Started on this"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Add a digest", "--no-edit"])
+    .assert()
+    .success();
+  let _ = commit("More tickets")?;
+
+  let output = ticket(&dir)?.args(["digest", "--since", "v1"]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.starts_with("Ticket digest from v1 to HEAD\n"));
+  assert!(out.contains("Created (1)\n- ") && out.contains(" Add a digest\n"));
+  assert!(out.contains("Closed (1)\n") && out.contains("Fix the build"));
+  assert!(out.contains("Write docs: 1 new comment from Tester"));
+  assert!(!out.contains("Deleted"));
+
+  // Every commit is after the date so every ticket is new
+  let output = ticket(&dir)?
+    .args(["digest", "--since", "2000-01-01"])
+    .output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("Created (4)") && out.contains("Old bug"));
+  let output = ticket(&dir)?.args(["digest", "--since", "HEAD"]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("Nothing happened"));
+  let _ = ticket(&dir)?
+    .args(["digest", "--since", "v9.9"])
+    .assert()
+    .failure();
+  Ok(())
+}
+
+#[test]
+fn export_and_import_json() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Started on this"])
+    .assert()
+    .success();
+  let dump = dir.path().join("tickets.json");
+  let _ = ticket(&dir)?
+    .args(["export", "json", "--output"])
+    .arg(&dump)
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["delete", id, "--force"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["import", "json"])
+    .arg(&dump)
+    .assert()
+    .success();
+  assert_eq!(list(&dir, &["--all"])?.lines().count(), 3);
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("Write docs"));
+  assert!(out.contains("Started on this"));
+  Ok(())
+}
+
+#[test]
+fn ticket_stats() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["tag", "add", id, "docs"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["close", "0d6df400-1553-11ea-8001-000502040903"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?.args(["stats", "--format", "json"]).output()?;
+  let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
+  assert_eq!(json["open"], 1);
+  assert_eq!(json["closed"], 2);
+  assert_eq!(json["by_tag"]["docs"], 1);
+  assert_eq!(json["by_assignee"]["Unassigned"], 1);
+  assert!(json["average_days_to_close"].as_f64().is_some());
+  assert_eq!(json["recently_active"].as_array().map(Vec::len), Some(2));
+  let _ = ticket(&dir)?.arg("stats").assert().success();
+  let _ = ticket(&dir)?
+    .args(["stats", "--format", "toml"])
+    .assert()
+    .success();
+  Ok(())
+}
+
+#[test]
+fn workload_report() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["estimate", build, "3"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["estimate", docs, "4h"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?
+    .args(["report", "workload", "--format", "json"])
+    .output()?;
+  assert!(output.status.success());
+  let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
+  let rows = json.as_array().ok_or("The report isn't a list")?;
+  let row = |name: &str| rows.iter().find(|row| row["name"] == name);
+  let tester = row("Tester").ok_or("Tester isn't in the report")?;
+  assert_eq!(tester["open"], 1);
+  assert_eq!(tester["estimated"]["open_points"], 3.0);
+  let unassigned = row("Unassigned").ok_or("No unassigned row")?;
+  assert_eq!(unassigned["open"], 1);
+  assert_eq!(unassigned["estimated"]["open_hours"], 4.0);
+
+  let output = ticket(&dir)?.args(["report", "workload"]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("Estimated"));
+  assert!(out
+    .lines()
+    .any(|l| l.contains("Tester") && l.contains("3pt")));
+  let _ = ticket(&dir)?
+    .args(["report", "workload", "--format", "toml"])
+    .assert()
+    .success();
+  Ok(())
+}
+
+#[test]
+fn estimates() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["estimate", build, "3"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["estimate", docs, "2d"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["estimate", docs, "soon"])
+    .assert()
+    .failure();
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  assert_eq!(saved["estimate"].as_str(), Some("16h"));
+  let output = ticket(&dir)?.args(["show", docs]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("Estimate") && out.contains("16h"));
+
+  for id in &[build, docs] {
+    let _ = ticket(&dir)?
+      .args(["tag", "add", id, "v1"])
+      .assert()
+      .success();
+  }
+  let _ = ticket(&dir)?.args(["close", build]).assert().success();
+  let output = ticket(&dir)?.args(["stats", "--format", "json"]).output()?;
+  let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
+  assert_eq!(json["estimated"]["open_hours"], 16.0);
+  assert_eq!(json["estimated"]["closed_points"], 3.0);
+  assert_eq!(json["estimated_by_tag"]["v1"]["open_hours"], 16.0);
+  assert_eq!(json["estimated_by_tag"]["v1"]["closed_points"], 3.0);
+  let _ = ticket(&dir)?
+    .args(["stats", "--format", "toml"])
+    .assert()
+    .success();
+
+  let _ = ticket(&dir)?
+    .args(["estimate", docs, "--clear"])
+    .assert()
+    .success();
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  assert!(saved.get("estimate").is_none());
+  Ok(())
+}
+
+#[test]
+fn sprints() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["sprint", "add", build])
+    .assert()
+    .failure();
+  let _ = ticket(&dir)?
+    .args(["sprint", "new", "old", "--start", "2000-01-01"])
+    .args(["--end", "2000-01-14"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["sprint", "new", "now", "--start", "2000-01-15"])
+    .args(["--end", "2999-12-31"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["sprint", "new", "backwards", "--start", "2000-01-15"])
+    .args(["--end", "2000-01-01"])
+    .assert()
+    .failure();
+  let path = dir.path().join(".dev-suite/ticket/sprints/now.toml");
+  assert!(path.exists());
+
+  let _ = ticket(&dir)?
+    .args(["sprint", "add", build, docs])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["sprint", "add", docs, "--sprint", "old"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["sprint", "remove", docs, "--sprint", "old"])
+    .assert()
+    .success();
+  for (id, estimate) in &[(build, "3"), (docs, "5")] {
+    let _ = ticket(&dir)?
+      .args(["estimate", id, estimate])
+      .assert()
+      .success();
+  }
+  let _ = ticket(&dir)?.args(["close", build]).assert().success();
+
+  let status = |args: &[&str]| -> Result<serde_json::Value, Box<dyn Error>> {
+    let output = ticket(&dir)?
+      .args(["sprint", "status", "--format", "json"])
+      .args(args)
+      .output()?;
+    Ok(serde_json::from_slice(&output.stdout)?)
+  };
+  let json = status(&[])?;
+  assert_eq!(json["name"], "now");
+  assert_eq!(json["open"], 1);
+  assert_eq!(json["closed"], 1);
+  assert_eq!(json["closed_points"], 3.0);
+  assert_eq!(json["total_points"], 8.0);
+  let json = status(&["old"])?;
+  assert_eq!(json["open"], 0);
+  assert_eq!(json["days_left"], 0);
+
+  let output = ticket(&dir)?.args(["sprint", "list"]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("now") && out.contains("2 tickets"));
+  let _ = ticket(&dir)?.args(["sprint", "status"]).assert().success();
+  let output = ticket(&dir)?.args(["history", docs]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("Removed from the sprint 'old'"));
+  Ok(())
+}
+
+#[test]
+fn burndown_and_velocity() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let today = chrono::Local::now().date_naive();
+  let start = today - chrono::Duration::days(3);
+  let end = today + chrono::Duration::days(3);
+  let _ = ticket(&dir)?
+    .args(["sprint", "new", "now", "--start", &start.to_string()])
+    .args(["--end", &end.to_string()])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["sprint", "add", build, docs])
+    .assert()
+    .success();
+  for (id, estimate) in &[(build, "3"), (docs, "5")] {
+    let _ = ticket(&dir)?
+      .args(["estimate", id, estimate])
+      .assert()
+      .success();
+  }
+  let _ = ticket(&dir)?.args(["close", build]).assert().success();
+
+  let output = ticket(&dir)?
+    .args(["burndown", "--format", "csv"])
+    .output()?;
+  let out = String::from_utf8(output.stdout)?;
+  let mut lines = out.lines();
+  assert_eq!(lines.next(), Some("date,remaining,ideal"));
+  assert_eq!(lines.next(), Some(format!("{},8,8", start).as_str()));
+  // Today only the open ticket is left and the days to come have no value
+  let today = format!("{},5,", today);
+  assert!(lines.clone().any(|line| line.starts_with(&today)));
+  assert_eq!(lines.last(), Some(format!("{},,0", end).as_str()));
+  let output = ticket(&dir)?.arg("burndown").output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("points left") && out.contains('#'));
+
+  let output = ticket(&dir)?
+    .args(["burndown", "--velocity", "--format", "csv"])
+    .output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains(&format!("now,{},{},1,3,0", start, end)));
+  let _ = ticket(&dir)?
+    .args(["burndown", "--velocity"])
+    .assert()
+    .success();
+  Ok(())
+}
+
+#[test]
+fn watch_and_inbox() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let other = dir.path().join("other");
+  fs::create_dir_all(other.join("dev-suite"))?;
+  fs::write(
+    other.join("dev-suite").join("user-config.toml"),
+    "name = 'Other'\nuuid = '5fd8dbd1-0bd6-4d4a-a1b3-e1f5a6a5e6c1'\n",
+  )?;
+  let inbox = |args: &[&str]| -> Result<String, Box<dyn Error>> {
+    let output = ticket(&dir)?.arg("inbox").args(args).output()?;
+    Ok(String::from_utf8(output.stdout)?)
+  };
+
+  let _ = ticket(&dir)?.args(["watch", docs]).assert().success();
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  assert_eq!(saved["watchers"][0].as_str(), Some(USER));
+  // Your own comments don't show up
+  let _ = ticket(&dir)?
+    .args(["comment", docs, "I'll take a look"])
+    .assert()
+    .success();
+  assert!(inbox(&[])?.contains("Nothing new"));
+
+  let _ = ticket(&dir)?
+    .env("XDG_CONFIG_HOME", &other)
+    .args(["comment", docs, "The wiki is down"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .env("XDG_CONFIG_HOME", &other)
+    .args(["close", docs])
+    .assert()
+    .success();
+  let out = inbox(&["--peek"])?;
+  assert!(out.contains("Other") && out.contains("The wiki is down"));
+  assert!(out.contains("Moved the ticket from Open to Closed"));
+  assert!(inbox(&[])?.contains("The wiki is down"));
+  assert!(inbox(&[])?.contains("Nothing new"));
+
+  let _ = ticket(&dir)?.args(["unwatch", docs]).assert().success();
+  let _ = ticket(&dir)?.args(["unwatch", docs]).assert().failure();
+  let saved = fs::read_to_string(
+    dir.path().join(".dev-suite/ticket/closed/write-docs.toml"),
+  )?
+  .parse::<toml::Value>()?;
+  assert!(saved.get("watchers").is_none());
+  Ok(())
+}
+
+#[test]
+fn link_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["link", docs, "blocked-by", build])
+    .assert()
+    .success();
+  let output = ticket(&dir)?.args(["show", build]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("Write docs"));
+  // The build being blocked by the docs would be a cycle
+  let _ = ticket(&dir)?
+    .args(["link", build, "blocked-by", docs])
+    .assert()
+    .failure();
+  let _ = ticket(&dir)?
+    .args(["link", docs, "blocked-by", build, "--remove"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["link", build, "blocked-by", docs])
+    .assert()
+    .success();
+
+  let tree = list(&dir, &["--tree"])?;
+  let lines = tree.lines().collect::<Vec<&str>>();
+  assert_eq!(lines.len(), 2);
+  assert!(lines[0].contains("Fix the build"));
+  assert!(lines[1].starts_with("└── ") && lines[1].contains("Write docs"));
+  let dot = list(&dir, &["--tree", "--format", "dot"])?;
+  assert!(dot.starts_with("digraph tickets {"));
+  assert_eq!(dot.matches(" -> ").count(), 1);
+  Ok(())
+}
+
+#[test]
+fn aliases_and_defaults() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  fs::write(
+    dir.path().join("config/dev-suite/user-config.toml"),
+    format!(
+      "name = 'Tester'\nuuid = '{}'\n\n[aliases]\nmine = 'list --assignee \
+       me'\n\n[defaults]\nlist = '--status closed'\n",
+      USER
+    ),
+  )?;
+  let out = list(&dir, &[])?;
+  assert!(out.contains("Old bug"));
+  assert!(!out.contains("Write docs"));
+  // Flags that were passed aren't added again
+  assert!(list(&dir, &["--status", "open"])?.contains("Write docs"));
+  let output = ticket(&dir)?.args(["mine", "--status", "open"]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("Fix the build"));
+  assert!(!out.contains("Write docs"));
+  Ok(())
+}
+
+#[test]
+fn task_checklist() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  for text in &["Outline", "First draft"] {
+    let _ = ticket(&dir)?
+      .args(["task", "add", id, text])
+      .assert()
+      .success();
+  }
+  let _ = ticket(&dir)?
+    .args(["task", "check", id, "2"])
+    .assert()
+    .success();
+  assert!(list(&dir, &[])?.contains("Write docs [1/2]"));
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("2. [x] First draft"));
+  let _ = ticket(&dir)?
+    .args(["task", "uncheck", id, "2"])
+    .assert()
+    .success();
+  assert!(list(&dir, &[])?.contains("Write docs [0/2]"));
+  let _ = ticket(&dir)?
+    .args(["task", "check", id, "3"])
+    .assert()
+    .failure();
+  Ok(())
+}
+
+#[test]
+fn export_markdown() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let output = ticket(&dir)?.args(["export", "markdown"]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("## Open"));
+  assert!(out.contains("- **Assignees:** Tester\n"));
+  assert!(out.contains("## Closed"));
+  assert!(out.contains(" - Old bug\n"));
+
+  let path = dir.path().join("tickets.md");
+  let _ = ticket(&dir)?
+    .args(["export", "markdown", "--status", "closed", "--output"])
+    .arg(&path)
+    .assert()
+    .success();
+  let out = fs::read_to_string(&path)?;
+  assert!(out.contains("Old bug"));
+  assert!(!out.contains("## Open"));
+  Ok(())
+}
+
+#[test]
+fn export_html() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let _ = ticket(&dir)?
+    .args(["comment", "0d6df400-1553-11ea-8001-000502040903"])
+    .arg("Breaks on <b>nightly</b>")
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["export", "html", "--out", "site"])
+    .assert()
+    .success();
+  let site = dir.path().join("site");
+  let index = fs::read_to_string(site.join("index.html"))?;
+  assert!(index.contains("Fix the build"));
+  assert!(index.contains("Old bug"));
+  assert!(site.join("style.css").exists());
+
+  let page = fs::read_dir(site.join("tickets"))?
+    .map(|entry| fs::read_to_string(entry?.path()))
+    .collect::<Result<Vec<_>, _>>()?
+    .into_iter()
+    .find(|page| page.contains("<title>Fix the build</title>"))
+    .unwrap();
+  assert!(page.contains("Breaks on &lt;b&gt;nightly&lt;/b&gt;"));
+  assert!(page.contains("<b>Status:</b> Open"));
+
+  // A site can't be written to stdout
+  let _ = ticket(&dir)?.args(["export", "html"]).assert().failure();
+  Ok(())
+}
+
+#[test]
+fn new_without_prompts() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "From flags", "--description", "Some text"])
+    .arg("--no-edit")
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "From stdin", "--description-file", "-"])
+    .arg("--no-edit")
+    .with_stdin()
+    .buffer("Piped text")
+    .assert()
+    .success();
+  let open = dir.path().join(".dev-suite/ticket/open");
+  assert!(fs::read_to_string(open.join("from-flags.toml"))?
+    .contains("description = 'Some text'"));
+  assert!(fs::read_to_string(open.join("from-stdin.toml"))?
+    .contains("description = 'Piped text'"));
+  let _ = ticket(&dir)?
+    .args(["new", "--title", " ", "--no-edit"])
+    .assert()
+    .failure();
+  Ok(())
+}
+
+#[test]
+fn default_assignee() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let config = dir.path().join(".dev-suite").join("repo-config.toml");
+  fs::write(&config, "maintainers = []\ndefault_assignee = 'creator'\n")?;
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Mine", "--description", "", "--no-edit"])
+    .assert()
+    .success();
+  assert!(list(&dir, &["--assignee", "me"])?.contains("Mine"));
+
+  fs::write(
+    &config,
+    "maintainers = [['Jane Doe', '5b9e2c3e-0f8a-4c44-9a4b-0a6f1e0b2a77']]\n\
+     default_assignee = 'jane'\n",
+  )?;
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Hers", "--description", "", "--no-edit"])
+    .assert()
+    .success();
+  assert!(list(&dir, &["--assignee", "Jane Doe"])?.contains("Hers"));
+  Ok(())
+}
+
+#[test]
+fn components() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let config = dir.path().join(".dev-suite").join("repo-config.toml");
+  fs::write(
+    &config,
+    "maintainers = [['Jane Doe', '5b9e2c3e-0f8a-4c44-9a4b-0a6f1e0b2a77']]\n\
+     default_assignee = 'creator'\n\
+     [components.tui]\n\
+     owners = ['jane']\n\
+     [components.hooks]\n",
+  )?;
+  let new = |title: &str, component: &str| -> Result<(), Box<dyn Error>> {
+    let _ = ticket(&dir)?
+      .args(["new", "--title", title, "--description", "", "--no-edit"])
+      .args(["--component", component])
+      .assert()
+      .success();
+    Ok(())
+  };
+  new("Flickers", "TUI")?;
+  assert!(list(&dir, &["--assignee", "Jane Doe"])?.contains("Flickers"));
+  // Components without owners fall back to the default assignee
+  new("Hook fails", "hooks")?;
+  assert!(list(&dir, &["--assignee", "me"])?.contains("Hook fails"));
+  let output = ticket(&dir)?
+    .args(["new", "--title", "Nope", "--no-edit", "--component", "docs"])
+    .output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("hooks, tui"));
+
+  let _ = ticket(&dir)?
+    .args(["component", id, "tui"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("Component: tui"));
+  let _ = ticket(&dir)?
+    .args(["component", id, "--clear"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(!String::from_utf8(output.stdout)?.contains("Component:"));
+  Ok(())
+}
+
+#[test]
+fn custom_fields() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let config = dir.path().join(".dev-suite").join("repo-config.toml");
+  fs::write(
+    &config,
+    "maintainers = []\n\
+     [[fields]]\n\
+     name = 'severity'\n\
+     type = 'enum'\n\
+     required = true\n\
+     values = ['low', 'high']\n\
+     [[fields]]\n\
+     name = 'customers'\n\
+     type = 'number'\n",
+  )?;
+  let new = |args: &[&str]| -> Result<Output, Box<dyn Error>> {
+    Ok(
+      ticket(&dir)?
+        .args(["new", "--description", "", "--no-edit"])
+        .args(args)
+        .output()?,
+    )
+  };
+  let output = new(&["--title", "Crash"])?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("severity is required"));
+  let output = new(&["--title", "Crash", "--field", "severity=urgent"])?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("low, high"));
+  let output = new(&[
+    "--title",
+    "Crash",
+    "--field",
+    "severity=HIGH",
+    "--field",
+    "customers=12",
+  ])?;
+  assert!(output.status.success());
+
+  let path = dir.path().join(".dev-suite/ticket/open/crash.toml");
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  assert_eq!(saved["fields"]["severity"].as_str(), Some("high"));
+  assert_eq!(saved["fields"]["customers"].as_float(), Some(12.0));
+  let listed = list(&dir, &["--field", "severity=high"])?;
+  assert!(listed.contains("Crash"));
+  assert!(!listed.contains("Write docs"));
+
+  let _ = ticket(&dir)?
+    .args(["set", id, "severity", "low"])
+    .assert()
+    .success();
+  assert!(list(&dir, &["--field", "severity=low"])?.contains("Write docs"));
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("severity: low"));
+  let output = ticket(&dir)?
+    .args(["set", id, "customers", "many"])
+    .output()?;
+  assert!(!output.status.success());
+
+  // The build ticket doesn't have the required severity
+  let output = ticket(&dir)?.args(["fsck"]).output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("severity is required"));
+
+  // Tickets made with nobody to ask for fields are warned about instead
+  fs::write(dir.path().join("lib.rs"), "// TODO(ds): Handle errors\n")?;
+  let output = ticket(&dir)?.arg("scan").output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stderr)?
+    .contains("doesn't have the required fields severity"));
+
+  fs::write(
+    &config,
+    "maintainers = []\n[[fields]]\nname = 'severity'\ntype = 'enum'\n",
+  )?;
+  let output = ticket(&dir)?
+    .args(["set", id, "severity", "low"])
+    .output()?;
+  assert!(String::from_utf8(output.stderr)?.contains("needs a list of values"));
+  Ok(())
+}
+
+#[test]
+fn show_as_json_and_toml() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "0d6df400-1553-11ea-8001-000502040903";
+  let output = ticket(&dir)?
+    .args(["show", id, "--format", "json"])
+    .output()?;
+  let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
+  assert_eq!(json["title"], "Fix the build");
+  assert_eq!(json["assignees"][0][1], "Tester");
+  let output = ticket(&dir)?
+    .args(["show", id, "--format", "toml"])
+    .output()?;
+  let toml = String::from_utf8(output.stdout)?.parse::<toml::Value>()?;
+  assert_eq!(toml["status"].as_str(), Some("Open"));
+  Ok(())
+}
+
+#[test]
+fn search_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let search = |args: &[&str]| -> Result<String, Box<dyn Error>> {
+    let output = ticket(&dir)?.arg("search").args(args).output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+  };
+  let _ = ticket(&dir)?
+    .args(["comment", "2d6df400-1553-11ea-8001-000502040903"])
+    .arg("Seen again\nthe BUILD broke\nafter the fix")
+    .assert()
+    .success();
+
+  let out = search(&["build"])?;
+  assert!(out.contains("Fix the build"));
+  assert!(!out.contains("Old bug"));
+  let out = search(&["build", "--ignore-case", "--field", "comments"])?;
+  assert!(!out.contains("title:"));
+  assert!(out.contains("Old bug"));
+  assert!(out.contains("1- Seen again"));
+  assert!(search(&["^Old", "--regex"])?.contains("Old bug"));
+  assert!(search(&["^Old"])?.is_empty());
+
+  // The index is kept up to date as tickets change
+  assert!(dir.path().join(".git/ds-cache/search-index.json").exists());
+  assert!(search(&["zebra"])?.is_empty());
+  let _ = ticket(&dir)?
+    .args(["comment", "1d6df400-1553-11ea-8001-000502040903", "Zebras"])
+    .assert()
+    .success();
+  assert!(search(&["zebra", "-i"])?.contains("Write docs"));
+  Ok(())
+}
+
+#[test]
+fn grep_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let grep = |args: &[&str]| -> Result<Output, Box<dyn Error>> {
+    Ok(ticket(&dir)?.arg("grep").args(args).output()?)
+  };
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Haystack", "--no-edit"])
+    .args(["--description", "first\na needle here\nlast"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["comment", "1d6df400-1553-11ea-8001-000502040903"])
+    .arg("No NEEDLE in\nthis one")
+    .assert()
+    .success();
+
+  let output = grep(&["needle"])?;
+  assert!(output.status.success());
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("Haystack\ndescription:2:a needle here\n"));
+  assert!(!out.contains("Write docs"));
+
+  let output = grep(&["-i", "--no-heading", "^no needle"])?;
+  let out = String::from_utf8(output.stdout)?;
+  let lines = out.lines().collect::<Vec<&str>>();
+  assert_eq!(lines.len(), 1);
+  assert!(lines[0].contains(":comment-"));
+  assert!(lines[0].ends_with(":1:No NEEDLE in"));
+
+  // Like grep, finding nothing is a failure
+  let output = grep(&["zebra"])?;
+  assert_eq!(output.status.code(), Some(1));
+  assert!(output.stdout.is_empty());
+  Ok(())
+}
+
+#[test]
+fn remind_due_and_blocked() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let remind = || -> Result<String, Box<dyn Error>> {
+    let output = ticket(&dir)?.arg("remind").output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+  };
+  assert!(remind()?.is_empty());
+
+  let _ = ticket(&dir)?
+    .args(["due", build, "2000-01-01"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["link", build, "blocked-by", docs])
+    .assert()
+    .success();
+  let out = remind()?;
+  assert!(out.contains("overdue"));
+  assert!(out.contains("is blocked by"));
+  // Blockers are only mentioned the first time
+  let out = remind()?;
+  assert!(out.contains("overdue"));
+  assert!(!out.contains("is blocked by"));
+
+  let _ = ticket(&dir)?
+    .args(["due", build, "--clear"])
+    .assert()
+    .success();
+  assert!(remind()?.is_empty());
+  Ok(())
+}
+
+#[test]
+fn lock_ticket() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let config = dir.path().join(".dev-suite").join("repo-config.toml");
+  fs::write(&config, "maintainers = []\n")?;
+  let output = ticket(&dir)?.args(["lock", id]).output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("Only maintainers"));
+
+  fs::write(&config, format!("maintainers = [['Tester', '{}']]\n", USER))?;
+  let _ = ticket(&dir)?.args(["lock", id]).assert().success();
+  let output = ticket(&dir)?.args(["comment", id, "Me too"]).output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("is locked"));
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("(locked)"));
+
+  // It can still be closed with a reason, which goes in its history instead
+  let _ = ticket(&dir)?
+    .args(["close", id, "--reason", "Done in the wiki"])
+    .assert()
+    .success();
+  let closed = dir.path().join(".dev-suite/ticket/closed/write-docs.toml");
+  let saved = fs::read_to_string(closed)?.parse::<toml::Value>()?;
+  assert!(saved["comments"].as_table().is_some_and(|c| c.is_empty()));
+  let output = ticket(&dir)?.args(["history", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?
+    .contains("Open to Closed because: Done in the wiki"));
+
+  let _ = ticket(&dir)?.args(["unlock", id]).assert().success();
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Me too"])
+    .assert()
+    .success();
+  Ok(())
+}
+
+#[test]
+fn redact_comment() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["comment", id, "the password is hunter2"])
+    .assert()
+    .success();
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  let comment = saved["comments"]
+    .as_table()
+    .and_then(|c| c.keys().next())
+    .ok_or("The comment wasn't saved")?
+    .to_owned();
+
+  let _ = ticket(&dir)?
+    .args(["redact", id, &comment[..8], "--reason", "Leaked a password"])
+    .assert()
+    .success();
+  let saved = fs::read_to_string(&path)?;
+  assert!(!saved.contains("hunter2"));
+  assert!(saved.contains("Leaked a password"));
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("[Redacted by Tester"));
+  let _ = ticket(&dir)?
+    .args(["redact", id, &comment[..8], "--reason", "Again"])
+    .assert()
+    .failure();
+  Ok(())
+}
+
+#[test]
+fn close_with_reason() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["close", id, "--reason", "Written in the wiki instead"])
+    .assert()
+    .success();
+  let path = dir.path().join(".dev-suite/ticket/closed/write-docs.toml");
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  assert_eq!(saved["closed_by"][1].as_str(), Some("Tester"));
+  assert!(saved.get("closed_at").is_some());
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("Written in the wiki instead"));
+  assert!(out.contains("by Tester"));
+
+  let _ = ticket(&dir)?.args(["reopen", id]).assert().success();
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  assert!(!fs::read_to_string(&path)?.contains("closed_by"));
+  Ok(())
+}
+
+#[test]
+fn history_records_changes() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["tag", "add", id, "docs"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?.args(["close", id]).assert().success();
+
+  let path = dir.path().join(".dev-suite/ticket/closed/write-docs.toml");
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  let history = saved["history"].as_array().ok_or("No history")?;
+  assert_eq!(history.len(), 2);
+  assert_eq!(history[1]["by"][1].as_str(), Some("Tester"));
+
+  let output = ticket(&dir)?.args(["history", id]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  let lines = out.lines().collect::<Vec<&str>>();
+  assert_eq!(lines.len(), 2);
+  assert!(lines[0].ends_with("Tester Added the tag 'docs'"));
+  assert!(lines[1].ends_with("Tester Moved the ticket from Open to Closed"));
+
+  // Changes also go in the audit log shared by every tool
+  let log = dir.path().join(".dev-suite/log").join(USER);
+  let log = fs::read_to_string(log.with_extension("jsonl"))?;
+  let entries = log
+    .lines()
+    .map(serde_json::from_str)
+    .collect::<Result<Vec<serde_json::Value>, _>>()?;
+  assert_eq!(entries.len(), 2);
+  assert_eq!(entries[1]["tool"], "ticket");
+  assert_eq!(entries[1]["who"]["name"], "Tester");
+  assert!(entries[1]["action"]
+    .as_str()
+    .is_some_and(|a| a.ends_with("Moved the ticket from Open to Closed")));
+  Ok(())
+}
+
+#[test]
+fn only_saved_tickets_are_logged() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  // Nobody to encrypt for so the ticket is never saved
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Leak", "--description", "Secret"])
+    .args(["--no-edit", "--encrypt"])
+    .assert()
+    .failure();
+  let log = dir.path().join(".dev-suite/log").join(USER);
+  assert!(!log.with_extension("jsonl").exists());
+
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Kept", "--description", "", "--no-edit"])
+    .assert()
+    .success();
+  let log = fs::read_to_string(log.with_extension("jsonl"))?;
+  assert_eq!(log.lines().count(), 1);
+  assert!(log.contains("Created the ticket 'Kept'"));
+  Ok(())
+}
+
+#[test]
+fn comments_stay_in_order() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let _ = ticket(&dir)?
+    .args(["comment", id, "First"])
+    .assert()
+    .success();
+
+  // Pretend the first comment came from a machine whose clock is far ahead
+  let mut saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  let times = saved["comment_times"]
+    .as_table_mut()
+    .ok_or("No comment times")?;
+  for (_, time) in times.iter_mut() {
+    *time = toml::Value::String("2100-01-01T00:00:00Z".into());
+  }
+  fs::write(&path, toml::to_string(&saved)?)?;
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Second"])
+    .assert()
+    .success();
+
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  let times = saved["comment_times"]
+    .as_table()
+    .ok_or("No comment times")?
+    .values()
+    .map(|t| t.as_str().unwrap_or_default().to_owned())
+    .collect::<Vec<String>>();
+  assert!(times.iter().all(|t| t.starts_with("2100-01-01T00:00:00")));
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  let first = out.find("First").ok_or("No first comment")?;
+  let second = out.find("Second").ok_or("No second comment")?;
+  assert!(first < second);
+  assert!(out.contains(" 2100-01-0"));
+  Ok(())
+}
+
+#[test]
+fn sort_by_activity() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["comment", docs, "Started on these"])
+    .assert()
+    .success();
+  let out = list(&dir, &["--all", "--sort", "activity"])?;
+  let lines = out.lines().collect::<Vec<&str>>();
+  assert!(lines[0].contains("Write docs"));
+  assert!(lines[0].ends_with("updated just now"));
+  assert!(lines[1].ends_with("years ago"));
+  Ok(())
+}
+
+#[test]
+fn sort_listings() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let titles = |args: &[&str]| -> Result<Vec<String>, Box<dyn Error>> {
+    let out = list(&dir, &[&["--all", "--sort"], args].concat())?;
+    Ok(
+      ["Fix the build", "Write docs", "Old bug", "Newest"]
+        .iter()
+        .filter_map(|title| Some((out.find(title)?, title.to_string())))
+        .collect::<BTreeMap<usize, String>>()
+        .into_values()
+        .collect(),
+    )
+  };
+  assert_eq!(
+    titles(&["title"])?,
+    ["Fix the build", "Old bug", "Write docs"]
+  );
+  assert_eq!(
+    titles(&["title", "--reverse"])?,
+    ["Write docs", "Old bug", "Fix the build"]
+  );
+  assert_eq!(titles(&["assignee"])?[0], "Fix the build");
+
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Newest", "--description", "", "--no-edit"])
+    .assert()
+    .success();
+  assert_eq!(titles(&["created"])?[0], "Newest");
+
+  let output = ticket(&dir)?
+    .args(["list", "--sort", "priority"])
+    .output()?;
+  assert!(!output.status.success());
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    "maintainers = []\n[[fields]]\nname = 'priority'\ntype = 'enum'\n\
+     values = ['high', 'low']\n",
+  )?;
+  for (id, priority) in [(build, "low"), (docs, "high")] {
+    let _ = ticket(&dir)?
+      .args(["set", id, "priority", priority])
+      .assert()
+      .success();
+  }
+  assert_eq!(
+    titles(&["priority"])?[..2],
+    ["Write docs".to_owned(), "Fix the build".to_owned()]
+  );
+  Ok(())
+}
+
+#[test]
+fn react_to_comment() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Shipped it"])
+    .assert()
+    .success();
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  let comment = saved["comments"]
+    .as_table()
+    .and_then(|c| c.keys().next())
+    .ok_or("The comment wasn't saved")?
+    .to_owned();
+
+  for emoji in &["+1", "🎉", "👍"] {
+    let _ = ticket(&dir)?
+      .args(["react", id, &comment[..8], emoji])
+      .assert()
+      .success();
+  }
+  let _ = ticket(&dir)?
+    .args(["react", id, &comment[..8], "nope"])
+    .assert()
+    .failure();
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("🎉 1  👍 1"));
+
+  let _ = ticket(&dir)?
+    .args(["react", id, &comment[..8], "🎉", "--remove"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["react", id, &comment[..8], "+1", "--remove"])
+    .assert()
+    .success();
+  assert!(!fs::read_to_string(&path)?.contains("reactions"));
+  Ok(())
+}
+
+#[cfg(not(windows))]
+#[test]
+fn open_ticket_file() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let open = dir.path().join(".dev-suite/ticket/open");
+  let editor = dir.path().join("editor.sh");
+  let open_with = |sed: &str| -> Result<Output, Box<dyn Error>> {
+    fs::write(&editor, format!("#!/bin/sh\nsed -i \"{}\" \"$1\"\n", sed))?;
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755))?;
+    Ok(
+      ticket(&dir)?
+        .env("EDITOR", &editor)
+        .args(["open", id])
+        .output()?,
+    )
+  };
+
+  let before = fs::read_to_string(open.join("write-docs.toml"))?;
+  let output = open_with("s/^status = .*/status = 'Nowhere'/")?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("isn't a status"));
+  assert_eq!(fs::read_to_string(open.join("write-docs.toml"))?, before);
+
+  let output = open_with("s/^title = .*/title = 'Write the docs'/")?;
+  assert!(output.status.success());
+  assert!(!open.join("write-docs.toml").exists());
+  assert!(open.join("write-the-docs.toml").exists());
+  let output = ticket(&dir)?.args(["history", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("Edited the ticket file"));
+
+  let output = open_with("s/^status = .*/status = 'Closed'/")?;
+  assert!(output.status.success());
+  assert!(dir
+    .path()
+    .join(".dev-suite/ticket/closed/write-the-docs.toml")
+    .exists());
+  Ok(())
+}
+
+#[cfg(not(windows))]
+#[test]
+fn edit_title_and_description() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let open = dir.path().join(".dev-suite/ticket/open");
+  let editor = dir.path().join("editor.sh");
+  fs::write(
+    &editor,
+    "#!/bin/sh\necho 'Cover the sync remotes' > \"$1\"\n",
+  )?;
+  fs::set_permissions(&editor, fs::Permissions::from_mode(0o755))?;
+
+  let _ = ticket(&dir)?
+    .env("EDITOR", &editor)
+    .args(["edit", id, "--title", "Write the docs"])
+    .assert()
+    .success();
+  assert!(!open.join("write-docs.toml").exists());
+  let contents = fs::read_to_string(open.join("write-the-docs.toml"))?;
+  assert!(contents.contains("title = 'Write the docs'"));
+  assert!(contents.contains("Cover the sync remotes"));
+  let output = ticket(&dir)?.args(["history", id]).output()?;
+  let history = String::from_utf8(output.stdout)?;
+  assert!(history.contains("Renamed the ticket from 'Write docs'"));
+  assert!(history.contains("Edited the description"));
+
+  // Leaving things as they were doesn't record anything
+  let _ = ticket(&dir)?
+    .env("EDITOR", &editor)
+    .args(["edit", id, "--title", "Write the docs"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?.args(["history", id]).output()?;
+  assert_eq!(String::from_utf8(output.stdout)?, history);
+
+  let output = ticket(&dir)?
+    .args(["edit", id, "--title", " ", "--no-edit"])
+    .output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("Title may not be empty"));
+  assert!(open.join("write-the-docs.toml").exists());
+  Ok(())
+}
+
+#[cfg(not(windows))]
+#[test]
+fn hooks_run_on_events() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let hooks = dir.path().join(".dev-suite/ticket/hooks");
+  fs::create_dir_all(&hooks)?;
+  let write_hook = |name: &str, script: &str| -> Result<(), Box<dyn Error>> {
+    let path = hooks.join(name);
+    fs::write(&path, script)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+  };
+  write_hook(
+    "on-close",
+    "#!/bin/sh\n\
+     echo \"- $TICKET_TITLE ($TICKET_COMMENT)\" >> CHANGELOG.md\n\
+     cat > closed.json\n",
+  )?;
+  write_hook("on-comment", "#!/bin/sh\necho 'No comments' >&2\nexit 1\n")?;
+
+  // Hooks only run in clones that have turned them on
+  let id = "0d6df400-1553-11ea-8001-000502040903";
+  let output = ticket(&dir)?.args(["comment", id, "Before"]).output()?;
+  assert!(output.status.success());
+  let stderr = String::from_utf8(output.stderr)?;
+  assert!(!stderr.contains("No comments"));
+  assert!(stderr.contains("ticket init --hooks"));
+  let _ = ticket(&dir)?.args(["init", "--hooks"]).assert().success();
+
+  let _ = ticket(&dir)?
+    .args(["close", id, "--reason", "Fixed in CI"])
+    .assert()
+    .success();
+  assert_eq!(
+    fs::read_to_string(dir.path().join("CHANGELOG.md"))?,
+    "- Fix the build (Fixed in CI)\n"
+  );
+  let closed = fs::read_to_string(dir.path().join("closed.json"))?;
+  assert!(closed.contains(id));
+  assert!(closed.contains("\"Closed\""));
+
+  // A failing hook is warned about but the comment is still made
+  let output = ticket(&dir)?.args(["comment", id, "Thanks"]).output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("No comments"));
+  let saved = dir
+    .path()
+    .join(".dev-suite/ticket/closed/fix-the-build.toml");
+  assert!(fs::read_to_string(saved)?.contains("Thanks"));
+  Ok(())
+}
+
+#[test]
+fn merge_driver_combines_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let attributes = fs::read_to_string(dir.path().join(".gitattributes"))?;
+  assert!(attributes.contains("merge=ticket"));
+  let config = fs::read_to_string(dir.path().join(".git").join("config"))?;
+  assert!(config.contains("ticket merge-driver %O %A %B"));
+
+  // Make each side by changing the ticket from the same starting point
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let base = fs::read_to_string(&path)?;
+  let side = |tag: &str, comment: &str| -> Result<String, Box<dyn Error>> {
+    fs::write(&path, &base)?;
+    let _ = ticket(&dir)?
+      .args(["tag", "add", id, tag])
+      .assert()
+      .success();
+    let _ = ticket(&dir)?
+      .args(["comment", id, comment])
+      .assert()
+      .success();
+    Ok(fs::read_to_string(&path)?)
+  };
+  let ours = side("ours", "Comment from ours")?;
+  let theirs = side("theirs", "Comment from theirs")?;
+  let merge = |ours: &str, theirs: &str| -> Result<_, Box<dyn Error>> {
+    fs::write(dir.path().join("base.toml"), &base)?;
+    fs::write(dir.path().join("ours.toml"), ours)?;
+    fs::write(dir.path().join("theirs.toml"), theirs)?;
+    let output = ticket(&dir)?
+      .args(["merge-driver", "base.toml", "ours.toml", "theirs.toml"])
+      .output()?;
+    let merged = fs::read_to_string(dir.path().join("ours.toml"))?;
+    Ok((output.status.success(), merged))
+  };
+
+  let (clean, merged) = merge(&ours, &theirs)?;
+  assert!(clean);
+  assert!(merged.contains("Comment from ours"));
+  assert!(merged.contains("Comment from theirs"));
+  let tags = merged
+    .lines()
+    .skip_while(|line| !line.starts_with("tags = ["))
+    .take_while(|line| *line != "]")
+    .collect::<String>();
+  assert!(tags.contains("ours") && tags.contains("theirs"));
+
+  // Different changes to the same line of the description are left marked
+  let (clean, merged) = merge(
+    &ours.replace("description = ''", "description = 'Use mdbook'"),
+    &theirs.replace("description = ''", "description = 'Use rustdoc'"),
+  )?;
+  assert!(!clean);
+  assert!(merged.contains("<<<<<<<"));
+  assert!(merged.contains("Use mdbook") && merged.contains("Use rustdoc"));
+  Ok(())
+}
+
+#[test]
+fn markdown_format() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let attributes = fs::read_to_string(dir.path().join(".gitattributes"))?;
+  assert!(attributes.contains(".dev-suite/ticket/**/*.md merge=ticket"));
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    format!(
+      "maintainers = [['Tester', '{}']]\nformat = 'markdown'\n",
+      USER
+    ),
+  )?;
+
+  let description = "# Steps\n\nRun `ticket` and **look**";
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Rendered", "--description", description])
+    .arg("--no-edit")
+    .assert()
+    .success();
+  let open = dir.path().join(".dev-suite/ticket/open");
+  let saved = fs::read_to_string(open.join("rendered.md"))?;
+  assert!(saved.starts_with("+++\n"));
+  assert!(saved.ends_with(&format!("\n+++\n\n{}\n", description)));
+  assert!(!saved.contains("description ="));
+  let id = saved
+    .lines()
+    .find_map(|line| line.strip_prefix("id = "))
+    .unwrap_or_default()
+    .trim_matches(|c| c == '"' || c == '\'');
+  let output = ticket(&dir)?
+    .args(["show", id, "--format", "json"])
+    .output()?;
+  let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
+  assert_eq!(json["description"], description);
+
+  // A comment with a fence line in it doesn't end the frontmatter
+  let fenced = "before\n+++\nafter";
+  let _ = ticket(&dir)?
+    .args(["comment", id, fenced])
+    .assert()
+    .success();
+  assert!(list(&dir, &[])?.contains("Rendered"));
+  let output = ticket(&dir)?
+    .args(["show", id, "--format", "json"])
+    .output()?;
+  let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
+  assert_eq!(json["description"], description);
+  assert!(json["comments"]
+    .to_string()
+    .contains("before\\n+++\\nafter"));
+
+  // Tickets saved before the switch are still read and are converted once
+  // they're saved again
+  assert!(list(&dir, &[])?.contains("Write docs"));
+  let _ = ticket(&dir)?
+    .args([
+      "comment",
+      "1d6df400-1553-11ea-8001-000502040903",
+      "Converted",
+    ])
+    .assert()
+    .success();
+  assert!(!open.join("write-docs.toml").exists());
+  let saved = fs::read_to_string(open.join("write-docs.md"))?;
+  assert!(saved.contains("Converted"));
+
+  let output = ticket(&dir)?.arg("fsck").output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("repo's format"));
+  let _ = ticket(&dir)?.args(["fsck", "--fix"]).assert().success();
+  assert!(open.join("fix-the-build.md").exists());
+  assert!(!open.join("fix-the-build.toml").exists());
+  let _ = ticket(&dir)?.arg("fsck").assert().success();
+  assert_eq!(list(&dir, &["--all"])?.lines().count(), 4);
+  Ok(())
+}
+
+#[test]
+fn archive_old_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let _ = ticket(&dir)?
+    .args(["close", "1d6df400-1553-11ea-8001-000502040903"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["archive", "--before", "2020-01-01"])
+    .assert()
+    .success();
+  let closed = dir.path().join(".dev-suite/ticket/closed");
+  assert!(closed.join("archive").join("old-bug.toml").exists());
+  assert!(!closed.join("old-bug.toml").exists());
+  assert!(closed.join("write-docs.toml").exists());
+
+  let all = list(&dir, &["--all"])?;
+  assert!(!all.contains("Old bug"));
+  assert!(all.contains("Write docs"));
+  let id = "2d6df400-1553-11ea-8001-000502040903";
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stdout)?.contains("Old bug"));
+
+  // Changing an archived ticket brings it back out
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Happened again"])
+    .assert()
+    .success();
+  assert!(!closed.join("archive").join("old-bug.toml").exists());
+  assert!(list(&dir, &["--all"])?.contains("Old bug"));
+  Ok(())
+}
+
+#[test]
+fn dedupe_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  for (title, description) in [
+    ("Login button is broken", "Clicking it does nothing"),
+    (
+      "The login button is broken",
+      "Clicking the button does nothing",
+    ),
+  ] {
+    let _ = ticket(&dir)?
+      .args(["new", "--title", title, "--description", description])
+      .arg("--no-edit")
+      .assert()
+      .success();
+  }
+
+  let output = ticket(&dir)?.arg("dedupe").output()?;
+  assert!(output.status.success());
+  let out = String::from_utf8(output.stdout)?;
+  assert!(out.contains("% alike"));
+  assert!(out.contains("Login button is broken"));
+  assert!(!out.contains("Write docs"));
+
+  let _ = ticket(&dir)?
+    .args(["dedupe", "--interactive"])
+    .with_stdin()
+    .buffer("y\n")
+    .assert()
+    .success();
+  let open = dir.path().join(".dev-suite/ticket/open");
+  let closed = dir.path().join(".dev-suite/ticket/closed");
+  assert!(open.join("login-button-is-broken.toml").exists());
+  let duplicate =
+    fs::read_to_string(closed.join("the-login-button-is-broken.toml"))?;
+  assert!(duplicate.contains("duplicate_of"));
+  assert!(duplicate.contains("Duplicate of"));
+  let original = fs::read_to_string(open.join("login-button-is-broken.toml"))?;
+  assert!(original.contains("duplicated_by"));
+
+  let output = ticket(&dir)?.arg("dedupe").output()?;
+  assert!(String::from_utf8(output.stdout)?.is_empty());
+  Ok(())
+}
+
+#[test]
+fn encrypted_tickets() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let gnupg = dir.path().join(".gnupg");
+  fs::create_dir(&gnupg)?;
+  #[cfg(not(windows))]
+  fs::set_permissions(&gnupg, fs::Permissions::from_mode(0o700))?;
+  let key = Command::new("gpg")
+    .env("GNUPGHOME", &gnupg)
+    .args(["--batch", "--passphrase", "", "--quick-gen-key"])
+    .args(["tester@example.com", "default", "default", "never"])
+    .output();
+  if !key.is_ok_and(|out| out.status.success()) {
+    eprintln!("Skipping since gpg isn't able to make a key");
+    return Ok(());
+  }
+  let ticket = |dir| -> Result<Command, Box<dyn Error>> {
+    let mut cmd = ticket(dir)?;
+    let _ = cmd.env("GNUPGHOME", &gnupg);
+    Ok(cmd)
+  };
+
+  // Nobody to encrypt for yet
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Leak", "--description", "Secret"])
+    .args(["--no-edit", "--encrypt"])
+    .assert()
+    .failure();
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    format!(
+      "maintainers = [['Tester', '{0}']]\n\n[keys]\n'{0}' = \
+       'tester@example.com'\n",
+      USER
+    ),
+  )?;
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Leak", "--description", "Secret"])
+    .args(["--no-edit", "--encrypt"])
+    .assert()
+    .success();
+  let path = dir.path().join(".dev-suite/ticket/open/leak.toml");
+  let saved = fs::read_to_string(&path)?;
+  assert!(saved.contains("-----BEGIN PGP MESSAGE-----"));
+  assert!(saved.contains("encrypted = true"));
+  assert!(!saved.contains("Secret"));
+  assert!(list(&dir, &[])?.contains("Leak"));
+
+  let id = "0d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Before encrypting"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?.args(["encrypt", id]).assert().success();
+  let _ = ticket(&dir)?
+    .args(["comment", id, "After encrypting"])
+    .assert()
+    .success();
+  let path = dir.path().join(".dev-suite/ticket/open/fix-the-build.toml");
+  let saved = fs::read_to_string(&path)?;
+  assert!(!saved.contains("Before encrypting"));
+  assert!(!saved.contains("After encrypting"));
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  let shown = String::from_utf8(output.stdout)?;
+  assert!(shown.contains("Before encrypting"));
+  assert!(shown.contains("After encrypting"));
+  assert!(!shown.contains("PGP MESSAGE"));
+
+  // Someone without the key still sees the rest of the ticket
+  let output = ticket(&dir)?
+    .env("GNUPGHOME", dir.path().join("elsewhere"))
+    .args(["show", id])
+    .output()?;
+  assert!(output.status.success());
+  let shown = String::from_utf8(output.stdout)?;
+  assert!(shown.contains("Fix the build"));
+  assert!(shown.contains("PGP MESSAGE"));
+
+  let _ = ticket(&dir)?.args(["decrypt", id]).assert().success();
+  let saved = fs::read_to_string(&path)?;
+  assert!(saved.contains("Before encrypting"));
+  assert!(saved.contains("After encrypting"));
+  assert!(!saved.contains("encrypted"));
+  let _ = Command::new("gpgconf")
+    .env("GNUPGHOME", &gnupg)
+    .args(["--kill", "gpg-agent"])
+    .output();
+  Ok(())
+}
+
+#[test]
+fn signed_comments_and_changes() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let key = dir.path().join("id_ed25519");
+  let made = Command::new("ssh-keygen")
+    .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+    .arg(&key)
+    .output();
+  if !made.is_ok_and(|out| out.status.success()) {
+    eprintln!("Skipping since ssh-keygen isn't able to make a key");
+    return Ok(());
+  }
+  let id = "0d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Unsigned"])
+    .assert()
+    .success();
+  fs::write(
+    dir.path().join("config/dev-suite/user-config.toml"),
+    format!(
+      "name = 'Tester'\nuuid = '{}'\n\n[signing]\nformat = 'ssh'\nkey = \
+       '{}'\n",
+      USER,
+      key.display()
+    ),
+  )?;
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Signed"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["tag", "add", id, "signed"])
+    .assert()
+    .success();
+
+  let path = dir.path().join(".dev-suite/ticket/open/fix-the-build.toml");
+  let saved = fs::read_to_string(&path)?;
+  let ticket_file = toml::from_str::<toml::Value>(&saved)?;
+  let signatures = ticket_file["signatures"].as_table().unwrap();
+  assert_eq!(signatures.len(), 1);
+  let (signed, signature) = signatures.iter().next().unwrap();
+  assert!(signature
+    .as_str()
+    .unwrap()
+    .starts_with("-----BEGIN SSH SIGNATURE-----"));
+  let comment = &ticket_file["comments"][signed.as_str()];
+  assert_eq!(comment[2].as_str(), Some("Signed"));
+  let tagged = ticket_file["history"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|e| e["action"].as_str() == Some("Added the tag 'signed'"))
+    .unwrap();
+  assert!(tagged.get("signature").is_some());
+
+  // A redacted comment no longer matches its signature so it's dropped
+  let _ = ticket(&dir)?
+    .args(["redact", id, &signed[..8], "--reason", "Oops"])
+    .assert()
+    .success();
+  let saved = fs::read_to_string(&path)?;
+  assert!(!saved.contains("[signatures]"));
+  Ok(())
+}
+
+#[test]
+fn verify_signatures() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let key = dir.path().join("id_ed25519");
+  let made = Command::new("ssh-keygen")
+    .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+    .arg(&key)
+    .output();
+  if !made.is_ok_and(|out| out.status.success()) {
+    eprintln!("Skipping since ssh-keygen isn't able to make a key");
+    return Ok(());
+  }
+  let public = fs::read_to_string(key.with_extension("pub"))?;
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    format!(
+      "maintainers = [['Tester', '{0}']]\n\n[keys]\n'{0}' = '{1}'\n",
+      USER,
+      public.trim()
+    ),
+  )?;
+  let user_config = dir.path().join("config/dev-suite/user-config.toml");
+  let signing = format!(
+    "name = 'Tester'\nuuid = '{}'\n\n[signing]\nformat = 'ssh'\nkey = '{}'\n",
+    USER,
+    key.display()
+  );
+  fs::write(&user_config, &signing)?;
+
+  let id = "0d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Looks good"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?.args(["close", id]).assert().success();
+  let output = ticket(&dir)?.arg("verify").output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("All 2 signatures"));
+
+  // Changing what a comment says breaks its signature
+  let path = dir
+    .path()
+    .join(".dev-suite/ticket/closed/fix-the-build.toml");
+  let saved = fs::read_to_string(&path)?;
+  fs::write(&path, saved.replace("Looks good", "Looks bad"))?;
+  let output = ticket(&dir)?.arg("verify").output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("bad signature"));
+  fs::write(&path, saved)?;
+
+  // A redacted comment is skipped as long as its redaction was signed
+  let comment = fs::read_to_string(&path)?.parse::<toml::Value>()?["comments"]
+    .as_table()
+    .and_then(|c| c.keys().next())
+    .ok_or("The comment wasn't saved")?
+    .to_owned();
+  let _ = ticket(&dir)?
+    .args(["redact", id, &comment[..8], "--reason", "Oops"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?.arg("verify").assert().success();
+
+  // Listing a comment as redacted doesn't let it get by unsigned
+  let saved = fs::read_to_string(&path)?;
+  let mut forged = saved.parse::<toml::Value>()?;
+  let forged_id = "3d6df400-1553-11ea-8001-000502040903";
+  let marker = "[Redacted by Tester on 2020-01-01: Spam]";
+  let entries = [
+    (forged_id, marker),
+    (
+      "4d6df400-1553-11ea-8001-000502040903",
+      "-----BEGIN PGP MESSAGE-----\ngarbage",
+    ),
+  ];
+  for (comment, text) in entries {
+    let _ = forged["comments"]
+      .as_table_mut()
+      .ok_or("No comments")?
+      .insert(
+        comment.into(),
+        toml::Value::try_from((USER, "Tester", text))?,
+      );
+  }
+  let mut redaction = toml::value::Table::new();
+  for (key, value) in [
+    ("comment", forged_id),
+    ("by", USER),
+    ("name", "Tester"),
+    ("reason", "Spam"),
+    ("at", "2020-01-01T00:00:00Z"),
+  ] {
+    let _ = redaction.insert(key.into(), value.into());
+  }
+  forged["redactions"]
+    .as_array_mut()
+    .ok_or("No redactions")?
+    .push(redaction.into());
+  fs::write(&path, toml::to_string(&forged)?)?;
+  let output = ticket(&dir)?.arg("verify").output()?;
+  assert!(!output.status.success());
+  let stderr = String::from_utf8(output.stderr)?;
+  assert!(stderr.contains("without a signed change redacting it"));
+  // Nor does a comment that only looks encrypted
+  assert!(stderr.contains("can't be decrypted"));
+  assert!(stderr.contains("Found 2 unsigned"));
+  fs::write(&path, saved)?;
+
+  // So does not signing at all
+  fs::write(
+    &user_config,
+    format!("name = 'Tester'\nuuid = '{}'\n", USER),
+  )?;
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Unsigned"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?.arg("verify").output()?;
+  assert!(!output.status.success());
+  let stderr = String::from_utf8(output.stderr)?;
+  assert!(stderr.contains("isn't signed"));
+  assert!(stderr.contains("Found 1 unsigned"));
+  // Entries from before a date can be left out
+  let tomorrow = (chrono::Local::now() + chrono::Duration::days(1))
+    .format("%Y-%m-%d")
+    .to_string();
+  let _ = ticket(&dir)?
+    .args(["verify", "--since", &tomorrow])
+    .assert()
+    .success();
+  Ok(())
+}
+
+#[test]
+fn verify_with_trusted_keys() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let mut public = BTreeMap::new();
+  for name in ["old", "new", "forged"] {
+    let key = dir.path().join(name);
+    let made = Command::new("ssh-keygen")
+      .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+      .arg(&key)
+      .output();
+    if !made.is_ok_and(|out| out.status.success()) {
+      eprintln!("Skipping since ssh-keygen isn't able to make a key");
+      return Ok(());
+    }
+    let _ = public.insert(
+      name,
+      fs::read_to_string(key.with_extension("pub"))?
+        .trim()
+        .to_owned(),
+    );
+  }
+  let config = dir.path().join(".dev-suite").join("repo-config.toml");
+  let with_key = |key: &str| {
+    format!(
+      "maintainers = [['Tester', '{0}']]\n\n[keys]\n'{0}' = '{1}'\n",
+      USER, key
+    )
+  };
+  fs::write(&config, with_key(&public["old"]))?;
+  let repo = git2::Repository::open(dir.path())?;
+  let mut index = repo.index()?;
+  index.add_all(["."], git2::IndexAddOption::DEFAULT, None)?;
+  index.write()?;
+  let tree = repo.find_tree(index.write_tree()?)?;
+  let sig = git2::Signature::now("Tester", "tester@example.com")?;
+  let _ = repo.commit(Some("HEAD"), &sig, &sig, "Keys", &tree, &[])?;
+
+  let user_config = dir.path().join("config/dev-suite/user-config.toml");
+  let sign_with = |name: &str| {
+    fs::write(
+      &user_config,
+      format!(
+        "name = 'Tester'\nuuid = '{}'\n\n[signing]\nformat = 'ssh'\n\
+         key = '{}'\n",
+        USER,
+        dir.path().join(name).display()
+      ),
+    )
+  };
+  let id = "0d6df400-1553-11ea-8001-000502040903";
+  let verify = || -> Result<Output, Box<dyn Error>> {
+    Ok(
+      ticket(&dir)?
+        .args(["verify", "--keys-from", "HEAD"])
+        .output()?,
+    )
+  };
+
+  // Swapping in another key passes when the pushed config is trusted but not
+  // against the keys that were already there
+  fs::write(&config, with_key(&public["forged"]))?;
+  sign_with("forged")?;
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Forged"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?.arg("verify").assert().success();
+  let output = verify()?;
+  assert!(!output.status.success());
+  let stderr = String::from_utf8(output.stderr)?;
+  assert!(stderr.contains("without being signed by a trusted key"));
+  assert!(stderr.contains("signed by someone without a key"));
+
+  // Nor can the new key vouch for itself
+  let _ = ticket(&dir)?
+    .args(["trust", USER, &public["forged"]])
+    .assert()
+    .success();
+  assert!(fs::read_to_string(&config)?.contains("[key_signatures."));
+  assert!(!verify()?.status.success());
+
+  // Changing a key is trusted when the old key signed it
+  let path = dir.path().join(".dev-suite/ticket/open/fix-the-build.toml");
+  let _ = ticket(&dir)?
+    .args(["delete", id, "--force"])
+    .assert()
+    .success();
+  assert!(!path.exists());
+  fs::write(&config, with_key(&public["old"]))?;
+  sign_with("old")?;
+  let _ = ticket(&dir)?
+    .args(["trust", USER, &public["new"]])
+    .assert()
+    .success();
+  sign_with("new")?;
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Rotated", "--no-edit"])
+    .assert()
+    .success();
+  let output = verify()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("signatures are good"));
+  let _ = ticket(&dir)?
+    .args(["trust", "3d6df400-1553-11ea-8001-000502040903", "key"])
+    .assert()
+    .failure();
+  Ok(())
+}
+
+#[test]
+fn uuid_prefixes() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  write_ticket(
+    dir.path(),
+    "Open",
+    "Fix the tests",
+    "0d6df400-1553-11ea-8001-000502040904",
+    "",
+  )?;
+  let output = ticket(&dir)?.args(["show", "1D6DF4"]).output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stdout)?.contains("Write docs"));
+  let output = ticket(&dir)?.args(["show", "1d6df4001553"]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("Write docs"));
+  let _ = ticket(&dir)?
+    .args(["comment", "2d6d", "Still broken"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?.args(["show", "2d6d"]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("Still broken"));
+
+  // Too short to be taken as a prefix
+  let _ = ticket(&dir)?.args(["show", "1d6"]).assert().failure();
+  let output = ticket(&dir)?.args(["close", "0d6df400"]).output()?;
+  assert!(!output.status.success());
+  let stderr = String::from_utf8(output.stderr)?;
+  assert!(stderr.contains("More than one ticket matches id 0d6df400"));
+  assert!(stderr.contains("0d6df400-1553-11ea-8001-000502040903"));
+  assert!(stderr.contains("0d6df400-1553-11ea-8001-000502040904 "));
+  assert_eq!(list(&dir, &[])?.lines().count(), 3);
+  Ok(())
+}
+
+#[test]
+fn attachments_shown() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let file = dir.path().join("trace.log");
+  fs::write(&file, "panicked at main.rs")?;
+  let _ = ticket(&dir)?
+    .arg("attach")
+    .arg(id)
+    .arg(&file)
+    .assert()
+    .success();
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("trace.log"));
+
+  // Keys edited by hand can be shorter than the part of a hash that's shown
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let mut saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  let mut attachments = toml::value::Table::new();
+  let _ = attachments.insert("abc".into(), "short.txt".into());
+  let _ = saved
+    .as_table_mut()
+    .ok_or("The ticket isn't a table")?
+    .insert("attachments".into(), attachments.into());
+  fs::write(&path, toml::to_string(&saved)?)?;
+  let output = ticket(&dir)?.args(["show", id]).output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stdout)?.contains("short.txt (abc)"));
+  Ok(())
+}
+
+#[test]
+fn nested_repo_warned_about_once() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let nested = dir.path().join("vendor");
+  let _ = git2::Repository::init(&nested)?;
+  let output = ticket(&dir)?.current_dir(&nested).arg("list").output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stdout)?.contains("Fix the build"));
+  let stderr = String::from_utf8(output.stderr)?;
+  assert_eq!(stderr.matches("as the dev-suite root").count(), 1);
+  Ok(())
+}
+
+#[test]
+fn lookup_by_slug_and_title() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let output = ticket(&dir)?.args(["show", "fix-the-build"]).output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stdout)?.contains("Fix the build"));
+  let _ = ticket(&dir)?
+    .args(["comment", "write-docs", "By its file"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?
+    .args(["show", "--title", "write DOCS"])
+    .output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stdout)?.contains("By its file"));
+  let _ = ticket(&dir)?
+    .args(["show", "--title", "Write"])
+    .assert()
+    .failure();
+  let _ = ticket(&dir)?.arg("show").assert().failure();
+
+  // Tickets sharing a title are told apart by the short id in their file name
+  let _ = ticket(&dir)?
+    .args(["new", "--title", "Write docs", "--no-edit"])
+    .assert()
+    .success();
+  let output = ticket(&dir)?
+    .args(["show", "--title", "Write docs"])
+    .output()?;
+  assert!(!output.status.success());
+  let stderr = String::from_utf8(output.stderr)?;
+  assert!(stderr.contains("More than one ticket matches the title"));
+  assert!(stderr.contains("1d6df400-1553-11ea-8001-000502040903"));
+  let open = dir.path().join(".dev-suite/ticket/open");
+  let name = fs::read_dir(open)?
+    .filter_map(|entry| entry.ok())
+    .filter_map(|entry| entry.file_name().into_string().ok())
+    .find(|name| name.starts_with("write-docs-"))
+    .unwrap();
+  let slug = name.trim_end_matches(".toml");
+  let _ = ticket(&dir)?.args(["close", slug]).assert().success();
+  assert!(list(&dir, &[])?.contains("Write docs"));
+  assert!(list(&dir, &["--closed"])?.contains("Write docs"));
+  Ok(())
+}
+
+#[test]
+fn delete_ticket() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let build = "0d6df400-1553-11ea-8001-000502040903";
+  let docs = "1d6df400-1553-11ea-8001-000502040903";
+  let open = dir.path().join(".dev-suite/ticket/open");
+
+  // Anything but a yes leaves the ticket where it is
+  for answer in ["n\n", "\n", ""] {
+    let _ = ticket(&dir)?
+      .args(["delete", build])
+      .with_stdin()
+      .buffer(answer)
+      .assert()
+      .success();
+    assert!(open.join("fix-the-build.toml").exists());
+  }
+
+  // Tickets related to the deleted one, archived or not, stop pointing at it
+  let _ = ticket(&dir)?
+    .args(["link", docs, "blocked-by", build])
+    .assert()
+    .success();
+  let old = "2d6df400-1553-11ea-8001-000502040903";
+  let _ = ticket(&dir)?
+    .args(["link", old, "related-to", build])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["archive", "--before", "2100-01-01"])
+    .assert()
+    .success();
+  let archived = dir.path().join(".dev-suite/ticket/closed/archive");
+  assert!(archived.join("old-bug.toml").exists());
+
+  let _ = ticket(&dir)?
+    .args(["delete", build])
+    .with_stdin()
+    .buffer("y\n")
+    .assert()
+    .success();
+  assert!(!open.join("fix-the-build.toml").exists());
+  let _ = ticket(&dir)?.args(["show", build]).assert().failure();
+  let output = ticket(&dir)?.args(["show", docs]).output()?;
+  assert!(!String::from_utf8(output.stdout)?.contains("Blocked by"));
+  let output = ticket(&dir)?.args(["history", docs]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("since it was deleted"));
+  let old_bug = fs::read_to_string(archived.join("old-bug.toml"))?;
+  assert!(!old_bug.contains(build));
+  assert!(old_bug.contains("since it was deleted"));
+  let _ = ticket(&dir)?.arg("fsck").assert().success();
+
+  let _ = ticket(&dir)?
+    .args(["delete", docs, "--force"])
+    .assert()
+    .success();
+  assert!(!open.join("write-docs.toml").exists());
+  assert!(!list(&dir, &[])?.contains("Write docs"));
+  Ok(())
+}
+
+#[test]
+fn add_list_and_remove_remotes() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let remotes = dir.path().join(".dev-suite/ticket/remotes");
+  let _ = ticket(&dir)?
+    .args(["remote", "add", "mirror", "github"])
+    .args(["https://github.com/a/b", "--push-only"])
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["remote", "add", "srht", "SourceHut"])
+    .arg("https://todo.sr.ht/~a/b")
+    .assert()
+    .success();
+  assert!(fs::read_to_string(remotes.join("mirror.toml"))?
+    .contains("direction = 'Push'"));
+  assert!(fs::read_to_string(remotes.join("srht.toml"))?
+    .contains("direction = 'Both'"));
+
+  let output = ticket(&dir)?.args(["remote", "list"]).output()?;
+  assert!(output.status.success());
+  let out = String::from_utf8(output.stdout)?;
+  let lines = out.lines().collect::<Vec<_>>();
+  assert_eq!(lines.len(), 2);
+  assert!(lines[0].contains("https://github.com/a/b (push only"));
+  assert!(lines[1].contains("https://todo.sr.ht/~a/b (both ways"));
+
+  let output = ticket(&dir)?
+    .args(["remote", "add", "mirror", "gitlab"])
+    .arg("https://gitlab.com/a/b")
+    .output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("already a remote"));
+  let _ = ticket(&dir)?
+    .args(["remote", "add", "other", "jira", "https://example.com"])
+    .assert()
+    .failure();
+  let _ = ticket(&dir)?
+    .args(["remote", "add", "../up", "github", "https://github.com/a/b"])
+    .assert()
+    .failure();
+  assert!(!dir.path().join(".dev-suite/ticket/up.toml").exists());
+
+  let _ = ticket(&dir)?
+    .args(["remote", "remove", "mirror"])
+    .assert()
+    .success();
+  assert!(!remotes.join("mirror.toml").exists());
+  let output = ticket(&dir)?.args(["remote", "list"]).output()?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(!out.contains("github.com"));
+  assert!(out.contains("todo.sr.ht"));
+  let output = ticket(&dir)?
+    .args(["remote", "remove", "mirror"])
+    .output()?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("no remote named"));
+  Ok(())
+}
@@ -0,0 +1,172 @@
+//! Helpers shared by the integration tests for setting up a repo with tickets
+//! in it and running `ticket` against it
+// Not every test file uses every helper
+#![allow(dead_code)]
+
+use assert_cmd::prelude::*;
+use git2::Repository;
+use serde_json::Value;
+use std::{
+  error::Error,
+  fs,
+  io::{
+    Read,
+    Write,
+  },
+  net::TcpListener,
+  path::Path,
+  process::Command,
+  thread,
+};
+use tempfile::{
+  tempdir,
+  TempDir,
+};
+
+pub const USER: &str = "a4ad1e12-7aa2-4c2b-a3ce-32b6d8c10a5d";
+
+pub fn setup() -> Result<TempDir, Box<dyn Error>> {
+  let dir = tempdir()?;
+  let _ = Repository::init(&dir)?;
+  let _ = ticket(&dir)?.arg("init").assert().success();
+  let conf = dir.path().join("config").join("dev-suite");
+  fs::create_dir_all(&conf)?;
+  fs::write(
+    conf.join("user-config.toml"),
+    format!("name = 'Tester'\nuuid = '{}'\n", USER),
+  )?;
+  Ok(dir)
+}
+
+pub fn ticket(dir: &TempDir) -> Result<Command, Box<dyn Error>> {
+  let mut cmd = Command::cargo_bin("ticket")?;
+  let _ = cmd
+    .current_dir(dir)
+    .env("XDG_CONFIG_HOME", dir.path().join("config"));
+  Ok(cmd)
+}
+
+pub fn write_ticket(
+  dir: &Path,
+  status: &str,
+  title: &str,
+  id: &str,
+  assignees: &str,
+) -> Result<(), Box<dyn Error>> {
+  let file = title.to_lowercase().replace(' ', "-") + ".toml";
+  fs::write(
+    dir
+      .join(".dev-suite")
+      .join("ticket")
+      .join(status.to_lowercase())
+      .join(file),
+    format!(
+      "title = '{}'\nstatus = '{}'\nid = '{}'\nassignees = [{}]\n\
+       description = ''\nversion = 'V1'\n\n[comments]\n",
+      title, status, id, assignees
+    ),
+  )?;
+  Ok(())
+}
+
+pub fn list(dir: &TempDir, args: &[&str]) -> Result<String, Box<dyn Error>> {
+  let output = ticket(dir)?.arg("list").args(args).output()?;
+  assert!(output.status.success());
+  Ok(String::from_utf8(output.stdout)?)
+}
+
+pub fn fixture() -> Result<TempDir, Box<dyn Error>> {
+  let dir = setup()?;
+  write_ticket(
+    dir.path(),
+    "Open",
+    "Fix the build",
+    "0d6df400-1553-11ea-8001-000502040903",
+    &format!("['{}', 'Tester']", USER),
+  )?;
+  write_ticket(
+    dir.path(),
+    "Open",
+    "Write docs",
+    "1d6df400-1553-11ea-8001-000502040903",
+    "",
+  )?;
+  write_ticket(
+    dir.path(),
+    "Closed",
+    "Old bug",
+    "2d6df400-1553-11ea-8001-000502040903",
+    "",
+  )?;
+  Ok(dir)
+}
+
+/// The uuid in the saved ticket
+pub fn uuid(saved: &str) -> &str {
+  saved
+    .lines()
+    .find_map(|line| line.strip_prefix("id = "))
+    .unwrap_or_default()
+    .trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// Serves a json API on a local port and returns the url for it. Each request
+/// is answered with what `respond` returns for its method, path with the
+/// query, and body.
+pub fn fake_api(
+  respond: impl Fn(&str, &str, Value) -> Value + Send + 'static,
+) -> Result<String, Box<dyn Error>> {
+  let listener = TcpListener::bind("127.0.0.1:0")?;
+  let url = format!("http://{}", listener.local_addr()?);
+  let _ = thread::spawn(move || {
+    for stream in listener.incoming() {
+      let mut stream = match stream {
+        Ok(stream) => stream,
+        Err(_) => continue,
+      };
+      let mut request = Vec::new();
+      let mut buf = [0; 1024];
+      let end = loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+          break end + 4;
+        }
+        match stream.read(&mut buf) {
+          Ok(0) | Err(_) => break request.len(),
+          Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+      };
+      let head = String::from_utf8_lossy(&request[..end]).into_owned();
+      let length = head
+        .lines()
+        .find_map(|line| {
+          let (name, value) = line.split_once(':')?;
+          if name.eq_ignore_ascii_case("content-length") {
+            value.trim().parse::<usize>().ok()
+          } else {
+            None
+          }
+        })
+        .unwrap_or(0);
+      while request.len() < end + length {
+        match stream.read(&mut buf) {
+          Ok(0) | Err(_) => break,
+          Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+      }
+      let body =
+        serde_json::from_slice::<Value>(&request[end..]).unwrap_or(Value::Null);
+      let mut words = head.split_whitespace();
+      let method = words.next().unwrap_or_default();
+      let path = words.next().unwrap_or_default();
+      let response = respond(method, path, body).to_string();
+      let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.len(),
+        response
+      );
+    }
+  });
+  Ok(url)
+}
@@ -0,0 +1,300 @@
+mod common;
+
+use assert_cmd::prelude::*;
+use common::*;
+use serde_json::{
+  json,
+  Value,
+};
+use std::{
+  error::Error,
+  fs,
+  process::Output,
+  sync::{
+    Arc,
+    Mutex,
+  },
+};
+use tempfile::TempDir;
+
+/// What the fake Gitea has on it
+struct Repo {
+  issues: Vec<Value>,
+  comments: Vec<Value>,
+  /// The bodies of the comments posted to it
+  posted: Vec<String>,
+}
+
+impl Repo {
+  fn issue(&mut self, number: u64) -> Option<&mut Value> {
+    self
+      .issues
+      .iter_mut()
+      .find(|issue| issue["number"] == number)
+  }
+}
+
+fn issue(number: u64, title: &str, body: &str) -> Value {
+  json!({
+    "number": number,
+    "title": title,
+    "body": body,
+    "state": "open",
+    "labels": [{"name": "bug"}],
+    "assignees": null,
+    "created_at": "2020-01-01T10:00:00Z",
+    "updated_at": "2020-01-03T10:00:00Z",
+    "closed_at": null,
+    "due_date": "2020-03-01T00:00:00Z"
+  })
+}
+
+/// The page of the list asked for, with the limit capped at 2 the way Gitea
+/// caps it at MAX_RESPONSE_ITEMS
+fn page(items: &[Value], path: &str) -> Value {
+  let query = path.split_once('?').map_or("", |(_, query)| query);
+  let param = |name: &str| {
+    query
+      .split('&')
+      .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+      .and_then(|value| value.parse::<usize>().ok())
+  };
+  let limit = param("limit").unwrap_or(2).min(2);
+  let page = param("page").unwrap_or(1).max(1);
+  Value::from(
+    items
+      .iter()
+      .skip((page - 1) * limit)
+      .take(limit)
+      .cloned()
+      .collect::<Vec<Value>>(),
+  )
+}
+
+fn comment(id: u64, body: &str) -> Value {
+  json!({
+    "id": id,
+    "body": body,
+    "user": {"login": "else", "full_name": "Someone Else"},
+    "created_at": "2020-01-02T10:00:00Z"
+  })
+}
+
+/// Serves the Gitea API for the repo 'owner/repo' on a local port and returns
+/// the url for it
+fn fake_gitea(repo: Arc<Mutex<Repo>>) -> Result<String, Box<dyn Error>> {
+  fake_api(move |method, full, body| {
+    let path = full.split('?').next().unwrap_or_default();
+    let parts = path
+      .trim_start_matches("/api/v1/repos/owner/repo/issues")
+      .split('/')
+      .collect::<Vec<&str>>();
+    let number = parts.get(1).and_then(|n| n.parse::<u64>().ok());
+
+    let mut repo = repo.lock().unwrap();
+    match (method, number, parts.get(2)) {
+      ("GET", None, _) => page(&repo.issues, full),
+      ("POST", None, _) => {
+        let number = repo.issues.len() as u64 + 1;
+        let new = issue(
+          number,
+          body["title"].as_str().unwrap_or_default(),
+          body["body"].as_str().unwrap_or_default(),
+        );
+        repo.issues.push(new.clone());
+        new
+      }
+      ("PATCH", Some(number), None) => {
+        let issue = repo.issue(number).unwrap();
+        for (key, value) in body.as_object().into_iter().flatten() {
+          issue[key] = value.clone();
+        }
+        issue.clone()
+      }
+      ("GET", Some(_), Some(&"comments")) => page(&repo.comments, full),
+      ("POST", Some(_), Some(&"comments")) => {
+        let text = body["body"].as_str().unwrap_or_default().to_owned();
+        repo.posted.push(text.clone());
+        let new = comment(100 + repo.posted.len() as u64, &text);
+        repo.comments.push(new.clone());
+        new
+      }
+      _ => Value::from(Vec::<Value>::new()),
+    }
+  })
+}
+
+/// Runs ticket in the repo without going through any proxy
+fn run(dir: &TempDir, args: &[&str]) -> Result<Output, Box<dyn Error>> {
+  Ok(
+    ticket(dir)?
+      .args(args)
+      .env_remove("http_proxy")
+      .env_remove("HTTP_PROXY")
+      .env_remove("GITEA_TOKEN")
+      .output()?,
+  )
+}
+
+#[test]
+fn mirror_and_push_gitea() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let repo = Arc::new(Mutex::new(Repo {
+    issues: vec![issue(1, "Crash on startup", "It crashes")],
+    comments: Vec::new(),
+    posted: Vec::new(),
+  }));
+  let url = fake_gitea(Arc::clone(&repo))?;
+  let import = |dir| -> Result<String, Box<dyn Error>> {
+    let output = run(
+      dir,
+      &["import", "gitea", "--repo", "owner/repo", "--url", &url],
+    )?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+  };
+
+  assert!(import(&dir)?.contains("Imported #1"));
+  let path = dir
+    .path()
+    .join(".dev-suite/ticket/open/crash-on-startup.toml");
+  let saved = fs::read_to_string(&path)?;
+  assert!(saved.contains("due = ") && saved.contains("2020-03-01"));
+  assert!(saved.contains("bug"));
+
+  // Comments made on the issue since are mirrored, but the issue isn't
+  // imported again
+  repo
+    .lock()
+    .unwrap()
+    .comments
+    .push(comment(10, "Happens on every launch"));
+  let out = import(&dir)?;
+  assert!(!out.contains("Imported #"));
+  assert!(out.contains("Mirrored 1 comments from #1"));
+  assert!(fs::read_to_string(&path)?.contains("Happens on every launch"));
+  assert_eq!(list(&dir, &["--all"])?.lines().count(), 1);
+  assert!(!import(&dir)?.contains("Mirrored"));
+
+  // Only the comment made locally is pushed, and only once
+  let saved = fs::read_to_string(&path)?;
+  let _ = ticket(&dir)?
+    .args(["comment", uuid(&saved), "Fixed locally"])
+    .assert()
+    .success();
+  let output = run(&dir, &["remote", "sync", "gitea"])?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stdout)?.contains("Pushed 1 comments"));
+  let output = run(&dir, &["remote", "sync", "gitea"])?;
+  assert!(output.status.success());
+  assert!(!String::from_utf8(output.stdout)?.contains("Pushed"));
+  let repo = repo.lock().unwrap();
+  assert_eq!(repo.posted.len(), 1);
+  assert!(repo.posted[0].contains("Fixed locally"));
+  Ok(())
+}
+
+#[test]
+fn import_every_page_from_gitea() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  // Gitea gives out fewer than asked for, so short pages aren't the last
+  let repo = Arc::new(Mutex::new(Repo {
+    issues: (1..=5)
+      .map(|number| issue(number, &format!("Issue {}", number), ""))
+      .collect(),
+    comments: (1..=3)
+      .map(|id| comment(id, &format!("Comment {}", id)))
+      .collect(),
+    posted: Vec::new(),
+  }));
+  let url = fake_gitea(Arc::clone(&repo))?;
+  let output = run(
+    &dir,
+    &["import", "gitea", "--repo", "owner/repo", "--url", &url],
+  )?;
+  assert!(output.status.success());
+  let out = list(&dir, &["--all"])?;
+  assert_eq!(out.lines().count(), 5);
+  assert!(out.contains("Issue 5"));
+  let saved =
+    fs::read_to_string(dir.path().join(".dev-suite/ticket/open/issue-1.toml"))?;
+  assert!(saved.contains("Comment 3"));
+  Ok(())
+}
+
+#[test]
+fn sync_merges_and_reports_conflicts() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let repo = Arc::new(Mutex::new(Repo {
+    issues: vec![issue(1, "Crash on startup", "one\ntwo\nthree\n")],
+    comments: Vec::new(),
+    posted: Vec::new(),
+  }));
+  let url = fake_gitea(Arc::clone(&repo))?;
+  let output = run(
+    &dir,
+    &["import", "gitea", "--repo", "owner/repo", "--url", &url],
+  )?;
+  assert!(output.status.success());
+  let path = dir
+    .path()
+    .join(".dev-suite/ticket/open/crash-on-startup.toml");
+  let id = uuid(&fs::read_to_string(&path)?).to_owned();
+
+  // Changes to different lines of the description on each side are merged
+  // and tickets without an issue get one
+  repo.lock().unwrap().issue(1).unwrap()["body"] = json!("ONE\ntwo\nthree\n");
+  fs::write(&path, fs::read_to_string(&path)?.replace("three", "THREE"))?;
+  let _ = ticket(&dir)?
+    .args([
+      "new",
+      "--title",
+      "Local only",
+      "--description",
+      "",
+      "--no-edit",
+    ])
+    .assert()
+    .success();
+  let output = run(&dir, &["remote", "sync", "gitea"])?;
+  assert!(output.status.success());
+  assert!(fs::read_to_string(&path)?.contains("ONE\ntwo\nTHREE"));
+  {
+    let mut repo = repo.lock().unwrap();
+    assert_eq!(repo.issue(1).unwrap()["body"], "ONE\ntwo\nTHREE\n");
+    assert_eq!(repo.issue(2).unwrap()["title"], "Local only");
+  }
+
+  // Changing the title on both sides is a conflict and neither is touched
+  repo.lock().unwrap().issue(1).unwrap()["title"] = json!("Crash at launch");
+  let _ = ticket(&dir)?
+    .args(["edit", &id, "--title", "Crash on boot", "--no-edit"])
+    .assert()
+    .success();
+  let output = run(&dir, &["remote", "sync", "gitea"])?;
+  assert!(!output.status.success());
+  assert!(String::from_utf8(output.stdout)?.contains("Conflict:"));
+  assert_eq!(
+    repo.lock().unwrap().issue(1).unwrap()["title"],
+    "Crash at launch"
+  );
+  assert!(list(&dir, &[])?.contains("Crash on boot"));
+
+  // Until one side is picked
+  let output = run(&dir, &["remote", "sync", "gitea", "--prefer", "remote"])?;
+  assert!(output.status.success());
+  let out = list(&dir, &[])?;
+  assert!(out.contains("Crash at launch"));
+  assert!(!out.contains("Crash on boot"));
+  assert!(run(&dir, &["remote", "sync", "gitea"])?.status.success());
+
+  // Issues closed since are closed when imported again
+  repo.lock().unwrap().issue(1).unwrap()["state"] = json!("closed");
+  let output = run(
+    &dir,
+    &["import", "gitea", "--repo", "owner/repo", "--url", &url],
+  )?;
+  assert!(String::from_utf8(output.stdout)?.contains("to Closed to match #1"));
+  assert!(list(&dir, &["--closed"])?.contains("Crash at launch"));
+  Ok(())
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::*;
+use serde_json::{
+  json,
+  Value,
+};
+use std::{
+  error::Error,
+  fs,
+  process::Output,
+  sync::{
+    Arc,
+    Mutex,
+  },
+};
+use tempfile::TempDir;
+
+/// What the fake GitHub has on it
+struct Repo {
+  issues: Vec<Value>,
+  comments: Vec<Value>,
+}
+
+impl Repo {
+  fn issue(&mut self, number: u64) -> Option<&mut Value> {
+    self
+      .issues
+      .iter_mut()
+      .find(|issue| issue["number"] == number)
+  }
+}
+
+fn issue(number: u64, title: &str, body: &str) -> Value {
+  json!({
+    "number": number,
+    "title": title,
+    "body": body,
+    "state": "open",
+    "labels": [{"name": "bug"}],
+    "assignees": [{"login": "else"}],
+    "created_at": "2020-01-01T10:00:00Z",
+    "updated_at": "2020-01-03T10:00:00Z",
+    "closed_at": null
+  })
+}
+
+/// Serves the GitHub Enterprise API for the repo 'owner/repo' on a local port
+/// and returns the url for it
+fn fake_github(repo: Arc<Mutex<Repo>>) -> Result<String, Box<dyn Error>> {
+  fake_api(move |method, path, body| {
+    let path = path.split('?').next().unwrap_or_default();
+    let parts = path
+      .trim_start_matches("/api/v3/repos/owner/repo/issues")
+      .split('/')
+      .collect::<Vec<&str>>();
+    let number = parts.get(1).and_then(|n| n.parse::<u64>().ok());
+
+    let mut repo = repo.lock().unwrap();
+    match (method, number, parts.get(2)) {
+      ("GET", None, _) => Value::from(repo.issues.clone()),
+      ("POST", None, _) => {
+        let number = repo.issues.len() as u64 + 1;
+        let new = issue(
+          number,
+          body["title"].as_str().unwrap_or_default(),
+          body["body"].as_str().unwrap_or_default(),
+        );
+        repo.issues.push(new.clone());
+        new
+      }
+      ("PATCH", Some(number), None) => {
+        let issue = repo.issue(number).unwrap();
+        for (key, value) in body.as_object().into_iter().flatten() {
+          issue[key] = value.clone();
+        }
+        issue.clone()
+      }
+      ("GET", Some(_), Some(&"comments")) => Value::from(repo.comments.clone()),
+      ("POST", Some(_), Some(&"comments")) => {
+        let new = json!({
+          "id": 100 + repo.comments.len(),
+          "body": body["body"],
+          "user": {"login": "tester"},
+          "created_at": "2020-01-04T10:00:00Z"
+        });
+        repo.comments.push(new.clone());
+        new
+      }
+      _ => Value::from(Vec::<Value>::new()),
+    }
+  })
+}
+
+/// Runs ticket in the repo without going through any proxy
+fn run(dir: &TempDir, args: &[&str]) -> Result<Output, Box<dyn Error>> {
+  let output = ticket(dir)?
+    .args(args)
+    .env_remove("http_proxy")
+    .env_remove("HTTP_PROXY")
+    .env_remove("GITHUB_TOKEN")
+    .output()?;
+  assert!(output.status.success());
+  Ok(output)
+}
+
+#[test]
+fn sync_github() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let mut pull = issue(2, "Fix the crash", "");
+  pull["pull_request"] = json!({});
+  let repo = Arc::new(Mutex::new(Repo {
+    issues: vec![issue(1, "Crash on startup", "It crashes"), pull],
+    comments: vec![json!({
+      "id": 10,
+      "body": "Happens on every launch",
+      "user": {"login": "else"},
+      "created_at": "2020-01-02T10:00:00Z"
+    })],
+  }));
+  let url = format!("{}/owner/repo", fake_github(Arc::clone(&repo))?);
+  let _ = run(&dir, &["remote", "add", "github", "github", &url])?;
+
+  // Pull requests aren't issues so only the one issue is brought in
+  let output = run(&dir, &["remote", "sync", "github"])?;
+  assert!(String::from_utf8(output.stdout)?.contains("Imported #1"));
+  assert_eq!(list(&dir, &["--all"])?.lines().count(), 1);
+  let path = dir
+    .path()
+    .join(".dev-suite/ticket/open/crash-on-startup.toml");
+  let saved = fs::read_to_string(&path)?;
+  assert!(saved.contains("bug"));
+  assert!(saved.contains("Happens on every launch"));
+
+  // Closing the issue closes the ticket
+  {
+    let mut repo = repo.lock().unwrap();
+    let issue = repo.issue(1).unwrap();
+    issue["state"] = json!("closed");
+    issue["closed_at"] = json!("2020-01-05T10:00:00Z");
+  }
+  let output = run(&dir, &["remote", "sync", "github"])?;
+  assert!(String::from_utf8(output.stdout)?.contains("to Closed to match #1"));
+  assert!(list(&dir, &["--closed"])?.contains("Crash on startup"));
+
+  // Reopening the ticket reopens the issue, and then they agree
+  let _ = run(&dir, &["reopen", uuid(&saved)])?;
+  let output = run(&dir, &["remote", "sync", "github"])?;
+  assert!(String::from_utf8(output.stdout)?.contains("Reopened #1"));
+  assert_eq!(repo.lock().unwrap().issue(1).unwrap()["state"], "open");
+  let output = run(&dir, &["remote", "sync", "github"])?;
+  let out = String::from_utf8(output.stdout)?;
+  assert!(!out.contains("Reopened") && !out.contains("Closed"));
+  Ok(())
+}
+
+#[test]
+fn push_only_mirrors_close_issues() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let repo = Arc::new(Mutex::new(Repo {
+    issues: Vec::new(),
+    comments: Vec::new(),
+  }));
+  let url = format!("{}/owner/repo", fake_github(Arc::clone(&repo))?);
+  let _ = run(
+    &dir,
+    &["remote", "add", "mirror", "github", &url, "--push-only"],
+  )?;
+  let _ = run(
+    &dir,
+    &[
+      "new",
+      "--title",
+      "Mirrored",
+      "--description",
+      "",
+      "--no-edit",
+    ],
+  )?;
+  let _ = run(&dir, &["remote", "sync", "mirror"])?;
+  assert_eq!(repo.lock().unwrap().issue(1).unwrap()["title"], "Mirrored");
+
+  let path = dir.path().join(".dev-suite/ticket/open/mirrored.toml");
+  let _ = run(&dir, &["close", uuid(&fs::read_to_string(path)?)])?;
+  let output = run(&dir, &["remote", "sync", "mirror"])?;
+  assert!(String::from_utf8(output.stdout)?.contains("Closed #1"));
+  assert_eq!(repo.lock().unwrap().issue(1).unwrap()["state"], "closed");
+
+  // Issues reopened on a mirror are closed again
+  repo.lock().unwrap().issue(1).unwrap()["state"] = json!("open");
+  let _ = run(&dir, &["remote", "sync", "mirror"])?;
+  assert_eq!(repo.lock().unwrap().issue(1).unwrap()["state"], "closed");
+  Ok(())
+}
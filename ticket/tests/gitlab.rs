@@ -0,0 +1,135 @@
+mod common;
+
+use common::*;
+use std::{
+  error::Error,
+  fs,
+  io::{
+    Read,
+    Write,
+  },
+  net::TcpListener,
+  thread,
+};
+
+const ISSUES: &str = r#"[
+  {
+    "iid": 1,
+    "title": "Crash on startup",
+    "description": "It crashes",
+    "state": "closed",
+    "labels": ["bug"],
+    "assignees": [{"name": "Tester", "username": "tester"}],
+    "created_at": "2020-01-01T10:00:00.000Z",
+    "updated_at": "2020-01-03T10:00:00.000Z",
+    "closed_at": "2020-01-03T10:00:00.000Z",
+    "closed_by": {"name": "Someone Else", "username": "else"},
+    "due_date": null
+  },
+  {
+    "iid": 2,
+    "title": "Add dark mode",
+    "description": null,
+    "state": "opened",
+    "labels": ["feature", "ui"],
+    "assignees": [],
+    "created_at": "2020-02-01T10:00:00.000Z",
+    "updated_at": "2020-02-01T10:00:00.000Z",
+    "closed_at": null,
+    "closed_by": null,
+    "due_date": "2020-03-01"
+  }
+]"#;
+
+const NOTES: &str = r#"[
+  {
+    "id": 301,
+    "body": "Fixed in master",
+    "author": {"name": "Someone Else", "username": "else"},
+    "created_at": "2020-01-02T10:00:00.000Z",
+    "system": false
+  },
+  {
+    "id": 302,
+    "body": "closed",
+    "author": {"name": "Someone Else", "username": "else"},
+    "created_at": "2020-01-03T10:00:00.000Z",
+    "system": true
+  }
+]"#;
+
+/// Serves canned responses for the GitLab API on a local port and returns the
+/// url for it
+fn fake_gitlab() -> Result<String, Box<dyn Error>> {
+  let listener = TcpListener::bind("127.0.0.1:0")?;
+  let url = format!("http://{}", listener.local_addr()?);
+  let _ = thread::spawn(move || {
+    for stream in listener.incoming() {
+      let mut stream = match stream {
+        Ok(stream) => stream,
+        Err(_) => continue,
+      };
+      let mut request = Vec::new();
+      let mut buf = [0; 1024];
+      while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+          Ok(0) | Err(_) => break,
+          Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+      }
+      let request = String::from_utf8_lossy(&request);
+      let path = request.split_whitespace().nth(1).unwrap_or_default();
+      let body = if !path.starts_with("/api/v4/projects/group%2Fproject/") {
+        "[]"
+      } else if path.contains("/issues/1/notes") {
+        NOTES
+      } else if path.contains("/notes") {
+        "[]"
+      } else {
+        ISSUES
+      };
+      let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+      );
+    }
+  });
+  Ok(url)
+}
+
+#[test]
+fn import_from_gitlab() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let url = fake_gitlab()?;
+  let import = |dir| -> Result<String, Box<dyn Error>> {
+    let output = ticket(dir)?
+      .args(["import", "gitlab", "--project", "group/project", "--url"])
+      .arg(&url)
+      .env_remove("http_proxy")
+      .env_remove("HTTP_PROXY")
+      .output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+  };
+
+  let out = import(&dir)?;
+  assert!(out.contains("Imported #1"));
+  assert!(out.contains("Imported #2"));
+  assert!(list(&dir, &["--closed"])?.contains("Crash on startup"));
+  let saved = fs::read_to_string(
+    dir.path().join(".dev-suite/ticket/open/add-dark-mode.toml"),
+  )?;
+  assert!(saved.contains("due = ") && saved.contains("2020-03-01"));
+  assert!(saved.contains("feature"));
+
+  let output = ticket(&dir)?.args(["search", "Fixed in master"]).output()?;
+  assert!(String::from_utf8(output.stdout)?.contains("Crash on startup"));
+
+  // Issues that were imported before are skipped
+  assert!(!import(&dir)?.contains("Imported #"));
+  assert_eq!(list(&dir, &["--all"])?.lines().count(), 2);
+  Ok(())
+}
@@ -0,0 +1,136 @@
+//! Randomized tests for the properties tickets should keep no matter what's in
+//! them. Each run uses a new seed which is printed so that a failure can be
+//! reproduced by setting `TICKET_TEST_SEED` to it.
+
+mod common;
+
+use assert_cmd::prelude::*;
+use common::*;
+use rand::{
+  prelude::*,
+  rngs::StdRng,
+};
+use std::{
+  collections::BTreeMap,
+  env,
+  error::Error,
+  fs,
+  path::Path,
+};
+
+/// Characters that need quoting or escaping in TOML or are otherwise easy to
+/// get wrong, picked more often than random ones
+const TRICKY: &[char] = &[
+  '\'', '"', '\\', '\n', '\r', '\t', '#', '=', '[', ']', '{', '}', ',', '.',
+  ' ', 'é', '✓', '🎫',
+];
+
+fn rng() -> StdRng {
+  let seed = env::var("TICKET_TEST_SEED")
+    .ok()
+    .and_then(|s| s.parse().ok())
+    .unwrap_or_else(random);
+  println!("Using seed {}", seed);
+  StdRng::seed_from_u64(seed)
+}
+
+/// A string that isn't only whitespace, since those aren't allowed as tasks
+fn text(rng: &mut StdRng) -> String {
+  loop {
+    let len = rng.gen_range(1, 40);
+    let text = (0..len)
+      .map(|_| {
+        if rng.gen_bool(0.5) {
+          TRICKY[rng.gen_range(0, TRICKY.len())]
+        } else {
+          // Arguments can't contain nul bytes
+          Some(rng.gen::<char>())
+            .filter(|c| *c != '\0')
+            .unwrap_or('x')
+        }
+      })
+      .collect::<String>();
+    if !text.trim().is_empty() {
+      return text;
+    }
+  }
+}
+
+/// The contents of every ticket file keyed by path
+fn ticket_files(
+  root: &Path,
+) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+  let mut files = BTreeMap::new();
+  for status in fs::read_dir(root.join(".dev-suite").join("ticket"))? {
+    let status = status?.path();
+    if !status.is_dir() {
+      continue;
+    }
+    for file in fs::read_dir(&status)? {
+      let file = file?.path();
+      let _ =
+        files.insert(file.display().to_string(), fs::read_to_string(&file)?);
+    }
+  }
+  Ok(files)
+}
+
+#[test]
+fn tasks_round_trip() -> Result<(), Box<dyn Error>> {
+  let mut rng = rng();
+  let dir = fixture()?;
+  let id = "1d6df400-1553-11ea-8001-000502040903";
+  let texts = (0..10).map(|_| text(&mut rng)).collect::<Vec<String>>();
+  for text in &texts {
+    let _ = ticket(&dir)?
+      .args(["task", "add", id])
+      .arg(text)
+      .assert()
+      .success();
+  }
+
+  let path = dir.path().join(".dev-suite/ticket/open/write-docs.toml");
+  let saved = fs::read_to_string(&path)?.parse::<toml::Value>()?;
+  let tasks = saved["tasks"].as_array().ok_or("No tasks were saved")?;
+  assert_eq!(tasks.len(), texts.len());
+  for (task, text) in tasks.iter().zip(&texts) {
+    assert_eq!(task["text"].as_str(), Some(text.as_str()));
+  }
+  // The ticket still loads after being saved with them
+  let _ = ticket(&dir)?.args(["show", id]).assert().success();
+  Ok(())
+}
+
+#[test]
+fn migrate_is_idempotent() -> Result<(), Box<dyn Error>> {
+  let mut rng = rng();
+  let dir = fixture()?;
+  let ids = [
+    "0d6df400-1553-11ea-8001-000502040903",
+    "1d6df400-1553-11ea-8001-000502040903",
+    "2d6df400-1553-11ea-8001-000502040903",
+  ];
+  let _ = ticket(&dir)?
+    .args(["link", ids[0], "related-to", ids[1]])
+    .assert()
+    .success();
+  for _ in 0..5 {
+    let id = ids[rng.gen_range(0, ids.len())];
+    let _ = ticket(&dir)?
+      .args(["task", "add", id])
+      .arg(text(&mut rng))
+      .assert()
+      .success();
+    let _ = ticket(&dir)?
+      .args(["comment", id])
+      .arg(text(&mut rng))
+      .assert()
+      .success();
+  }
+
+  let _ = ticket(&dir)?.arg("migrate").assert().success();
+  let once = ticket_files(dir.path())?;
+  let _ = ticket(&dir)?.arg("migrate").assert().success();
+  assert_eq!(once, ticket_files(dir.path())?);
+  Ok(())
+}
@@ -0,0 +1,378 @@
+mod common;
+
+use common::*;
+use reqwest::{
+  blocking::Client,
+  StatusCode,
+};
+use serde_json::{
+  json,
+  Value,
+};
+use std::{
+  error::Error,
+  fs,
+  net::TcpListener,
+  process::{
+    Child,
+    Stdio,
+  },
+  thread,
+  time::Duration,
+};
+use tempfile::TempDir;
+
+/// `ticket serve` running in the background, which is killed when dropped
+struct Server {
+  child: Child,
+  url: String,
+  client: Client,
+}
+
+impl Server {
+  fn start(dir: &TempDir, args: &[&str]) -> Result<Self, Box<dyn Error>> {
+    // Let the OS pick a free port then hand it over to the server
+    let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    let child = ticket(dir)?
+      .args(["serve", "--port", &port.to_string()])
+      .args(args)
+      .stdout(Stdio::null())
+      .spawn()?;
+    let server = Self {
+      child,
+      url: format!("http://127.0.0.1:{}", port),
+      client: Client::builder().no_proxy().build()?,
+    };
+    for _ in 0..100 {
+      if server.get("/").is_ok() {
+        return Ok(server);
+      }
+      thread::sleep(Duration::from_millis(100));
+    }
+    Err("ticket serve never started".into())
+  }
+
+  fn get(&self, path: &str) -> Result<(StatusCode, String), Box<dyn Error>> {
+    let response = self.client.get(&format!("{}{}", self.url, path)).send()?;
+    Ok((response.status(), response.text()?))
+  }
+
+  fn post(
+    &self,
+    path: &str,
+    form: &[(&str, &str)],
+  ) -> Result<(StatusCode, String), Box<dyn Error>> {
+    let response = self
+      .client
+      .post(&format!("{}{}", self.url, path))
+      .form(form)
+      .send()?;
+    Ok((response.status(), response.text()?))
+  }
+
+  fn post_json(
+    &self,
+    path: &str,
+    json: Value,
+  ) -> Result<(StatusCode, Value), Box<dyn Error>> {
+    let response = self
+      .client
+      .post(&format!("{}{}", self.url, path))
+      .header("Content-Type", "application/json")
+      .body(json.to_string())
+      .send()?;
+    Ok((response.status(), serde_json::from_str(&response.text()?)?))
+  }
+
+  fn get_json(
+    &self,
+    path: &str,
+  ) -> Result<(StatusCode, Value), Box<dyn Error>> {
+    let (status, body) = self.get(path)?;
+    Ok((status, serde_json::from_str(&body)?))
+  }
+}
+
+impl Drop for Server {
+  fn drop(&mut self) {
+    let _ = self.child.kill();
+    let _ = self.child.wait();
+  }
+}
+
+/// The link to the ticket's page from the index
+fn page_of(index: &str, title: &str) -> String {
+  let end = index.find(&format!("\">{}</a>", title)).unwrap();
+  let start = index[..end].rfind("href=\"").unwrap() + "href=\"".len();
+  format!("/{}", &index[start..end])
+}
+
+/// The token `ticket serve` put in the page's forms
+fn token_of(page: &str) -> String {
+  let field = "name=\"token\" value=\"";
+  let start = page.find(field).unwrap() + field.len();
+  page[start..].split('"').next().unwrap().to_owned()
+}
+
+#[test]
+fn serve_browse_and_edit() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let tickets = dir.path().join(".dev-suite/ticket");
+  let server = Server::start(&dir, &[])?;
+
+  let (status, index) = server.get("/")?;
+  assert_eq!(status, StatusCode::OK);
+  assert!(index.contains("Fix the build"));
+  assert!(index.contains("Old bug"));
+  let page = page_of(&index, "Fix the build");
+  let token = token_of(&index);
+  let (status, body) = server.get(&page)?;
+  assert_eq!(status, StatusCode::OK);
+  assert!(body.contains("<form"));
+  assert_eq!(token_of(&body), token);
+
+  // Forms redirect back to the ticket's page after writing to its file
+  let (status, body) = server.post(
+    &page,
+    &[
+      ("token", &token),
+      ("action", "comment"),
+      ("comment", "From a <browser>"),
+    ],
+  )?;
+  assert_eq!(status, StatusCode::OK);
+  assert!(body.contains("From a &lt;browser&gt;"));
+  assert!(fs::read_to_string(tickets.join("open/fix-the-build.toml"))?
+    .contains("From a <browser>"));
+
+  let (status, _) = server.post(
+    &page,
+    &[
+      ("token", &token),
+      ("action", "edit"),
+      ("title", "Fix the nightly build"),
+      ("description", "Line one\r\nLine two"),
+    ],
+  )?;
+  assert_eq!(status, StatusCode::OK);
+  assert!(!tickets.join("open/fix-the-build.toml").exists());
+  let saved =
+    fs::read_to_string(tickets.join("open/fix-the-nightly-build.toml"))?;
+  assert!(saved.contains("Line one\nLine two"));
+
+  let (status, _) = server.post(
+    &page,
+    &[
+      ("token", &token),
+      ("action", "status"),
+      ("status", "Closed"),
+    ],
+  )?;
+  assert_eq!(status, StatusCode::OK);
+  assert!(tickets.join("closed/fix-the-nightly-build.toml").exists());
+
+  let (status, body) = server.post(
+    "/index.html",
+    &[
+      ("token", &token),
+      ("action", "new"),
+      ("title", "From the web"),
+      ("description", ""),
+    ],
+  )?;
+  assert_eq!(status, StatusCode::OK);
+  assert!(body.contains("From the web"));
+  assert!(tickets.join("open/from-the-web.toml").exists());
+
+  // Bad requests don't touch the tickets
+  let (status, _) = server.post(
+    &page,
+    &[
+      ("token", &token),
+      ("action", "edit"),
+      ("title", " "),
+      ("description", ""),
+    ],
+  )?;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  assert!(tickets.join("closed/fix-the-nightly-build.toml").exists());
+  let edit = [("action", "edit"), ("title", "Forged"), ("description", "")];
+  assert_eq!(server.post(&page, &edit)?.0, StatusCode::BAD_REQUEST);
+  let forged = [("token", "0000"), edit[0], edit[1], edit[2]];
+  assert_eq!(server.post(&page, &forged)?.0, StatusCode::BAD_REQUEST);
+
+  // Pages on other sites can't send requests to it, even with the token
+  let with_token = [("token", token.as_str()), edit[0], edit[1], edit[2]];
+  let response = server
+    .client
+    .post(&format!("{}{}", server.url, page))
+    .header("Origin", "http://example.com")
+    .form(&with_token)
+    .send()?;
+  assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  let port = server.url.rsplit(':').next().unwrap();
+  let response = server
+    .client
+    .get(&format!("{}{}", server.url, page))
+    .header("Host", format!("example.com:{}", port))
+    .send()?;
+  assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  assert!(!list(&dir, &["--closed"])?.contains("Forged"));
+  assert_eq!(
+    server.get("/tickets/nothing.html")?.0,
+    StatusCode::NOT_FOUND
+  );
+  Ok(())
+}
+
+#[test]
+fn serve_api() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let server = Server::start(&dir, &["--api"])?;
+
+  let (status, tickets) = server.get_json("/tickets")?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(tickets.as_array().unwrap().len(), 3);
+  let (_, tickets) = server.get_json("/tickets?status=closed")?;
+  assert_eq!(tickets[0]["title"], "Old bug");
+  assert_eq!(tickets.as_array().unwrap().len(), 1);
+
+  let (status, ticket) = server.post_json(
+    "/tickets",
+    json!({ "title": "From CI", "description": "The nightly job failed" }),
+  )?;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(ticket["status"], "Open");
+  let id = ticket["id"].as_str().unwrap().to_owned();
+  let (status, ticket) = server.get_json(&format!("/tickets/{}", id))?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(ticket["title"], "From CI");
+
+  let (status, ticket) = server.post_json(
+    &format!("/tickets/{}/comments", id),
+    json!({ "comment": "Flaky test" }),
+  )?;
+  assert_eq!(status, StatusCode::CREATED);
+  assert!(ticket["comments"].to_string().contains("Flaky test"));
+  let (status, ticket) = server.post_json(
+    &format!("/tickets/{}/close", id),
+    json!({ "reason": "Retried" }),
+  )?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(ticket["status"], "Closed");
+  assert!(list(&dir, &["--closed"])?.contains("From CI"));
+
+  // Errors come back as json with a status to match
+  let (status, error) =
+    server.post_json(&format!("/tickets/{}/close", id), json!({}))?;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  assert!(error["error"].as_str().unwrap().contains("already closed"));
+  let (status, error) = server.get_json("/tickets/nothing")?;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  assert!(error["error"].is_string());
+  assert_eq!(server.get_json("/elsewhere")?.0, StatusCode::NOT_FOUND);
+  Ok(())
+}
+
+#[test]
+fn serve_api_guards() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  let server = Server::start(&dir, &["--api", "--token", "hunter2"])?;
+  let url = format!("{}/tickets", server.url);
+
+  let (status, error) = server.get_json("/tickets")?;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+  assert!(error["error"].as_str().unwrap().contains("token"));
+  let response = server.client.get(&url).bearer_auth("hunter2").send()?;
+  assert_eq!(response.status(), StatusCode::OK);
+  let response = server.client.get(&url).bearer_auth("letmein").send()?;
+  assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+  // Forms that any site could post aren't taken
+  let response = server
+    .client
+    .post(&url)
+    .bearer_auth("hunter2")
+    .header("Content-Type", "text/plain")
+    .body(json!({ "title": "From a form" }).to_string())
+    .send()?;
+  assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+  let response = server
+    .client
+    .post(&url)
+    .bearer_auth("hunter2")
+    .header("Content-Type", "application/json; charset=utf-8")
+    .body(json!({ "title": "From a bot" }).to_string())
+    .send()?;
+  assert_eq!(response.status(), StatusCode::CREATED);
+
+  // Nor are requests from other sites or to other names for the server
+  let response = server
+    .client
+    .get(&url)
+    .bearer_auth("hunter2")
+    .header("Origin", "http://example.com")
+    .send()?;
+  assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  let port = server.url.rsplit(':').next().unwrap();
+  let response = server
+    .client
+    .get(&url)
+    .bearer_auth("hunter2")
+    .header("Host", format!("example.com:{}", port))
+    .send()?;
+  assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  assert!(!list(&dir, &[])?.contains("From a form"));
+  assert!(list(&dir, &[])?.contains("From a bot"));
+  Ok(())
+}
+
+#[test]
+fn serve_required_fields() -> Result<(), Box<dyn Error>> {
+  let dir = fixture()?;
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    format!(
+      "maintainers = [['Tester', '{}']]\n[[fields]]\nname = 'severity'\n\
+       type = 'enum'\nrequired = true\nvalues = ['low', 'high']\n",
+      USER
+    ),
+  )?;
+
+  // The web page has nowhere to set fields so it can't make tickets
+  let server = Server::start(&dir, &[])?;
+  let (_, index) = server.get("/")?;
+  let token = token_of(&index);
+  let (status, body) = server.post(
+    "/",
+    &[
+      ("token", &token),
+      ("title", "From a browser"),
+      ("description", ""),
+    ],
+  )?;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  assert!(body.contains("severity is required"));
+  drop(server);
+
+  let server = Server::start(&dir, &["--api"])?;
+  let (status, error) =
+    server.post_json("/tickets", json!({ "title": "From CI" }))?;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  let error = error["error"].as_str().unwrap();
+  assert!(error.contains("severity is required"));
+  let (status, error) = server.post_json(
+    "/tickets",
+    json!({ "title": "From CI", "fields": { "severity": "urgent" } }),
+  )?;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  assert!(error["error"].as_str().unwrap().contains("low, high"));
+  let (status, ticket) = server.post_json(
+    "/tickets",
+    json!({ "title": "From CI", "fields": { "severity": "HIGH" } }),
+  )?;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(ticket["fields"]["severity"], "high");
+  assert!(!list(&dir, &[])?.contains("From a browser"));
+  Ok(())
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use assert_cmd::prelude::*;
+use common::*;
+use serde_json::Value;
+use std::{
+  error::Error,
+  fs,
+  io::{
+    Read,
+    Write,
+  },
+  net::TcpListener,
+  sync::{
+    Arc,
+    Mutex,
+  },
+  thread,
+};
+
+/// Listens on a local port for webhooks and keeps the json posted to it
+fn receiver(
+  received: Arc<Mutex<Vec<Value>>>,
+) -> Result<String, Box<dyn Error>> {
+  let listener = TcpListener::bind("127.0.0.1:0")?;
+  let url = format!("http://{}/hook", listener.local_addr()?);
+  let _ = thread::spawn(move || {
+    for stream in listener.incoming() {
+      let mut stream = match stream {
+        Ok(stream) => stream,
+        Err(_) => continue,
+      };
+      let mut request = Vec::new();
+      let mut buf = [0; 1024];
+      let end = loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+          break end + 4;
+        }
+        match stream.read(&mut buf) {
+          Ok(0) | Err(_) => break request.len(),
+          Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+      };
+      let head = String::from_utf8_lossy(&request[..end]).into_owned();
+      let length = head
+        .lines()
+        .find_map(|line| {
+          let (name, value) = line.split_once(':')?;
+          if name.eq_ignore_ascii_case("content-length") {
+            value.trim().parse::<usize>().ok()
+          } else {
+            None
+          }
+        })
+        .unwrap_or(0);
+      while request.len() < end + length {
+        match stream.read(&mut buf) {
+          Ok(0) | Err(_) => break,
+          Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+      }
+      if let Ok(body) = serde_json::from_slice(&request[end..]) {
+        received.lock().unwrap().push(body);
+      }
+      let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+      );
+    }
+  });
+  Ok(url)
+}
+
+#[test]
+fn webhooks_get_ticket_events() -> Result<(), Box<dyn Error>> {
+  let dir = setup()?;
+  let all = Arc::new(Mutex::new(Vec::new()));
+  let closed = Arc::new(Mutex::new(Vec::new()));
+  // Nothing listens on port 9 so this one always fails
+  fs::write(
+    dir.path().join(".dev-suite").join("repo-config.toml"),
+    format!(
+      "maintainers = []\n\n[[webhooks]]\nurl = '{}'\n\n\
+       [[webhooks]]\nurl = '{}'\nevents = ['closed']\n\n\
+       [[webhooks]]\nurl = 'http://127.0.0.1:9/hook'\nevents = ['created']\n",
+      receiver(Arc::clone(&all))?,
+      receiver(Arc::clone(&closed))?
+    ),
+  )?;
+
+  // A webhook that can't be reached doesn't stop the ticket being made
+  let output = ticket(&dir)?
+    .args(["new", "--title", "Hooked", "--description", "", "--no-edit"])
+    .env_remove("http_proxy")
+    .env_remove("HTTP_PROXY")
+    .output()?;
+  assert!(output.status.success());
+  assert!(String::from_utf8(output.stderr)?.contains("127.0.0.1:9"));
+  let saved =
+    fs::read_to_string(dir.path().join(".dev-suite/ticket/open/hooked.toml"))?;
+  let id = saved
+    .lines()
+    .find_map(|line| line.strip_prefix("id = "))
+    .unwrap_or_default()
+    .trim_matches(|c| c == '"' || c == '\'');
+
+  let _ = ticket(&dir)?
+    .args(["comment", id, "Looking into it"])
+    .env_remove("http_proxy")
+    .env_remove("HTTP_PROXY")
+    .assert()
+    .success();
+  let _ = ticket(&dir)?
+    .args(["close", id, "--reason", "Done"])
+    .env_remove("http_proxy")
+    .env_remove("HTTP_PROXY")
+    .assert()
+    .success();
+
+  let all = all.lock().unwrap();
+  let events = all
+    .iter()
+    .map(|event| event["event"].as_str().unwrap_or_default())
+    .collect::<Vec<_>>();
+  assert_eq!(events, ["created", "commented", "closed"]);
+  assert_eq!(all[0]["ticket"]["title"], "Hooked");
+  assert_eq!(all[0]["by"], "Tester");
+  assert_eq!(all[1]["comment"], "Looking into it");
+  assert!(all[2]["text"].as_str().unwrap().contains("Tester closed"));
+  assert_eq!(all[2]["ticket"]["status"], "Closed");
+
+  let closed = closed.lock().unwrap();
+  assert_eq!(closed.len(), 1);
+  assert_eq!(closed[0]["comment"], "Done");
+  Ok(())
+}